@@ -1,6 +1,7 @@
 use crate::apu::MAX_VOLUME;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Default)]
+#[derive(Clone, Default, Serialize, Deserialize)]
 struct EnvelopeTimer {
     value: u8, // 4 bits
     is_increasing: bool,
@@ -41,7 +42,7 @@ impl EnvelopeTimer {
     }
 }
 
-#[derive(Clone, Default)]
+#[derive(Clone, Default, Serialize, Deserialize)]
 pub struct VolumeAndEnvelope {
     timer: EnvelopeTimer,
     register: u8,
@@ -1,7 +1,9 @@
+use serde::{Deserialize, Serialize};
+
 pub const MASK_8_BITS: u8 = 0xff;
 pub const MASK_6_BITS: u8 = 0x3f;
 
-#[derive(Clone, Default, Debug)]
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
 pub struct Length<const MASK: u8> {
     is_enabled: bool,
     current_timer_value: Option<u8>, // None = overflowed
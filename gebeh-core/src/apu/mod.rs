@@ -1,4 +1,5 @@
 use core::{f32::consts::PI, ops::Deref};
+use serde::{Deserialize, Serialize};
 
 use crate::{
     FallingEdge,
@@ -22,7 +23,7 @@ mod wave_channel;
 // Importantly, the slope is negative: “digital 0” maps to “analog 1”, not “analog -1”.
 const MAX_VOLUME: u8 = 0x0f;
 
-#[derive(Clone, Default)]
+#[derive(Clone, Default, Serialize, Deserialize)]
 pub struct Apu {
     is_on: bool,
     nr51: Nr51,
@@ -37,7 +38,7 @@ pub struct Apu {
 }
 
 bitflags::bitflags! {
-    #[derive(Debug, Clone, Copy,  PartialEq, Eq)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
     pub struct Nr52: u8 {
         const AUDIO_ON_OFF = 1 << 7;
         const CH4_ON = 1 << 3;
@@ -49,7 +50,7 @@ bitflags::bitflags! {
 
 // Sound panning
 bitflags::bitflags! {
-    #[derive(Debug, Clone, Copy,  PartialEq, Eq, Default)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
     pub struct Nr51: u8 {
         const CH4_LEFT = 1 << 7;
         const CH3_LEFT = 1 << 6;
@@ -64,7 +65,7 @@ bitflags::bitflags! {
 
 // Master volume & VIN panning
 bitflags::bitflags! {
-    #[derive(Debug, Clone, Copy,  PartialEq, Eq, Default)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
     pub struct Nr50: u8 {
         const VIN_LEFT = 1 << 7;
         const LEFT_VOLUME_MASK = 0b01110000;
@@ -339,6 +340,7 @@ pub struct MixedSampler<'a, T: Deref<Target = [u8]>> {
     sampler: Sampler,
     sample: f32,
     mixer: &'a mut Mixer<T>,
+    cartridge_tone: Option<f32>,
 }
 
 impl<T: Deref<Target = [u8]>> Mixer<T> {
@@ -383,24 +385,41 @@ impl<T: Deref<Target = [u8]>> Mixer<T> {
             sampler,
             sample,
             mixer: self,
+            cartridge_tone: None,
         }
     }
 }
 
 impl<T: Deref<Target = [u8]>> MixedSampler<'_, T> {
+    // the square wave of the cartridge speaker (HuC3) in Hz, the APU registers don't affect it
+    #[must_use]
+    pub fn with_cartridge_tone(mut self, frequency: Option<f32>) -> Self {
+        self.cartridge_tone = frequency;
+        self
+    }
     pub fn sample_left(&mut self) -> f32 {
-        self.mixer.hpf_left.apply(self.sampler.sample_left(
-            self.sample,
-            &self.mixer.noise,
-            &self.mixer.short_noise,
-        ))
+        let sample =
+            self.sampler
+                .sample_left(self.sample, &self.mixer.noise, &self.mixer.short_noise)
+                + self.sample_cartridge_tone();
+        self.mixer.hpf_left.apply(sample)
     }
     pub fn sample_right(&mut self) -> f32 {
-        self.mixer.hpf_right.apply(self.sampler.sample_right(
-            self.sample,
-            &self.mixer.noise,
-            &self.mixer.short_noise,
-        ))
+        let sample =
+            self.sampler
+                .sample_right(self.sample, &self.mixer.noise, &self.mixer.short_noise)
+                + self.sample_cartridge_tone();
+        self.mixer.hpf_right.apply(sample)
+    }
+    fn sample_cartridge_tone(&self) -> f32 {
+        let Some(frequency) = self.cartridge_tone else {
+            return 0.;
+        };
+        (if (self.sample * frequency) % 1. < 0.5 {
+            1.
+        } else {
+            -1.
+        }) / CHANNEL_COUNT
     }
 }
 
@@ -3,8 +3,9 @@ use crate::apu::{
     envelope::VolumeAndEnvelope,
     length::{Length, MASK_6_BITS},
 };
+use serde::{Deserialize, Serialize};
 
-#[derive(Default, Clone)]
+#[derive(Default, Clone, Serialize, Deserialize)]
 pub struct NoiseChannel {
     length: Length<MASK_6_BITS>,
     volume_and_envelope: VolumeAndEnvelope,
@@ -4,6 +4,7 @@ use crate::apu::{
     length::{Length, MASK_6_BITS},
     sweep::{Ch1Sweep, Sweep},
 };
+use serde::{Deserialize, Serialize};
 
 type Wave = [u8; 8];
 
@@ -13,7 +14,7 @@ const WAVE_01: Wave = [0, 1, 1, 1, 1, 1, 1, 0];
 const WAVE_10: Wave = [0, 1, 1, 1, 1, 0, 0, 0];
 const WAVE_11: Wave = [1, 0, 0, 0, 0, 0, 0, 1];
 
-#[derive(Clone, Default)]
+#[derive(Clone, Default, Serialize, Deserialize)]
 pub struct PulseChannel<S: Sweep> {
     length: Length<MASK_6_BITS>,
     duty_cycle: u8,
@@ -1,6 +1,7 @@
 use core::num::NonZeroU8;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Ch1Sweep {
     nr10: u8,
     pace_countdown: NonZeroU8,
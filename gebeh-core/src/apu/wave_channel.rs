@@ -2,8 +2,9 @@ use crate::apu::{
     MAX_VOLUME,
     length::{Length, MASK_8_BITS},
 };
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct WaveChannel {
     is_enabled: bool,
     is_dac_on: bool,
@@ -0,0 +1,509 @@
+// The state left by the boot ROMs when they jump to 0x0100, to start the cartridge without them.
+// https://gbdev.io/pandocs/Power_Up_Sequence.html
+
+use crate::{
+    Cgb, Emulator, Model,
+    addresses::*,
+    cpu::{Cpu, Flags},
+    mbc::Mbc,
+    revision::{CgbRevision, DmgBootRegisters},
+};
+
+const LOGO: u16 = 0x0104;
+const TITLE: u16 = 0x0134;
+const NEW_LICENSEE_CODE: u16 = 0x0144;
+const CGB_FLAG: u16 = 0x0143;
+const OLD_LICENSEE_CODE: u16 = 0x014b;
+const HEADER_CHECKSUM: u16 = 0x014d;
+
+// the tiles 1 to 24, the registered trademark is the tile 25
+const LOGO_TILES: u16 = 0x8010;
+const TRADEMARK_TILE: u16 = 0x8190;
+const TRADEMARK: [u8; 8] = [0x3c, 0x42, 0xb9, 0xa5, 0xb9, 0xa5, 0x42, 0x3c];
+
+// The system counter, DIV is its upper byte.
+// https://gbdev.io/pandocs/Power_Up_Sequence.html#hardware-registers
+// Pan Docs gives DIV for the DMG0 (0x18) and for the DMG and the MGB (0xab), the lower byte is not
+// documented and only matters to the tests measuring the phase of DIV.
+pub(crate) const DMG0_SYSTEM_COUNTER: u16 = 0x1800;
+pub(crate) const DMG_SYSTEM_COUNTER: u16 = 0xabcc;
+// Pan Docs gives no value for the SGB, the boot lasts until the SNES answers, and none for the CGB,
+// the boot ROM spends more time on the DMG games to choose their palette. These are estimates used
+// for every game, nothing can rely on them.
+pub(crate) const SGB_SYSTEM_COUNTER: u16 = DMG_SYSTEM_COUNTER;
+pub(crate) const CGB_SYSTEM_COUNTER: u16 = 0x1ea0;
+
+#[derive(Clone, Copy)]
+pub(crate) struct Registers {
+    pub a: u8,
+    pub f: Flags,
+    pub b: u8,
+    pub c: u8,
+    pub d: u8,
+    pub e: u8,
+    pub h: u8,
+    pub l: u8,
+}
+
+impl Registers {
+    fn apply<M: Model>(self, cpu: &mut Cpu<M>) {
+        cpu.a = self.a;
+        cpu.f = self.f;
+        cpu.b = self.b;
+        cpu.c = self.c;
+        cpu.d = self.d;
+        cpu.e = self.e;
+        cpu.h = self.h;
+        cpu.l = self.l;
+        cpu.sp = 0xfffe;
+        cpu.pc = 0x0100;
+    }
+
+    // the last instruction of the AGB boot ROM, the C flag is kept
+    fn inc_b(mut self) -> Self {
+        self.b = self.b.wrapping_add(1);
+        self.f &= Flags::C;
+        self.f.set(Flags::Z, self.b == 0);
+        self.f.set(Flags::H, self.b & 0x0f == 0);
+        self
+    }
+
+    // the CGB0 boot ROM leaves the same registers as the CGB one
+    fn for_cgb_revision(self, revision: CgbRevision) -> Self {
+        match revision {
+            CgbRevision::Cgb0 | CgbRevision::Cgb => self,
+            CgbRevision::Agb => self.inc_b(),
+        }
+    }
+}
+
+// The header check ends by adding the checksum to the sum of the other bytes, its opposite for a
+// valid header. Pan Docs rounds it to H and C set when the checksum isn't 0.
+fn get_header_flags(mbc: &(impl Mbc + ?Sized)) -> Flags {
+    let checksum = mbc.read(HEADER_CHECKSUM);
+    let mut flags = Flags::Z;
+    flags.set(Flags::H, checksum & 0x0f != 0);
+    flags.set(Flags::C, checksum != 0);
+    flags
+}
+
+impl DmgBootRegisters {
+    pub(crate) fn get_registers(self, mbc: &(impl Mbc + ?Sized)) -> Registers {
+        Registers {
+            a: self.a,
+            f: if self.header_flags {
+                get_header_flags(mbc)
+            } else {
+                Flags::empty()
+            },
+            b: self.b,
+            c: self.c,
+            d: self.d,
+            e: self.e,
+            h: self.h,
+            l: self.l,
+        }
+    }
+}
+
+// Each bit of the header logo is doubled horizontally and vertically, one byte makes 4 rows
+fn load_logo<M: Model>(emulator: &mut Emulator<M>, mbc: &mut (impl Mbc + ?Sized)) {
+    let mut address = LOGO_TILES;
+    for index in 0..48 {
+        let byte = mbc.read(LOGO + index);
+        for nibble in [byte >> 4, byte & 0x0f] {
+            let row = (0..4).fold(0, |row, bit| {
+                row | (((nibble >> bit) & 1) * 0b11) << (bit * 2)
+            });
+            for _ in 0..2 {
+                emulator.write_memory(address, row, mbc);
+                address += 2;
+            }
+        }
+    }
+    for (index, row) in (0..).zip(TRADEMARK) {
+        emulator.write_memory(TRADEMARK_TILE + index * 2, row, mbc);
+    }
+}
+
+// the logo in the middle of the first tile map, as the DMG boot ROM displays it
+fn load_logo_tile_map<M: Model>(emulator: &mut Emulator<M>, mbc: &mut (impl Mbc + ?Sized)) {
+    emulator.write_memory(0x9910, 0x19, mbc);
+    for tile in 1..=12 {
+        emulator.write_memory(0x9903 + u16::from(tile), tile, mbc);
+        emulator.write_memory(0x9923 + u16::from(tile), tile + 12, mbc);
+    }
+}
+
+// Shared by all the models, the boot ROM mapping is disabled at the end
+fn finish<M: Model>(
+    emulator: &mut Emulator<M>,
+    mbc: &mut (impl Mbc + ?Sized),
+    registers: Registers,
+    system_counter: u16,
+    has_boot_sound: bool,
+) {
+    emulator.write_memory(JOYPAD, 0x00, mbc);
+    emulator.write_memory(AUDIO_MASTER_CONTROL, 0x80, mbc);
+    emulator.write_memory(CH1_LENGTH_TIMER_AND_DUTY_CYCLE, 0x80, mbc);
+    if has_boot_sound {
+        // The sound has faded out but the channel 1 is still on. Its envelope is set at the
+        // maximum volume then the zombie mode brings it to 0 with the final register value.
+        emulator.write_memory(CH1_VOLUME_AND_ENVELOPE, 0xf8, mbc);
+        emulator.write_memory(CH1_PERIOD_LOW, 0xc1, mbc);
+        emulator.write_memory(CH1_PERIOD_HIGH_AND_CONTROL, 0x87, mbc);
+    }
+    emulator.write_memory(CH1_VOLUME_AND_ENVELOPE, 0xf3, mbc);
+    emulator.write_memory(MASTER_VOLUME_AND_VIN_PANNING, 0x77, mbc);
+    emulator.write_memory(SOUND_PANNING, 0xf3, mbc);
+    emulator.write_memory(BGP, 0xfc, mbc);
+    emulator.write_memory(LCD_CONTROL, 0x91, mbc);
+    emulator.ppu.skip_boot_rom();
+    emulator.write_memory(INTERRUPT_FLAG, 0xe1, mbc);
+    // the usual values are in T-cycles, the timer counts M-cycles
+    emulator.timer.set_system_counter(system_counter >> 2);
+    emulator.write_memory(BOOT_ROM_MAPPING_CONTROL, 0x01, mbc);
+    registers.apply(&mut emulator.cpu);
+}
+
+pub(crate) fn skip_dmg_boot_rom<M: Model>(
+    emulator: &mut Emulator<M>,
+    mbc: &mut (impl Mbc + ?Sized),
+    registers: Registers,
+    system_counter: u16,
+    has_boot_sound: bool,
+) {
+    load_logo(emulator, mbc);
+    load_logo_tile_map(emulator, mbc);
+    finish(emulator, mbc, registers, system_counter, has_boot_sound);
+}
+
+// https://gbdev.io/pandocs/Power_Up_Sequence.html#compatibility-palettes
+// The tables come from the boot ROM sources in boot-rom/cgb_boot.asm.
+const TITLE_CHECKSUMS: [u8; 94] = [
+    0x00, 0x88, 0x16, 0x36, 0xd1, 0xdb, 0xf2, 0x3c, 0x8c, 0x92, 0x3d, 0x5c, 0x58, 0xc9, 0x3e, 0x70,
+    0x1d, 0x59, 0x69, 0x19, 0x35, 0xa8, 0x14, 0xaa, 0x75, 0x95, 0x99, 0x34, 0x6f, 0x15, 0xff, 0x97,
+    0x4b, 0x90, 0x17, 0x10, 0x39, 0xf7, 0xf6, 0xa2, 0x49, 0x4e, 0x43, 0x68, 0xe0, 0x8b, 0xf0, 0xce,
+    0x0c, 0x29, 0xe8, 0xb7, 0x86, 0x9a, 0x52, 0x01, 0x9d, 0x71, 0x9c, 0xbd, 0x5d, 0x6d, 0x67, 0x3f,
+    0x6b, 0xb3, 0x46, 0x28, 0xa5, 0xc6, 0xd3, 0x27, 0x61, 0x18, 0x66, 0x6a, 0xbf, 0x0d, 0xf4, 0xb3,
+    0x46, 0x28, 0xa5, 0xc6, 0xd3, 0x27, 0x61, 0x18, 0x66, 0x6a, 0xbf, 0x0d, 0xf4, 0xb3,
+];
+// from this index, the 4th letter of the title must match too
+const FIRST_CHECKSUM_WITH_DUPLICATE: usize = 65;
+const DUPLICATES_4TH_LETTER: &[u8; 29] = b"BEFAARBEKEK R-URAR INAILICE R";
+// 0x80 means that the game needs the logo tile map
+const PALETTE_PER_CHECKSUM: [u8; 94] = [
+    0x00, 0x04, 0x05, 0x23, 0x22, 0x03, 0x1f, 0x0f, 0x0a, 0x05, 0x13, 0x24, 0x87, 0x25, 0x1e, 0x2c,
+    0x15, 0x20, 0x1f, 0x14, 0x05, 0x21, 0x0d, 0x0e, 0x05, 0x1d, 0x05, 0x12, 0x09, 0x03, 0x02, 0x1a,
+    0x19, 0x19, 0x29, 0x2a, 0x1a, 0x2d, 0x2a, 0x2d, 0x24, 0x26, 0x9a, 0x2a, 0x1e, 0x29, 0x22, 0x22,
+    0x05, 0x2a, 0x06, 0x05, 0x21, 0x19, 0x2a, 0x2a, 0x28, 0x02, 0x10, 0x19, 0x2a, 0x2a, 0x05, 0x00,
+    0x27, 0x24, 0x16, 0x19, 0x06, 0x20, 0x0c, 0x24, 0x0b, 0x27, 0x12, 0x27, 0x18, 0x1f, 0x32, 0x11,
+    0x2e, 0x06, 0x1b, 0x00, 0x2f, 0x29, 0x29, 0x00, 0x00, 0x13, 0x22, 0x17, 0x12, 0x1d,
+];
+// OBJ0, OBJ1 and BG, the index of the first color in COLORS. Some of them start in the middle
+// of a palette.
+const PALETTE_COMBINATIONS: [[u8; 3]; 51] = [
+    [16, 16, 116],
+    [72, 72, 72],
+    [80, 80, 80],
+    [96, 96, 96],
+    [36, 36, 36],
+    [0, 0, 0],
+    [108, 108, 108],
+    [20, 20, 20],
+    [48, 48, 48],
+    [104, 104, 104],
+    [64, 32, 32],
+    [16, 112, 112],
+    [16, 8, 8],
+    [12, 16, 16],
+    [16, 116, 116],
+    [112, 16, 112],
+    [8, 68, 8],
+    [64, 64, 32],
+    [16, 16, 28],
+    [16, 16, 72],
+    [16, 16, 80],
+    [76, 76, 36],
+    [15, 15, 44],
+    [68, 68, 8],
+    [16, 16, 8],
+    [16, 16, 12],
+    [112, 112, 0],
+    [12, 12, 0],
+    [0, 0, 4],
+    [72, 88, 72],
+    [80, 88, 80],
+    [96, 88, 96],
+    [64, 88, 32],
+    [68, 16, 52],
+    [111, 0, 56],
+    [111, 16, 60],
+    [76, 91, 36],
+    [64, 112, 40],
+    [16, 92, 112],
+    [68, 88, 8],
+    [16, 0, 8],
+    [16, 112, 12],
+    [112, 12, 0],
+    [12, 112, 16],
+    [84, 112, 16],
+    [12, 112, 0],
+    [100, 12, 112],
+    [0, 112, 32],
+    [16, 12, 112],
+    [112, 12, 24],
+    [16, 112, 116],
+];
+const COLORS: [[u16; 4]; 30] = [
+    [0x7fff, 0x32bf, 0x00d0, 0x0000],
+    [0x639f, 0x4279, 0x15b0, 0x04cb],
+    [0x7fff, 0x6e31, 0x454a, 0x0000],
+    [0x7fff, 0x1bef, 0x0200, 0x0000],
+    [0x7fff, 0x421f, 0x1cf2, 0x0000],
+    [0x7fff, 0x5294, 0x294a, 0x0000],
+    [0x7fff, 0x03ff, 0x012f, 0x0000],
+    [0x7fff, 0x03ef, 0x01d6, 0x0000],
+    [0x7fff, 0x42b5, 0x3dc8, 0x0000],
+    [0x7e74, 0x03ff, 0x0180, 0x0000],
+    [0x67ff, 0x77ac, 0x1a13, 0x2d6b],
+    [0x7ed6, 0x4bff, 0x2175, 0x0000],
+    [0x53ff, 0x4a5f, 0x7e52, 0x0000],
+    [0x4fff, 0x7ed2, 0x3a4c, 0x1ce0],
+    [0x03ed, 0x7fff, 0x255f, 0x0000],
+    [0x036a, 0x021f, 0x03ff, 0x7fff],
+    [0x7fff, 0x01df, 0x0112, 0x0000],
+    [0x231f, 0x035f, 0x00f2, 0x0009],
+    [0x7fff, 0x03ea, 0x011f, 0x0000],
+    [0x299f, 0x001a, 0x000c, 0x0000],
+    [0x7fff, 0x027f, 0x001f, 0x0000],
+    [0x7fff, 0x03e0, 0x0206, 0x0120],
+    [0x7fff, 0x7eeb, 0x001f, 0x7c00],
+    [0x7fff, 0x3fff, 0x7e00, 0x001f],
+    [0x7fff, 0x03ff, 0x001f, 0x0000],
+    [0x03ff, 0x001f, 0x000c, 0x0000],
+    [0x7fff, 0x033f, 0x0193, 0x0000],
+    [0x0000, 0x4200, 0x037f, 0x7fff],
+    [0x7fff, 0x7e8c, 0x7c00, 0x0000],
+    [0x7fff, 0x1bef, 0x6180, 0x0000],
+];
+
+// only the games published by Nintendo have a palette
+fn get_title_checksum(mbc: &(impl Mbc + ?Sized)) -> Option<u8> {
+    let is_nintendo = match mbc.read(OLD_LICENSEE_CODE) {
+        0x01 => true,
+        0x33 => mbc.read(NEW_LICENSEE_CODE) == b'0' && mbc.read(NEW_LICENSEE_CODE + 1) == b'1',
+        _ => false,
+    };
+    is_nintendo.then(|| (0..16).fold(0u8, |sum, index| sum.wrapping_add(mbc.read(TITLE + index))))
+}
+
+fn get_palette_index(mbc: &(impl Mbc + ?Sized), title_checksum: u8) -> u8 {
+    let fourth_letter = mbc.read(TITLE + 3);
+    TITLE_CHECKSUMS
+        .iter()
+        .enumerate()
+        .position(|(index, checksum)| {
+            *checksum == title_checksum
+                && index
+                    .checked_sub(FIRST_CHECKSUM_WITH_DUPLICATE)
+                    .is_none_or(|index| DUPLICATES_4TH_LETTER[index] == fourth_letter)
+        })
+        .map_or(0, |index| PALETTE_PER_CHECKSUM[index])
+}
+
+fn write_palette(
+    emulator: &mut Emulator<Cgb>,
+    mbc: &mut (impl Mbc + ?Sized),
+    data_register: u16,
+    first_color: u8,
+) {
+    for color in COLORS.as_flattened()[usize::from(first_color)..][..4].iter() {
+        for byte in color.to_le_bytes() {
+            emulator.write_memory(data_register, byte, mbc);
+        }
+    }
+}
+
+pub(crate) fn skip_cgb_boot_rom(
+    emulator: &mut Emulator<Cgb>,
+    mbc: &mut (impl Mbc + ?Sized),
+    system_counter: u16,
+) {
+    let cgb_flag = mbc.read(CGB_FLAG);
+    if cgb_flag & 0x80 != 0 {
+        // the background is white, the objects palettes are not initialized
+        emulator.write_memory(BCPS_BGPI, 0x80, mbc);
+        for _ in 0..32 {
+            emulator.write_memory(BCPD_BGPD, 0xff, mbc);
+            emulator.write_memory(BCPD_BGPD, 0x7f, mbc);
+        }
+        emulator.write_memory(DMG_COMPATIBILITY_MODE, cgb_flag, mbc);
+        let registers = Registers {
+            a: 0x11,
+            f: Flags::Z,
+            b: 0x00,
+            c: 0x00,
+            d: 0xff,
+            e: 0x56,
+            h: 0x00,
+            l: 0x0d,
+        };
+        let registers = registers.for_cgb_revision(emulator.revision);
+        finish(emulator, mbc, registers, system_counter, true);
+        return;
+    }
+
+    let title_checksum = get_title_checksum(mbc);
+    let palette_index = title_checksum.map_or(0, |checksum| get_palette_index(mbc, checksum));
+    let needs_tile_map = palette_index & 0x80 != 0;
+    let [obj0, obj1, bg] = PALETTE_COMBINATIONS[usize::from(palette_index & 0x7f)];
+    emulator.write_memory(OCPS_OGPI, 0x80, mbc);
+    write_palette(emulator, mbc, OCPD_OGPD, obj0);
+    write_palette(emulator, mbc, OCPD_OGPD, obj1);
+    emulator.write_memory(BCPS_BGPI, 0x80, mbc);
+    write_palette(emulator, mbc, BCPD_BGPD, bg);
+
+    load_logo(emulator, mbc);
+    if needs_tile_map {
+        load_logo_tile_map(emulator, mbc);
+    }
+    emulator.write_memory(DMG_COMPATIBILITY_MODE, 0x04, mbc);
+    emulator.write_memory(OBJECT_PRIORITY_MODE, 0x01, mbc);
+    let [h, l] = if needs_tile_map {
+        [0x99, 0x1a]
+    } else {
+        [0x00, 0x7c]
+    };
+    let registers = Registers {
+        a: 0x11,
+        f: Flags::Z,
+        b: title_checksum.unwrap_or(0),
+        c: 0x00,
+        d: 0x00,
+        e: 0x08,
+        h,
+        l,
+    };
+    let registers = registers.for_cgb_revision(emulator.revision);
+    finish(emulator, mbc, registers, system_counter, true);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Cgb, Dmg, Emulator, EmulatorExt, Model, mbc::Mbc1, revision::CgbRevision};
+
+    // the Nintendo logo
+    const LOGO: [u8; 48] = [
+        0xce, 0xed, 0x66, 0x66, 0xcc, 0x0d, 0x00, 0x0b, 0x03, 0x73, 0x00, 0x83, 0x00, 0x0c, 0x00,
+        0x0d, 0x00, 0x08, 0x11, 0x1f, 0x88, 0x89, 0x00, 0x0e, 0xdc, 0xcc, 0x6e, 0xe6, 0xdd, 0xdd,
+        0xd9, 0x99, 0xbb, 0xbb, 0x67, 0x63, 0x6e, 0x0e, 0xec, 0xcc, 0xdd, 0xdc, 0x99, 0x9f, 0xbb,
+        0xb9, 0x33, 0x3e,
+    ];
+
+    fn get_rom(title: &[u8]) -> [u8; 0x8000] {
+        let mut rom = [0; 0x8000];
+        rom[0x104..0x134].copy_from_slice(&LOGO);
+        rom[0x134..][..title.len()].copy_from_slice(title);
+        // Nintendo
+        rom[0x14b] = 0x01;
+        rom[0x14d] = 0x42;
+        rom
+    }
+
+    #[test]
+    fn dmg() {
+        let mut rom = get_rom(b"TETRIS");
+        // ld a, 0x42; jr -2
+        rom[0x100..0x104].copy_from_slice(&[0x3e, 0x42, 0x18, 0xfc]);
+        let mut mbc = Mbc1::new(rom.as_slice());
+        let mut emulator = Emulator::<Dmg>::without_boot_rom(&mut mbc);
+        let cpu = emulator.get_cpu();
+        assert_eq!(
+            [
+                cpu.a,
+                cpu.f.bits(),
+                cpu.b,
+                cpu.c,
+                cpu.d,
+                cpu.e,
+                cpu.h,
+                cpu.l
+            ],
+            [0x01, 0xb0, 0x00, 0x13, 0x00, 0xd8, 0x01, 0x4d]
+        );
+        assert_eq!(emulator.get_timer().get_div(), 0xab);
+        assert_eq!(emulator.get_ppu().get_ly(), 0);
+        assert_eq!(emulator.get_ppu().get_lcd_status().bits(), 0x05);
+        assert_eq!(emulator.get_apu().get_nr52(0), 0xf1);
+        // the top of the N
+        assert_eq!(
+            emulator.get_ppu().get_vram().get_inner()[0x10..0x14],
+            [0xf0, 0, 0xf0, 0]
+        );
+        assert_eq!(emulator.get_ppu().get_vram().get_inner()[0x1904], 0x01);
+
+        for _ in 0..10 {
+            emulator.execute(&mut mbc);
+        }
+        assert_eq!(emulator.get_cpu().a, 0x42);
+    }
+
+    #[test]
+    fn cgb_compatibility_palettes() {
+        let rom = get_rom(b"TETRIS");
+        let mut emulator = Emulator::<Cgb>::without_boot_rom(&mut Mbc1::new(rom.as_slice()));
+        let cpu = emulator.get_cpu();
+        assert_eq!(
+            [cpu.a, cpu.b, cpu.d, cpu.e, cpu.h, cpu.l],
+            [0x11, 0xdb, 0, 0x08, 0, 0x7c]
+        );
+        let palettes = emulator.get_ppu().get_color_palettes();
+        // the combination 3
+        assert_eq!(
+            palettes.background.get_palette(0),
+            [0x7fff, 0x03ff, 0x001f, 0x0000]
+        );
+        assert!(emulator.get_ppu().get_dmg_mode().is_dmg_compatible());
+
+        // not published by Nintendo
+        let mut rom = get_rom(b"TETRIS");
+        rom[0x14b] = 0x02;
+        emulator = Emulator::<Cgb>::without_boot_rom(&mut Mbc1::new(rom.as_slice()));
+        let palettes = emulator.get_ppu().get_color_palettes();
+        assert_eq!(
+            palettes.background.get_palette(0),
+            [0x7fff, 0x1bef, 0x6180, 0x0000]
+        );
+        assert_eq!(emulator.get_cpu().b, 0);
+    }
+
+    #[test]
+    fn cgb() {
+        let mut rom = get_rom(b"GAME");
+        rom[0x143] = 0x80;
+        let emulator = Emulator::<Cgb>::without_boot_rom(&mut Mbc1::new(rom.as_slice()));
+        let cpu = emulator.get_cpu();
+        assert_eq!(
+            [
+                cpu.a,
+                cpu.f.bits(),
+                cpu.b,
+                cpu.c,
+                cpu.d,
+                cpu.e,
+                cpu.h,
+                cpu.l
+            ],
+            [0x11, 0x80, 0x00, 0x00, 0xff, 0x56, 0x00, 0x0d]
+        );
+        assert!(!emulator.get_ppu().get_dmg_mode().is_dmg_compatible());
+        let palettes = emulator.get_ppu().get_color_palettes();
+        assert_eq!(palettes.background.get_palette(7), [0x7fff; 4]);
+
+        let mut emulator = Emulator::<Cgb>::with_revision(CgbRevision::Agb);
+        Cgb::skip_boot_rom(&mut emulator, &mut Mbc1::new(rom.as_slice()));
+        let cpu = emulator.get_cpu();
+        assert_eq!([cpu.a, cpu.f.bits(), cpu.b], [0x11, 0x00, 0x01]);
+    }
+}
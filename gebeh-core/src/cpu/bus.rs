@@ -62,7 +62,8 @@ impl<M: Model> Cpu<M> {
             0xFF71..HRAM => 0xff,
             HRAM..INTERRUPT_ENABLE => self.hram[usize::from(index - HRAM)],
             INTERRUPT_ENABLE => self.interrupt_enable.bits(),
-            _ => todo!("Reading ${index:04x} from internal bus"),
+            // open bus
+            _ => 0xff,
         }
     }
     pub fn write(
@@ -124,7 +125,13 @@ impl<M: Model> Cpu<M> {
             SPEED => self.speed_switch.write_value(value),
             0xff4e => {}
             VRAM_BANK => peripherals.ppu.get_vram_mut().write_bank(value),
-            BOOT_ROM_MAPPING_CONTROL => self.boot_rom_mapping_control |= value != 0,
+            BOOT_ROM_MAPPING_CONTROL => {
+                if value != 0 {
+                    self.boot_rom_mapping_control = true;
+                    // it can't be mapped again, the dump is not kept in the save states
+                    self.boot_rom = None;
+                }
+            }
             HDMA_SOURCE_HIGH => peripherals.hdma.write_source_address_high(value),
             HDMA_SOURCE_LOW => peripherals.hdma.write_source_address_low(value),
             HDMA_DESTINATION_HIGH => peripherals.hdma.write_destination_address_high(value),
@@ -4,7 +4,7 @@ use super::instructions::{
 };
 use crate::Model;
 use crate::cpu::speed_switch::SpeedSwitch;
-use crate::cpu::{Cpu, Flags};
+use crate::cpu::{Cpu, Flags, SPEED_SWITCH_DURATION, StopMode};
 use crate::{Peripherals, interrupts::Interrupts, mbc::Mbc};
 
 fn is_half_carry(a: u8, b: u8, result: u8) -> bool {
@@ -488,9 +488,29 @@ impl<M: Model> Cpu<M> {
                 flags.set(Flags::C, carry == 1);
             }
             NoRead(Stop) => {
-                // let's ignore the stop mode
-                self.speed_switch.trigger();
+                // https://gbdev.io/pandocs/Reducing_Power_Consumption.html#using-the-stop-instruction
+                let is_button_held = peripherals.joypad.get_register() & 0x0f != 0x0f;
+                let is_interrupt_pending = !interrupts_to_execute.is_empty();
+                // STOP is a 2-byte opcode if no interrupt is pending, the second byte is skipped
+                if !is_interrupt_pending {
+                    self.pc = self.pc.wrapping_add(1);
+                }
+                if is_button_held {
+                    // the div register is not reset and the mode doesn't change
+                    // except if HALT is entered
+                    self.is_halted = !is_interrupt_pending;
+                } else {
+                    peripherals.timer.reset_system_counter();
+                    self.stop_mode = if self.speed_switch.trigger() {
+                        // the case where IME is set with a pending interrupt is not deterministic
+                        // on real hardware so it is handled like the others.
+                        StopMode::SwitchingSpeed(SPEED_SWITCH_DURATION)
+                    } else {
+                        StopMode::Stopped
+                    };
+                }
             }
+            NoRead(LockUp) => self.is_locked_up = true,
             NoRead(WriteLsbSpToCachedAddressAndIncCachedAddress) => {
                 let [_, lsb] = self.sp.to_be_bytes();
                 let wz = u16::from_be_bytes([self.msb, self.lsb]);
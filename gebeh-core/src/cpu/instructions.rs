@@ -1,6 +1,7 @@
 use arrayvec::ArrayVec;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Register8Bit {
     A,
     B,
@@ -16,7 +17,7 @@ pub enum Register8Bit {
     Z,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Register16Bit {
     AF,
     BC,
@@ -53,19 +54,19 @@ impl Register16Bit {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum Flag {
     Z,
     C,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct Condition {
     pub flag: Flag,
     pub not: bool,
 }
 
-#[derive(Clone, Copy, Default, Debug)]
+#[derive(Clone, Copy, Default, Debug, Serialize, Deserialize)]
 pub enum NoReadInstruction {
     #[default]
     Nop,
@@ -125,6 +126,9 @@ pub enum NoReadInstruction {
     Rlca,
     // https://gist.github.com/SonoSooS/c0055300670d678b5ae8433e20bea595#nop-and-stop
     Stop,
+    // https://gbdev.io/pandocs/CPU_Instruction_Set.html
+    // the illegal opcodes hard-lock the cpu
+    LockUp,
     WriteLsbSpToCachedAddressAndIncCachedAddress,
     WriteMsbSpToCachedAddress,
     AddSpE,
@@ -154,7 +158,7 @@ pub enum NoReadInstruction {
     CbMode,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum ReadInstruction {
     ReadIntoLsb,
     ReadIntoMsb,
@@ -163,14 +167,14 @@ pub enum ReadInstruction {
     ConditionalJump(Condition),
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum OpAfterRead {
     None,
     Inc,
     Dec,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum ReadAddress {
     Register {
         register: Register16Bit,
@@ -200,7 +204,7 @@ pub const POP_SP: ReadAddress = ReadAddress::Register {
     op: OpAfterRead::Inc,
 };
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum Instruction {
     NoRead(NoReadInstruction),
     Read(ReadAddress, ReadInstruction),
@@ -233,13 +237,13 @@ pub fn vec<const N: usize, const O: usize>(insts: [Instruction; N]) -> ArrayVec<
 }
 
 // what to set pc with after the last instruction
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub enum SetPc {
     WithIncrement(Register16Bit),
     NoIncrement, // Halt https://gist.github.com/SonoSooS/c0055300670d678b5ae8433e20bea595#halt
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Prefetch {
     pub check_interrupts: bool, // false only for DI and CB
     pub set_pc: SetPc,
@@ -1094,7 +1098,9 @@ pub fn get_instructions(opcode: u8, is_cb_mode: bool) -> InstructionsAndSetPc {
         0xfb => (Ei.into(), Default::default()),
         0xfe => (Read(CONSUME_PC, ReadIntoLsb), vec([Cp8Bit(Z).into()])),
         0xff => rst_n(0x38),
-        _ => panic!("Opcode not implemented: 0x{opcode:02x}"),
+        0xd3 | 0xdb | 0xdd | 0xe3 | 0xe4 | 0xeb | 0xec | 0xed | 0xf4 | 0xfc | 0xfd => {
+            (LockUp.into(), Default::default())
+        }
     }
     .into()
 }
@@ -1,5 +1,7 @@
 mod bus;
 mod execute_instruction;
+use serde::{Deserialize, Serialize};
+
 pub mod instructions;
 pub mod speed_switch;
 
@@ -8,6 +10,7 @@ use crate::{
     interrupts::Interrupts, mbc::Mbc,
 };
 use arrayvec::ArrayVec;
+use core::ops::Deref;
 use instructions::{
     AfterReadInstruction, Instruction, InstructionsAndSetPc, NoReadInstruction, OpAfterRead,
     Prefetch, ReadAddress, Register8Bit, Register16Bit, SetPc, get_instructions, vec,
@@ -31,7 +34,29 @@ pub const BOOTIX_BOOT_ROM: [u8; 256] = [
     0, 0, 0, 0, 0, 0, 0, 0, 0, 62, 1, 224, 80,
 ];
 
-#[derive(Clone)]
+// the CGB boot ROM is the biggest, mapped at 0x0000-0x00ff and 0x0200-0x08ff
+pub const BOOT_ROM_MAX_SIZE: usize = 0x900;
+
+/// A boot ROM dump supplied by the frontend, it replaces the built-in one.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BootRom(ArrayVec<u8, BOOT_ROM_MAX_SIZE>);
+
+impl BootRom {
+    /// Returns `None` if the dump is bigger than the CGB boot ROM.
+    pub fn new(dump: &[u8]) -> Option<Self> {
+        ArrayVec::try_from(dump).ok().map(Self)
+    }
+}
+
+impl Deref for BootRom {
+    type Target = [u8];
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
 pub struct Cpu<M: Model> {
     pub sp: u16,
     pub lsb: u8,
@@ -50,18 +75,36 @@ pub struct Cpu<M: Model> {
     pub ime: bool,
     old_ime: bool,
     pub is_halted: bool,
+    pub stop_mode: StopMode,
+    // set by an illegal opcode, only a reset can unlock the cpu
+    pub is_locked_up: bool,
     // test purposes
     pub current_opcode: u8,
     pub is_dispatching_interrupt: bool,
     pub interrupt_enable: Interrupts,
+    #[serde(with = "crate::state::byte_array")]
     pub hram: [u8; 0x7f],
     pub boot_rom_mapping_control: bool,
-    pub boot_rom: &'static [u8],
+    // the built-in boot ROM of the model when None, dropped once unmapped
+    pub boot_rom: Option<BootRom>,
     pub speed_switch: M::SpeedSwitch,
 }
 
+// https://gbdev.io/pandocs/Reducing_Power_Consumption.html#using-the-stop-instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StopMode {
+    Running,
+    // The system clock is stopped until a selected joypad line goes low
+    Stopped,
+    // The cpu and the timer are paused during the CGB speed switch, the value is the remaining m-cycles
+    SwitchingSpeed(u16),
+}
+
+// https://gbdev.io/pandocs/CGB_Registers.html#ff4d--key1spd-cgb-mode-only-prepare-speed-switch
+pub const SPEED_SWITCH_DURATION: u16 = 2050;
+
 bitflags::bitflags! {
-    #[derive(Debug, Clone, Default, Copy, PartialEq, Eq)]
+    #[derive(Debug, Clone, Default, Copy, PartialEq, Eq, Serialize, Deserialize)]
     pub struct Flags: u8 {
         const Z = 1 << 7;
         const N = 1 << 6;
@@ -79,8 +122,8 @@ bitflags::bitflags! {
 // Pour l'instant, il semble que les écritures/lectures du CPU sont toujours traités à la fin d'un cycle.
 // Par exemple, il écrase les modif du timer pendant le cycle courant, et il a conscience des changements immédiats du ppu
 
-impl<M: Model> Cpu<M> {
-    pub fn new(boot_rom: &'static [u8]) -> Self {
+impl<M: Model> Default for Cpu<M> {
+    fn default() -> Self {
         Self {
             sp: Default::default(),
             lsb: Default::default(),
@@ -101,15 +144,23 @@ impl<M: Model> Cpu<M> {
             ime: false,
             old_ime: false,
             is_halted: Default::default(),
+            stop_mode: StopMode::Running,
+            is_locked_up: false,
             current_opcode: 0,
             is_dispatching_interrupt: false,
             interrupt_enable: Interrupts::empty(),
             hram: [0; 0x7f],
             boot_rom_mapping_control: false,
-            boot_rom,
+            boot_rom: None,
             speed_switch: Default::default(),
         }
     }
+}
+
+impl<M: Model> Cpu<M> {
+    pub fn is_system_clock_stopped(&self) -> bool {
+        self.stop_mode != StopMode::Running
+    }
     fn get_8bit_register(&self, register: Register8Bit) -> u8 {
         match register {
             Register8Bit::A => self.a,
@@ -172,16 +223,20 @@ impl<M: Model> Cpu<M> {
         self.set_8bit_register(register.get_lsb(), lsb);
     }
 
-    fn read(
+    pub(crate) fn read(
         &self,
         index: u16,
         peripherals: PeripheralsRef<impl Mbc + ?Sized, M>,
         cycles: u64,
     ) -> u8 {
         // https://gbdev.io/pandocs/Power_Up_Sequence.html#size
+        let boot_rom = self.boot_rom.as_deref().unwrap_or(M::BOOT_ROM);
         match index {
-            ..0x100 | 0x200..0x900 if !self.boot_rom_mapping_control => {
-                self.boot_rom[usize::from(index)]
+            // the DMG boot ROM is only 256 bytes long
+            ..0x100 | 0x200..0x900
+                if !self.boot_rom_mapping_control && usize::from(index) < boot_rom.len() =>
+            {
+                boot_rom[usize::from(index)]
             }
             ..OAM => external_bus_read(
                 index,
@@ -198,6 +253,25 @@ impl<M: Model> Cpu<M> {
         mut peripherals: Peripherals<impl Mbc + ?Sized, M>,
         cycle_count: u64,
     ) {
+        // no more fetches and the interrupts are ignored
+        if self.is_locked_up {
+            peripherals
+                .ppu
+                .execute_dma(peripherals.mbc, peripherals.wram, cycle_count);
+            return;
+        }
+
+        if let StopMode::SwitchingSpeed(remaining) = &mut self.stop_mode {
+            *remaining -= 1;
+            if *remaining == 0 {
+                self.stop_mode = StopMode::Running;
+            }
+            peripherals
+                .ppu
+                .execute_dma(peripherals.mbc, peripherals.wram, cycle_count);
+            return;
+        }
+
         let interrupts_to_execute =
             Interrupts::from_bits_truncate(self.interrupt_enable.bits()) & *peripherals.interrupts;
         // Peripherals interrupts are not handled the same cycle they are triggered.
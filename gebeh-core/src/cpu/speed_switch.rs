@@ -1,13 +1,17 @@
 // https://gbdev.io/pandocs/CGB_Registers.html#ff4d--key1spd-cgb-mode-only-prepare-speed-switch
 
+use serde::{Deserialize, Serialize};
+
 pub trait SpeedSwitch: Default + Clone + Send + Sync {
     fn write_value(&mut self, value: u8);
     fn read_value(&self) -> u8;
-    fn trigger(&mut self);
+    // returns true if the speed has been switched
+    fn trigger(&mut self) -> bool;
+    fn is_double_speed(&self) -> bool;
 }
 
 bitflags::bitflags! {
-    #[derive(Debug, Clone, Copy, Default)]
+    #[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
     pub struct CgbSpeedSwitch: u8 {
         const DOUBLE_SPEED = 1 << 7;
         const ARMED = 1;
@@ -21,7 +25,13 @@ impl SpeedSwitch for () {
         0xff
     }
 
-    fn trigger(&mut self) {}
+    fn trigger(&mut self) -> bool {
+        false
+    }
+
+    fn is_double_speed(&self) -> bool {
+        false
+    }
 }
 
 impl SpeedSwitch for CgbSpeedSwitch {
@@ -33,10 +43,16 @@ impl SpeedSwitch for CgbSpeedSwitch {
         self.bits() | 0b0111_1110
     }
 
-    fn trigger(&mut self) {
-        if self.contains(CgbSpeedSwitch::ARMED) {
-            self.toggle(CgbSpeedSwitch::DOUBLE_SPEED);
-            self.remove(CgbSpeedSwitch::ARMED);
+    fn trigger(&mut self) -> bool {
+        if !self.contains(CgbSpeedSwitch::ARMED) {
+            return false;
         }
+        self.toggle(CgbSpeedSwitch::DOUBLE_SPEED);
+        self.remove(CgbSpeedSwitch::ARMED);
+        true
+    }
+
+    fn is_double_speed(&self) -> bool {
+        self.contains(CgbSpeedSwitch::DOUBLE_SPEED)
     }
 }
@@ -1,5 +1,7 @@
+use serde::{Deserialize, Serialize};
+
 bitflags::bitflags! {
-    #[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+    #[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
     pub struct Interrupts: u8 {
         const VBLANK = 1;
         const LCD = 1 << 1;
@@ -1,5 +1,7 @@
+use serde::{Deserialize, Serialize};
+
 bitflags::bitflags! {
-    #[derive(Debug, Clone, Copy,  PartialEq, Eq)]
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
     struct JoypadFlags: u8 {
         const NOT_BUTTONS = 1 << 5;
         const NOT_DPAD = 1 << 4;
@@ -10,7 +12,7 @@ bitflags::bitflags! {
     }
 }
 
-#[derive(Clone, Default, Copy, PartialEq, Eq, Debug)]
+#[derive(Clone, Default, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct JoypadInput {
     pub a: bool,
     pub b: bool,
@@ -22,55 +24,141 @@ pub struct JoypadInput {
     pub down: bool,
 }
 
-#[derive(Clone, Default, Copy)]
+// For the cartridges with an accelerometer (MBC7). In g, 0 when the console is flat,
+// x is positive when the right side is lowered and y when the bottom side is lowered.
+#[derive(Clone, Default, Copy, PartialEq, Debug)]
+pub struct Tilt {
+    pub x: f32,
+    pub y: f32,
+}
+
+pub const SGB_PACKET_SIZE: usize = 16;
+
+// The Super Game Boy packets are sent bit by bit with P14 and P15. Both low resets the transfer,
+// then each bit is a pulse on P14 (0) or P15 (1) followed by both high. A 0 ends the packet.
+// https://gbdev.io/pandocs/SGB_Command_Packet.html
+#[derive(Clone, Default, Copy, Serialize, Deserialize)]
+struct PacketReceiver {
+    lines: JoypadFlags,
+    // None when no reset pulse has been seen
+    bit_index: Option<u8>,
+    packet: [u8; SGB_PACKET_SIZE],
+    is_complete: bool,
+}
+
+impl PacketReceiver {
+    fn write(&mut self, lines: JoypadFlags) {
+        let previous_lines = core::mem::replace(&mut self.lines, lines);
+        if lines.is_empty() {
+            self.bit_index = Some(0);
+            self.packet = [0; _];
+            return;
+        }
+        let both_high = JoypadFlags::NOT_BUTTONS | JoypadFlags::NOT_DPAD;
+        if previous_lines != both_high || lines == both_high {
+            return;
+        }
+        let Some(index) = self.bit_index else {
+            return;
+        };
+        let bit = lines == JoypadFlags::NOT_DPAD;
+        let index = usize::from(index);
+        if index == SGB_PACKET_SIZE * 8 {
+            self.bit_index = None;
+            self.is_complete = !bit;
+            return;
+        }
+        self.packet[index / 8] |= u8::from(bit) << (index % 8);
+        self.bit_index = Some(u8::try_from(index + 1).unwrap());
+    }
+}
+
+#[derive(Clone, Default, Copy, Serialize, Deserialize)]
 pub struct Joypad {
     pub input: JoypadInput,
     pub is_dpad_selected: bool,
     pub is_buttons_selected: bool,
+    packet_receiver: PacketReceiver,
+    // Super Game Boy multiplayer (MLT_REQ), 0 means 1 player
+    players: u8,
+    player: u8,
 }
 
 impl Joypad {
     pub fn set_register(&mut self, value: u8) {
         let value = JoypadFlags::from_bits_retain(value);
+        let lines = value & (JoypadFlags::NOT_BUTTONS | JoypadFlags::NOT_DPAD);
+        // releasing P15 selects the next joypad
+        if self.players > 1
+            && !self
+                .packet_receiver
+                .lines
+                .contains(JoypadFlags::NOT_BUTTONS)
+            && lines.contains(JoypadFlags::NOT_BUTTONS)
+        {
+            self.player = (self.player + 1) % self.players;
+        }
+        self.packet_receiver.write(lines);
         self.is_dpad_selected = !value.contains(JoypadFlags::NOT_DPAD);
         self.is_buttons_selected = !value.contains(JoypadFlags::NOT_BUTTONS);
     }
+    // only the Super Game Boy listens to the packets
+    pub(crate) fn take_packet(&mut self) -> Option<[u8; SGB_PACKET_SIZE]> {
+        if !core::mem::take(&mut self.packet_receiver.is_complete) {
+            return None;
+        }
+        Some(self.packet_receiver.packet)
+    }
+    pub(crate) fn set_players(&mut self, players: u8) {
+        self.players = players;
+        self.player = 0;
+    }
     pub fn get_register(&self) -> u8 {
         let mut value = JoypadFlags::empty()
             | JoypadFlags::NOT_START_DOWN
             | JoypadFlags::NOT_SELECT_UP
             | JoypadFlags::NOT_B_LEFT
             | JoypadFlags::NOT_A_RIGHT;
+        // the other players have no buttons pressed
+        let input = if self.player == 0 {
+            self.input
+        } else {
+            JoypadInput::default()
+        };
         if self.is_dpad_selected {
-            if self.input.down {
+            if input.down {
                 value.remove(JoypadFlags::NOT_START_DOWN);
             }
-            if self.input.up {
+            if input.up {
                 value.remove(JoypadFlags::NOT_SELECT_UP);
             }
-            if self.input.left {
+            if input.left {
                 value.remove(JoypadFlags::NOT_B_LEFT);
             }
-            if self.input.right {
+            if input.right {
                 value.remove(JoypadFlags::NOT_A_RIGHT);
             }
         }
         if self.is_buttons_selected {
-            if self.input.start {
+            if input.start {
                 value.remove(JoypadFlags::NOT_START_DOWN);
             }
-            if self.input.select {
+            if input.select {
                 value.remove(JoypadFlags::NOT_SELECT_UP);
             }
-            if self.input.b {
+            if input.b {
                 value.remove(JoypadFlags::NOT_B_LEFT);
             }
-            if self.input.a {
+            if input.a {
                 value.remove(JoypadFlags::NOT_A_RIGHT);
             }
         }
         value.set(JoypadFlags::NOT_DPAD, !self.is_dpad_selected);
         value.set(JoypadFlags::NOT_BUTTONS, !self.is_buttons_selected);
+        // the ID of the current joypad is read when no line is selected
+        if self.players > 1 && !self.is_dpad_selected && !self.is_buttons_selected {
+            return (value.bits() & 0xf0) | (0x0f - self.player) | 0b11000000;
+        }
         value.bits() | 0b11000000 // unused bits return 1
     }
 }
@@ -2,11 +2,12 @@
 #![forbid(unsafe_code)]
 
 use arrayvec::ArrayVec;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
 
 use crate::{
     apu::Apu,
     cpu::{
-        BOOTIX_BOOT_ROM, CGB_BOOT_ROM, Cpu,
+        BootRom, CGB_BOOT_ROM, Cpu, StopMode,
         speed_switch::{CgbSpeedSwitch, SpeedSwitch},
     },
     interrupts::Interrupts,
@@ -18,29 +19,38 @@ use crate::{
         dmg_mode::{DmgMode, DmgModeRegs},
         hdma::{Hdma, HdmaRegs},
         renderer::{CgbRenderer, DmgRenderer, Renderer},
-        scanline::{DmgScanlineBuilder, ScanlineBuilder},
+        scanline::{CgbScanline, DmgScanlineBuilder, ScanlineBuilder},
         vram::{CgbVram, DmgVram, VramRegs},
     },
+    revision::{CgbRevision, DMG_BOOT_ROM, DmgRevision, Revision, SGB_BOOT_ROM, SgbRevision},
     serial::{CgbSerial, DmgSerial, Serial},
+    sgb::SuperGameBoy,
     timer::Timer,
     wram::{CgbWram, DmgWram, Wram},
 };
 
 pub mod addresses;
 pub mod apu;
+mod boot;
 pub mod cpu;
 pub mod external_bus;
 pub mod interrupts;
 pub mod joypad;
 pub mod mbc;
+pub mod power_on;
 pub mod ppu;
+pub mod revision;
 pub mod serial;
+pub mod sgb;
+mod state;
 pub mod timer;
 pub mod wram;
 
 pub trait Ram: Default + Clone + Send + Sync {
     fn read(&self, index: u16) -> u8;
     fn write(&mut self, index: u16, value: u8);
+    // every bank, to initialize the memory at power-on
+    fn get_banks_mut(&mut self) -> &mut [u8];
 }
 
 pub struct Peripherals<'a, M: Mbc + ?Sized, Mo: Model> {
@@ -89,26 +99,54 @@ pub const HEIGHT: u8 = 144;
 pub const SYSTEM_CLOCK_FREQUENCY: u32 = 4194304 / 4;
 
 pub trait Model: Clone + 'static {
-    type Renderer: Renderer<Self>;
-    type StatRegisterHandler: StatRegisterHandler;
-    type Wram: Wram;
-    type HdmaRegs: HdmaRegs;
-    type SpeedSwitch: SpeedSwitch;
-    type Serial: Serial;
-    type Vram: VramRegs;
-    type ColorPalettes: ColorPalettesRegs;
-    type ScanlineBuilder: ScanlineBuilder;
-    type DmgMode: DmgModeRegs;
+    type Renderer: Renderer<Self> + Serialize + DeserializeOwned;
+    type StatRegisterHandler: StatRegisterHandler + Serialize + DeserializeOwned;
+    type Wram: Wram + Serialize + DeserializeOwned;
+    type HdmaRegs: HdmaRegs + Serialize + DeserializeOwned;
+    type SpeedSwitch: SpeedSwitch + Serialize + DeserializeOwned;
+    type Serial: Serial + Serialize + DeserializeOwned;
+    type Vram: VramRegs + Serialize + DeserializeOwned;
+    type ColorPalettes: ColorPalettesRegs + Serialize + DeserializeOwned;
+    type ScanlineBuilder: ScanlineBuilder + Serialize + DeserializeOwned;
+    type DmgMode: DmgModeRegs + Serialize + DeserializeOwned;
+    type SuperGameBoy: Clone + Default + Send + Sync + Serialize + DeserializeOwned;
+    type Revision: Revision;
+    const BOOT_ROM: &'static [u8];
+    // written in the save states
+    const NAME: &'static str;
     fn execute<M: Mbc + ?Sized>(emulator: &mut Emulator<Self>, mbc: &mut M) -> Option<u8>
     where
         Self: Sized;
     fn get_emulator() -> Emulator<Self>;
+    // the state the boot ROM leaves when it jumps to 0x0100
+    fn skip_boot_rom<M: Mbc + ?Sized>(emulator: &mut Emulator<Self>, mbc: &mut M)
+    where
+        Self: Sized;
+    // the colors as the screen of the revision shows them
+    fn get_screen_scanline(
+        _: Self::Revision,
+        scanline: &<Self::ScanlineBuilder as ScanlineBuilder>::Scanline,
+    ) -> <Self::ScanlineBuilder as ScanlineBuilder>::Scanline {
+        *scanline
+    }
 }
 
 #[derive(Clone)]
 pub struct Dmg;
 #[derive(Clone)]
 pub struct Cgb;
+// a DMG with a SNES around it
+#[derive(Clone)]
+pub struct Sgb;
+
+// the models built around the DMG CPU and PPU
+pub trait DmgModel:
+    Model<HdmaRegs = (), ScanlineBuilder = DmgScanlineBuilder, Vram = DmgVram>
+{
+}
+
+impl DmgModel for Dmg {}
+impl DmgModel for Sgb {}
 
 impl Model for Dmg {
     type Renderer = DmgRenderer;
@@ -121,13 +159,17 @@ impl Model for Dmg {
     type ColorPalettes = ();
     type ScanlineBuilder = DmgScanlineBuilder;
     type DmgMode = ();
+    type SuperGameBoy = ();
+    type Revision = DmgRevision;
+    const BOOT_ROM: &'static [u8] = &DMG_BOOT_ROM;
+    const NAME: &'static str = "DMG";
     fn execute<M: Mbc + ?Sized>(emulator: &mut Emulator<Self>, mbc: &mut M) -> Option<u8> {
-        emulator.execute(mbc)
+        emulator.dmg_execute(mbc)
     }
     fn get_emulator() -> Emulator<Self> {
         Emulator {
             ppu: Default::default(),
-            cpu: Cpu::new(&BOOTIX_BOOT_ROM),
+            cpu: Cpu::default(),
             interrupts: Interrupts::default(),
             timer: Timer::default(),
             joypad: Joypad::default(),
@@ -136,8 +178,61 @@ impl Model for Dmg {
             wram: DmgWram::default(),
             cycles: 0,
             hdma: (),
+            sgb: (),
+            revision: Default::default(),
         }
     }
+    fn skip_boot_rom<M: Mbc + ?Sized>(emulator: &mut Emulator<Self>, mbc: &mut M) {
+        let registers = emulator.revision.get_boot_registers().get_registers(mbc);
+        let system_counter = match emulator.revision {
+            DmgRevision::Dmg0 => boot::DMG0_SYSTEM_COUNTER,
+            DmgRevision::Dmg | DmgRevision::Mgb => boot::DMG_SYSTEM_COUNTER,
+        };
+        boot::skip_dmg_boot_rom(emulator, mbc, registers, system_counter, true);
+    }
+}
+
+impl Model for Sgb {
+    type Renderer = DmgRenderer;
+    type StatRegisterHandler = StatInterruptWriteQuirk;
+    type Wram = DmgWram;
+    type HdmaRegs = ();
+    type SpeedSwitch = ();
+    type Serial = DmgSerial;
+    type Vram = DmgVram;
+    type ColorPalettes = ();
+    type ScanlineBuilder = DmgScanlineBuilder;
+    type DmgMode = ();
+    type SuperGameBoy = SuperGameBoy;
+    type Revision = SgbRevision;
+    const BOOT_ROM: &'static [u8] = &SGB_BOOT_ROM;
+    const NAME: &'static str = "SGB";
+    fn execute<M: Mbc + ?Sized>(emulator: &mut Emulator<Self>, mbc: &mut M) -> Option<u8> {
+        let master_serial_byte = emulator.dmg_execute(mbc);
+        emulator.sgb_execute();
+        master_serial_byte
+    }
+    fn get_emulator() -> Emulator<Self> {
+        Emulator {
+            ppu: Default::default(),
+            cpu: Cpu::default(),
+            interrupts: Interrupts::default(),
+            timer: Timer::default(),
+            joypad: Joypad::default(),
+            apu: Apu::default(),
+            serial: DmgSerial::default(),
+            wram: DmgWram::default(),
+            cycles: 0,
+            hdma: (),
+            sgb: SuperGameBoy::default(),
+            revision: Default::default(),
+        }
+    }
+    fn skip_boot_rom<M: Mbc + ?Sized>(emulator: &mut Emulator<Self>, mbc: &mut M) {
+        let registers = emulator.revision.get_boot_registers().get_registers(mbc);
+        // no boot sound, the SNES plays its own
+        boot::skip_dmg_boot_rom(emulator, mbc, registers, boot::SGB_SYSTEM_COUNTER, false);
+    }
 }
 
 impl Model for Cgb {
@@ -151,13 +246,17 @@ impl Model for Cgb {
     type ColorPalettes = ColorPalettes;
     type ScanlineBuilder = ArrayVec<u16, 160>;
     type DmgMode = DmgMode;
+    type SuperGameBoy = ();
+    type Revision = CgbRevision;
+    const BOOT_ROM: &'static [u8] = CGB_BOOT_ROM;
+    const NAME: &'static str = "CGB";
     fn execute<M: Mbc + ?Sized>(emulator: &mut Emulator<Self>, mbc: &mut M) -> Option<u8> {
         emulator.execute(mbc)
     }
     fn get_emulator() -> Emulator<Self> {
         Emulator {
             ppu: Default::default(),
-            cpu: Cpu::new(CGB_BOOT_ROM),
+            cpu: Cpu::default(),
             interrupts: Interrupts::default(),
             timer: Timer::default(),
             joypad: Joypad::default(),
@@ -166,11 +265,24 @@ impl Model for Cgb {
             wram: Default::default(),
             cycles: 0,
             hdma: Hdma::default(),
+            sgb: (),
+            revision: Default::default(),
+        }
+    }
+    fn skip_boot_rom<M: Mbc + ?Sized>(emulator: &mut Emulator<Self>, mbc: &mut M) {
+        boot::skip_cgb_boot_rom(emulator, mbc, boot::CGB_SYSTEM_COUNTER);
+    }
+    // the screen of the AGB is darker
+    fn get_screen_scanline(revision: CgbRevision, scanline: &CgbScanline) -> CgbScanline {
+        match revision {
+            CgbRevision::Cgb0 | CgbRevision::Cgb => *scanline,
+            CgbRevision::Agb => scanline.to_agb(),
         }
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
 pub struct Emulator<M: Model> {
     ppu: Ppu<M>,
     cpu: Cpu<M>,
@@ -182,6 +294,8 @@ pub struct Emulator<M: Model> {
     wram: M::Wram,
     cycles: u64,
     hdma: M::HdmaRegs,
+    sgb: M::SuperGameBoy,
+    revision: M::Revision,
 }
 
 impl<M: Model> Default for Emulator<M> {
@@ -191,9 +305,22 @@ impl<M: Model> Default for Emulator<M> {
 }
 
 impl<M: Model> Emulator<M> {
+    // tells if the next call to execute will return a byte
     pub fn will_serial_emit_byte(&self) -> bool {
-        self.serial
-            .will_emit_byte(self.timer.get_system_counter().wrapping_add(1))
+        let system_counter = self.timer.get_system_counter();
+        if !self.cpu.speed_switch.is_double_speed() {
+            return self.serial.will_emit_byte(system_counter.wrapping_add(1));
+        }
+        // the system counter is incremented twice during a double speed execution
+        let mut serial = self.serial.clone();
+        serial
+            .execute(
+                system_counter.wrapping_add(1),
+                &mut Interrupts::empty(),
+                self.cycles,
+            )
+            .is_some()
+            || serial.will_emit_byte(system_counter.wrapping_add(2))
     }
     pub fn get_ppu(&self) -> &Ppu<M> {
         &self.ppu
@@ -209,6 +336,10 @@ impl<M: Model> Emulator<M> {
         // if some bits went from 1 to 0
         if previous_joypad.get_register() & !self.joypad.get_register() != 0 {
             self.interrupts.insert(Interrupts::JOYPAD);
+            // https://gbdev.io/pandocs/Reducing_Power_Consumption.html#using-the-stop-instruction
+            if self.cpu.stop_mode == StopMode::Stopped {
+                self.cpu.stop_mode = StopMode::Running;
+            }
         }
     }
     pub fn get_joypad(&self) -> &JoypadInput {
@@ -220,13 +351,83 @@ impl<M: Model> Emulator<M> {
     pub fn get_timer(&self) -> &Timer {
         &self.timer
     }
+    pub fn get_wram(&self) -> &M::Wram {
+        &self.wram
+    }
+    pub fn is_stopped(&self) -> bool {
+        self.cpu.stop_mode == StopMode::Stopped
+    }
+    /// The scanline drawn during this m-cycle. In STOP mode the LCD keeps its last image, nothing is
+    /// drawn even if the PPU was stopped at the end of a scanline.
+    pub fn get_scanline_if_ready(
+        &self,
+    ) -> Option<&<M::ScanlineBuilder as ScanlineBuilder>::Scanline> {
+        if self.is_stopped() {
+            return None;
+        }
+        self.ppu.get_scanline_if_ready()
+    }
+    /// The scanline with the colors of the screen of the revision, for the frontends.
+    pub fn get_screen_scanline_if_ready(
+        &self,
+    ) -> Option<<M::ScanlineBuilder as ScanlineBuilder>::Scanline> {
+        self.get_scanline_if_ready()
+            .map(|scanline| M::get_screen_scanline(self.revision, scanline))
+    }
+    /// Starts at 0x0100 with the state left by the boot ROM, without the logo animation.
+    pub fn without_boot_rom(mbc: &mut (impl Mbc + ?Sized)) -> Self {
+        let mut emulator = Self::default();
+        M::skip_boot_rom(&mut emulator, mbc);
+        emulator
+    }
+    /// Executes a boot ROM dump instead of the built-in one.
+    pub fn with_boot_rom(boot_rom: BootRom) -> Self {
+        let mut emulator = Self::default();
+        emulator.set_boot_rom(boot_rom);
+        emulator
+    }
+    pub fn with_revision(revision: M::Revision) -> Self {
+        let mut emulator = Self {
+            revision,
+            ..Default::default()
+        };
+        emulator.cpu.boot_rom = revision.get_boot_rom().and_then(BootRom::new);
+        emulator
+    }
+    // must be called before the execution
+    pub fn set_boot_rom(&mut self, boot_rom: BootRom) {
+        self.cpu.boot_rom = Some(boot_rom);
+    }
+    pub fn get_revision(&self) -> M::Revision {
+        self.revision
+    }
+    // writes like the CPU does, the cheat devices use it to patch the RAM
+    pub fn write_memory(&mut self, address: u16, value: u8, mbc: &mut (impl Mbc + ?Sized)) {
+        self.cpu.write(
+            address,
+            value,
+            &mut Peripherals {
+                mbc,
+                timer: &mut self.timer,
+                joypad: &mut self.joypad,
+                apu: &mut self.apu,
+                ppu: &mut self.ppu,
+                serial: &mut self.serial,
+                wram: &mut self.wram,
+                interrupts: &mut self.interrupts,
+                hdma: &mut self.hdma,
+            },
+            self.cycles,
+        );
+    }
+    // counts normal speed m-cycles, even in double speed mode
     pub fn get_cycles(&self) -> u64 {
         self.cycles
     }
 }
 
-impl Emulator<Dmg> {
-    fn execute<M: Mbc + ?Sized>(&mut self, mbc: &mut M) -> Option<u8> {
+impl<Mo: DmgModel> Emulator<Mo> {
+    fn dmg_execute<M: Mbc + ?Sized>(&mut self, mbc: &mut M) -> Option<u8> {
         self.timer.execute(&mut self.interrupts, self.cycles);
         let master_serial_byte = self.serial.execute(
             self.timer.get_system_counter(),
@@ -275,9 +476,25 @@ impl Emulator<Dmg> {
     }
 }
 
+impl Emulator<Sgb> {
+    // the SNES listens to the joypad register and watches the screen
+    fn sgb_execute(&mut self) {
+        if let Some(packet) = self.joypad.take_packet() {
+            self.sgb.receive_packet(packet, &mut self.joypad);
+        }
+        if let Some(scanline) = self.ppu.get_scanline_if_ready() {
+            self.sgb.push_scanline(self.ppu.get_ly(), scanline);
+        }
+    }
+
+    pub fn get_super_game_boy(&self) -> &SuperGameBoy {
+        &self.sgb
+    }
+}
+
 impl Emulator<Cgb> {
     fn execute<M: Mbc + ?Sized>(&mut self, mbc: &mut M) -> Option<u8> {
-        if self.cpu.speed_switch.contains(CgbSpeedSwitch::DOUBLE_SPEED) {
+        if self.cpu.speed_switch.is_double_speed() {
             return self.double_speed_execute(mbc);
         }
 
@@ -289,7 +506,10 @@ impl Emulator<Cgb> {
                     .hdma
                     .execute(self.ppu.get_vram_mut(), mbc, &self.wram, ppu_mode);
 
-        self.timer.execute(&mut self.interrupts, self.cycles);
+        // the div register doesn't tick during the speed switch
+        if !self.cpu.is_system_clock_stopped() {
+            self.timer.execute(&mut self.interrupts, self.cycles);
+        }
         let master_serial_byte = self.serial.execute(
             self.timer.get_system_counter(),
             &mut self.interrupts,
@@ -343,63 +563,77 @@ impl Emulator<Cgb> {
         master_serial_byte
     }
 
-    // yea the emulation doesn't speed up. It's not trivial to adapt the network logic for double speed mode.
-    // TODO: what about ppu and cpu synchronization?
+    // In double speed mode, the cpu, the timer and the serial execute two "fast" m-cycles
+    // during a normal m-cycle. The ppu, the hdma and the apu keep their pace.
+    // The cycles counter still counts normal m-cycles so the network clock doesn't depend on the speed.
+    // https://gbdev.io/pandocs/CGB_Registers.html#ff4d--key1spd-cgb-mode-only-prepare-speed-switch
     fn double_speed_execute<M: Mbc + ?Sized>(&mut self, mbc: &mut M) -> Option<u8> {
-        let ppu_mode = self.ppu.get_ppu_mode();
-
-        // hdma is executed only once in a "fast" m-cycle
-        let hdma_has_performed =
-            self.hdma
-                .execute(self.ppu.get_vram_mut(), mbc, &self.wram, ppu_mode);
+        let mut master_serial_byte = None;
+        let mut must_increment_div_apu = false;
 
-        self.timer.execute(&mut self.interrupts, self.cycles);
-        let master_serial_byte = self.serial.execute(
-            self.timer.get_system_counter(),
-            &mut self.interrupts,
-            self.cycles,
-        );
-        let must_increment_div_apu = self.apu.execute(self.timer.get_div());
+        for _ in 0..2 {
+            let ppu_mode = self.ppu.get_ppu_mode();
 
-        let interrupts_from_previous_cycle = self.interrupts;
+            // hdma is executed only once in a "fast" m-cycle
+            let hdma_has_performed =
+                self.hdma
+                    .execute(self.ppu.get_vram_mut(), mbc, &self.wram, ppu_mode);
 
-        // ppu is executed only twice in a "fast" m-cycle
-        self.ppu.execute(&mut self.interrupts, self.cycles);
-        // I don't understand halt timings https://gekkio.fi/blog/2016/game-boy-research-status
-        let mut slowed_interrupts_in_halt_mode = None;
-        if self.cpu.is_halted {
-            slowed_interrupts_in_halt_mode = Some(self.interrupts);
-            self.interrupts = interrupts_from_previous_cycle;
-        }
-        if hdma_has_performed {
-            self.ppu.execute_dma(mbc, &self.wram, self.cycles);
-        } else {
-            self.cpu.execute(
-                Peripherals {
-                    mbc,
-                    timer: &mut self.timer,
-                    joypad: &mut self.joypad,
-                    apu: &mut self.apu,
-                    ppu: &mut self.ppu,
-                    serial: &mut self.serial,
-                    wram: &mut self.wram,
-                    interrupts: &mut self.interrupts,
-                    hdma: &mut self.hdma,
-                },
+            // the div register doesn't tick during the speed switch
+            if !self.cpu.is_system_clock_stopped() {
+                self.timer.execute(&mut self.interrupts, self.cycles);
+            }
+            // a transfer lasts much longer than a normal m-cycle, so at most one byte is emitted
+            master_serial_byte = master_serial_byte.or(self.serial.execute(
+                self.timer.get_system_counter(),
+                &mut self.interrupts,
                 self.cycles,
-            );
-        }
+            ));
+            // the div register goes twice as fast, the apu uses the next bit to keep its pace
+            // https://gbdev.io/pandocs/Audio_details.html#div-apu
+            must_increment_div_apu |= self.apu.execute(self.timer.get_div() >> 1);
 
-        if let Some(interrupt_flag) = slowed_interrupts_in_halt_mode {
-            self.interrupts = interrupt_flag;
-        }
+            let interrupts_from_previous_cycle = self.interrupts;
 
-        self.ppu.execute(&mut self.interrupts, self.cycles);
+            // ppu is executed only twice in a "fast" m-cycle
+            self.ppu.execute(&mut self.interrupts, self.cycles);
+            // I don't understand halt timings https://gekkio.fi/blog/2016/game-boy-research-status
+            let mut slowed_interrupts_in_halt_mode = None;
+            if self.cpu.is_halted {
+                slowed_interrupts_in_halt_mode = Some(self.interrupts);
+                self.interrupts = interrupts_from_previous_cycle;
+            }
+            if hdma_has_performed {
+                self.ppu.execute_dma(mbc, &self.wram, self.cycles);
+            } else {
+                self.cpu.execute(
+                    Peripherals {
+                        mbc,
+                        timer: &mut self.timer,
+                        joypad: &mut self.joypad,
+                        apu: &mut self.apu,
+                        ppu: &mut self.ppu,
+                        serial: &mut self.serial,
+                        wram: &mut self.wram,
+                        interrupts: &mut self.interrupts,
+                        hdma: &mut self.hdma,
+                    },
+                    self.cycles,
+                );
+            }
+
+            if let Some(interrupt_flag) = slowed_interrupts_in_halt_mode {
+                self.interrupts = interrupt_flag;
+            }
+
+            self.ppu.execute(&mut self.interrupts, self.cycles);
+
+            self.timer.commit_tima_overflow();
+        }
 
         if must_increment_div_apu {
             self.apu.increment_div_apu();
         }
-        self.timer.commit_tima_overflow();
         self.cycles = self.cycles.wrapping_add(1);
         master_serial_byte
     }
@@ -411,11 +645,18 @@ pub trait EmulatorExt {
 
 impl<M: Model> EmulatorExt for Emulator<M> {
     fn execute(&mut self, mbc: &mut (impl Mbc + ?Sized)) -> Option<u8> {
-        M::execute(self, mbc)
+        // In STOP mode the system clock is stopped, nothing is executed.
+        if self.is_stopped() {
+            self.cycles = self.cycles.wrapping_add(1);
+            return None;
+        }
+        let master_serial_byte = M::execute(self, mbc);
+        mbc.execute();
+        master_serial_byte
     }
 }
 
-#[derive(Default, Clone)]
+#[derive(Default, Clone, Serialize, Deserialize)]
 pub struct FallingEdge(bool);
 
 impl FallingEdge {
@@ -425,3 +666,318 @@ impl FallingEdge {
         previous && !value
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        Cgb, Dmg, Emulator, EmulatorExt, Model, Peripherals, Sgb,
+        addresses::*,
+        cpu::{BootRom, SPEED_SWITCH_DURATION, StopMode, speed_switch::SpeedSwitch},
+        interrupts::Interrupts,
+        joypad::JoypadInput,
+        mbc::Mbc1,
+    };
+
+    #[test]
+    fn custom_boot_rom() {
+        let mut dump = [0; 0x100];
+        // ld a, 0x42; ldh (BOOT), a
+        dump[0xfc..].copy_from_slice(&[0x3e, 0x42, 0xe0, 0x50]);
+        let mut rom = [0; 0x8000];
+        // jr -2
+        rom[0x100..0x102].copy_from_slice(&[0x18, 0xfe]);
+        let mut mbc = Mbc1::new(rom.as_slice());
+        let mut emulator = Emulator::<Dmg>::with_boot_rom(BootRom::new(&dump).unwrap());
+
+        while !emulator.cpu.boot_rom_mapping_control {
+            assert!(emulator.get_cycles() < 0x200);
+            EmulatorExt::execute(&mut emulator, &mut mbc);
+        }
+        assert_eq!(emulator.cpu.a, 0x42);
+        assert!(emulator.cpu.boot_rom.is_none());
+        // the built-in boot ROM sets the stack pointer
+        assert_eq!(emulator.cpu.sp, 0);
+        assert!(BootRom::new(&[0; 0x901]).is_none());
+    }
+
+    #[test]
+    fn stop_mode() {
+        let mut rom = [0; 0x8000];
+        // ld a, 0x91; ldh (LCDC), a; stop; jr -2
+        rom[0x100..0x108].copy_from_slice(&[0x3e, 0x91, 0xe0, 0x40, 0x10, 0x00, 0x18, 0xfe]);
+        let mut mbc = Mbc1::new(rom.as_slice());
+        let mut emulator = Emulator::<Dmg>::default();
+        emulator.cpu.boot_rom_mapping_control = true;
+        emulator.cpu.pc = 0x100;
+
+        while emulator.cpu.stop_mode != StopMode::Stopped {
+            assert!(emulator.get_cycles() < 10);
+            EmulatorExt::execute(&mut emulator, &mut mbc);
+        }
+        let ly = emulator.get_ppu().get_ly();
+        for _ in 0..100_000 {
+            EmulatorExt::execute(&mut emulator, &mut mbc);
+        }
+        assert_eq!(emulator.get_timer().get_system_counter(), 0);
+        assert_eq!(emulator.get_ppu().get_ly(), ly);
+
+        // no line is selected
+        emulator.set_joypad(JoypadInput {
+            a: true,
+            ..Default::default()
+        });
+        assert_eq!(emulator.cpu.stop_mode, StopMode::Stopped);
+
+        emulator.set_joypad(Default::default());
+        emulator.joypad.is_buttons_selected = true;
+        emulator.set_joypad(JoypadInput {
+            a: true,
+            ..Default::default()
+        });
+        assert_eq!(emulator.cpu.stop_mode, StopMode::Running);
+        for _ in 0..100 {
+            EmulatorExt::execute(&mut emulator, &mut mbc);
+            // the second byte of STOP has been skipped
+            assert!((0x106..=0x108).contains(&emulator.cpu.pc));
+        }
+        assert_ne!(emulator.get_timer().get_system_counter(), 0);
+    }
+
+    #[test]
+    fn stop_mode_keeps_last_image() {
+        let mut rom = [0; 0x8000];
+        // ld a, 0x91; ldh (LCDC), a; jr -2
+        rom[0x100..0x106].copy_from_slice(&[0x3e, 0x91, 0xe0, 0x40, 0x18, 0xfe]);
+        let mut mbc = Mbc1::new(rom.as_slice());
+        let mut emulator = Emulator::<Dmg>::default();
+        emulator.cpu.boot_rom_mapping_control = true;
+        emulator.cpu.pc = 0x100;
+
+        while emulator.get_scanline_if_ready().is_none() {
+            assert!(emulator.get_cycles() < 1000);
+            EmulatorExt::execute(&mut emulator, &mut mbc);
+        }
+        // stopped at the end of a scanline, it must not be drawn again
+        emulator.cpu.stop_mode = StopMode::Stopped;
+        let ly = emulator.get_ppu().get_ly();
+        for _ in 0..100_000 {
+            EmulatorExt::execute(&mut emulator, &mut mbc);
+            assert!(emulator.get_scanline_if_ready().is_none());
+        }
+        assert_eq!(emulator.get_ppu().get_ly(), ly);
+
+        emulator.cpu.stop_mode = StopMode::Running;
+        let mut scanlines = 0;
+        for _ in 0..1000 {
+            EmulatorExt::execute(&mut emulator, &mut mbc);
+            scanlines += usize::from(emulator.get_scanline_if_ready().is_some());
+        }
+        assert!(scanlines > 1);
+    }
+
+    #[test]
+    fn illegal_opcode_lock_up() {
+        let mut rom = [0; 0x8000];
+        // ld a, 0x91; ldh (LCDC), a; ei; illegal
+        rom[0x100..0x106].copy_from_slice(&[0x3e, 0x91, 0xe0, 0x40, 0xfb, 0xd3]);
+        let mut mbc = Mbc1::new(rom.as_slice());
+        let mut emulator = Emulator::<Dmg>::default();
+        emulator.cpu.boot_rom_mapping_control = true;
+        emulator.cpu.pc = 0x100;
+        emulator.cpu.interrupt_enable = Interrupts::VBLANK;
+
+        while !emulator.cpu.is_locked_up {
+            assert!(emulator.get_cycles() < 10);
+            EmulatorExt::execute(&mut emulator, &mut mbc);
+        }
+        let pc = emulator.cpu.pc;
+        let ly = emulator.get_ppu().get_ly();
+        for _ in 0..100_000 {
+            EmulatorExt::execute(&mut emulator, &mut mbc);
+            assert_eq!(emulator.cpu.pc, pc);
+        }
+        // the vblank interrupt is not serviced
+        assert!(emulator.interrupts.contains(Interrupts::VBLANK));
+        assert_ne!(emulator.get_ppu().get_ly(), ly);
+    }
+
+    #[test]
+    fn double_speed() {
+        let mut rom = [0; 0x8000];
+        // inc bc; jr -3
+        rom[0x100..0x103].copy_from_slice(&[0x03, 0x18, 0xfd]);
+        let mut mbc = Mbc1::new(rom.as_slice());
+        let mut emulator = Emulator::<Cgb>::default();
+        emulator.cpu.boot_rom_mapping_control = true;
+        emulator.cpu.pc = 0x100;
+        emulator.cpu.speed_switch.write_value(1);
+        assert!(emulator.cpu.speed_switch.trigger());
+
+        // each iteration is 5 fast m-cycles, 2 fast m-cycles per normal m-cycle
+        for _ in 0..10_000 {
+            EmulatorExt::execute(&mut emulator, &mut mbc);
+        }
+        let iterations = u16::from_be_bytes([emulator.cpu.b, emulator.cpu.c]);
+        assert!((3999..=4001).contains(&iterations), "{iterations}");
+        assert_eq!(emulator.get_timer().get_system_counter(), 20_000);
+        assert_eq!(emulator.get_cycles(), 10_000);
+    }
+
+    #[test]
+    fn speed_switch() {
+        let mut rom = [0; 0x8000];
+        // ld a, 1; ldh (KEY1), a; stop; jr -2
+        rom[0x100..0x108].copy_from_slice(&[0x3e, 0x01, 0xe0, 0x4d, 0x10, 0x00, 0x18, 0xfe]);
+        let mut mbc = Mbc1::new(rom.as_slice());
+        let mut emulator = Emulator::<Cgb>::default();
+        emulator.cpu.boot_rom_mapping_control = true;
+        emulator.cpu.pc = 0x100;
+
+        while emulator.cpu.stop_mode == StopMode::Running {
+            assert!(emulator.get_cycles() < 10);
+            EmulatorExt::execute(&mut emulator, &mut mbc);
+        }
+        assert!(emulator.cpu.speed_switch.is_double_speed());
+        assert_eq!(emulator.cpu.speed_switch.read_value(), 0xfe);
+        // the cpu and the timer are paused, then the cpu continues after STOP
+        let start = emulator.get_cycles();
+        while emulator.cpu.is_system_clock_stopped() {
+            assert_eq!(emulator.get_timer().get_system_counter(), 0);
+            assert_eq!(emulator.cpu.pc, 0x106);
+            assert!(emulator.get_cycles() - start <= u64::from(SPEED_SWITCH_DURATION));
+            EmulatorExt::execute(&mut emulator, &mut mbc);
+        }
+        assert!(emulator.get_cycles() - start >= u64::from(SPEED_SWITCH_DURATION) / 2);
+        for _ in 0..100 {
+            EmulatorExt::execute(&mut emulator, &mut mbc);
+            assert!((0x106..=0x108).contains(&emulator.cpu.pc));
+        }
+        assert_eq!(emulator.get_timer().get_system_counter(), 200);
+    }
+
+    // the cycles until the length timer of the channel 1 turns it off
+    fn get_length_duration<M: Model>(emulator: &mut Emulator<M>) -> u64 {
+        let rom = [0; 0x8000];
+        let mut mbc = Mbc1::new(rom.as_slice());
+        // jr -2
+        emulator.write_memory(0xc000, 0x18, &mut mbc);
+        emulator.write_memory(0xc001, 0xfe, &mut mbc);
+        emulator.cpu.boot_rom_mapping_control = true;
+        emulator.cpu.pc = 0xc000;
+        emulator.write_memory(AUDIO_MASTER_CONTROL, 0x80, &mut mbc);
+        // 64 ticks of the 256 Hz length timer, about 262144 m-cycles
+        emulator.write_memory(CH1_LENGTH_TIMER_AND_DUTY_CYCLE, 0x00, &mut mbc);
+        emulator.write_memory(CH1_VOLUME_AND_ENVELOPE, 0xf0, &mut mbc);
+        emulator.write_memory(CH1_PERIOD_HIGH_AND_CONTROL, 0xc0, &mut mbc);
+        let start = emulator.get_cycles();
+        while emulator.get_apu().get_nr52(0) & 1 != 0 {
+            assert!(emulator.get_cycles() - start < 1_000_000);
+            EmulatorExt::execute(emulator, &mut mbc);
+        }
+        emulator.get_cycles() - start
+    }
+
+    #[test]
+    fn apu_pace_in_double_speed() {
+        let dmg_duration = get_length_duration(&mut Emulator::<Dmg>::default());
+        let mut emulator = Emulator::<Cgb>::default();
+        emulator.cpu.speed_switch.write_value(1);
+        assert!(emulator.cpu.speed_switch.trigger());
+        let cgb_duration = get_length_duration(&mut emulator);
+        // the phase of the div-apu differs by at most one tick
+        assert!(
+            dmg_duration.abs_diff(cgb_duration) <= 4096,
+            "{cgb_duration}"
+        );
+        assert!(
+            (258_048..=266_240).contains(&cgb_duration),
+            "{cgb_duration}"
+        );
+    }
+
+    #[test]
+    fn serial_in_double_speed() {
+        let rom = [0; 0x8000];
+        let mut mbc = Mbc1::new(rom.as_slice());
+        // the byte can be emitted by either increment of the system counter
+        for system_counter in [0, 1] {
+            let mut emulator = Emulator::<Cgb>::default();
+            emulator.cpu.speed_switch.write_value(1);
+            emulator.cpu.speed_switch.trigger();
+            emulator.timer.set_system_counter(system_counter);
+            emulator.write_memory(0xc000, 0x18, &mut mbc);
+            emulator.write_memory(0xc001, 0xfe, &mut mbc);
+            emulator.cpu.boot_rom_mapping_control = true;
+            emulator.cpu.pc = 0xc000;
+            emulator.write_memory(SB, 0x42, &mut mbc);
+            emulator.write_memory(SC, 0x81, &mut mbc);
+
+            let start = emulator.get_cycles();
+            loop {
+                let will_emit_byte = emulator.will_serial_emit_byte();
+                let byte = EmulatorExt::execute(&mut emulator, &mut mbc);
+                assert_eq!(will_emit_byte, byte.is_some());
+                if let Some(byte) = byte {
+                    assert_eq!(byte, 0x42);
+                    break;
+                }
+                assert!(emulator.get_cycles() - start < 1024);
+            }
+            // 8 bits at 8192 Hz, twice as fast as in normal speed
+            let duration = emulator.get_cycles() - start;
+            assert!((480..=544).contains(&duration), "{duration}");
+        }
+    }
+
+    fn bus_sweep<M: Model>() {
+        let rom = [0; 0x8000];
+        let mut mbc = Mbc1::new(rom.as_slice());
+        let mut emulator = Emulator::<M>::default();
+        let Emulator {
+            ppu,
+            cpu,
+            interrupts,
+            timer,
+            joypad,
+            apu,
+            serial,
+            wram,
+            hdma,
+            ..
+        } = &mut emulator;
+        let mut peripherals = Peripherals {
+            mbc: &mut mbc,
+            timer,
+            joypad,
+            apu,
+            ppu,
+            serial,
+            wram,
+            interrupts,
+            hdma,
+        };
+
+        // every value written to every address, then read back
+        for value in 0..=u8::MAX {
+            for address in 0..=u16::MAX {
+                cpu.write(address, value, &mut peripherals, 0);
+                cpu.read(address, peripherals.get_ref(), 0);
+            }
+        }
+    }
+
+    #[test]
+    fn dmg_no_panic_on_any_address() {
+        bus_sweep::<Dmg>();
+    }
+
+    #[test]
+    fn cgb_no_panic_on_any_address() {
+        bus_sweep::<Cgb>();
+    }
+
+    #[test]
+    fn sgb_no_panic_on_any_address() {
+        bus_sweep::<Sgb>();
+    }
+}
@@ -0,0 +1,159 @@
+// https://github.com/mgba-emu/mgba/blob/master/src/gb/mbc/unlicensed.c
+// MBC5 clones with the data lines of the switchable ROM bank and the bits of the bank number
+// wired in another order, each game selects the wiring it was built for at boot.
+
+use crate::{addresses::*, mbc::*};
+use core::ops::Deref;
+
+#[derive(Clone, Copy)]
+pub enum BbdWiring {
+    Bbd,
+    // Hitek carts use the same registers with other wirings
+    Hitek,
+}
+
+type BitOrders = [[u8; 8]; 8];
+
+// the orders not found in a game yet are left untouched
+const BBD_DATA_ORDERS: BitOrders = [
+    [0, 1, 2, 3, 4, 5, 6, 7],
+    [0, 1, 2, 3, 4, 5, 6, 7],
+    [0, 1, 2, 3, 4, 5, 6, 7],
+    [0, 1, 2, 3, 4, 5, 6, 7],
+    // Garou
+    [0, 5, 1, 3, 4, 2, 6, 7],
+    // Harry
+    [0, 4, 2, 3, 1, 5, 6, 7],
+    [0, 1, 2, 3, 4, 5, 6, 7],
+    // Digimon
+    [0, 1, 5, 3, 4, 2, 6, 7],
+];
+
+const BBD_BANK_ORDERS: BitOrders = [
+    [0, 1, 2, 3, 4, 5, 6, 7],
+    [0, 1, 2, 3, 4, 5, 6, 7],
+    [0, 1, 2, 3, 4, 5, 6, 7],
+    // Digimon, Garou
+    [3, 4, 2, 0, 1, 5, 6, 7],
+    [0, 1, 2, 3, 4, 5, 6, 7],
+    // Harry
+    [1, 2, 3, 4, 0, 5, 6, 7],
+    [0, 1, 2, 3, 4, 5, 6, 7],
+    [0, 1, 2, 3, 4, 5, 6, 7],
+];
+
+const HITEK_DATA_ORDERS: BitOrders = [
+    [0, 1, 2, 3, 4, 5, 6, 7],
+    [0, 6, 5, 3, 4, 1, 2, 7],
+    [0, 5, 6, 3, 4, 2, 1, 7],
+    [0, 6, 2, 3, 4, 5, 1, 7],
+    [0, 6, 1, 3, 4, 5, 2, 7],
+    [0, 1, 6, 3, 4, 5, 2, 7],
+    [0, 2, 6, 3, 4, 1, 5, 7],
+    [0, 6, 2, 3, 4, 1, 5, 7],
+];
+
+const HITEK_BANK_ORDERS: BitOrders = [
+    [0, 1, 2, 3, 4, 5, 6, 7],
+    [3, 2, 1, 0, 4, 5, 6, 7],
+    [2, 1, 0, 3, 4, 5, 6, 7],
+    [1, 0, 3, 2, 4, 5, 6, 7],
+    [0, 3, 2, 1, 4, 5, 6, 7],
+    [2, 3, 0, 1, 4, 5, 6, 7],
+    [3, 0, 1, 2, 4, 5, 6, 7],
+    [2, 0, 3, 1, 4, 5, 6, 7],
+];
+
+// the bit N of the result is the bit order[N] of the value
+fn reorder_bits(value: u8, order: &[u8; 8]) -> u8 {
+    (0..8).zip(order).fold(0, |reordered, (bit, source)| {
+        reordered | ((value >> source) & 1) << bit
+    })
+}
+
+#[derive(Clone)]
+pub struct Bbd<T> {
+    mbc5: Mbc5<T>,
+    wiring: BbdWiring,
+    // 3 bits
+    data_order: u8,
+    // 3 bits
+    bank_order: u8,
+}
+
+impl<T: Deref<Target = [u8]>> Bbd<T> {
+    pub fn new(rom: T, wiring: BbdWiring) -> Self {
+        Self {
+            mbc5: Mbc5::new(rom),
+            wiring,
+            data_order: 0,
+            bank_order: 0,
+        }
+    }
+
+    fn get_orders(&self) -> (&'static BitOrders, &'static BitOrders) {
+        match self.wiring {
+            BbdWiring::Bbd => (&BBD_DATA_ORDERS, &BBD_BANK_ORDERS),
+            BbdWiring::Hitek => (&HITEK_DATA_ORDERS, &HITEK_BANK_ORDERS),
+        }
+    }
+}
+
+impl<T: Deref<Target = [u8]>> Mbc for Bbd<T> {
+    fn read(&self, index: u16) -> u8 {
+        let value = self.mbc5.read(index);
+        match index {
+            SWITCHABLE_ROM_BANK..VIDEO_RAM => {
+                reorder_bits(value, &self.get_orders().0[usize::from(self.data_order)])
+            }
+            _ => value,
+        }
+    }
+
+    fn write(&mut self, index: u16, value: u8) {
+        match index & 0xf0ff {
+            0x2000 => {
+                let value = reorder_bits(value, &self.get_orders().1[usize::from(self.bank_order)]);
+                self.mbc5.write(index, value);
+            }
+            0x2001 => self.data_order = value & 0x07,
+            0x2080 => self.bank_order = value & 0x07,
+            _ => self.mbc5.write(index, value),
+        }
+    }
+
+    fn load_saved_ram(&mut self, save: &[u8]) {
+        self.mbc5.load_saved_ram(save);
+    }
+
+    fn load_additional_data(&mut self, _: &[u8]) {}
+
+    fn get_ram_to_save(&self) -> Option<&[u8]> {
+        self.mbc5.get_ram_to_save()
+    }
+
+    fn get_additional_data_to_save(&self, _: &mut [u8]) -> usize {
+        0
+    }
+
+    // the MBC5 registers followed by the orders
+    fn load_saved_registers(&mut self, registers: &[u8]) {
+        let Some((mbc5_registers, [data_order, bank_order])) = registers.split_last_chunk() else {
+            log::warn!("Unexpected BBD registers length: {}", registers.len());
+            return;
+        };
+        self.mbc5.load_saved_registers(mbc5_registers);
+        self.data_order = data_order & 0x07;
+        self.bank_order = bank_order & 0x07;
+    }
+
+    fn get_registers_to_save(&self, buffer: &mut [u8]) -> usize {
+        let length = self.mbc5.get_registers_to_save(buffer);
+        buffer[length..length + 2].copy_from_slice(&[self.data_order, self.bank_order]);
+        length + 2
+    }
+
+    fn get_rom(&self) -> &[u8] {
+        self.mbc5.get_rom()
+    }
+}
@@ -48,31 +48,33 @@ impl<T: Deref<Target = [u8]>> Huc1<T> {
 impl<T: Deref<Target = [u8]>> Mbc for Huc1<T> {
     fn read(&self, index: u16) -> u8 {
         match index {
-            ROM_BANK..SWITCHABLE_ROM_BANK => self.rom[usize::from(index)],
-            SWITCHABLE_ROM_BANK..VIDEO_RAM => {
-                self.rom
-                    [self.get_rom_offset() + usize::from(index) - usize::from(SWITCHABLE_ROM_BANK)]
-            }
+            ROM_BANK..SWITCHABLE_ROM_BANK => read_rom(&self.rom, usize::from(index)),
+            SWITCHABLE_ROM_BANK..VIDEO_RAM => read_rom(
+                &self.rom,
+                self.get_rom_offset() + usize::from(index) - usize::from(SWITCHABLE_ROM_BANK),
+            ),
             EXTERNAL_RAM..WORK_RAM => match self.mode {
                 Mode::Ir => 0xc0,
                 Mode::Ram => self.read_external_ram(index),
             },
-            _ => panic!(),
+            _ => 0xff,
         }
     }
 
     fn write(&mut self, index: u16, value: u8) {
         match index {
             0x0000..0x2000 => self.mode = if value == 0x0e { Mode::Ir } else { Mode::Ram },
-            0x2000..0x4000 => self.rom_bank = value,
-            0x4000..0x6000 => self.ram_bank = value,
+            // https://gbdev.io/pandocs/HuC1.html
+            0x2000..0x4000 => self.rom_bank = value & 0x3f,
+            // only 4 banks of RAM
+            0x4000..0x6000 => self.ram_bank = value & 0x03,
             0x6000..0x8000 => {}
             EXTERNAL_RAM..WORK_RAM => {
                 if core::matches!(self.mode, Mode::Ram) {
                     self.write_external_ram(index, value);
                 }
             }
-            _ => panic!(),
+            _ => {}
         }
     }
 
@@ -91,6 +93,29 @@ impl<T: Deref<Target = [u8]>> Mbc for Huc1<T> {
         0
     }
 
+    fn load_saved_registers(&mut self, registers: &[u8]) {
+        let Ok([rom_bank, ram_bank, mode]) = <[u8; 3]>::try_from(registers) else {
+            log::warn!("Unexpected HuC1 registers length: {}", registers.len());
+            return;
+        };
+        self.rom_bank = rom_bank;
+        self.ram_bank = ram_bank;
+        self.mode = if mode == 0 { Mode::Ram } else { Mode::Ir };
+    }
+
+    fn get_registers_to_save(&self, buffer: &mut [u8]) -> usize {
+        let registers = [
+            self.rom_bank,
+            self.ram_bank,
+            match self.mode {
+                Mode::Ram => 0,
+                Mode::Ir => 1,
+            },
+        ];
+        buffer[..registers.len()].copy_from_slice(&registers);
+        registers.len()
+    }
+
     fn get_rom(&self) -> &[u8] {
         &self.rom
     }
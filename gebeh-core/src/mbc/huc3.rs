@@ -0,0 +1,370 @@
+// https://gbdev.io/pandocs/HuC3.html
+// The RTC commands are poorly documented, the behavior is inspired by SameBoy.
+
+use crate::{addresses::*, mbc::*};
+use core::ops::Deref;
+
+const MINUTES_PER_DAY: u16 = 24 * 60;
+// the day counter of the Rtc trait has 9 bits, the HuC3 one has 12 bits
+const RTC_DAYS: u16 = 512;
+// the tone generator is not documented, this is only an approximation of the beep
+const TONE_FREQUENCY: f32 = 2048.;
+
+// selected by writing to $0000-$1FFF
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    RamReadOnly,
+    RamReadWrite,
+    RtcCommand,
+    RtcResponse,
+    RtcSemaphore,
+    Ir,
+    Unknown(u8),
+}
+
+impl Mode {
+    fn from_register(value: u8) -> Self {
+        match value & 0x0f {
+            0x0 => Mode::RamReadOnly,
+            0xa => Mode::RamReadWrite,
+            0xb => Mode::RtcCommand,
+            0xc => Mode::RtcResponse,
+            0xd => Mode::RtcSemaphore,
+            0xe => Mode::Ir,
+            value => Mode::Unknown(value),
+        }
+    }
+
+    fn to_register(self) -> u8 {
+        match self {
+            Mode::RamReadOnly => 0x0,
+            Mode::RamReadWrite => 0xa,
+            Mode::RtcCommand => 0xb,
+            Mode::RtcResponse => 0xc,
+            Mode::RtcSemaphore => 0xd,
+            Mode::Ir => 0xe,
+            Mode::Unknown(value) => value,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Huc3<T, U> {
+    rom: T,
+    // 7 bits
+    rom_bank: u8,
+    ram_bank: u8,
+    mode: Mode,
+    // 32 KiB
+    ram: [u8; 0x8000],
+    rtc: U,
+    // added to the day counter of the Rtc trait when it overflows
+    day_offset: u16,
+    // the time seen by the game, refreshed when it selects an address
+    minutes: u16,
+    days: u16,
+    alarm_minutes: u16,
+    alarm_days: u16,
+    is_alarm_enabled: bool,
+    // the RTC memory is made of nibbles
+    access_index: u8,
+    access_flags: u8,
+    response: u8,
+    is_tone_playing: bool,
+    is_ir_led_on: bool,
+    is_ir_light_received: bool,
+}
+
+impl<T: Deref<Target = [u8]>, U: Rtc> Huc3<T, U> {
+    pub fn new(rom: T, rtc: U) -> Self {
+        Self {
+            rom,
+            rom_bank: 1,
+            ram_bank: 0,
+            mode: Mode::RamReadOnly,
+            ram: [0; 0x8000],
+            rtc,
+            day_offset: 0,
+            minutes: 0,
+            days: 0,
+            alarm_minutes: 0,
+            alarm_days: 0,
+            is_alarm_enabled: false,
+            access_index: 0,
+            access_flags: 0,
+            response: 0,
+            is_tone_playing: false,
+            is_ir_led_on: false,
+            is_ir_light_received: false,
+        }
+    }
+
+    fn get_rom_bank_count(&self) -> u8 {
+        // HuC3 can't address more than 2 MiB
+        (get_factor_32_kib_rom(self.rom.deref()).min(64) as u8) << 1
+    }
+
+    fn get_switchable_rom_offset(&self) -> usize {
+        usize::from(self.rom_bank & (self.get_rom_bank_count() - 1)) * usize::from(ROM_BANK_SIZE)
+    }
+
+    fn get_ram_offset(&self, index: u16) -> usize {
+        usize::from(RAM_BANK_SIZE) * usize::from(self.ram_bank) + usize::from(index - EXTERNAL_RAM)
+    }
+
+    fn refresh_time(&mut self) {
+        let registers = self.rtc.get_clock_data();
+        // the carry is only set once, when the counter wraps
+        if registers.upper_1bit_day_counter_carry_halt & 0x80 != 0 {
+            self.day_offset = self.day_offset.wrapping_add(RTC_DAYS) & 0xfff;
+        }
+        self.minutes = u16::from(registers.hours) * 60 + u16::from(registers.minutes);
+        self.days = (registers.get_day_counter() + self.day_offset) & 0xfff;
+    }
+
+    fn commit_time(&mut self) {
+        self.minutes %= MINUTES_PER_DAY;
+        self.days &= 0xfff;
+        self.day_offset = self.days - self.days % RTC_DAYS;
+        self.rtc.set_clock_data(RtcRegisters::from_seconds(
+            (u32::from(self.days % RTC_DAYS) * u32::from(MINUTES_PER_DAY)
+                + u32::from(self.minutes))
+                * 60,
+            false,
+            false,
+        ));
+    }
+
+    // the time and the alarm are accessed through 12 bits values split in nibbles
+    fn get_nibble(value: u16, index: u8) -> u8 {
+        (value >> (index * 4)) as u8 & 0x0f
+    }
+
+    fn set_nibble(value: &mut u16, index: u8, nibble: u8) {
+        *value = *value & !(0x0f << (index * 4)) | (u16::from(nibble & 0x0f) << (index * 4));
+    }
+
+    fn read_rtc_memory(&self) -> u8 {
+        match self.access_index {
+            index @ 0x00..0x03 => Self::get_nibble(self.minutes, index),
+            index @ 0x03..0x07 => Self::get_nibble(self.days, index - 0x03),
+            index @ 0x58..0x5b => Self::get_nibble(self.alarm_minutes, index - 0x58),
+            index @ 0x5b..0x5f => Self::get_nibble(self.alarm_days, index - 0x5b),
+            0x5f => self.is_alarm_enabled.into(),
+            _ => 0,
+        }
+    }
+
+    fn write_rtc_memory(&mut self, nibble: u8) {
+        match self.access_index {
+            index @ 0x00..0x03 => {
+                Self::set_nibble(&mut self.minutes, index, nibble);
+                self.commit_time();
+            }
+            index @ 0x03..0x07 => {
+                Self::set_nibble(&mut self.days, index - 0x03, nibble);
+                self.commit_time();
+            }
+            index @ 0x58..0x5b => Self::set_nibble(&mut self.alarm_minutes, index - 0x58, nibble),
+            index @ 0x5b..0x5f => Self::set_nibble(&mut self.alarm_days, index - 0x5b, nibble),
+            0x5f => self.is_alarm_enabled = nibble & 1 != 0,
+            _ => {}
+        }
+    }
+
+    // bits 6-4: command, bits 3-0: argument
+    fn execute_rtc_command(&mut self, value: u8) {
+        let argument = value & 0x0f;
+        match (value >> 4) & 0x07 {
+            // read and increment
+            0x1 => {
+                self.response = self.read_rtc_memory();
+                self.access_index = self.access_index.wrapping_add(1);
+            }
+            // write
+            0x2 => self.write_rtc_memory(argument),
+            // write and increment
+            0x3 => {
+                self.write_rtc_memory(argument);
+                self.access_index = self.access_index.wrapping_add(1);
+            }
+            0x4 => {
+                self.access_index = self.access_index & 0xf0 | argument;
+                self.refresh_time();
+            }
+            0x5 => {
+                self.access_index = self.access_index & 0x0f | (argument << 4);
+                self.refresh_time();
+            }
+            // extended commands
+            0x6 => {
+                self.access_flags = argument;
+                self.is_tone_playing = argument == 0xe;
+            }
+            _ => {}
+        }
+    }
+}
+
+// day offset, alarm minutes, alarm days, alarm enabled, then the RTC
+const HUC3_DATA_SIZE: usize = 7;
+
+impl<T: Deref<Target = [u8]>, U: Rtc> Mbc for Huc3<T, U> {
+    fn read(&self, index: u16) -> u8 {
+        match index {
+            ROM_BANK..SWITCHABLE_ROM_BANK => read_rom(&self.rom, usize::from(index)),
+            SWITCHABLE_ROM_BANK..VIDEO_RAM => read_rom(
+                &self.rom,
+                self.get_switchable_rom_offset() + usize::from(index)
+                    - usize::from(SWITCHABLE_ROM_BANK),
+            ),
+            EXTERNAL_RAM..WORK_RAM => match self.mode {
+                Mode::RamReadOnly | Mode::RamReadWrite => self.ram[self.get_ram_offset(index)],
+                // the status is always "ready"
+                Mode::RtcResponse if self.access_flags == 0x2 => 0x01,
+                Mode::RtcResponse => self.response,
+                // the commands are executed instantly
+                Mode::RtcSemaphore => 0x01,
+                Mode::Ir => 0xc0 | u8::from(self.is_ir_light_received),
+                Mode::RtcCommand | Mode::Unknown(_) => 0xff,
+            },
+            _ => 0xff,
+        }
+    }
+
+    fn write(&mut self, index: u16, value: u8) {
+        match index {
+            0x0000..0x2000 => self.mode = Mode::from_register(value),
+            0x2000..0x4000 => self.rom_bank = value & 0x7f,
+            0x4000..0x6000 => self.ram_bank = value & 0x03,
+            EXTERNAL_RAM..WORK_RAM => match self.mode {
+                Mode::RamReadWrite => self.ram[self.get_ram_offset(index)] = value,
+                Mode::RtcCommand => self.execute_rtc_command(value),
+                Mode::Ir => self.is_ir_led_on = value & 1 != 0,
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+
+    fn load_saved_ram(&mut self, save: &[u8]) {
+        let min = save.len().min(self.ram.len());
+        self.ram[..min].copy_from_slice(&save[..min]);
+    }
+
+    fn load_additional_data(&mut self, additional_data: &[u8]) {
+        let Some((data, rtc)) = additional_data.split_first_chunk::<HUC3_DATA_SIZE>() else {
+            log::warn!(
+                "Unexpected HuC3 additional data length: {}",
+                additional_data.len()
+            );
+            return;
+        };
+        let [
+            day_offset_high,
+            day_offset_low,
+            alarm_minutes_high,
+            alarm_minutes_low,
+            alarm_days_high,
+            alarm_days_low,
+            is_alarm_enabled,
+        ] = *data;
+        self.day_offset = u16::from_be_bytes([day_offset_high, day_offset_low]) & 0xfff;
+        self.alarm_minutes = u16::from_be_bytes([alarm_minutes_high, alarm_minutes_low]);
+        self.alarm_days = u16::from_be_bytes([alarm_days_high, alarm_days_low]);
+        self.is_alarm_enabled = is_alarm_enabled != 0;
+        self.rtc.deserialize(rtc);
+    }
+
+    fn get_ram_to_save(&self) -> Option<&[u8]> {
+        Some(&self.ram)
+    }
+
+    fn get_additional_data_to_save(&self, buffer: &mut [u8]) -> usize {
+        let [day_offset_high, day_offset_low] = self.day_offset.to_be_bytes();
+        let [alarm_minutes_high, alarm_minutes_low] = self.alarm_minutes.to_be_bytes();
+        let [alarm_days_high, alarm_days_low] = self.alarm_days.to_be_bytes();
+        let data = [
+            day_offset_high,
+            day_offset_low,
+            alarm_minutes_high,
+            alarm_minutes_low,
+            alarm_days_high,
+            alarm_days_low,
+            self.is_alarm_enabled.into(),
+        ];
+        buffer[..HUC3_DATA_SIZE].copy_from_slice(&data);
+        HUC3_DATA_SIZE + self.rtc.serialize(&mut buffer[HUC3_DATA_SIZE..])
+    }
+
+    fn load_saved_registers(&mut self, registers: &[u8]) {
+        let Ok(
+            [
+                rom_bank,
+                ram_bank,
+                mode,
+                minutes_high,
+                minutes_low,
+                days_high,
+                days_low,
+                access_index,
+                access_flags,
+                response,
+                flags,
+            ],
+        ) = <[u8; 11]>::try_from(registers)
+        else {
+            log::warn!("Unexpected HuC3 registers length: {}", registers.len());
+            return;
+        };
+        self.rom_bank = rom_bank & 0x7f;
+        self.ram_bank = ram_bank & 0x03;
+        self.mode = Mode::from_register(mode);
+        self.minutes = u16::from_be_bytes([minutes_high, minutes_low]);
+        self.days = u16::from_be_bytes([days_high, days_low]);
+        self.access_index = access_index;
+        self.access_flags = access_flags & 0x0f;
+        self.response = response;
+        self.is_tone_playing = flags & 0b001 != 0;
+        self.is_ir_led_on = flags & 0b010 != 0;
+        self.is_ir_light_received = flags & 0b100 != 0;
+    }
+
+    fn get_registers_to_save(&self, buffer: &mut [u8]) -> usize {
+        let [minutes_high, minutes_low] = self.minutes.to_be_bytes();
+        let [days_high, days_low] = self.days.to_be_bytes();
+        let registers = [
+            self.rom_bank,
+            self.ram_bank,
+            self.mode.to_register(),
+            minutes_high,
+            minutes_low,
+            days_high,
+            days_low,
+            self.access_index,
+            self.access_flags,
+            self.response,
+            u8::from(self.is_tone_playing)
+                | (u8::from(self.is_ir_led_on) << 1)
+                | (u8::from(self.is_ir_light_received) << 2),
+        ];
+        buffer[..registers.len()].copy_from_slice(&registers);
+        registers.len()
+    }
+
+    fn get_rom(&self) -> &[u8] {
+        &self.rom
+    }
+
+    fn get_tone_frequency(&self) -> Option<f32> {
+        self.is_tone_playing.then_some(TONE_FREQUENCY)
+    }
+
+    fn is_infrared_led_on(&self) -> Option<bool> {
+        Some(self.is_ir_led_on)
+    }
+
+    fn set_infrared_light_received(&mut self, is_received: bool) {
+        self.is_ir_light_received = is_received;
+    }
+}
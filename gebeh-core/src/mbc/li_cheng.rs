@@ -0,0 +1,56 @@
+// https://github.com/mgba-emu/mgba/blob/master/src/gb/mbc/unlicensed.c
+// An MBC5 clone whose ROM bank register only answers to $2000-$2100.
+
+use crate::mbc::*;
+use core::ops::Deref;
+
+#[derive(Clone)]
+pub struct LiCheng<T>(Mbc5<T>);
+
+impl<T: Deref<Target = [u8]>> LiCheng<T> {
+    pub fn new(rom: T) -> Self {
+        Self(Mbc5::new(rom))
+    }
+}
+
+impl<T: Deref<Target = [u8]>> Mbc for LiCheng<T> {
+    fn read(&self, index: u16) -> u8 {
+        self.0.read(index)
+    }
+
+    fn write(&mut self, index: u16, value: u8) {
+        // the games write there to confuse the MBC5 emulators
+        if let 0x2101..0x3000 = index {
+            return;
+        }
+        self.0.write(index, value);
+    }
+
+    fn load_saved_ram(&mut self, save: &[u8]) {
+        self.0.load_saved_ram(save);
+    }
+
+    fn load_additional_data(&mut self, additional_data: &[u8]) {
+        self.0.load_additional_data(additional_data);
+    }
+
+    fn get_ram_to_save(&self) -> Option<&[u8]> {
+        self.0.get_ram_to_save()
+    }
+
+    fn get_additional_data_to_save(&self, buffer: &mut [u8]) -> usize {
+        self.0.get_additional_data_to_save(buffer)
+    }
+
+    fn load_saved_registers(&mut self, registers: &[u8]) {
+        self.0.load_saved_registers(registers);
+    }
+
+    fn get_registers_to_save(&self, buffer: &mut [u8]) -> usize {
+        self.0.get_registers_to_save(buffer)
+    }
+
+    fn get_rom(&self) -> &[u8] {
+        self.0.get_rom()
+    }
+}
@@ -24,27 +24,20 @@ impl<T: Deref<Target = [u8]>> M161<T> {
 impl<T: Deref<Target = [u8]>> Mbc for M161<T> {
     fn read(&self, index: u16) -> u8 {
         match index {
-            ROM_BANK..VIDEO_RAM => {
-                self.rom[usize::from(self.rom_bank) * usize::from(ROM_BANK_SIZE) * 2
-                    + usize::from(index)]
-            }
-            EXTERNAL_RAM..WORK_RAM => 0xff,
-            _ => panic!(),
+            ROM_BANK..VIDEO_RAM => read_rom(
+                &self.rom,
+                usize::from(self.rom_bank) * usize::from(ROM_BANK_SIZE) * 2 + usize::from(index),
+            ),
+            _ => 0xff,
         }
     }
 
     fn write(&mut self, index: u16, value: u8) {
-        match index {
-            ROM_BANK..VIDEO_RAM => {
-                if self.disable_bank_switch {
-                    return;
-                }
-                self.rom_bank = value & 0x07;
-                self.disable_bank_switch = true;
-            }
-            EXTERNAL_RAM..WORK_RAM => {}
-            _ => panic!(),
+        if !(ROM_BANK..VIDEO_RAM).contains(&index) || self.disable_bank_switch {
+            return;
         }
+        self.rom_bank = value & 0x07;
+        self.disable_bank_switch = true;
     }
 
     fn load_saved_ram(&mut self, _: &[u8]) {}
@@ -59,6 +52,21 @@ impl<T: Deref<Target = [u8]>> Mbc for M161<T> {
         0
     }
 
+    fn load_saved_registers(&mut self, registers: &[u8]) {
+        let Ok([rom_bank, disable_bank_switch]) = <[u8; 2]>::try_from(registers) else {
+            log::warn!("Unexpected M161 registers length: {}", registers.len());
+            return;
+        };
+        self.rom_bank = rom_bank & 0x07;
+        self.disable_bank_switch = disable_bank_switch != 0;
+    }
+
+    fn get_registers_to_save(&self, buffer: &mut [u8]) -> usize {
+        let registers = [self.rom_bank, self.disable_bank_switch.into()];
+        buffer[..registers.len()].copy_from_slice(&registers);
+        registers.len()
+    }
+
     fn get_rom(&self) -> &[u8] {
         &self.rom
     }
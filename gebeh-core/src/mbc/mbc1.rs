@@ -36,7 +36,8 @@ impl<T: Deref<Target = [u8]>> Mbc1<T> {
     }
 
     fn get_rom_bank_count(&self) -> u8 {
-        u8::try_from(get_factor_32_kib_rom(self.rom.deref())).unwrap() << 1
+        // MBC1 can't address more than 2 MiB
+        (get_factor_32_kib_rom(self.rom.deref()).min(64) as u8) << 1
     }
 
     fn get_rom_bank_upper_bits(&self) -> u8 {
@@ -105,13 +106,16 @@ const LIMIT_ROM_BANK_COUNT_BEFORE_ADVANCED: u8 = 32;
 impl<T: Deref<Target = [u8]>> Mbc for Mbc1<T> {
     fn read(&self, index: u16) -> u8 {
         match index {
-            ROM_BANK..SWITCHABLE_ROM_BANK => self.rom[usize::from(index) + self.get_rom_offset()],
-            SWITCHABLE_ROM_BANK..VIDEO_RAM => {
-                self.rom[self.get_switchable_rom_offset() + usize::from(index)
-                    - usize::from(SWITCHABLE_ROM_BANK)]
+            ROM_BANK..SWITCHABLE_ROM_BANK => {
+                read_rom(&self.rom, usize::from(index) + self.get_rom_offset())
             }
+            SWITCHABLE_ROM_BANK..VIDEO_RAM => read_rom(
+                &self.rom,
+                self.get_switchable_rom_offset() + usize::from(index)
+                    - usize::from(SWITCHABLE_ROM_BANK),
+            ),
             EXTERNAL_RAM..WORK_RAM => self.read_external_ram(index),
-            _ => panic!(),
+            _ => 0xff,
         }
     }
 
@@ -134,7 +138,7 @@ impl<T: Deref<Target = [u8]>> Mbc for Mbc1<T> {
                 self.write_banking_mode(value);
             }
             EXTERNAL_RAM..WORK_RAM => self.write_external_ram(index, value),
-            _ => panic!(),
+            _ => {}
         }
     }
 
@@ -153,6 +157,33 @@ impl<T: Deref<Target = [u8]>> Mbc for Mbc1<T> {
         0
     }
 
+    fn load_saved_registers(&mut self, registers: &[u8]) {
+        let Ok([rom_bank, advanced_bank, ram_enabled, banking_mode]) =
+            <[u8; 4]>::try_from(registers)
+        else {
+            log::warn!("Unexpected MBC1 registers length: {}", registers.len());
+            return;
+        };
+        self.rom_bank = NonZeroU8::new(rom_bank & 0x1f).unwrap_or(NonZeroU8::MIN);
+        self.advanced_bank = advanced_bank & 0x03;
+        self.ram_enabled = ram_enabled != 0;
+        self.write_banking_mode(banking_mode);
+    }
+
+    fn get_registers_to_save(&self, buffer: &mut [u8]) -> usize {
+        let registers = [
+            self.rom_bank.get(),
+            self.advanced_bank,
+            self.ram_enabled.into(),
+            match self.banking_mode {
+                BankingMode::Simple => 0,
+                BankingMode::Advanced => 1,
+            },
+        ];
+        buffer[..registers.len()].copy_from_slice(&registers);
+        registers.len()
+    }
+
     fn get_rom(&self) -> &[u8] {
         &self.rom
     }
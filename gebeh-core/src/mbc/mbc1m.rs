@@ -14,18 +14,20 @@ impl<T: Deref<Target = [u8]>> Mbc for Mbc1M<T> {
     fn read(&self, index: u16) -> u8 {
         match index {
             ROM_BANK..SWITCHABLE_ROM_BANK => match self.0.banking_mode {
-                BankingMode::Simple => self.0.rom[usize::from(index)],
-                BankingMode::Advanced => {
-                    self.0.rom[(usize::from(self.0.advanced_bank) << 18) | usize::from(index)]
-                }
+                BankingMode::Simple => read_rom(&self.0.rom, usize::from(index)),
+                BankingMode::Advanced => read_rom(
+                    &self.0.rom,
+                    (usize::from(self.0.advanced_bank) << 18) | usize::from(index),
+                ),
             },
-            SWITCHABLE_ROM_BANK..VIDEO_RAM => {
-                self.0.rom[(usize::from(self.0.advanced_bank) << 18)
+            SWITCHABLE_ROM_BANK..VIDEO_RAM => read_rom(
+                &self.0.rom,
+                (usize::from(self.0.advanced_bank) << 18)
                     | (usize::from(self.0.rom_bank.get() & 0x0f) << 14)
-                    | (usize::from(index) - usize::from(SWITCHABLE_ROM_BANK))]
-            }
+                    | (usize::from(index) - usize::from(SWITCHABLE_ROM_BANK)),
+            ),
             EXTERNAL_RAM..WORK_RAM => self.0.read_external_ram(index),
-            _ => panic!(),
+            _ => 0xff,
         }
     }
     fn write(&mut self, index: u16, value: u8) {
@@ -37,7 +39,7 @@ impl<T: Deref<Target = [u8]>> Mbc for Mbc1M<T> {
             0x4000..0x6000 => self.0.advanced_bank = value & 0x03,
             0x6000..0x8000 => self.0.write_banking_mode(value),
             EXTERNAL_RAM..WORK_RAM => self.0.write_external_ram(index, value),
-            _ => panic!(),
+            _ => {}
         }
     }
 
@@ -57,6 +59,14 @@ impl<T: Deref<Target = [u8]>> Mbc for Mbc1M<T> {
         self.0.get_additional_data_to_save(buffer)
     }
 
+    fn load_saved_registers(&mut self, registers: &[u8]) {
+        self.0.load_saved_registers(registers);
+    }
+
+    fn get_registers_to_save(&self, buffer: &mut [u8]) -> usize {
+        self.0.get_registers_to_save(buffer)
+    }
+
     fn get_rom(&self) -> &[u8] {
         self.0.get_rom()
     }
@@ -20,7 +20,8 @@ impl<T: Deref<Target = [u8]>> Mbc2<T> {
     }
 
     fn get_rom_bank_count(&self) -> u8 {
-        (get_factor_32_kib_rom(self.rom.deref()) as u8) << 1
+        // MBC2 can't address more than 256 KiB
+        (get_factor_32_kib_rom(self.rom.deref()).min(8) as u8) << 1
     }
 
     fn get_switchable_rom_offset(&self) -> usize {
@@ -32,11 +33,12 @@ impl<T: Deref<Target = [u8]>> Mbc2<T> {
 impl<T: Deref<Target = [u8]>> Mbc for Mbc2<T> {
     fn read(&self, index: u16) -> u8 {
         match index {
-            ROM_BANK..SWITCHABLE_ROM_BANK => self.rom[usize::from(index)],
-            SWITCHABLE_ROM_BANK..VIDEO_RAM => {
-                self.rom[self.get_switchable_rom_offset() + usize::from(index)
-                    - usize::from(SWITCHABLE_ROM_BANK)]
-            }
+            ROM_BANK..SWITCHABLE_ROM_BANK => read_rom(&self.rom, usize::from(index)),
+            SWITCHABLE_ROM_BANK..VIDEO_RAM => read_rom(
+                &self.rom,
+                self.get_switchable_rom_offset() + usize::from(index)
+                    - usize::from(SWITCHABLE_ROM_BANK),
+            ),
             EXTERNAL_RAM..WORK_RAM => {
                 if !self.ram_enabled {
                     return 0xff;
@@ -46,7 +48,7 @@ impl<T: Deref<Target = [u8]>> Mbc for Mbc2<T> {
                 let addr = (index - EXTERNAL_RAM) & 0x01ff;
                 self.ram[usize::from(addr)]
             }
-            _ => panic!(),
+            _ => 0xff,
         }
     }
 
@@ -87,6 +89,21 @@ impl<T: Deref<Target = [u8]>> Mbc for Mbc2<T> {
         0
     }
 
+    fn load_saved_registers(&mut self, registers: &[u8]) {
+        let Ok([rom_bank, ram_enabled]) = <[u8; 2]>::try_from(registers) else {
+            log::warn!("Unexpected MBC2 registers length: {}", registers.len());
+            return;
+        };
+        self.rom_bank = NonZeroU8::new(rom_bank & 0x0f).unwrap_or(NonZeroU8::MIN);
+        self.ram_enabled = ram_enabled != 0;
+    }
+
+    fn get_registers_to_save(&self, buffer: &mut [u8]) -> usize {
+        let registers = [self.rom_bank.get(), self.ram_enabled.into()];
+        buffer[..registers.len()].copy_from_slice(&registers);
+        registers.len()
+    }
+
     fn get_rom(&self) -> &[u8] {
         &self.rom
     }
@@ -52,15 +52,43 @@ enum RamRtcSelect {
     Rtc(RtcSelect),
 }
 
+impl RamRtcSelect {
+    fn from_register(value: u8) -> Option<Self> {
+        match value {
+            0..0x08 => Some(RamRtcSelect::Ram(value)),
+            0x08 => Some(RamRtcSelect::Rtc(RtcSelect::Seconds)),
+            0x09 => Some(RamRtcSelect::Rtc(RtcSelect::Minutes)),
+            0x0a => Some(RamRtcSelect::Rtc(RtcSelect::Hours)),
+            0x0b => Some(RamRtcSelect::Rtc(RtcSelect::Lower8bitsDayCounter)),
+            0x0c => Some(RamRtcSelect::Rtc(RtcSelect::Upper1bitDayCounterCarryHalt)),
+            _ => None,
+        }
+    }
+
+    fn to_register(self) -> u8 {
+        match self {
+            RamRtcSelect::Ram(bank) => bank,
+            RamRtcSelect::Rtc(RtcSelect::Seconds) => 0x08,
+            RamRtcSelect::Rtc(RtcSelect::Minutes) => 0x09,
+            RamRtcSelect::Rtc(RtcSelect::Hours) => 0x0a,
+            RamRtcSelect::Rtc(RtcSelect::Lower8bitsDayCounter) => 0x0b,
+            RamRtcSelect::Rtc(RtcSelect::Upper1bitDayCounterCarryHalt) => 0x0c,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct Mbc3<T, U> {
     rom: T,
     rom_offset: usize,
-    // 32 KiB
-    ram: [u8; 0x8000],
+    // 64 KiB for the MBC30, the MBC3 only has 4 banks
+    ram: [u8; 0x10000],
     ram_rtc_select: RamRtcSelect,
     ram_enabled: bool,
-    rom_bank_count: u8,
+    rom_bank_count: u16,
+    ram_bank_count: u8,
+    // the RTC registers can't be selected on the cartridges without a timer
+    has_rtc: bool,
     rtc: U,
     rtc_registers: RtcRegisters,
     latch_reg: u8,
@@ -76,11 +104,14 @@ pub trait Rtc {
 impl<T: Deref<Target = [u8]>, U> Mbc3<T, U> {
     pub fn new(rom: T, rtc: U) -> Self {
         Self {
-            rom_bank_count: u8::try_from(get_factor_32_kib_rom(rom.deref())).unwrap() * 2,
+            // the MBC30 can address 4 MiB with the full 8 bits of the bank register
+            rom_bank_count: get_factor_32_kib_rom(rom.deref()).min(128) << 1,
+            ram_bank_count: get_factor_8_kib_ram(rom.deref()).min(8),
+            has_rtc: matches!(rom.get(CARTRIDGE_TYPE_HEADER), Some(0x0f | 0x10)),
             rom,
             rom_offset: usize::from(ROM_BANK_SIZE),
             ram_rtc_select: RamRtcSelect::Ram(0),
-            ram: [0; 0x8000],
+            ram: [0; 0x10000],
             ram_enabled: false,
             rtc,
             rtc_registers: Default::default(),
@@ -92,12 +123,40 @@ impl<T: Deref<Target = [u8]>, U> Mbc3<T, U> {
     pub fn set_rom_bank(&mut self, rom_bank: u16) {
         self.rom_offset = usize::from(rom_bank) * usize::from(ROM_BANK_SIZE);
     }
+
+    // for the cartridges with a wrong header
+    pub fn set_has_rtc(&mut self, has_rtc: bool) {
+        self.has_rtc = has_rtc;
+    }
+
+    // for the cartridges with a wrong header, rounded to a power of two banks
+    pub fn set_ram_size(&mut self, ram_size: usize) {
+        self.ram_bank_count = match ram_size.div_ceil(usize::from(RAM_BANK_SIZE)) {
+            0 => 0,
+            banks => banks.next_power_of_two().min(8) as u8,
+        };
+    }
+
+    fn get_ram_size(&self) -> usize {
+        usize::from(self.ram_bank_count) * usize::from(RAM_BANK_SIZE)
+    }
+
+    // the unused bits of the bank are ignored, the banks 4 to 7 are only available on MBC30
+    fn get_ram_offset(&self, bank: u8, index: u16) -> Option<usize> {
+        if self.ram_bank_count == 0 {
+            return None;
+        }
+        Some(
+            usize::from(bank & (self.ram_bank_count - 1)) * usize::from(RAM_BANK_SIZE)
+                + usize::from(index - EXTERNAL_RAM),
+        )
+    }
 }
 
 impl<T: Deref<Target = [u8]>, U: Rtc> Mbc for Mbc3<T, U> {
     fn read(&self, index: u16) -> u8 {
         match index {
-            ROM_BANK..SWITCHABLE_ROM_BANK => self.rom[usize::from(index)],
+            ROM_BANK..SWITCHABLE_ROM_BANK => read_rom(&self.rom, usize::from(index)),
             SWITCHABLE_ROM_BANK..VIDEO_RAM => *self
                 .rom
                 .get(self.rom_offset + (index - 0x4000) as usize)
@@ -108,10 +167,11 @@ impl<T: Deref<Target = [u8]>, U: Rtc> Mbc for Mbc3<T, U> {
                 }
                 use RtcSelect::*;
                 match self.ram_rtc_select {
-                    RamRtcSelect::Ram(bank) => {
-                        self.ram[usize::from(u16::from(bank) * RAM_BANK_SIZE)
-                            + (index - 0xa000) as usize]
-                    }
+                    RamRtcSelect::Ram(bank) => self
+                        .get_ram_offset(bank, index)
+                        .map(|offset| self.ram[offset])
+                        .unwrap_or(0xff),
+                    RamRtcSelect::Rtc(_) if !self.has_rtc => 0xff,
                     RamRtcSelect::Rtc(rtc_select) => match rtc_select {
                         Seconds => self.rtc_registers.seconds,
                         Minutes => self.rtc_registers.minutes,
@@ -123,7 +183,7 @@ impl<T: Deref<Target = [u8]>, U: Rtc> Mbc for Mbc3<T, U> {
                     },
                 }
             }
-            _ => panic!(),
+            _ => 0xff,
         }
     }
     fn write(&mut self, index: u16, value: u8) {
@@ -136,26 +196,18 @@ impl<T: Deref<Target = [u8]>, U: Rtc> Mbc for Mbc3<T, U> {
                 }
             }
             0x2000..=0x3fff => {
-                let mut rom_bank = value as u16 & 0x7f;
-                rom_bank &= u16::from(self.rom_bank_count) * 2 - 1;
+                let mut rom_bank = u16::from(value) & (self.rom_bank_count - 1);
                 if rom_bank == 0 {
                     rom_bank = 1;
                 }
                 self.set_rom_bank(rom_bank);
             }
             0x4000..=0x5fff => {
-                self.ram_rtc_select = match value {
-                    0..0x08 => RamRtcSelect::Ram(value),
-                    0x08 => RamRtcSelect::Rtc(RtcSelect::Seconds),
-                    0x09 => RamRtcSelect::Rtc(RtcSelect::Minutes),
-                    0x0a => RamRtcSelect::Rtc(RtcSelect::Hours),
-                    0x0b => RamRtcSelect::Rtc(RtcSelect::Lower8bitsDayCounter),
-                    0x0c => RamRtcSelect::Rtc(RtcSelect::Upper1bitDayCounterCarryHalt),
-                    _ => self.ram_rtc_select,
-                };
+                self.ram_rtc_select =
+                    RamRtcSelect::from_register(value).unwrap_or(self.ram_rtc_select);
             }
             0x6000..VIDEO_RAM => {
-                if self.latch_reg == 0 && value == 1 {
+                if self.has_rtc && self.latch_reg == 0 && value == 1 {
                     self.rtc_registers = self.rtc.get_clock_data()
                 }
                 self.latch_reg = value;
@@ -166,9 +218,11 @@ impl<T: Deref<Target = [u8]>, U: Rtc> Mbc for Mbc3<T, U> {
                 }
                 match self.ram_rtc_select {
                     RamRtcSelect::Ram(bank) => {
-                        self.ram[usize::from(u16::from(bank) * RAM_BANK_SIZE)
-                            + usize::from(index - EXTERNAL_RAM)] = value
+                        if let Some(offset) = self.get_ram_offset(bank, index) {
+                            self.ram[offset] = value;
+                        }
                     }
+                    RamRtcSelect::Rtc(_) if !self.has_rtc => {}
                     RamRtcSelect::Rtc(rtc_select) => {
                         match rtc_select {
                             RtcSelect::Seconds => self.rtc_registers.seconds = value % 60,
@@ -185,12 +239,12 @@ impl<T: Deref<Target = [u8]>, U: Rtc> Mbc for Mbc3<T, U> {
                     }
                 }
             }
-            _ => panic!("Writing 0x{value:02x} to ${index:04x}"),
+            _ => {}
         }
     }
 
     fn load_saved_ram(&mut self, save: &[u8]) {
-        let min = save.len().min(self.ram.len());
+        let min = save.len().min(self.get_ram_size());
         self.ram[..min].copy_from_slice(&save[..min]);
     }
 
@@ -200,13 +254,69 @@ impl<T: Deref<Target = [u8]>, U: Rtc> Mbc for Mbc3<T, U> {
     }
 
     fn get_ram_to_save(&self) -> Option<&[u8]> {
-        Some(&self.ram)
+        Some(&self.ram[..self.get_ram_size()])
     }
 
     fn get_additional_data_to_save(&self, buffer: &mut [u8]) -> usize {
         self.rtc.serialize(buffer)
     }
 
+    fn load_saved_registers(&mut self, registers: &[u8]) {
+        let Ok(
+            [
+                rom_bank_high,
+                rom_bank_low,
+                ram_rtc_select,
+                ram_enabled,
+                latch_reg,
+                seconds,
+                minutes,
+                hours,
+                lower_8bits_day_counter,
+                upper_1bit_day_counter_carry_halt,
+            ],
+        ) = <[u8; 10]>::try_from(registers)
+        else {
+            log::warn!("Unexpected MBC3 registers length: {}", registers.len());
+            return;
+        };
+        self.set_rom_bank(
+            u16::from_be_bytes([rom_bank_high, rom_bank_low]) & (self.rom_bank_count - 1),
+        );
+        self.ram_rtc_select =
+            RamRtcSelect::from_register(ram_rtc_select).unwrap_or(RamRtcSelect::Ram(0));
+        self.ram_enabled = ram_enabled != 0;
+        self.latch_reg = latch_reg;
+        self.rtc_registers = RtcRegisters {
+            seconds,
+            minutes,
+            hours,
+            lower_8bits_day_counter,
+            upper_1bit_day_counter_carry_halt,
+        };
+    }
+
+    fn get_registers_to_save(&self, buffer: &mut [u8]) -> usize {
+        let [rom_bank_high, rom_bank_low] =
+            u16::try_from(self.rom_offset / usize::from(ROM_BANK_SIZE))
+                .unwrap()
+                .to_be_bytes();
+        let registers = [
+            rom_bank_high,
+            rom_bank_low,
+            self.ram_rtc_select.to_register(),
+            self.ram_enabled.into(),
+            self.latch_reg,
+            self.rtc_registers.seconds,
+            self.rtc_registers.minutes,
+            self.rtc_registers.hours,
+            self.rtc_registers.lower_8bits_day_counter,
+            self.rtc_registers.upper_1bit_day_counter_carry_halt,
+        ];
+        buffer[..registers.len()].copy_from_slice(&registers);
+        registers.len()
+    }
+
     fn get_rom(&self) -> &[u8] {
         &self.rom
     }
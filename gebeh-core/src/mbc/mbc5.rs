@@ -11,16 +11,26 @@ pub struct Mbc5<T> {
     // 128 KiB
     ram: [u8; 0x20000],
     ram_enabled: bool,
+    // https://gbdev.io/pandocs/MBC5.html#rumble
+    has_rumble: bool,
+    // RAM bank writes since the last take_rumble_intensity, the games modulate the motor strength
+    // by switching it on and off every frame
+    rumble_writes: u16,
+    rumble_on_writes: u16,
 }
 
 impl<T: Deref<Target = [u8]>> Mbc5<T> {
     pub fn new(rom: T) -> Self {
+        let has_rumble = matches!(rom.get(CARTRIDGE_TYPE_HEADER), Some(0x1c..=0x1e));
         Self {
             rom,
             rom_bank: 1,
             ram_bank: 0,
             ram: [0; 0x20000],
             ram_enabled: false,
+            has_rumble,
+            rumble_writes: 0,
+            rumble_on_writes: 0,
         }
     }
 
@@ -33,25 +43,37 @@ impl<T: Deref<Target = [u8]>> Mbc5<T> {
     }
 
     fn get_ram_offset(&self) -> usize {
-        usize::from(self.ram_bank) * usize::from(RAM_BANK_SIZE)
+        // Citation: On cartridges which feature a rumble motor, bit 3 of the RAM Bank register is
+        // connected to the Rumble circuitry instead of the RAM chip.
+        let ram_bank = if self.has_rumble {
+            self.ram_bank & 0x07
+        } else {
+            self.ram_bank
+        };
+        usize::from(ram_bank) * usize::from(RAM_BANK_SIZE)
+    }
+
+    fn is_motor_on(&self) -> bool {
+        self.has_rumble && self.ram_bank & 0x08 != 0
     }
 }
 
 impl<T: Deref<Target = [u8]>> Mbc for Mbc5<T> {
     fn read(&self, index: u16) -> u8 {
         match index {
-            ROM_BANK..SWITCHABLE_ROM_BANK => self.rom[usize::from(index)],
-            SWITCHABLE_ROM_BANK..VIDEO_RAM => {
-                self.rom[self.get_switchable_rom_offset() + usize::from(index)
-                    - usize::from(SWITCHABLE_ROM_BANK)]
-            }
+            ROM_BANK..SWITCHABLE_ROM_BANK => read_rom(&self.rom, usize::from(index)),
+            SWITCHABLE_ROM_BANK..VIDEO_RAM => read_rom(
+                &self.rom,
+                self.get_switchable_rom_offset() + usize::from(index)
+                    - usize::from(SWITCHABLE_ROM_BANK),
+            ),
             EXTERNAL_RAM..WORK_RAM => {
                 if !self.ram_enabled {
                     return 0xff;
                 }
                 self.ram[self.get_ram_offset() + usize::from(index) - usize::from(EXTERNAL_RAM)]
             }
-            _ => panic!(),
+            _ => 0xff,
         }
     }
     fn write(&mut self, index: u16, value: u8) {
@@ -59,7 +81,15 @@ impl<T: Deref<Target = [u8]>> Mbc for Mbc5<T> {
             0x0000..0x2000 => self.ram_enabled = (value & 0x0f) == 0x0a,
             0x2000..0x3000 => self.rom_bank = self.rom_bank & 0xff00 | u16::from(value),
             0x3000..0x4000 => self.rom_bank = (u16::from(value & 1) << 8) | self.rom_bank & 0xff,
-            0x4000..0x6000 => self.ram_bank = value & 0x0f,
+            0x4000..0x6000 => {
+                self.ram_bank = value & 0x0f;
+                if self.has_rumble {
+                    self.rumble_writes = self.rumble_writes.saturating_add(1);
+                    if self.is_motor_on() {
+                        self.rumble_on_writes = self.rumble_on_writes.saturating_add(1);
+                    }
+                }
+            }
             EXTERNAL_RAM..WORK_RAM => {
                 if !self.ram_enabled {
                     return;
@@ -86,7 +116,45 @@ impl<T: Deref<Target = [u8]>> Mbc for Mbc5<T> {
         0
     }
 
+    fn load_saved_registers(&mut self, registers: &[u8]) {
+        let Ok([rom_bank_high, rom_bank_low, ram_bank, ram_enabled]) =
+            <[u8; 4]>::try_from(registers)
+        else {
+            log::warn!("Unexpected MBC5 registers length: {}", registers.len());
+            return;
+        };
+        self.rom_bank = u16::from_be_bytes([rom_bank_high & 1, rom_bank_low]);
+        self.ram_bank = ram_bank & 0x0f;
+        self.ram_enabled = ram_enabled != 0;
+    }
+
+    fn get_registers_to_save(&self, buffer: &mut [u8]) -> usize {
+        let [rom_bank_high, rom_bank_low] = self.rom_bank.to_be_bytes();
+        let registers = [
+            rom_bank_high,
+            rom_bank_low,
+            self.ram_bank,
+            self.ram_enabled.into(),
+        ];
+        buffer[..registers.len()].copy_from_slice(&registers);
+        registers.len()
+    }
+
     fn get_rom(&self) -> &[u8] {
         &self.rom
     }
+
+    fn take_rumble_intensity(&mut self) -> Option<f32> {
+        if !self.has_rumble {
+            return None;
+        }
+        let intensity = if self.rumble_writes == 0 {
+            if self.is_motor_on() { 1.0 } else { 0.0 }
+        } else {
+            f32::from(self.rumble_on_writes) / f32::from(self.rumble_writes)
+        };
+        self.rumble_writes = 0;
+        self.rumble_on_writes = 0;
+        Some(intensity)
+    }
 }
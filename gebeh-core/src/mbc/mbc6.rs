@@ -0,0 +1,300 @@
+// https://gbdev.io/pandocs/MBC6.html
+// The flash chip is a Macronix MX29F008TC, it answers to the usual JEDEC commands.
+
+use crate::{addresses::*, mbc::*};
+use core::ops::{Deref, DerefMut};
+
+pub const MBC6_RAM_SIZE: usize = 0x8000;
+pub const MBC6_FLASH_SIZE: usize = 0x100000;
+// the RAM followed by the flash
+pub const MBC6_SAVE_SIZE: usize = MBC6_RAM_SIZE + MBC6_FLASH_SIZE;
+
+// the ROM, the flash and the RAM are mapped in two independent halves
+const HALF_ROM_BANK_SIZE: usize = 0x2000;
+const HALF_RAM_BANK_SIZE: usize = 0x1000;
+
+const FLASH_MANUFACTURER_ID: u8 = 0xc2;
+const FLASH_DEVICE_ID: u8 = 0x81;
+const FLASH_SECTOR_SIZE: usize = 0x20000;
+const FLASH_PAGE_SIZE: usize = 0x80;
+
+#[derive(Clone, Copy)]
+enum FlashCommand {
+    None,
+    // after $AA at $5555
+    Unlocked,
+    // after $55 at $2AAA
+    Ready,
+    Erase,
+    EraseUnlocked,
+    EraseReady,
+    // the bytes of a 128 bytes page can be written after a single command
+    Program { page: Option<usize>, written: u8 },
+}
+
+#[derive(Clone)]
+pub struct Mbc6<T, U> {
+    rom: T,
+    // too big for the stack, allocated by the frontend
+    save: U,
+    ram_enabled: bool,
+    // 3 bits
+    ram_banks: [u8; 2],
+    // 7 bits
+    rom_banks: [u8; 2],
+    is_flash_selected: [bool; 2],
+    is_flash_enabled: bool,
+    is_flash_write_enabled: bool,
+    // the reads return the manufacturer and device IDs
+    is_flash_id_mode: bool,
+    flash_command: FlashCommand,
+}
+
+const MBC6_REGISTERS_SIZE: usize = 10;
+
+impl<T: Deref<Target = [u8]>, U: DerefMut<Target = [u8]>> Mbc6<T, U> {
+    /// `save` should be `MBC6_SAVE_SIZE` bytes long, the RAM cleared and the flash filled with 0xff
+    /// like an erased flash.
+    pub fn new(rom: T, save: U) -> Self {
+        Self {
+            rom,
+            save,
+            ram_enabled: false,
+            ram_banks: [0; 2],
+            rom_banks: [0; 2],
+            is_flash_selected: [false; 2],
+            is_flash_enabled: false,
+            is_flash_write_enabled: false,
+            is_flash_id_mode: false,
+            flash_command: FlashCommand::None,
+        }
+    }
+
+    fn get_half_rom_bank_count(&self) -> u16 {
+        get_factor_32_kib_rom(self.rom.deref()) << 2
+    }
+
+    fn get_flash_address(&self, half: usize, index: u16) -> usize {
+        usize::from(self.rom_banks[half] & 0x7f) * HALF_ROM_BANK_SIZE
+            + usize::from(index) % HALF_ROM_BANK_SIZE
+    }
+
+    fn get_ram_address(&self, index: u16) -> usize {
+        let half = usize::from(index >= 0xb000);
+        usize::from(self.ram_banks[half]) * HALF_RAM_BANK_SIZE
+            + usize::from(index) % HALF_RAM_BANK_SIZE
+    }
+
+    fn read_flash(&self, address: usize) -> u8 {
+        if !self.is_flash_enabled {
+            return 0xff;
+        }
+        if self.is_flash_id_mode {
+            return if address & 1 == 0 {
+                FLASH_MANUFACTURER_ID
+            } else {
+                FLASH_DEVICE_ID
+            };
+        }
+        self.save
+            .get(MBC6_RAM_SIZE + address)
+            .copied()
+            .unwrap_or(0xff)
+    }
+
+    fn erase_flash(&mut self, start: usize, length: usize) {
+        let start = (MBC6_RAM_SIZE + start).min(self.save.len());
+        let end = (start + length).min(self.save.len());
+        self.save[start..end].fill(0xff);
+    }
+
+    fn write_flash(&mut self, address: usize, value: u8) {
+        if !self.is_flash_enabled {
+            return;
+        }
+        if let FlashCommand::Program { page, written } = self.flash_command {
+            let page = page.unwrap_or(address & !(FLASH_PAGE_SIZE - 1));
+            if address & !(FLASH_PAGE_SIZE - 1) == page {
+                // the flash can only clear bits, erasing sets them back
+                if let Some(byte) = self.save.get_mut(MBC6_RAM_SIZE + address) {
+                    *byte &= value;
+                }
+                let written = written + 1;
+                self.flash_command = if usize::from(written) == FLASH_PAGE_SIZE {
+                    FlashCommand::None
+                } else {
+                    FlashCommand::Program {
+                        page: Some(page),
+                        written,
+                    }
+                };
+                return;
+            }
+            self.flash_command = FlashCommand::None;
+        }
+
+        if value == 0xf0 {
+            self.is_flash_id_mode = false;
+            self.flash_command = FlashCommand::None;
+            return;
+        }
+
+        self.flash_command = match (self.flash_command, address & 0x7fff, value) {
+            (FlashCommand::None, 0x5555, 0xaa) => FlashCommand::Unlocked,
+            (FlashCommand::Unlocked, 0x2aaa, 0x55) => FlashCommand::Ready,
+            (FlashCommand::Ready, 0x5555, 0x90) => {
+                self.is_flash_id_mode = true;
+                FlashCommand::None
+            }
+            (FlashCommand::Ready, 0x5555, 0x80) => FlashCommand::Erase,
+            (FlashCommand::Ready, 0x5555, 0xa0) if self.is_flash_write_enabled => {
+                FlashCommand::Program {
+                    page: None,
+                    written: 0,
+                }
+            }
+            (FlashCommand::Erase, 0x5555, 0xaa) => FlashCommand::EraseUnlocked,
+            (FlashCommand::EraseUnlocked, 0x2aaa, 0x55) => FlashCommand::EraseReady,
+            (FlashCommand::EraseReady, 0x5555, 0x10) if self.is_flash_write_enabled => {
+                self.erase_flash(0, MBC6_FLASH_SIZE);
+                FlashCommand::None
+            }
+            (FlashCommand::EraseReady, _, 0x30) if self.is_flash_write_enabled => {
+                self.erase_flash(address & !(FLASH_SECTOR_SIZE - 1), FLASH_SECTOR_SIZE);
+                FlashCommand::None
+            }
+            _ => FlashCommand::None,
+        };
+    }
+}
+
+impl<T: Deref<Target = [u8]>, U: DerefMut<Target = [u8]>> Mbc for Mbc6<T, U> {
+    fn read(&self, index: u16) -> u8 {
+        match index {
+            ROM_BANK..SWITCHABLE_ROM_BANK => read_rom(&self.rom, usize::from(index)),
+            SWITCHABLE_ROM_BANK..VIDEO_RAM => {
+                let half = usize::from(index >= 0x6000);
+                if self.is_flash_selected[half] {
+                    self.read_flash(self.get_flash_address(half, index))
+                } else {
+                    let bank =
+                        u16::from(self.rom_banks[half]) & (self.get_half_rom_bank_count() - 1);
+                    read_rom(
+                        &self.rom,
+                        usize::from(bank) * HALF_ROM_BANK_SIZE
+                            + usize::from(index) % HALF_ROM_BANK_SIZE,
+                    )
+                }
+            }
+            EXTERNAL_RAM..WORK_RAM => {
+                if !self.ram_enabled {
+                    return 0xff;
+                }
+                self.save
+                    .get(self.get_ram_address(index))
+                    .copied()
+                    .unwrap_or(0xff)
+            }
+            _ => 0xff,
+        }
+    }
+
+    fn write(&mut self, index: u16, value: u8) {
+        match index {
+            0x0000..0x0400 => self.ram_enabled = (value & 0x0f) == 0x0a,
+            0x0400..0x0800 => self.ram_banks[0] = value & 0x07,
+            0x0800..0x0c00 => self.ram_banks[1] = value & 0x07,
+            0x0c00..0x1000 => self.is_flash_enabled = value & 1 != 0,
+            0x1000 => self.is_flash_write_enabled = value & 1 != 0,
+            0x2000..0x2800 => self.rom_banks[0] = value & 0x7f,
+            0x2800..0x3000 => self.is_flash_selected[0] = value & 0x08 != 0,
+            0x3000..0x3800 => self.rom_banks[1] = value & 0x7f,
+            0x3800..0x4000 => self.is_flash_selected[1] = value & 0x08 != 0,
+            SWITCHABLE_ROM_BANK..VIDEO_RAM => {
+                let half = usize::from(index >= 0x6000);
+                if self.is_flash_selected[half] {
+                    self.write_flash(self.get_flash_address(half, index), value);
+                }
+            }
+            EXTERNAL_RAM..WORK_RAM => {
+                if !self.ram_enabled {
+                    return;
+                }
+                let address = self.get_ram_address(index);
+                if let Some(byte) = self.save.get_mut(address) {
+                    *byte = value;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn load_saved_ram(&mut self, save: &[u8]) {
+        let min = save.len().min(self.save.len());
+        self.save[..min].copy_from_slice(&save[..min]);
+    }
+
+    fn load_additional_data(&mut self, _: &[u8]) {}
+
+    fn get_ram_to_save(&self) -> Option<&[u8]> {
+        Some(&self.save)
+    }
+
+    fn get_volatile_ram_size(&self) -> usize {
+        MBC6_RAM_SIZE
+    }
+
+    fn get_additional_data_to_save(&self, _: &mut [u8]) -> usize {
+        0
+    }
+
+    fn load_saved_registers(&mut self, registers: &[u8]) {
+        let Ok(
+            [
+                ram_enabled,
+                ram_bank_a,
+                ram_bank_b,
+                rom_bank_a,
+                rom_bank_b,
+                is_flash_selected_a,
+                is_flash_selected_b,
+                is_flash_enabled,
+                is_flash_write_enabled,
+                is_flash_id_mode,
+            ],
+        ) = <[u8; MBC6_REGISTERS_SIZE]>::try_from(registers)
+        else {
+            log::warn!("Unexpected MBC6 registers length: {}", registers.len());
+            return;
+        };
+        self.ram_enabled = ram_enabled != 0;
+        self.ram_banks = [ram_bank_a & 0x07, ram_bank_b & 0x07];
+        self.rom_banks = [rom_bank_a & 0x7f, rom_bank_b & 0x7f];
+        self.is_flash_selected = [is_flash_selected_a != 0, is_flash_selected_b != 0];
+        self.is_flash_enabled = is_flash_enabled != 0;
+        self.is_flash_write_enabled = is_flash_write_enabled != 0;
+        self.is_flash_id_mode = is_flash_id_mode != 0;
+        self.flash_command = FlashCommand::None;
+    }
+
+    fn get_registers_to_save(&self, buffer: &mut [u8]) -> usize {
+        let registers: [u8; MBC6_REGISTERS_SIZE] = [
+            self.ram_enabled.into(),
+            self.ram_banks[0],
+            self.ram_banks[1],
+            self.rom_banks[0],
+            self.rom_banks[1],
+            self.is_flash_selected[0].into(),
+            self.is_flash_selected[1].into(),
+            self.is_flash_enabled.into(),
+            self.is_flash_write_enabled.into(),
+            self.is_flash_id_mode.into(),
+        ];
+        buffer[..registers.len()].copy_from_slice(&registers);
+        registers.len()
+    }
+
+    fn get_rom(&self) -> &[u8] {
+        &self.rom
+    }
+}
@@ -0,0 +1,385 @@
+// https://gbdev.io/pandocs/MBC7.html
+
+use crate::{addresses::*, joypad::Tilt, mbc::*};
+use core::ops::Deref;
+
+// around $81D0 when the console is flat, the values change by about $70 per g
+const ACCELEROMETER_CENTER: f32 = 0x81d0 as f32;
+const ACCELEROMETER_G: f32 = 0x70 as f32;
+const ACCELEROMETER_ERASED: u16 = 0x8000;
+
+// 93LC56, 128 words of 16 bits
+const EEPROM_SIZE: usize = 0x100;
+
+#[derive(Clone, Copy, PartialEq)]
+enum EepromCommand {
+    // waiting for the start bit
+    Idle,
+    // receiving the opcode and the address
+    Receiving,
+    Read(u8),
+    // None for WRAL
+    Write(Option<u8>),
+}
+
+#[derive(Clone)]
+struct Eeprom {
+    // the words are little endian like in the other emulators
+    memory: [u8; EEPROM_SIZE],
+    chip_select: bool,
+    clock: bool,
+    data_in: bool,
+    data_out: bool,
+    is_write_enabled: bool,
+    command: EepromCommand,
+    shift_register: u16,
+    shift_count: u8,
+}
+
+impl Default for Eeprom {
+    fn default() -> Self {
+        Self {
+            memory: [0xff; EEPROM_SIZE],
+            chip_select: false,
+            clock: false,
+            data_in: false,
+            data_out: true,
+            is_write_enabled: false,
+            command: EepromCommand::Idle,
+            shift_register: 0,
+            shift_count: 0,
+        }
+    }
+}
+
+impl Eeprom {
+    fn get_word(&self, address: u8) -> u16 {
+        let index = usize::from(address & 0x7f) * 2;
+        u16::from_le_bytes([self.memory[index], self.memory[index + 1]])
+    }
+
+    fn set_word(&mut self, address: u8, value: u16) {
+        if !self.is_write_enabled {
+            return;
+        }
+        let index = usize::from(address & 0x7f) * 2;
+        self.memory[index..index + 2].copy_from_slice(&value.to_le_bytes());
+    }
+
+    fn start_read(&mut self, address: u8) {
+        self.command = EepromCommand::Read(address & 0x7f);
+        self.shift_register = self.get_word(address);
+        self.shift_count = 0;
+    }
+
+    fn read(&self) -> u8 {
+        (u8::from(self.chip_select) << 7)
+            | (u8::from(self.clock) << 6)
+            | (u8::from(self.data_in) << 1)
+            | u8::from(self.data_out)
+    }
+
+    // bit 7: CS, bit 6: CLK, bit 1: DI
+    fn write(&mut self, value: u8) {
+        let chip_select = value & 0x80 != 0;
+        let clock = value & 0x40 != 0;
+        self.data_in = value & 0x02 != 0;
+
+        if !chip_select {
+            // the writes are instantaneous so the chip is always ready
+            self.command = EepromCommand::Idle;
+            self.data_out = true;
+        } else if !self.clock && clock {
+            self.on_rising_edge();
+        }
+
+        self.chip_select = chip_select;
+        self.clock = clock;
+    }
+
+    fn on_rising_edge(&mut self) {
+        let bit = u16::from(self.data_in);
+        match self.command {
+            EepromCommand::Idle => {
+                // the leading zeros are ignored
+                if self.data_in {
+                    self.command = EepromCommand::Receiving;
+                    self.shift_register = 0;
+                    self.shift_count = 0;
+                }
+            }
+            EepromCommand::Receiving => {
+                self.shift_register = (self.shift_register << 1) | bit;
+                self.shift_count += 1;
+                // 2 bits of opcode and 8 bits of address (the most significant one is ignored)
+                if self.shift_count == 10 {
+                    self.execute_command();
+                }
+            }
+            EepromCommand::Read(address) => {
+                self.data_out = self.shift_register & 0x8000 != 0;
+                self.shift_register <<= 1;
+                self.shift_count += 1;
+                // sequential read
+                if self.shift_count == 16 {
+                    self.start_read(address.wrapping_add(1));
+                }
+            }
+            EepromCommand::Write(address) => {
+                self.shift_register = (self.shift_register << 1) | bit;
+                self.shift_count += 1;
+                if self.shift_count == 16 {
+                    match address {
+                        Some(address) => self.set_word(address, self.shift_register),
+                        None => {
+                            for address in 0..(EEPROM_SIZE / 2) as u8 {
+                                self.set_word(address, self.shift_register);
+                            }
+                        }
+                    }
+                    self.command = EepromCommand::Idle;
+                    self.data_out = true;
+                }
+            }
+        }
+    }
+
+    fn execute_command(&mut self) {
+        let [opcode, address] = self.shift_register.to_be_bytes();
+        self.command = EepromCommand::Idle;
+        match opcode & 0b11 {
+            // READ, a dummy zero is sent before the data
+            0b10 => {
+                self.data_out = false;
+                self.start_read(address);
+            }
+            // WRITE
+            0b01 => {
+                self.command = EepromCommand::Write(Some(address & 0x7f));
+                self.shift_register = 0;
+                self.shift_count = 0;
+            }
+            // ERASE
+            0b11 => self.set_word(address, 0xffff),
+            _ => match address >> 6 {
+                // EWDS
+                0b00 => self.is_write_enabled = false,
+                // WRAL
+                0b01 => {
+                    self.command = EepromCommand::Write(None);
+                    self.shift_register = 0;
+                    self.shift_count = 0;
+                }
+                // ERAL
+                0b10 => {
+                    for address in 0..(EEPROM_SIZE / 2) as u8 {
+                        self.set_word(address, 0xffff);
+                    }
+                }
+                // EWEN
+                _ => self.is_write_enabled = true,
+            },
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Mbc7<T> {
+    rom: T,
+    // 7 bits
+    rom_bank: u8,
+    // both must be enabled to access the registers
+    ram_enabled_1: bool,
+    ram_enabled_2: bool,
+    tilt: Tilt,
+    x_latch: u16,
+    y_latch: u16,
+    is_latch_erased: bool,
+    eeprom: Eeprom,
+}
+
+impl<T: Deref<Target = [u8]>> Mbc7<T> {
+    pub fn new(rom: T) -> Self {
+        Self {
+            rom,
+            rom_bank: 1,
+            ram_enabled_1: false,
+            ram_enabled_2: false,
+            tilt: Tilt::default(),
+            x_latch: ACCELEROMETER_ERASED,
+            y_latch: ACCELEROMETER_ERASED,
+            is_latch_erased: false,
+            eeprom: Eeprom::default(),
+        }
+    }
+
+    fn get_rom_bank_count(&self) -> u8 {
+        // MBC7 can't address more than 2 MiB
+        (get_factor_32_kib_rom(self.rom.deref()).min(64) as u8) << 1
+    }
+
+    fn get_switchable_rom_offset(&self) -> usize {
+        usize::from(self.rom_bank & (self.get_rom_bank_count() - 1)) * usize::from(ROM_BANK_SIZE)
+    }
+
+    fn get_accelerometer_value(axis: f32) -> u16 {
+        (ACCELEROMETER_CENTER + axis * ACCELEROMETER_G).clamp(0.0, f32::from(u16::MAX)) as u16
+    }
+
+    fn are_registers_enabled(&self) -> bool {
+        self.ram_enabled_1 && self.ram_enabled_2
+    }
+}
+
+impl<T: Deref<Target = [u8]>> Mbc for Mbc7<T> {
+    fn read(&self, index: u16) -> u8 {
+        match index {
+            ROM_BANK..SWITCHABLE_ROM_BANK => read_rom(&self.rom, usize::from(index)),
+            SWITCHABLE_ROM_BANK..VIDEO_RAM => read_rom(
+                &self.rom,
+                self.get_switchable_rom_offset() + usize::from(index)
+                    - usize::from(SWITCHABLE_ROM_BANK),
+            ),
+            EXTERNAL_RAM..0xb000 if self.are_registers_enabled() => match (index >> 4) & 0x0f {
+                0x2 => self.x_latch.to_le_bytes()[0],
+                0x3 => self.x_latch.to_le_bytes()[1],
+                0x4 => self.y_latch.to_le_bytes()[0],
+                0x5 => self.y_latch.to_le_bytes()[1],
+                0x6 => 0x00,
+                0x8 => self.eeprom.read(),
+                _ => 0xff,
+            },
+            _ => 0xff,
+        }
+    }
+
+    fn write(&mut self, index: u16, value: u8) {
+        match index {
+            0x0000..0x2000 => self.ram_enabled_1 = value == 0x0a,
+            0x2000..0x4000 => self.rom_bank = value & 0x7f,
+            0x4000..0x6000 => self.ram_enabled_2 = value == 0x40,
+            EXTERNAL_RAM..0xb000 if self.are_registers_enabled() => match (index >> 4) & 0x0f {
+                0x0 if value == 0x55 => {
+                    self.x_latch = ACCELEROMETER_ERASED;
+                    self.y_latch = ACCELEROMETER_ERASED;
+                    self.is_latch_erased = true;
+                }
+                // the latch must be erased before latching again
+                0x1 if value == 0xaa && self.is_latch_erased => {
+                    self.x_latch = Self::get_accelerometer_value(self.tilt.x);
+                    self.y_latch = Self::get_accelerometer_value(self.tilt.y);
+                    self.is_latch_erased = false;
+                }
+                0x8 => self.eeprom.write(value),
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+
+    fn load_saved_ram(&mut self, save: &[u8]) {
+        let min = save.len().min(self.eeprom.memory.len());
+        self.eeprom.memory[..min].copy_from_slice(&save[..min]);
+    }
+
+    fn load_additional_data(&mut self, _: &[u8]) {}
+
+    fn get_ram_to_save(&self) -> Option<&[u8]> {
+        Some(&self.eeprom.memory)
+    }
+
+    fn get_volatile_ram_size(&self) -> usize {
+        0
+    }
+
+    fn get_additional_data_to_save(&self, _: &mut [u8]) -> usize {
+        0
+    }
+
+    fn load_saved_registers(&mut self, registers: &[u8]) {
+        let Ok(
+            [
+                rom_bank,
+                ram_enabled_1,
+                ram_enabled_2,
+                x_latch_high,
+                x_latch_low,
+                y_latch_high,
+                y_latch_low,
+                is_latch_erased,
+                eeprom_pins,
+                is_write_enabled,
+                command,
+                command_address,
+                shift_register_high,
+                shift_register_low,
+                shift_count,
+            ],
+        ) = <[u8; 15]>::try_from(registers)
+        else {
+            log::warn!("Unexpected MBC7 registers length: {}", registers.len());
+            return;
+        };
+        self.rom_bank = rom_bank & 0x7f;
+        self.ram_enabled_1 = ram_enabled_1 != 0;
+        self.ram_enabled_2 = ram_enabled_2 != 0;
+        self.x_latch = u16::from_be_bytes([x_latch_high, x_latch_low]);
+        self.y_latch = u16::from_be_bytes([y_latch_high, y_latch_low]);
+        self.is_latch_erased = is_latch_erased != 0;
+        self.eeprom.chip_select = eeprom_pins & 0x80 != 0;
+        self.eeprom.clock = eeprom_pins & 0x40 != 0;
+        self.eeprom.data_in = eeprom_pins & 0x02 != 0;
+        self.eeprom.data_out = eeprom_pins & 0x01 != 0;
+        self.eeprom.is_write_enabled = is_write_enabled != 0;
+        self.eeprom.command = match command {
+            1 => EepromCommand::Receiving,
+            2 => EepromCommand::Read(command_address & 0x7f),
+            3 => EepromCommand::Write(Some(command_address & 0x7f)),
+            4 => EepromCommand::Write(None),
+            _ => EepromCommand::Idle,
+        };
+        self.eeprom.shift_register = u16::from_be_bytes([shift_register_high, shift_register_low]);
+        // the longest sequence is 16 bits
+        self.eeprom.shift_count = shift_count.min(15);
+    }
+
+    fn get_registers_to_save(&self, buffer: &mut [u8]) -> usize {
+        let [x_latch_high, x_latch_low] = self.x_latch.to_be_bytes();
+        let [y_latch_high, y_latch_low] = self.y_latch.to_be_bytes();
+        let [shift_register_high, shift_register_low] = self.eeprom.shift_register.to_be_bytes();
+        let (command, command_address) = match self.eeprom.command {
+            EepromCommand::Idle => (0, 0),
+            EepromCommand::Receiving => (1, 0),
+            EepromCommand::Read(address) => (2, address),
+            EepromCommand::Write(Some(address)) => (3, address),
+            EepromCommand::Write(None) => (4, 0),
+        };
+        let registers = [
+            self.rom_bank,
+            self.ram_enabled_1.into(),
+            self.ram_enabled_2.into(),
+            x_latch_high,
+            x_latch_low,
+            y_latch_high,
+            y_latch_low,
+            self.is_latch_erased.into(),
+            self.eeprom.read(),
+            self.eeprom.is_write_enabled.into(),
+            command,
+            command_address,
+            shift_register_high,
+            shift_register_low,
+            self.eeprom.shift_count,
+        ];
+        buffer[..registers.len()].copy_from_slice(&registers);
+        registers.len()
+    }
+
+    fn get_rom(&self) -> &[u8] {
+        &self.rom
+    }
+
+    fn set_tilt(&mut self, tilt: Tilt) {
+        self.tilt = tilt;
+    }
+}
@@ -0,0 +1,261 @@
+// https://gbdev.io/pandocs/MMM01.html
+// The behavior of the locked bits is inspired by SameBoy.
+
+use crate::{addresses::*, mbc::*};
+use core::ops::Deref;
+
+// the menu and its header are in the last 32 KiB of the ROM
+pub const MMM01_MENU_SIZE: usize = 0x8000;
+
+#[derive(Clone)]
+pub struct Mmm01<T> {
+    rom: T,
+    // 128 KiB
+    ram: [u8; 0x20000],
+    ram_enabled: bool,
+    // the menu is mapped until the game is selected, then the outer bank bits are locked
+    is_mapped: bool,
+    // RA18-14, the bits set in the mask can't be changed by the game
+    rom_bank_low: u8,
+    // RA20-19
+    rom_bank_mid: u8,
+    // RA22-21
+    rom_bank_high: u8,
+    // RA18-15
+    rom_bank_mask: u8,
+    // RA14-13, the bits set in the mask can't be changed by the game
+    ram_bank_low: u8,
+    // RA16-15
+    ram_bank_high: u8,
+    ram_bank_mask: u8,
+    banking_mode: BankingMode,
+    is_banking_mode_locked: bool,
+    // the low RAM bank bits and the middle ROM bank bits are swapped
+    is_multiplexed: bool,
+}
+
+const MMM01_REGISTERS_SIZE: usize = 12;
+
+impl<T: Deref<Target = [u8]>> Mmm01<T> {
+    pub fn new(rom: T) -> Self {
+        Self {
+            rom,
+            ram: [0; 0x20000],
+            ram_enabled: false,
+            is_mapped: false,
+            rom_bank_low: 0,
+            rom_bank_mid: 0,
+            rom_bank_high: 0,
+            rom_bank_mask: 0,
+            ram_bank_low: 0,
+            ram_bank_high: 0,
+            ram_bank_mask: 0,
+            banking_mode: BankingMode::Simple,
+            is_banking_mode_locked: false,
+            is_multiplexed: false,
+        }
+    }
+
+    fn get_menu_offset(&self) -> usize {
+        self.rom.len().saturating_sub(MMM01_MENU_SIZE)
+    }
+
+    fn get_menu(&self) -> &[u8] {
+        &self.rom[self.get_menu_offset()..]
+    }
+
+    fn get_rom_bank_count(&self) -> u16 {
+        get_factor_32_kib_rom(self.get_menu()) << 1
+    }
+
+    fn get_ram_bank_count(&self) -> u8 {
+        get_factor_8_kib_ram(self.get_menu())
+    }
+
+    // returns the banks mapped at 0000-3FFF and 4000-7FFF
+    fn get_rom_banks(&self) -> (u16, u16) {
+        let (first_middle, middle) = if self.is_multiplexed {
+            // like the MBC1, the RAM bank register only affects 0000-3FFF in advanced mode
+            let first_middle = match self.banking_mode {
+                BankingMode::Simple => 0,
+                BankingMode::Advanced => self.ram_bank_low,
+            };
+            (first_middle, self.ram_bank_low)
+        } else {
+            (self.rom_bank_mid, self.rom_bank_mid)
+        };
+        let high = u16::from(self.rom_bank_high) << 7;
+        let first_bank = high
+            | (u16::from(first_middle) << 5)
+            | u16::from(self.rom_bank_low & (self.rom_bank_mask << 1));
+        let mut bank = high | (u16::from(middle) << 5) | u16::from(self.rom_bank_low);
+        // like the MBC1, the first bank of the game can't be mapped twice
+        if bank == first_bank {
+            bank += 1;
+        }
+        let mask = self.get_rom_bank_count() - 1;
+        (first_bank & mask, bank & mask)
+    }
+
+    fn get_ram_offset(&self, index: u16) -> Option<usize> {
+        if !self.ram_enabled || self.get_ram_bank_count() == 0 {
+            return None;
+        }
+        let low = if self.is_multiplexed {
+            self.rom_bank_mid
+        } else {
+            self.ram_bank_low
+        };
+        let bank = ((self.ram_bank_high << 2) | low) & (self.get_ram_bank_count() - 1);
+        Some(usize::from(bank) * usize::from(RAM_BANK_SIZE) + usize::from(index - EXTERNAL_RAM))
+    }
+}
+
+impl<T: Deref<Target = [u8]>> Mbc for Mmm01<T> {
+    fn read(&self, index: u16) -> u8 {
+        match index {
+            ROM_BANK..VIDEO_RAM if !self.is_mapped => {
+                read_rom(&self.rom, self.get_menu_offset() + usize::from(index))
+            }
+            ROM_BANK..SWITCHABLE_ROM_BANK => read_rom(
+                &self.rom,
+                usize::from(self.get_rom_banks().0) * usize::from(ROM_BANK_SIZE)
+                    + usize::from(index),
+            ),
+            SWITCHABLE_ROM_BANK..VIDEO_RAM => read_rom(
+                &self.rom,
+                usize::from(self.get_rom_banks().1) * usize::from(ROM_BANK_SIZE)
+                    + usize::from(index - SWITCHABLE_ROM_BANK),
+            ),
+            EXTERNAL_RAM..WORK_RAM => self
+                .get_ram_offset(index)
+                .map(|offset| self.ram[offset])
+                .unwrap_or(0xff),
+            _ => 0xff,
+        }
+    }
+
+    fn write(&mut self, index: u16, value: u8) {
+        match index {
+            0x0000..0x2000 => {
+                self.ram_enabled = (value & 0x0f) == 0x0a;
+                if !self.is_mapped {
+                    self.ram_bank_mask = (value >> 4) & 0x03;
+                    self.is_mapped = value & 0x40 != 0;
+                }
+            }
+            0x2000..0x4000 => {
+                if !self.is_mapped {
+                    self.rom_bank_mid = (value >> 5) & 0x03;
+                }
+                let mask = self.rom_bank_mask << 1;
+                self.rom_bank_low = (self.rom_bank_low & mask) | (value & !mask & 0x1f);
+            }
+            0x4000..0x6000 => {
+                let mask = self.ram_bank_mask;
+                self.ram_bank_low = (self.ram_bank_low & mask) | (value & !mask & 0x03);
+                if !self.is_mapped {
+                    self.ram_bank_high = (value >> 2) & 0x03;
+                    self.rom_bank_high = (value >> 4) & 0x03;
+                    self.is_banking_mode_locked = value & 0x40 != 0;
+                }
+            }
+            0x6000..0x8000 => {
+                if !self.is_banking_mode_locked {
+                    self.banking_mode = if value & 1 == 0 {
+                        BankingMode::Simple
+                    } else {
+                        BankingMode::Advanced
+                    };
+                }
+                if !self.is_mapped {
+                    self.rom_bank_mask = (value >> 2) & 0x0f;
+                    self.is_multiplexed = value & 0x40 != 0;
+                }
+            }
+            EXTERNAL_RAM..WORK_RAM => {
+                if let Some(offset) = self.get_ram_offset(index) {
+                    self.ram[offset] = value;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn load_saved_ram(&mut self, save: &[u8]) {
+        let min = save.len().min(self.ram.len());
+        self.ram[..min].copy_from_slice(&save[..min]);
+    }
+
+    fn load_additional_data(&mut self, _: &[u8]) {}
+
+    fn get_ram_to_save(&self) -> Option<&[u8]> {
+        Some(&self.ram)
+    }
+
+    fn get_additional_data_to_save(&self, _: &mut [u8]) -> usize {
+        0
+    }
+
+    fn load_saved_registers(&mut self, registers: &[u8]) {
+        let Ok(
+            [
+                ram_enabled,
+                is_mapped,
+                rom_bank_low,
+                rom_bank_mid,
+                rom_bank_high,
+                rom_bank_mask,
+                ram_bank_low,
+                ram_bank_high,
+                ram_bank_mask,
+                banking_mode,
+                is_banking_mode_locked,
+                is_multiplexed,
+            ],
+        ) = <[u8; MMM01_REGISTERS_SIZE]>::try_from(registers)
+        else {
+            log::warn!("Unexpected MMM01 registers length: {}", registers.len());
+            return;
+        };
+        self.ram_enabled = ram_enabled != 0;
+        self.is_mapped = is_mapped != 0;
+        self.rom_bank_low = rom_bank_low & 0x1f;
+        self.rom_bank_mid = rom_bank_mid & 0x03;
+        self.rom_bank_high = rom_bank_high & 0x03;
+        self.rom_bank_mask = rom_bank_mask & 0x0f;
+        self.ram_bank_low = ram_bank_low & 0x03;
+        self.ram_bank_high = ram_bank_high & 0x03;
+        self.ram_bank_mask = ram_bank_mask & 0x03;
+        self.banking_mode = if banking_mode == 0 {
+            BankingMode::Simple
+        } else {
+            BankingMode::Advanced
+        };
+        self.is_banking_mode_locked = is_banking_mode_locked != 0;
+        self.is_multiplexed = is_multiplexed != 0;
+    }
+
+    fn get_registers_to_save(&self, buffer: &mut [u8]) -> usize {
+        let registers: [u8; MMM01_REGISTERS_SIZE] = [
+            self.ram_enabled.into(),
+            self.is_mapped.into(),
+            self.rom_bank_low,
+            self.rom_bank_mid,
+            self.rom_bank_high,
+            self.rom_bank_mask,
+            self.ram_bank_low,
+            self.ram_bank_high,
+            self.ram_bank_mask,
+            matches!(self.banking_mode, BankingMode::Advanced).into(),
+            self.is_banking_mode_locked.into(),
+            self.is_multiplexed.into(),
+        ];
+        buffer[..registers.len()].copy_from_slice(&registers);
+        registers.len()
+    }
+
+    fn get_rom(&self) -> &[u8] {
+        &self.rom
+    }
+}
@@ -1,22 +1,45 @@
+mod bbd;
 mod huc1;
+mod huc3;
+mod li_cheng;
 mod m161;
 mod mbc1;
 mod mbc1m;
 mod mbc2;
 mod mbc3;
 mod mbc5;
+mod mbc6;
+mod mbc7;
+mod mmm01;
+mod pocket_camera;
+mod rocket;
+mod sachen;
 mod tama5;
 mod wisdom_tree;
 
 use core::ops::Deref;
 
+use crate::{
+    addresses::{ROM_BANK, VIDEO_RAM},
+    joypad::Tilt,
+};
+
+pub use bbd::*;
 pub use huc1::*;
+pub use huc3::*;
+pub use li_cheng::*;
 pub use m161::*;
 pub use mbc1::*;
 pub use mbc1m::*;
 pub use mbc2::*;
 pub use mbc3::*;
 pub use mbc5::*;
+pub use mbc6::*;
+pub use mbc7::*;
+pub use mmm01::*;
+pub use pocket_camera::*;
+pub use rocket::*;
+pub use sachen::*;
 pub use tama5::*;
 pub use wisdom_tree::*;
 
@@ -29,15 +52,56 @@ pub trait Mbc {
     // useful for RTC at the moment
     fn load_additional_data(&mut self, additional_data: &[u8]);
     fn read(&self, address: u16) -> u8;
+    /// What `read` returns, without the side effects of a read by the console, for the debuggers.
+    /// Only the cartridges counting the reads need to override it.
+    fn peek(&self, address: u16) -> u8 {
+        self.read(address)
+    }
     fn write(&mut self, address: u16, value: u8);
     fn get_ram_to_save(&self) -> Option<&[u8]>;
+    /// The first bytes of the save which are RAM, random at power-on. The EEPROM and the flash come
+    /// after them, or instead of them, and stay erased.
+    fn get_volatile_ram_size(&self) -> usize {
+        self.get_ram_to_save().map_or(0, <[u8]>::len)
+    }
     /// Returns how many bytes were written into the buffer. Panics if the buffer is not big enough.
     fn get_additional_data_to_save(&self, buffer: &mut [u8]) -> usize;
+    // banking registers and such, for the save states
+    fn load_saved_registers(&mut self, registers: &[u8]);
+    /// Returns how many bytes were written into the buffer. Panics if the buffer is not big enough.
+    fn get_registers_to_save(&self, buffer: &mut [u8]) -> usize;
+    /// Returns the rumble strength since the last call, between 0 (motor off) and 1 (always on).
+    /// None if the cartridge has no motor. The frontends should call it once per frame.
+    fn take_rumble_intensity(&mut self) -> Option<f32> {
+        None
+    }
+    // only useful for the cartridges with an accelerometer
+    fn set_tilt(&mut self, _: Tilt) {}
+    /// The frequency in Hz of the square wave played by the cartridge speaker (HuC3), if any.
+    fn get_tone_frequency(&self) -> Option<f32> {
+        None
+    }
+    /// None if the cartridge has no infrared port. Like the serial port, a frontend can
+    /// forward the LED state to the sensor of a peer with `set_infrared_light_received`.
+    fn is_infrared_led_on(&self) -> Option<bool> {
+        None
+    }
+    fn set_infrared_light_received(&mut self, _: bool) {}
+    // called once per m-cycle, for the cartridges with their own timing
+    fn execute(&mut self) {}
+}
+
+// Open bus when the ROM is smaller than what the banking selects
+pub(crate) fn read_rom(rom: &[u8], index: usize) -> u8 {
+    rom.get(index).copied().unwrap_or(0xff)
 }
 
 impl<T: Deref<Target = [u8]>> Mbc for T {
     fn read(&self, address: u16) -> u8 {
-        self[usize::from(address)]
+        match address {
+            ROM_BANK..VIDEO_RAM => read_rom(self, usize::from(address)),
+            _ => 0xff,
+        }
     }
 
     fn write(&mut self, _: u16, _: u8) {}
@@ -49,6 +113,10 @@ impl<T: Deref<Target = [u8]>> Mbc for T {
     }
     fn load_saved_ram(&mut self, _: &[u8]) {}
     fn load_additional_data(&mut self, _: &[u8]) {}
+    fn load_saved_registers(&mut self, _: &[u8]) {}
+    fn get_registers_to_save(&self, _: &mut [u8]) -> usize {
+        0
+    }
 
     fn get_rom(&self) -> &[u8] {
         self
@@ -63,17 +131,35 @@ pub enum CartridgeType {
     Mbc1RamBattery,
     Mbc2,
     Mbc2Battery,
+    Mmm01,
+    Mmm01Ram,
+    Mmm01RamBattery,
     Mbc3TimerBattery,
     Mbc3TimerRamBattery,
     Mbc3,
     Mbc3Ram,
     Mbc3RamBattery,
     Mbc5,
+    Mbc5Ram,
     Mbc5RamBattery,
+    Mbc5Rumble,
+    Mbc5RumbleRam,
+    Mbc5RumbleRamBattery,
+    Mbc6,
+    Mbc7,
+    PocketCamera,
     Tama5,
     Huc1,
+    Huc3,
+    // the unlicensed mappers are only detected by the frontends
     WisdomTree,
     M161,
+    SachenMmc1,
+    SachenMmc2,
+    Rocket,
+    Bbd,
+    Hitek,
+    LiCheng,
 }
 
 impl CartridgeType {
@@ -83,14 +169,29 @@ impl CartridgeType {
             self,
             Mbc1RamBattery
                 | Mbc2Battery
+                | Mmm01RamBattery
                 | Mbc3TimerBattery
                 | Mbc3TimerRamBattery
                 | Mbc3RamBattery
                 | Mbc5RamBattery
+                | Mbc5RumbleRamBattery
+                | Mbc6
+                | Mbc7
+                | PocketCamera
                 | Tama5
                 | Huc1
+                | Huc3
+                // the MBC5 clones, their RAM size isn't known
+                | Bbd
+                | Hitek
+                | LiCheng
         )
     }
+
+    pub fn has_rumble(&self) -> bool {
+        use CartridgeType::*;
+        matches!(self, Mbc5Rumble | Mbc5RumbleRam | Mbc5RumbleRamBattery)
+    }
 }
 
 impl TryFrom<u8> for CartridgeType {
@@ -105,14 +206,26 @@ impl TryFrom<u8> for CartridgeType {
             3 => Ok(Self::Mbc1RamBattery),
             5 => Ok(Self::Mbc2),
             6 => Ok(Self::Mbc2Battery),
+            0x0b => Ok(Self::Mmm01),
+            0x0c => Ok(Self::Mmm01Ram),
+            0x0d => Ok(Self::Mmm01RamBattery),
             0x0f => Ok(Self::Mbc3TimerBattery),
             0x10 => Ok(Self::Mbc3TimerRamBattery),
             0x11 => Ok(Self::Mbc3),
             0x12 => Ok(Self::Mbc3Ram),
             0x13 => Ok(Self::Mbc3RamBattery),
             0x19 => Ok(Self::Mbc5),
+            0x1a => Ok(Self::Mbc5Ram),
             0x1b => Ok(Self::Mbc5RamBattery),
+            0x1c => Ok(Self::Mbc5Rumble),
+            0x1d => Ok(Self::Mbc5RumbleRam),
+            0x1e => Ok(Self::Mbc5RumbleRamBattery),
+            0x20 => Ok(Self::Mbc6),
+            // MBC7+SENSOR+RUMBLE+RAM+BATTERY
+            0x22 => Ok(Self::Mbc7),
+            0xfc => Ok(Self::PocketCamera),
             0xfd => Ok(Self::Tama5),
+            0xfe => Ok(Self::Huc3),
             0xff => Ok(Self::Huc1),
             _ => Err(value),
         }
@@ -122,20 +235,576 @@ impl TryFrom<u8> for CartridgeType {
 pub const ROM_BANK_SIZE: u16 = 16384;
 pub const RAM_BANK_SIZE: u16 = 8192;
 
+pub const CARTRIDGE_TYPE_HEADER: usize = 0x147;
 pub const ROM_SIZE_HEADER: usize = 0x148;
+pub const RAM_SIZE_HEADER: usize = 0x149;
 
+// https://gbdev.io/pandocs/The_Cartridge_Header.html#0148--rom-size
+// The frontends reject the unknown values, here they are clamped to 8 MiB to never panic.
 pub fn get_factor_32_kib_rom(rom: &[u8]) -> u16 {
-    1 << rom[ROM_SIZE_HEADER]
+    1 << rom.get(ROM_SIZE_HEADER).copied().unwrap_or(0).min(8)
 }
 
 // https://gbdev.io/pandocs/The_Cartridge_Header.html#0149--ram-size
 pub fn get_factor_8_kib_ram(rom: &[u8]) -> u8 {
-    match rom[0x149] {
-        0 => 0,
-        2 => 1,
-        3 => 4,
-        4 => 16,
-        5 => 8,
-        _ => panic!(),
+    match rom.get(RAM_SIZE_HEADER) {
+        Some(2) => 1,
+        Some(3) => 4,
+        Some(4) => 16,
+        Some(5) => 8,
+        // no RAM, or an unknown value already rejected by the frontends
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct FrozenRtc(RtcRegisters);
+
+    impl Rtc for FrozenRtc {
+        fn get_clock_data(&mut self) -> RtcRegisters {
+            self.0
+        }
+        fn set_clock_data(&mut self, registers: RtcRegisters) {
+            self.0 = registers;
+        }
+        fn deserialize(&mut self, _: &[u8]) {}
+        fn serialize(&self, _: &mut [u8]) -> usize {
+            0
+        }
+    }
+
+    // counts whole seconds from a clock in milliseconds, like a real RTC
+    struct MillisRtc<'a> {
+        now: &'a core::cell::Cell<u64>,
+        base: u64,
+        base_seconds: u32,
+    }
+
+    impl Rtc for MillisRtc<'_> {
+        fn get_clock_data(&mut self) -> RtcRegisters {
+            let seconds = self.base_seconds + ((self.now.get() - self.base) / 1000) as u32;
+            RtcRegisters::from_seconds(seconds, false, false)
+        }
+        fn set_clock_data(&mut self, registers: RtcRegisters) {
+            self.base = self.now.get();
+            self.base_seconds = registers.get_total_seconds();
+        }
+        fn deserialize(&mut self, _: &[u8]) {}
+        fn serialize(&self, _: &mut [u8]) -> usize {
+            0
+        }
+    }
+
+    #[derive(Clone)]
+    struct FlatSensor(u8);
+
+    impl ImageSensor for FlatSensor {
+        fn capture(&mut self, image: &mut SensorImage) {
+            for row in image {
+                row.fill(self.0);
+            }
+        }
+    }
+
+    // every value written to every address, then read back
+    fn sweep(mbc: &mut dyn Mbc) {
+        for value in 0..=u8::MAX {
+            for address in 0..=u16::MAX {
+                mbc.write(address, value);
+                mbc.read(address);
+            }
+        }
+    }
+
+    #[test]
+    fn no_panic_on_any_address() {
+        // the second header announces a 2 MiB ROM with 32 KiB of RAM but the ROM is only 32 KiB,
+        // the third one has unknown sizes
+        for (rom_size, ram_size) in [(0x00, 0x00), (0x06, 0x03), (0xff, 0xff)] {
+            let mut rom = [0; 0x8000];
+            rom[ROM_SIZE_HEADER] = rom_size;
+            rom[RAM_SIZE_HEADER] = ram_size;
+            let rom = rom.as_slice();
+            sweep(&mut &*rom);
+            sweep(&mut Mbc1::new(rom));
+            sweep(&mut Mbc1M::new(rom));
+            sweep(&mut Mbc2::new(rom));
+            sweep(&mut Mmm01::new(rom));
+            sweep(&mut Mbc3::new(rom, FrozenRtc::default()));
+            sweep(&mut Mbc5::new(rom));
+            // a smaller save, the flash accesses beyond it are ignored
+            sweep(&mut Mbc6::new(rom, &mut [0xff; MBC6_RAM_SIZE + 0x8000][..]));
+            sweep(&mut Mbc7::new(rom));
+            sweep(&mut PocketCamera::new(rom, FlatSensor(0x80)));
+            sweep(&mut Huc1::new(rom));
+            sweep(&mut Huc3::new(rom, FrozenRtc::default()));
+            sweep(&mut Tama5::new(rom, FrozenRtc::default()));
+            sweep(&mut WisdomTree::new(rom));
+            sweep(&mut M161::new(rom));
+            sweep(&mut Sachen::new(rom, SachenModel::Mmc1));
+            sweep(&mut Sachen::new(rom, SachenModel::Mmc2));
+            sweep(&mut Rocket::new(rom));
+            sweep(&mut Bbd::new(rom, BbdWiring::Bbd));
+            sweep(&mut Bbd::new(rom, BbdWiring::Hitek));
+            sweep(&mut LiCheng::new(rom));
+        }
+    }
+
+    #[test]
+    fn mbc5_rumble() {
+        let mut rom = [0; 0x8000];
+        rom[CARTRIDGE_TYPE_HEADER] = 0x1e;
+        rom[RAM_SIZE_HEADER] = 0x03;
+        let mut mbc = Mbc5::new(rom.as_slice());
+        mbc.write(0x0000, 0x0a);
+        assert_eq!(mbc.take_rumble_intensity(), Some(0.0));
+
+        // the motor bit doesn't select another RAM bank
+        mbc.write(0x4000, 0x08);
+        mbc.write(0xa000, 0x42);
+        mbc.write(0x4000, 0x00);
+        assert_eq!(mbc.read(0xa000), 0x42);
+        assert_eq!(mbc.take_rumble_intensity(), Some(0.5));
+
+        mbc.write(0x4000, 0x08);
+        assert_eq!(mbc.take_rumble_intensity(), Some(1.0));
+        // no write during the last frame, the motor is still on
+        assert_eq!(mbc.take_rumble_intensity(), Some(1.0));
+
+        rom[CARTRIDGE_TYPE_HEADER] = 0x1b;
+        assert_eq!(Mbc5::new(rom.as_slice()).take_rumble_intensity(), None);
+    }
+
+    // start bit and opcode, then the address and the data, MSB first
+    fn send_to_eeprom(mbc: &mut dyn Mbc, bits: u32, count: u8) {
+        for i in (0..count).rev() {
+            let data_in = if bits & (1 << i) != 0 { 0x02 } else { 0 };
+            mbc.write(0xa080, 0x80 | data_in);
+            mbc.write(0xa080, 0xc0 | data_in);
+        }
+    }
+
+    #[test]
+    fn mbc7() {
+        let mut rom = [0; 0x8000];
+        rom[CARTRIDGE_TYPE_HEADER] = 0x22;
+        let mut mbc = Mbc7::new(rom.as_slice());
+        mbc.write(0x0000, 0x0a);
+        assert_eq!(mbc.read(0xa080), 0xff);
+        mbc.write(0x4000, 0x40);
+
+        mbc.set_tilt(Tilt { x: 1.0, y: -1.0 });
+        // not erased yet
+        mbc.write(0xa010, 0xaa);
+        assert_eq!([mbc.read(0xa020), mbc.read(0xa030)], [0x00, 0x80]);
+        mbc.write(0xa000, 0x55);
+        mbc.write(0xa010, 0xaa);
+        assert_eq!([mbc.read(0xa020), mbc.read(0xa030)], [0x40, 0x82]);
+        assert_eq!([mbc.read(0xa040), mbc.read(0xa050)], [0x60, 0x81]);
+
+        // EWEN
+        send_to_eeprom(&mut mbc, 0b100, 3);
+        send_to_eeprom(&mut mbc, 0b1100_0000, 8);
+        mbc.write(0xa080, 0x00);
+        // WRITE
+        send_to_eeprom(&mut mbc, 0b101, 3);
+        send_to_eeprom(&mut mbc, 5, 8);
+        send_to_eeprom(&mut mbc, 0x1234, 16);
+        mbc.write(0xa080, 0x00);
+        // READ
+        send_to_eeprom(&mut mbc, 0b110, 3);
+        send_to_eeprom(&mut mbc, 5, 8);
+        assert_eq!(mbc.read(0xa080) & 1, 0);
+        let mut word = 0u16;
+        for _ in 0..16 {
+            send_to_eeprom(&mut mbc, 0, 1);
+            word = (word << 1) | u16::from(mbc.read(0xa080) & 1);
+        }
+        mbc.write(0xa080, 0x00);
+        assert_eq!(word, 0x1234);
+        assert_eq!(mbc.get_ram_to_save().unwrap()[10..12], [0x34, 0x12]);
+    }
+
+    #[test]
+    fn huc3_rtc() {
+        let mut rom = [0; 0x8000];
+        rom[CARTRIDGE_TYPE_HEADER] = 0xfe;
+        let mut mbc = Huc3::new(rom.as_slice(), FrozenRtc::default());
+
+        // 0x123 minutes and 0x456 days, more than the 511 days of the Rtc trait
+        mbc.write(0x0000, 0x0b);
+        mbc.write(0xa000, 0x40);
+        mbc.write(0xa000, 0x50);
+        for nibble in [0x3, 0x2, 0x1, 0x6, 0x5, 0x4, 0x0] {
+            mbc.write(0xa000, 0x30 | nibble);
+        }
+
+        mbc.write(0xa000, 0x40);
+        let mut nibbles = [0; 7];
+        for nibble in &mut nibbles {
+            mbc.write(0x0000, 0x0b);
+            mbc.write(0xa000, 0x10);
+            mbc.write(0x0000, 0x0c);
+            *nibble = mbc.read(0xa000);
+        }
+        assert_eq!(nibbles, [0x3, 0x2, 0x1, 0x6, 0x5, 0x4, 0x0]);
+
+        mbc.write(0x0000, 0x0e);
+        assert_eq!(mbc.is_infrared_led_on(), Some(false));
+        mbc.write(0xa000, 0x01);
+        assert_eq!(mbc.is_infrared_led_on(), Some(true));
+        mbc.set_infrared_light_received(true);
+        assert_eq!(mbc.read(0xa000), 0xc1);
+    }
+
+    #[test]
+    fn tama5_rtc() {
+        let rom = [0; 0x8000];
+        // 59 days and 10 seconds
+        let rtc = FrozenRtc(RtcRegisters::from_seconds(
+            59 * 24 * 60 * 60 + 10,
+            false,
+            false,
+        ));
+        let mut mbc = Tama5::new(rom.as_slice(), rtc);
+
+        // 2023-12-31 23:59:50, a sunday, in 24 hours mode, 3 years after a leap year
+        // the RTC was at 0 at the last latch
+        let mut data = [0; 37];
+        data[..8].copy_from_slice(&[0x05, 0x95, 0x32, 0x01, 0x32, 0x13, 0x20, 0x00]);
+        data[8..16].copy_from_slice(&[0x00, 0x00, 0x00, 0x00, 0x00, 0x13, 0x01, 0x00]);
+        mbc.load_additional_data(&data);
+
+        let read_timer = |mbc: &mut Tama5<_, _>, address| {
+            for (register, value) in [(0x4, address), (0x6, 0x8), (0x7, 0x1)] {
+                mbc.write(0xa001, register);
+                mbc.write(0xa000, value);
+            }
+            mbc.write(0xa001, 0xc);
+            mbc.read(0xa000) & 0x0f
+        };
+        let mut timer = [0; 13];
+        for (address, nibble) in (0..).zip(&mut timer) {
+            *nibble = read_timer(&mut mbc, address);
+        }
+        // 2024-02-29 00:00:00, a thursday
+        assert_eq!(timer, [0, 0, 0, 0, 0, 0, 4, 9, 2, 2, 0, 4, 2]);
+
+        let mut buffer = [0; 64];
+        let length = mbc.get_additional_data_to_save(&mut buffer);
+        assert_eq!(length, 37);
+        // the leap year counter is reset
+        assert_eq!(buffer[13], 0x10);
+    }
+
+    #[test]
+    fn tama5_rtc_latched_often() {
+        let rom = [0; 0x8000];
+        let now = core::cell::Cell::new(0);
+        let mut mbc = Tama5::new(
+            rom.as_slice(),
+            MillisRtc {
+                now: &now,
+                base: 0,
+                base_seconds: 0,
+            },
+        );
+        // enable timer, then RAM reads
+        for (register, value) in [(0x6, 0x4), (0x7, 0x1), (0x6, 0x2)] {
+            mbc.write(0xa001, register);
+            mbc.write(0xa000, value);
+        }
+        // each RAM read latches, 10 times per second during 3 seconds
+        for _ in 0..30 {
+            now.set(now.get() + 100);
+            mbc.write(0xa001, 0x7);
+            mbc.write(0xa000, 0x0);
+        }
+        // read the seconds of the timer page
+        for (register, value) in [(0x4, 0x0), (0x6, 0x8), (0x7, 0x1)] {
+            mbc.write(0xa001, register);
+            mbc.write(0xa000, value);
+        }
+        mbc.write(0xa001, 0xc);
+        assert_eq!(mbc.read(0xa000) & 0x0f, 3);
+    }
+
+    #[test]
+    fn tama5_alarm() {
+        let rom = [0; 0x8000];
+        let now = core::cell::Cell::new(0);
+        let mut mbc = Tama5::new(
+            rom.as_slice(),
+            MillisRtc {
+                now: &now,
+                base: 0,
+                base_seconds: 0,
+            },
+        );
+        // 2023-12-31 23:59:50, a sunday, the alarm on monday the 1st at 00:00
+        let mut data = [0; 37];
+        data[..8].copy_from_slice(&[0x05, 0x95, 0x32, 0x01, 0x32, 0x13, 0x20, 0x00]);
+        data[8..16].copy_from_slice(&[0x00, 0x00, 0x00, 0x11, 0x00, 0x13, 0x01, 0x00]);
+        mbc.load_additional_data(&data);
+        let command = |mbc: &mut Tama5<_, _>, address_hi, address_lo| {
+            mbc.write(0xa001, 0x6);
+            mbc.write(0xa000, address_hi);
+            mbc.write(0xa001, 0x7);
+            mbc.write(0xa000, address_lo);
+        };
+        const ENABLE_ALARM: u8 = 0x1;
+        const DISABLE_ALARM: u8 = 0x0;
+        const RAM_READ: u8 = 0x0;
+
+        command(&mut mbc, 0x5, ENABLE_ALARM);
+        assert!(!mbc.is_alarm_ringing());
+        // each command latches the clock
+        now.set(10_000);
+        command(&mut mbc, 0x2, RAM_READ);
+        assert!(mbc.is_alarm_ringing());
+        command(&mut mbc, 0x5, DISABLE_ALARM);
+        assert!(!mbc.is_alarm_ringing());
+        command(&mut mbc, 0x5, ENABLE_ALARM);
+        assert!(mbc.is_alarm_ringing());
+        now.set(70_000);
+        command(&mut mbc, 0x2, RAM_READ);
+        assert!(!mbc.is_alarm_ringing());
+        // a monday the 8th
+        now.set(10_000 + 7 * 24 * 60 * 60 * 1000);
+        command(&mut mbc, 0x2, RAM_READ);
+        assert!(!mbc.is_alarm_ringing());
+    }
+
+    #[test]
+    fn mmm01() {
+        // 8 banks, the menu is in the last two
+        let mut rom = [0; 0x20000];
+        for (bank, chunk) in (0..).zip(rom.chunks_exact_mut(usize::from(ROM_BANK_SIZE))) {
+            chunk[0] = bank;
+        }
+        rom[0x18000 + CARTRIDGE_TYPE_HEADER] = 0x0d;
+        rom[0x18000 + ROM_SIZE_HEADER] = 0x02;
+        let mut mbc = Mmm01::new(rom.as_slice());
+        assert_eq!([mbc.read(0x0000), mbc.read(0x4000)], [6, 7]);
+
+        // the menu maps a 32 KiB game at bank 2 and locks the outer bits
+        mbc.write(0x2000, 0x02);
+        mbc.write(0x6000, 0x0f << 2);
+        mbc.write(0x0000, 0x40);
+        assert_eq!([mbc.read(0x0000), mbc.read(0x4000)], [2, 3]);
+
+        mbc.write(0x2000, 0x00);
+        assert_eq!(mbc.read(0x4000), 3);
+        mbc.write(0x2000, 0x1f);
+        assert_eq!(mbc.read(0x4000), 3);
+        mbc.write(0x0000, 0x00);
+        assert_eq!([mbc.read(0x0000), mbc.read(0x4000)], [2, 3]);
+    }
+
+    #[test]
+    fn mmm01_menu_and_game() {
+        // 16 banks, a 64 KiB game at banks 4 to 7, the menu and 32 KiB of RAM in the last two
+        let mut rom = [0; 0x40000];
+        for (bank, chunk) in (0..).zip(rom.chunks_exact_mut(usize::from(ROM_BANK_SIZE))) {
+            chunk[0] = bank;
+        }
+        rom[0x38000 + CARTRIDGE_TYPE_HEADER] = 0x0d;
+        rom[0x38000 + ROM_SIZE_HEADER] = 0x03;
+        rom[0x38000 + RAM_SIZE_HEADER] = 0x03;
+        let mut mbc = Mmm01::new(rom.as_slice());
+
+        // the menu stays mapped while it sets the registers
+        mbc.write(0x2000, 0x04);
+        mbc.write(0x4000, 0x01);
+        // the 2 low bits of the ROM bank and the high bit of the RAM bank are left to the game
+        mbc.write(0x6000, 0x0e << 2);
+        assert_eq!([mbc.read(0x0000), mbc.read(0x4000)], [14, 15]);
+
+        // enables the RAM, locks the low bit of the RAM bank and maps the game
+        mbc.write(0x0000, 0x5a);
+        assert_eq!([mbc.read(0x0000), mbc.read(0x4000)], [4, 5]);
+        mbc.write(0x2000, 0x02);
+        assert_eq!(mbc.read(0x4000), 6);
+        mbc.write(0x2000, 0x1f);
+        assert_eq!(mbc.read(0x4000), 7);
+
+        // the game can't unlock the menu or change the masks
+        mbc.write(0x0000, 0x0a);
+        mbc.write(0x6000, 0x00);
+        mbc.write(0x2000, 0x00);
+        assert_eq!([mbc.read(0x0000), mbc.read(0x4000)], [4, 5]);
+
+        // the RAM bank 3, the low bit stays set when the game selects the bank 2
+        mbc.write(0x4000, 0x02);
+        mbc.write(0xa000, 0x33);
+        mbc.write(0x4000, 0x00);
+        assert_eq!(mbc.read(0xa000), 0x00);
+        mbc.write(0xa000, 0x11);
+        mbc.write(0x4000, 0x02);
+        assert_eq!(mbc.read(0xa000), 0x33);
+        assert_eq!(mbc.get_ram_to_save().unwrap()[0x6000], 0x33);
+        assert_eq!(mbc.get_ram_to_save().unwrap()[0x2000], 0x11);
+    }
+
+    #[test]
+    fn mbc30() {
+        // the header announces 4 MiB of ROM and 64 KiB of RAM, only the registers are checked
+        let mut rom = [0; 0x8000];
+        rom[CARTRIDGE_TYPE_HEADER] = 0x10;
+        rom[ROM_SIZE_HEADER] = 0x07;
+        rom[RAM_SIZE_HEADER] = 0x05;
+        let mut mbc = Mbc3::new(rom.as_slice(), FrozenRtc::default());
+        mbc.write(0x2000, 0xff);
+        let mut registers = [0; 10];
+        mbc.get_registers_to_save(&mut registers);
+        assert_eq!(registers[..2], [0x00, 0xff]);
+
+        mbc.write(0x0000, 0x0a);
+        for bank in 0..8 {
+            mbc.write(0x4000, bank);
+            mbc.write(0xa000, bank + 1);
+        }
+        mbc.write(0x4000, 0x03);
+        assert_eq!(mbc.read(0xa000), 4);
+        let ram = mbc.get_ram_to_save().unwrap();
+        assert_eq!(ram.len(), 0x10000);
+        assert_eq!(ram[7 * usize::from(RAM_BANK_SIZE)], 8);
+
+        // a regular MBC3 with 32 KiB of RAM ignores the third bit of the RAM bank
+        rom[ROM_SIZE_HEADER] = 0x06;
+        rom[RAM_SIZE_HEADER] = 0x03;
+        let mut mbc = Mbc3::new(rom.as_slice(), FrozenRtc::default());
+        mbc.write(0x2000, 0xff);
+        mbc.get_registers_to_save(&mut registers);
+        assert_eq!(registers[..2], [0x00, 0x7f]);
+        mbc.write(0x0000, 0x0a);
+        mbc.write(0x4000, 0x07);
+        mbc.write(0xa000, 0x42);
+        mbc.write(0x4000, 0x03);
+        assert_eq!(mbc.read(0xa000), 0x42);
+        assert_eq!(mbc.get_ram_to_save().unwrap().len(), 0x8000);
+    }
+
+    #[test]
+    fn sachen() {
+        // the Sachen logo at $0184, the Nintendo one at $0104, both scrambled
+        let mut rom = [0; 0x10000];
+        for offset in 0..0x30 {
+            rom[usize::from(unscramble_sachen_address(0x184 + offset))] = 0x5a;
+            rom[usize::from(unscramble_sachen_address(0x104 + offset))] = 0xce;
+        }
+        rom[0x8000] = 2;
+        rom[0xc000] = 3;
+        let mut mbc = Sachen::new(rom.as_slice(), SachenModel::Mmc1);
+        // the debuggers don't unlock the mapper
+        for _ in 0..0x100 {
+            assert_eq!(mbc.peek(0x0104), 0x5a);
+        }
+        for _ in 0..0x30 {
+            assert_eq!(mbc.read(0x0104), 0x5a);
+        }
+        for _ in 0..0x30 {
+            assert_eq!(mbc.read(0x0104), 0xce);
+        }
+
+        // the outer bank registers are only writable with the bits 4 and 5 of the bank
+        mbc.write(0x2000, 0x30);
+        mbc.write(0x0000, 0x02);
+        mbc.write(0x4000, 0x02);
+        mbc.write(0x2000, 0x01);
+        assert_eq!([mbc.read(0x0000), mbc.read(0x4000)], [2, 3]);
+        mbc.write(0x0000, 0x00);
+        assert_eq!(mbc.read(0x0000), 2);
+    }
+
+    #[test]
+    fn bbd() {
+        let mut rom = [0; 0x8000];
+        rom[usize::from(ROM_BANK_SIZE)] = 0b0000_0110;
+        let mut mbc = Bbd::new(rom.as_slice(), BbdWiring::Bbd);
+        // Digimon: the bit 0 of the bank comes from the bit 3 of the value
+        mbc.write(0x2080, 0x03);
+        mbc.write(0x2001, 0x07);
+        mbc.write(0x2000, 0b0000_1000);
+        // the bits 2 and 5 of the data are swapped
+        assert_eq!(mbc.read(0x4000), 0b0010_0010);
+        // Garou: the bits 1, 2 and 5 are rotated
+        mbc.write(0x2001, 0x04);
+        assert_eq!(mbc.read(0x4000), 0b0010_0100);
+
+        let mut registers = [0; 6];
+        assert_eq!(mbc.get_registers_to_save(&mut registers), 6);
+        let mut other = Bbd::new(rom.as_slice(), BbdWiring::Bbd);
+        other.load_saved_registers(&registers);
+        assert_eq!(other.read(0x4000), 0b0010_0100);
+    }
+
+    #[test]
+    fn mbc6_flash() {
+        let rom = [0; 0x8000];
+        let mut save = [0xff; MBC6_RAM_SIZE + 0x8000];
+        let mut mbc = Mbc6::new(rom.as_slice(), &mut save[..]);
+        mbc.write(0x0c00, 0x01);
+        mbc.write(0x1000, 0x01);
+        // flash bank 2 at 4000-5FFF and flash bank 1 at 6000-7FFF, to reach $5555 and $2AAA
+        mbc.write(0x2000, 0x02);
+        mbc.write(0x2800, 0x08);
+        mbc.write(0x3000, 0x01);
+        mbc.write(0x3800, 0x08);
+        let command = |mbc: &mut Mbc6<_, _>, command| {
+            mbc.write(0x5555, 0xaa);
+            mbc.write(0x6aaa, 0x55);
+            mbc.write(0x5555, command);
+        };
+
+        command(&mut mbc, 0x90);
+        assert_eq!([mbc.read(0x4000), mbc.read(0x4001)], [0xc2, 0x81]);
+        mbc.write(0x4000, 0xf0);
+        assert_eq!(mbc.read(0x4000), 0xff);
+
+        command(&mut mbc, 0xa0);
+        mbc.write(0x4010, 0x12);
+        mbc.write(0x4011, 0x34);
+        assert_eq!([mbc.read(0x4010), mbc.read(0x4011)], [0x12, 0x34]);
+
+        // sector erase
+        command(&mut mbc, 0x80);
+        mbc.write(0x5555, 0xaa);
+        mbc.write(0x6aaa, 0x55);
+        mbc.write(0x4000, 0x30);
+        assert_eq!(mbc.read(0x4010), 0xff);
+    }
+
+    #[test]
+    fn pocket_camera() {
+        let rom = [0; 0x8000];
+        let mut mbc = PocketCamera::new(rom.as_slice(), FlatSensor(0x80));
+        mbc.write(0x4000, 0x10);
+        // exposure
+        mbc.write(0xa002, 0x10);
+        mbc.write(0xa003, 0x00);
+        for pixel in 0..16 {
+            for (threshold, value) in (0..).zip([0x40, 0x90, 0xc0]) {
+                mbc.write(0xa006 + pixel * 3 + threshold, value);
+            }
+        }
+        mbc.write(0xa000, 0x01);
+
+        let mut cycles = 0;
+        while mbc.read(0xa000) & 1 != 0 {
+            mbc.execute();
+            cycles += 1;
+        }
+        assert_eq!(cycles, 32446 + 512 + 16 * 0x1000);
+
+        // the whole picture has the second darkest color
+        mbc.write(0x4000, 0x00);
+        assert_eq!([mbc.read(0xa100), mbc.read(0xa101)], [0x00, 0xff]);
+        assert_eq!([mbc.read(0xaefe), mbc.read(0xaeff)], [0x00, 0xff]);
+        assert_eq!(mbc.read(0xaf00), 0x00);
     }
 }
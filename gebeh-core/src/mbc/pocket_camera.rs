@@ -0,0 +1,259 @@
+// https://gbdev.io/pandocs/Gameboy_Camera.html
+// The image processing of the M64282FP sensor is only approximated, the games adjust the exposure
+// by themselves until the picture looks right.
+
+use crate::{addresses::*, mbc::*};
+use core::ops::Deref;
+
+// the sensor has 128x128 pixels but the camera only keeps 112 lines
+pub const SENSOR_WIDTH: usize = 128;
+pub const SENSOR_HEIGHT: usize = 112;
+
+pub type SensorImage = [[u8; SENSOR_WIDTH]; SENSOR_HEIGHT];
+
+/// What the camera sees, supplied by the frontend (a still image, a webcam, a test pattern...).
+pub trait ImageSensor {
+    /// Fills the image with luminance values, 0 is black and 255 is white.
+    fn capture(&mut self, image: &mut SensorImage);
+}
+
+const CAMERA_REGISTERS_SIZE: usize = 0x36;
+const SHOOT_REGISTER: usize = 0x0;
+const EDGE_AND_GAIN_REGISTER: usize = 0x1;
+const EXPOSURE_HIGH_REGISTER: usize = 0x2;
+const EXPOSURE_LOW_REGISTER: usize = 0x3;
+const EDGE_RATIO_AND_INVERT_REGISTER: usize = 0x4;
+// 4x4 pixels, 3 thresholds per pixel
+const DITHER_MATRIX_REGISTER: usize = 0x6;
+// the picture is written as tiles in the first RAM bank
+const IMAGE_RAM_OFFSET: usize = 0x100;
+// in quarters, selected by bits 4-6 of the register 4
+const EDGE_RATIOS: [i32; 8] = [2, 3, 4, 5, 8, 12, 16, 20];
+
+#[derive(Clone)]
+pub struct PocketCamera<T, U> {
+    rom: T,
+    // 6 bits
+    rom_bank: u8,
+    // the camera registers are mapped instead of the RAM when bit 4 is set
+    ram_bank: u8,
+    // the RAM can always be read
+    ram_write_enabled: bool,
+    // 128 KiB
+    ram: [u8; 0x20000],
+    sensor: U,
+    registers: [u8; CAMERA_REGISTERS_SIZE],
+    // m-cycles before the end of the capture
+    capture_cycles: u32,
+}
+
+const POCKET_CAMERA_REGISTERS_SIZE: usize = 3 + CAMERA_REGISTERS_SIZE + 4;
+
+impl<T: Deref<Target = [u8]>, U: ImageSensor> PocketCamera<T, U> {
+    pub fn new(rom: T, sensor: U) -> Self {
+        Self {
+            rom,
+            rom_bank: 1,
+            ram_bank: 0,
+            ram_write_enabled: false,
+            ram: [0; 0x20000],
+            sensor,
+            registers: [0; CAMERA_REGISTERS_SIZE],
+            capture_cycles: 0,
+        }
+    }
+
+    fn get_rom_bank_count(&self) -> u16 {
+        // the camera can't address more than 1 MiB
+        get_factor_32_kib_rom(self.rom.deref()).min(32) << 1
+    }
+
+    fn get_ram_offset(&self, index: u16) -> usize {
+        usize::from(self.ram_bank & 0x0f) * usize::from(RAM_BANK_SIZE)
+            + usize::from(index - EXTERNAL_RAM)
+    }
+
+    fn are_registers_mapped(&self) -> bool {
+        self.ram_bank & 0x10 != 0
+    }
+
+    fn get_exposure(&self) -> u16 {
+        u16::from_be_bytes([
+            self.registers[EXPOSURE_HIGH_REGISTER],
+            self.registers[EXPOSURE_LOW_REGISTER],
+        ])
+    }
+
+    fn start_capture(&mut self) {
+        // https://gbdev.io/pandocs/Gameboy_Camera.html#exposure-time
+        let n = self.registers[EDGE_AND_GAIN_REGISTER] & 0x80 != 0;
+        self.capture_cycles = 32446 + if n { 0 } else { 512 } + 16 * u32::from(self.get_exposure());
+    }
+
+    fn get_processed_image(&mut self) -> SensorImage {
+        let mut image = [[0; SENSOR_WIDTH]; SENSOR_HEIGHT];
+        self.sensor.capture(&mut image);
+
+        let exposure = i32::from(self.get_exposure());
+        let edge_mode = (self.registers[EDGE_AND_GAIN_REGISTER] >> 5) & 0x03;
+        let edge_ratio =
+            EDGE_RATIOS[usize::from((self.registers[EDGE_RATIO_AND_INVERT_REGISTER] >> 4) & 0x07)];
+        let is_inverted = self.registers[EDGE_RATIO_AND_INVERT_REGISTER] & 0x08 != 0;
+
+        let get = |x: usize, y: usize| i32::from(image[y][x]) * exposure / 0x1000;
+        let mut processed = [[0; SENSOR_WIDTH]; SENSOR_HEIGHT];
+        for (y, row) in processed.iter_mut().enumerate() {
+            for (x, pixel) in row.iter_mut().enumerate() {
+                let center = get(x, y);
+                let mut edge = 0;
+                // horizontal
+                if edge_mode & 1 != 0 {
+                    edge += 2 * center
+                        - get(x.saturating_sub(1), y)
+                        - get((x + 1).min(SENSOR_WIDTH - 1), y);
+                }
+                // vertical
+                if edge_mode & 2 != 0 {
+                    edge += 2 * center
+                        - get(x, y.saturating_sub(1))
+                        - get(x, (y + 1).min(SENSOR_HEIGHT - 1));
+                }
+                let value = (center + edge * edge_ratio / 4).clamp(0, 255) as u8;
+                *pixel = if is_inverted { 255 - value } else { value };
+            }
+        }
+        processed
+    }
+
+    fn finish_capture(&mut self) {
+        let image = self.get_processed_image();
+        let tiles = &mut self.ram[IMAGE_RAM_OFFSET..];
+        for (y, row) in image.iter().enumerate() {
+            for (x, pixel) in row.iter().enumerate() {
+                let thresholds = DITHER_MATRIX_REGISTER + ((y % 4) * 4 + x % 4) * 3;
+                let color = match self.registers[thresholds..thresholds + 3]
+                    .iter()
+                    .position(|threshold| *pixel < *threshold)
+                {
+                    Some(index) => 3 - index as u8,
+                    None => 0,
+                };
+                let tile = (y / 8) * (SENSOR_WIDTH / 8) + x / 8;
+                let line = tile * 16 + (y % 8) * 2;
+                let bit = 7 - x % 8;
+                tiles[line] = tiles[line] & !(1 << bit) | ((color & 1) << bit);
+                tiles[line + 1] = tiles[line + 1] & !(1 << bit) | ((color >> 1) << bit);
+            }
+        }
+        self.registers[SHOOT_REGISTER] &= !1;
+    }
+}
+
+impl<T: Deref<Target = [u8]>, U: ImageSensor> Mbc for PocketCamera<T, U> {
+    fn read(&self, index: u16) -> u8 {
+        match index {
+            ROM_BANK..SWITCHABLE_ROM_BANK => read_rom(&self.rom, usize::from(index)),
+            SWITCHABLE_ROM_BANK..VIDEO_RAM => read_rom(
+                &self.rom,
+                usize::from(u16::from(self.rom_bank) & (self.get_rom_bank_count() - 1))
+                    * usize::from(ROM_BANK_SIZE)
+                    + usize::from(index - SWITCHABLE_ROM_BANK),
+            ),
+            // only the first register can be read back
+            EXTERNAL_RAM..WORK_RAM if self.are_registers_mapped() => {
+                if index & 0x7f == 0 {
+                    self.registers[SHOOT_REGISTER]
+                } else {
+                    0x00
+                }
+            }
+            // the RAM can't be read while the sensor is working
+            EXTERNAL_RAM..WORK_RAM if self.capture_cycles != 0 => 0x00,
+            EXTERNAL_RAM..WORK_RAM => self.ram[self.get_ram_offset(index)],
+            _ => 0xff,
+        }
+    }
+
+    fn write(&mut self, index: u16, value: u8) {
+        match index {
+            0x0000..0x2000 => self.ram_write_enabled = (value & 0x0f) == 0x0a,
+            0x2000..0x4000 => self.rom_bank = value & 0x3f,
+            0x4000..0x6000 => self.ram_bank = value & 0x1f,
+            EXTERNAL_RAM..WORK_RAM if self.are_registers_mapped() => {
+                let register = usize::from(index & 0x7f);
+                if register == SHOOT_REGISTER {
+                    let was_capturing = self.registers[SHOOT_REGISTER] & 1 != 0;
+                    self.registers[SHOOT_REGISTER] = value & 0x07;
+                    match (was_capturing, value & 1 != 0) {
+                        (false, true) => self.start_capture(),
+                        // aborted
+                        (true, false) => self.capture_cycles = 0,
+                        _ => {}
+                    }
+                } else if let Some(register) = self.registers.get_mut(register) {
+                    *register = value;
+                }
+            }
+            EXTERNAL_RAM..WORK_RAM if self.ram_write_enabled && self.capture_cycles == 0 => {
+                self.ram[self.get_ram_offset(index)] = value;
+            }
+            _ => {}
+        }
+    }
+
+    fn execute(&mut self) {
+        if self.capture_cycles == 0 {
+            return;
+        }
+        self.capture_cycles -= 1;
+        if self.capture_cycles == 0 {
+            self.finish_capture();
+        }
+    }
+
+    fn load_saved_ram(&mut self, save: &[u8]) {
+        let min = save.len().min(self.ram.len());
+        self.ram[..min].copy_from_slice(&save[..min]);
+    }
+
+    fn load_additional_data(&mut self, _: &[u8]) {}
+
+    fn get_ram_to_save(&self) -> Option<&[u8]> {
+        Some(&self.ram)
+    }
+
+    fn get_additional_data_to_save(&self, _: &mut [u8]) -> usize {
+        0
+    }
+
+    fn load_saved_registers(&mut self, registers: &[u8]) {
+        let Ok(registers) = <&[u8; POCKET_CAMERA_REGISTERS_SIZE]>::try_from(registers) else {
+            log::warn!(
+                "Unexpected Pocket Camera registers length: {}",
+                registers.len()
+            );
+            return;
+        };
+        let [rom_bank, ram_bank, ram_write_enabled, rest @ ..] = registers;
+        let (camera_registers, capture_cycles) = rest.split_at(CAMERA_REGISTERS_SIZE);
+        self.rom_bank = rom_bank & 0x3f;
+        self.ram_bank = ram_bank & 0x1f;
+        self.ram_write_enabled = *ram_write_enabled != 0;
+        self.registers.copy_from_slice(camera_registers);
+        self.capture_cycles = u32::from_be_bytes(capture_cycles.try_into().unwrap());
+    }
+
+    fn get_registers_to_save(&self, buffer: &mut [u8]) -> usize {
+        let buffer = &mut buffer[..POCKET_CAMERA_REGISTERS_SIZE];
+        let (banks, rest) = buffer.split_at_mut(3);
+        banks.copy_from_slice(&[self.rom_bank, self.ram_bank, self.ram_write_enabled.into()]);
+        let (camera_registers, capture_cycles) = rest.split_at_mut(CAMERA_REGISTERS_SIZE);
+        camera_registers.copy_from_slice(&self.registers);
+        capture_cycles.copy_from_slice(&self.capture_cycles.to_be_bytes());
+        POCKET_CAMERA_REGISTERS_SIZE
+    }
+
+    fn get_rom(&self) -> &[u8] {
+        &self.rom
+    }
+}
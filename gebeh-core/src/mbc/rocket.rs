@@ -0,0 +1,112 @@
+// Rocket Games carts keep their own logo in the header and the Nintendo one at $0184. How the
+// logo is switched isn't documented: here the boot ROM is shown the cartridge logo, then the
+// Nintendo one while it checks it. The banking is compatible with the MBC1 for their small ROMs.
+
+use crate::{addresses::*, mbc::*};
+use core::{cell::Cell, ops::Deref};
+
+const LOGO_START: u16 = 0x0104;
+const LOGO_END: u16 = 0x0134;
+const NINTENDO_LOGO_OFFSET: u16 = 0x80;
+// the boot ROM reads the logo once to display it and once to check it
+const LOGO_READS: u8 = 2 * (LOGO_END - LOGO_START) as u8;
+
+#[derive(Clone)]
+pub struct Rocket<T> {
+    rom: T,
+    // 5 bits
+    rom_bank: u8,
+    // the reads are counted by the cartridge
+    logo_reads: Cell<u8>,
+}
+
+impl<T: Deref<Target = [u8]>> Rocket<T> {
+    pub fn new(rom: T) -> Self {
+        Self {
+            rom,
+            rom_bank: 1,
+            logo_reads: Cell::new(0),
+        }
+    }
+
+    fn get_rom_bank_count(&self) -> u16 {
+        get_factor_32_kib_rom(self.rom.deref()).min(16) << 1
+    }
+
+    // the cartridge logo is shown by the first half of the reads, the Nintendo one by the other
+    fn get_logo_offset(&self, index: u16) -> u16 {
+        let logo_reads = self.logo_reads.get();
+        if (LOGO_START..LOGO_END).contains(&index)
+            && (LOGO_READS / 2..LOGO_READS).contains(&logo_reads)
+        {
+            NINTENDO_LOGO_OFFSET
+        } else {
+            0
+        }
+    }
+
+    fn read_banked(&self, index: u16) -> u8 {
+        match index {
+            ROM_BANK..SWITCHABLE_ROM_BANK => read_rom(&self.rom, usize::from(index)),
+            SWITCHABLE_ROM_BANK..VIDEO_RAM => read_rom(
+                &self.rom,
+                usize::from(u16::from(self.rom_bank) & (self.get_rom_bank_count() - 1))
+                    * usize::from(ROM_BANK_SIZE)
+                    + usize::from(index - SWITCHABLE_ROM_BANK),
+            ),
+            _ => 0xff,
+        }
+    }
+}
+
+impl<T: Deref<Target = [u8]>> Mbc for Rocket<T> {
+    fn read(&self, index: u16) -> u8 {
+        let offset = self.get_logo_offset(index);
+        let logo_reads = self.logo_reads.get();
+        if (LOGO_START..LOGO_END).contains(&index) && logo_reads < LOGO_READS {
+            self.logo_reads.set(logo_reads + 1);
+        }
+        self.read_banked(index + offset)
+    }
+
+    fn peek(&self, index: u16) -> u8 {
+        self.read_banked(index + self.get_logo_offset(index))
+    }
+
+    fn write(&mut self, index: u16, value: u8) {
+        if let 0x2000..0x4000 = index {
+            self.rom_bank = (value & 0x1f).max(1);
+        }
+    }
+
+    fn load_saved_ram(&mut self, _: &[u8]) {}
+
+    fn load_additional_data(&mut self, _: &[u8]) {}
+
+    fn get_ram_to_save(&self) -> Option<&[u8]> {
+        None
+    }
+
+    fn get_additional_data_to_save(&self, _: &mut [u8]) -> usize {
+        0
+    }
+
+    fn load_saved_registers(&mut self, registers: &[u8]) {
+        let Ok([rom_bank, logo_reads]) = <[u8; 2]>::try_from(registers) else {
+            log::warn!("Unexpected Rocket registers length: {}", registers.len());
+            return;
+        };
+        self.rom_bank = (rom_bank & 0x1f).max(1);
+        self.logo_reads.set(logo_reads.min(LOGO_READS));
+    }
+
+    fn get_registers_to_save(&self, buffer: &mut [u8]) -> usize {
+        let registers = [self.rom_bank, self.logo_reads.get()];
+        buffer[..registers.len()].copy_from_slice(&registers);
+        registers.len()
+    }
+
+    fn get_rom(&self) -> &[u8] {
+        &self.rom
+    }
+}
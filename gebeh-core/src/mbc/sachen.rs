@@ -0,0 +1,170 @@
+// https://github.com/mgba-emu/mgba/blob/master/src/gb/mbc/unlicensed.c
+// The Sachen mappers swap some address lines of $0100-$01FF, so the boot ROM finds the Nintendo
+// logo at a scrambled location. The MMC1 also forces A7 while it is locked: the boot ROM shows
+// the Sachen logo of $0184 and checks the Nintendo one once the logo has been read.
+
+use crate::{addresses::*, mbc::*};
+use core::{cell::Cell, ops::Deref};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum SachenModel {
+    Mmc1,
+    // the CGB lock counts the reads of the work RAM that the cartridge doesn't see here, so only
+    // the DMG behavior is emulated
+    Mmc2,
+}
+
+// the first read of the logo check unlocks the mapper
+const UNLOCK_READS: u8 = 0x31;
+
+// A0 and A6 are swapped, as well as A1 and A4
+pub fn unscramble_sachen_address(index: u16) -> u16 {
+    index & 0xffac
+        | (index & 0x40) >> 6
+        | (index & 0x10) >> 3
+        | (index & 0x02) << 3
+        | (index & 0x01) << 6
+}
+
+#[derive(Clone)]
+pub struct Sachen<T> {
+    rom: T,
+    model: SachenModel,
+    // the bits set in the mask come from the base bank, for the multicarts
+    base_bank: u8,
+    mask: u8,
+    unmasked_bank: u8,
+    // the reads are counted by the cartridge
+    is_locked: Cell<bool>,
+    locked_reads: Cell<u8>,
+}
+
+const SACHEN_REGISTERS_SIZE: usize = 5;
+
+impl<T: Deref<Target = [u8]>> Sachen<T> {
+    pub fn new(rom: T, model: SachenModel) -> Self {
+        Self {
+            rom,
+            model,
+            base_bank: 0,
+            mask: 0,
+            unmasked_bank: 1,
+            is_locked: Cell::new(true),
+            locked_reads: Cell::new(0),
+        }
+    }
+
+    // the header is scrambled, the size is taken from the ROM itself
+    fn get_rom_bank_mask(&self) -> u8 {
+        ((self.rom.len() / usize::from(ROM_BANK_SIZE)).next_power_of_two() - 1) as u8
+    }
+
+    // the base bank and the mask can only be changed when the game isn't selected yet
+    fn are_outer_bank_registers_writable(&self) -> bool {
+        self.unmasked_bank & 0x30 == 0x30
+    }
+
+    fn is_counted(&self, index: u16) -> bool {
+        match self.model {
+            SachenModel::Mmc1 => index & 0xff00 == 0x0100,
+            SachenModel::Mmc2 => index & 0x8700 == 0x0100,
+        }
+    }
+
+    // returns true if the read happens while the mapper is locked
+    fn count_locked_read(&self, index: u16) -> bool {
+        if !self.is_locked.get() || !self.is_counted(index) {
+            return false;
+        }
+        let reads = self.locked_reads.get() + 1;
+        self.locked_reads.set(reads);
+        if reads >= UNLOCK_READS {
+            self.is_locked.set(false);
+        }
+        self.is_locked.get()
+    }
+
+    fn read_banked(&self, mut index: u16, is_locked: bool) -> u8 {
+        if is_locked && self.model == SachenModel::Mmc1 {
+            index |= 0x80;
+        }
+        if index & 0xff00 == 0x0100 {
+            index = unscramble_sachen_address(index);
+        }
+        let mask = self.get_rom_bank_mask();
+        let bank = match index {
+            ROM_BANK..SWITCHABLE_ROM_BANK => self.base_bank & self.mask,
+            SWITCHABLE_ROM_BANK..VIDEO_RAM => {
+                (self.unmasked_bank & !self.mask) | (self.base_bank & self.mask)
+            }
+            _ => return 0xff,
+        };
+        read_rom(
+            &self.rom,
+            usize::from(bank & mask) * usize::from(ROM_BANK_SIZE)
+                + usize::from(index % ROM_BANK_SIZE),
+        )
+    }
+}
+
+impl<T: Deref<Target = [u8]>> Mbc for Sachen<T> {
+    fn read(&self, index: u16) -> u8 {
+        let is_locked = self.count_locked_read(index);
+        self.read_banked(index, is_locked)
+    }
+
+    fn peek(&self, index: u16) -> u8 {
+        self.read_banked(index, self.is_locked.get() && self.is_counted(index))
+    }
+
+    fn write(&mut self, index: u16, value: u8) {
+        match index {
+            0x0000..0x2000 if self.are_outer_bank_registers_writable() => self.base_bank = value,
+            0x2000..0x4000 => self.unmasked_bank = value.max(1),
+            0x4000..0x6000 if self.are_outer_bank_registers_writable() => self.mask = value,
+            _ => {}
+        }
+    }
+
+    fn load_saved_ram(&mut self, _: &[u8]) {}
+
+    fn load_additional_data(&mut self, _: &[u8]) {}
+
+    fn get_ram_to_save(&self) -> Option<&[u8]> {
+        None
+    }
+
+    fn get_additional_data_to_save(&self, _: &mut [u8]) -> usize {
+        0
+    }
+
+    fn load_saved_registers(&mut self, registers: &[u8]) {
+        let Ok([base_bank, mask, unmasked_bank, is_locked, locked_reads]) =
+            <[u8; SACHEN_REGISTERS_SIZE]>::try_from(registers)
+        else {
+            log::warn!("Unexpected Sachen registers length: {}", registers.len());
+            return;
+        };
+        self.base_bank = base_bank;
+        self.mask = mask;
+        self.unmasked_bank = unmasked_bank.max(1);
+        self.is_locked.set(is_locked != 0);
+        self.locked_reads.set(locked_reads.min(UNLOCK_READS));
+    }
+
+    fn get_registers_to_save(&self, buffer: &mut [u8]) -> usize {
+        let registers: [u8; SACHEN_REGISTERS_SIZE] = [
+            self.base_bank,
+            self.mask,
+            self.unmasked_bank,
+            self.is_locked.get().into(),
+            self.locked_reads.get(),
+        ];
+        buffer[..registers.len()].copy_from_slice(&registers);
+        registers.len()
+    }
+
+    fn get_rom(&self) -> &[u8] {
+        &self.rom
+    }
+}
@@ -22,22 +22,23 @@ const GBTAMA6_RTC_PA0_MINUTE_1: u8 = 0x2;
 const GBTAMA6_RTC_PA0_MINUTE_10: u8 = 0x3;
 const GBTAMA6_RTC_PA0_HOUR_1: u8 = 0x4;
 const GBTAMA6_RTC_PA0_HOUR_10: u8 = 0x5;
-// const GBTAMA6_RTC_PA0_WEEK: u8 = 0x6;
-// const GBTAMA6_RTC_PA0_DAY_1: u8 = 0x7;
-// const GBTAMA6_RTC_PA0_DAY_10: u8 = 0x8;
-// const GBTAMA6_RTC_PA0_MONTH_1: u8 = 0x9;
-// const GBTAMA6_RTC_PA0_MONTH_10: u8 = 0xA;
-// const GBTAMA6_RTC_PA0_YEAR_1: u8 = 0xB;
-// const GBTAMA6_RTC_PA0_YEAR_10: u8 = 0xC;
-// const GBTAMA6_RTC_PA1_MINUTE_1: u8 = 0x2;
-// const GBTAMA6_RTC_PA1_MINUTE_10: u8 = 0x3;
-// const GBTAMA6_RTC_PA1_HOUR_1: u8 = 0x4;
-// const GBTAMA6_RTC_PA1_HOUR_10: u8 = 0x5;
-// const GBTAMA6_RTC_PA1_WEEK: u8 = 0x6;
-// const GBTAMA6_RTC_PA1_DAY_1: u8 = 0x7;
-// const GBTAMA6_RTC_PA1_DAY_10: u8 = 0x8;
-// const GBTAMA6_RTC_PA1_24_HOUR: u8 = 0xA;
-// const GBTAMA6_RTC_PA1_LEAP_YEAR: u8 = 0xB;
+const GBTAMA6_RTC_PA0_WEEK: u8 = 0x6;
+const GBTAMA6_RTC_PA0_DAY_1: u8 = 0x7;
+const GBTAMA6_RTC_PA0_DAY_10: u8 = 0x8;
+const GBTAMA6_RTC_PA0_MONTH_1: u8 = 0x9;
+const GBTAMA6_RTC_PA0_MONTH_10: u8 = 0xA;
+const GBTAMA6_RTC_PA0_YEAR_1: u8 = 0xB;
+const GBTAMA6_RTC_PA0_YEAR_10: u8 = 0xC;
+const GBTAMA6_RTC_PA1_MINUTE_1: u8 = 0x2;
+const GBTAMA6_RTC_PA1_MINUTE_10: u8 = 0x3;
+const GBTAMA6_RTC_PA1_HOUR_1: u8 = 0x4;
+const GBTAMA6_RTC_PA1_HOUR_10: u8 = 0x5;
+const GBTAMA6_RTC_PA1_WEEK: u8 = 0x6;
+const GBTAMA6_RTC_PA1_DAY_1: u8 = 0x7;
+const GBTAMA6_RTC_PA1_DAY_10: u8 = 0x8;
+const GBTAMA6_RTC_PA1_24_HOUR: u8 = 0xA;
+// years since the last leap year
+const GBTAMA6_RTC_PA1_LEAP_YEAR: u8 = 0xB;
 const GBTAMA6_RTC_PAGE: u8 = 0xD;
 // const GBTAMA6_RTC_TEST: u8 = 0xE;
 // const GBTAMA6_RTC_RESET: u8 = 0xF;
@@ -52,6 +53,10 @@ const GBTAMA6_HOUR_READ: u8 = 0x7;
 const GBTAMA6_DISABLE_ALARM: u8 = 0x10;
 const GBTAMA6_ENABLE_ALARM: u8 = 0x11;
 
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+// the calendar repeats itself every 4 years
+const DAYS_PER_4_YEARS: u64 = 4 * 365 + 1;
+
 static TAMA6_RTC_MASK: [u8; 32] = [
     0xF, 0x7, 0xF, 0x7, 0xF, 0x3, 0x7, 0xF, 0x3, 0xF, 0x1, 0xF, 0xF, 0x0, 0x0, 0x0, 0x0, 0x0, 0xF,
     0x7, 0xF, 0x3, 0x7, 0xF, 0x3, 0x0, 0x1, 0x3, 0x0, 0x0, 0x0, 0x0,
@@ -67,7 +72,65 @@ pub struct Tama5State {
     pub rtc_free_page0: [u8; GBTAMA6_RTC_MAX as usize],
     pub rtc_free_page1: [u8; GBTAMA6_RTC_MAX as usize],
     pub disabled: bool,
-    pub rtc_last_latch: i64,
+    // the total seconds of the RTC at the last latch
+    pub last_rtc_seconds: u32,
+}
+
+const TAMA5_STATE_SIZE: usize = GBTAMA5_MAX as usize + 2 + GBTAMA6_RTC_MAX as usize * 4 + 1 + 4;
+const TAMA5_PAGES_SIZE: usize = GBTAMA6_RTC_MAX as usize * 2;
+// the pages packed as nibbles, the disabled flag, then the RTC seconds at the last latch
+const TAMA5_DATA_SIZE: usize = TAMA5_PAGES_SIZE + 1 + 4;
+
+impl Tama5State {
+    fn pages(&self) -> [&[u8; GBTAMA6_RTC_MAX as usize]; 4] {
+        [
+            &self.rtc_timer_page,
+            &self.rtc_alarm_page,
+            &self.rtc_free_page0,
+            &self.rtc_free_page1,
+        ]
+    }
+
+    fn pages_mut(&mut self) -> [&mut [u8; GBTAMA6_RTC_MAX as usize]; 4] {
+        [
+            &mut self.rtc_timer_page,
+            &mut self.rtc_alarm_page,
+            &mut self.rtc_free_page0,
+            &mut self.rtc_free_page1,
+        ]
+    }
+
+    fn serialize(&self, buffer: &mut [u8; TAMA5_STATE_SIZE]) {
+        let (registers, rest) = buffer.split_at_mut(self.registers.len());
+        registers.copy_from_slice(&self.registers);
+        rest[0] = self.reg;
+        rest[1] = self.rom_bank;
+        let (pages, rest) = rest[2..].split_at_mut(usize::from(GBTAMA6_RTC_MAX) * 4);
+        for (chunk, page) in pages
+            .chunks_exact_mut(usize::from(GBTAMA6_RTC_MAX))
+            .zip(self.pages())
+        {
+            chunk.copy_from_slice(page);
+        }
+        rest[0] = self.disabled.into();
+        rest[1..5].copy_from_slice(&self.last_rtc_seconds.to_be_bytes());
+    }
+
+    fn deserialize(&mut self, buffer: &[u8; TAMA5_STATE_SIZE]) {
+        let (registers, rest) = buffer.split_at(self.registers.len());
+        self.registers.copy_from_slice(registers);
+        self.reg = rest[0];
+        self.rom_bank = rest[1];
+        let (pages, rest) = rest[2..].split_at(usize::from(GBTAMA6_RTC_MAX) * 4);
+        for (chunk, page) in pages
+            .chunks_exact(usize::from(GBTAMA6_RTC_MAX))
+            .zip(self.pages_mut())
+        {
+            page.copy_from_slice(chunk);
+        }
+        self.disabled = rest[0] != 0;
+        self.last_rtc_seconds = u32::from_be_bytes(rest[1..5].try_into().unwrap());
+    }
 }
 
 impl Default for Tama5State {
@@ -88,27 +151,166 @@ impl Default for Tama5State {
             rtc_free_page0,
             rtc_free_page1,
             disabled: Default::default(),
-            rtc_last_latch: Default::default(),
+            last_rtc_seconds: Default::default(),
         }
     }
 }
 
 #[derive(Clone)]
-pub struct Tama5<T> {
+pub struct Tama5<T, U> {
     rom: T,
     ram: [u8; 0x20],
     state: Tama5State,
+    // only measures the time elapsed between the latches, the TAMA6 keeps its own calendar
+    rtc: U,
 }
 
-impl<T: Deref<Target = [u8]>> Tama5<T> {
-    pub fn new(rom: T) -> Self {
+fn get_bcd(page: &[u8; GBTAMA6_RTC_MAX as usize], one: u8, ten: u8) -> u64 {
+    u64::from(page[usize::from(one)]) + u64::from(page[usize::from(ten)]) * 10
+}
+
+fn set_bcd(page: &mut [u8; GBTAMA6_RTC_MAX as usize], one: u8, ten: u8, value: u64) {
+    page[usize::from(one)] = (value % 10) as u8;
+    page[usize::from(ten)] = (value / 10 % 10) as u8;
+}
+
+fn get_days_in_month(month: u64, years_since_leap_year: u8) -> u64 {
+    match month {
+        2 if years_since_leap_year == 0 => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+impl<T: Deref<Target = [u8]>, U: Rtc> Tama5<T, U> {
+    pub fn new(rom: T, rtc: U) -> Self {
         Self {
             rom,
             ram: [0; 0x20],
             state: Tama5State::default(),
+            rtc,
+        }
+    }
+
+    // The RTC is never reset, it would lose the fraction of second elapsed since the last latch.
+    fn latch_rtc(&mut self) {
+        let seconds = self.rtc.get_clock_data().get_total_seconds();
+        // the Rtc implementations wrap modulo MAX_RTC_SECONDS
+        let elapsed = (u64::from(seconds) + u64::from(MAX_RTC_SECONDS)
+            - u64::from(self.state.last_rtc_seconds))
+            % u64::from(MAX_RTC_SECONDS);
+        self.state.last_rtc_seconds = seconds;
+        if elapsed != 0 && !self.state.disabled {
+            self.advance_timer(elapsed);
         }
     }
 
+    /// Whether the alarm output of the TAMA6 is on, while the time at the last latch matches the
+    /// alarm page. Nothing on the cartridge is known to read it.
+    pub fn is_alarm_ringing(&self) -> bool {
+        self.state.rtc_timer_page[usize::from(GBTAMA6_RTC_PAGE)] & 0x4 != 0
+            && [
+                GBTAMA6_RTC_PA1_MINUTE_1,
+                GBTAMA6_RTC_PA1_MINUTE_10,
+                GBTAMA6_RTC_PA1_HOUR_1,
+                GBTAMA6_RTC_PA1_HOUR_10,
+                GBTAMA6_RTC_PA1_WEEK,
+                GBTAMA6_RTC_PA1_DAY_1,
+                GBTAMA6_RTC_PA1_DAY_10,
+            ]
+            .into_iter()
+            .all(|index| {
+                self.state.rtc_alarm_page[usize::from(index)]
+                    == self.state.rtc_timer_page[usize::from(index)]
+            })
+    }
+
+    fn advance_timer(&mut self, seconds: u64) {
+        let is_24_hour = self.state.rtc_alarm_page[usize::from(GBTAMA6_RTC_PA1_24_HOUR)] != 0;
+        let mut years_since_leap_year =
+            self.state.rtc_alarm_page[usize::from(GBTAMA6_RTC_PA1_LEAP_YEAR)] & 0x3;
+        let page = &mut self.state.rtc_timer_page;
+
+        let hours = if is_24_hour {
+            get_bcd(page, GBTAMA6_RTC_PA0_HOUR_1, GBTAMA6_RTC_PA0_HOUR_10)
+        } else {
+            // bit 1 of the tens is PM
+            let hour_10 = page[usize::from(GBTAMA6_RTC_PA0_HOUR_10)];
+            (u64::from(page[usize::from(GBTAMA6_RTC_PA0_HOUR_1)]) + u64::from(hour_10 & 1) * 10)
+                % 12
+                + if hour_10 & 2 != 0 { 12 } else { 0 }
+        };
+        let time = get_bcd(page, GBTAMA6_RTC_PA0_SECOND_1, GBTAMA6_RTC_PA0_SECOND_10)
+            + get_bcd(page, GBTAMA6_RTC_PA0_MINUTE_1, GBTAMA6_RTC_PA0_MINUTE_10) * 60
+            + hours * 60 * 60
+            + seconds;
+        let days = time / SECONDS_PER_DAY;
+        let time = time % SECONDS_PER_DAY;
+
+        set_bcd(
+            page,
+            GBTAMA6_RTC_PA0_SECOND_1,
+            GBTAMA6_RTC_PA0_SECOND_10,
+            time % 60,
+        );
+        set_bcd(
+            page,
+            GBTAMA6_RTC_PA0_MINUTE_1,
+            GBTAMA6_RTC_PA0_MINUTE_10,
+            time / 60 % 60,
+        );
+        let hours = time / (60 * 60);
+        if is_24_hour {
+            set_bcd(page, GBTAMA6_RTC_PA0_HOUR_1, GBTAMA6_RTC_PA0_HOUR_10, hours);
+        } else {
+            set_bcd(
+                page,
+                GBTAMA6_RTC_PA0_HOUR_1,
+                GBTAMA6_RTC_PA0_HOUR_10,
+                hours % 12,
+            );
+            if hours >= 12 {
+                page[usize::from(GBTAMA6_RTC_PA0_HOUR_10)] |= 2;
+            }
+        }
+
+        let week = u64::from(page[usize::from(GBTAMA6_RTC_PA0_WEEK)]);
+        page[usize::from(GBTAMA6_RTC_PA0_WEEK)] = ((week + days) % 7) as u8;
+
+        let mut day = get_bcd(page, GBTAMA6_RTC_PA0_DAY_1, GBTAMA6_RTC_PA0_DAY_10);
+        let mut month = get_bcd(page, GBTAMA6_RTC_PA0_MONTH_1, GBTAMA6_RTC_PA0_MONTH_10);
+        let mut year = get_bcd(page, GBTAMA6_RTC_PA0_YEAR_1, GBTAMA6_RTC_PA0_YEAR_10)
+            + days / DAYS_PER_4_YEARS * 4;
+        for _ in 0..days % DAYS_PER_4_YEARS {
+            day += 1;
+            if day <= get_days_in_month(month, years_since_leap_year) {
+                continue;
+            }
+            day = 1;
+            month += 1;
+            if month > 12 {
+                month = 1;
+                year += 1;
+                years_since_leap_year = (years_since_leap_year + 1) & 0x3;
+            }
+        }
+        set_bcd(page, GBTAMA6_RTC_PA0_DAY_1, GBTAMA6_RTC_PA0_DAY_10, day);
+        set_bcd(
+            page,
+            GBTAMA6_RTC_PA0_MONTH_1,
+            GBTAMA6_RTC_PA0_MONTH_10,
+            month,
+        );
+        set_bcd(
+            page,
+            GBTAMA6_RTC_PA0_YEAR_1,
+            GBTAMA6_RTC_PA0_YEAR_10,
+            year % 100,
+        );
+        self.state.rtc_alarm_page[usize::from(GBTAMA6_RTC_PA1_LEAP_YEAR)] = years_since_leap_year;
+    }
+
     fn get_tama5_address(&self) -> u8 {
         ((self.state.registers[usize::from(GBTAMA5_ADDR_HI)] << 4) & 0x10)
             | self.state.registers[usize::from(GBTAMA5_ADDR_LO)]
@@ -136,6 +338,8 @@ impl<T: Deref<Target = [u8]>> Tama5<T> {
             }
             GBTAMA5_WRITE_LO | GBTAMA5_WRITE_HI | GBTAMA5_ADDR_HI => {}
             GBTAMA5_ADDR_LO => {
+                // the time is refreshed before the command, the game reads it afterwards
+                self.latch_rtc();
                 match self.state.registers[usize::from(GBTAMA5_ADDR_HI)] >> 1 {
                     0x0 => {
                         // RAM write
@@ -233,7 +437,7 @@ impl<T: Deref<Target = [u8]>> Tama5<T> {
                 value = self.ram[usize::from(address)];
             }
             0x2 => {
-                // RTC read - would need to latch here
+                // RTC read, latched when the address was written
                 match address {
                     GBTAMA6_MINUTE_READ => {
                         value = (self.state.rtc_timer_page[usize::from(GBTAMA6_RTC_PA0_MINUTE_10)]
@@ -254,11 +458,17 @@ impl<T: Deref<Target = [u8]>> Tama5<T> {
                 let rtc_addr = self.state.registers[usize::from(GBTAMA5_WRITE_LO)];
                 if rtc_addr > GBTAMA6_RTC_PAGE {
                     value = 0
-                } else if core::matches!(
-                    self.state.registers[usize::from(GBTAMA5_ADDR_LO)],
-                    1 | 3 | 5 | 7
-                ) {
-                    value = self.state.rtc_timer_page[usize::from(rtc_addr)];
+                } else {
+                    let page = match self.state.registers[usize::from(GBTAMA5_ADDR_LO)] {
+                        1 => Some(&self.state.rtc_timer_page),
+                        3 => Some(&self.state.rtc_alarm_page),
+                        5 => Some(&self.state.rtc_free_page0),
+                        7 => Some(&self.state.rtc_free_page1),
+                        _ => None,
+                    };
+                    if let Some(page) = page {
+                        value = page[usize::from(rtc_addr)];
+                    }
                 }
             }
             _ => {}
@@ -271,10 +481,10 @@ impl<T: Deref<Target = [u8]>> Tama5<T> {
     }
 }
 
-impl<T: Deref<Target = [u8]>> Mbc for Tama5<T> {
+impl<T: Deref<Target = [u8]>, U: Rtc> Mbc for Tama5<T, U> {
     fn read(&self, index: u16) -> u8 {
         match index {
-            ROM_BANK..SWITCHABLE_ROM_BANK => self.rom[usize::from(index)],
+            ROM_BANK..SWITCHABLE_ROM_BANK => read_rom(&self.rom, usize::from(index)),
             SWITCHABLE_ROM_BANK..VIDEO_RAM => {
                 let offset = usize::from(self.state.rom_bank) * usize::from(ROM_BANK_SIZE);
                 self.rom
@@ -282,7 +492,7 @@ impl<T: Deref<Target = [u8]>> Mbc for Tama5<T> {
                     .copied()
                     .unwrap_or(0xff)
             }
-            VIDEO_RAM..EXTERNAL_RAM => panic!(),
+            VIDEO_RAM..EXTERNAL_RAM => 0xff,
             EXTERNAL_RAM.. => {
                 if index & 1 == 1 {
                     return 0xff;
@@ -313,16 +523,60 @@ impl<T: Deref<Target = [u8]>> Mbc for Tama5<T> {
         self.ram[..min].copy_from_slice(&save[..min]);
     }
 
-    fn load_additional_data(&mut self, _: &[u8]) {
-        todo!()
+    fn load_additional_data(&mut self, additional_data: &[u8]) {
+        let Some((data, rtc)) = additional_data.split_first_chunk::<TAMA5_DATA_SIZE>() else {
+            log::warn!(
+                "Unexpected TAMA5 additional data length: {}",
+                additional_data.len()
+            );
+            return;
+        };
+        let (pages, rest) = data.split_at(TAMA5_PAGES_SIZE);
+        for (chunk, page) in pages
+            .chunks_exact(usize::from(GBTAMA6_RTC_MAX) / 2)
+            .zip(self.state.pages_mut())
+        {
+            for (byte, nibbles) in chunk.iter().zip(page.chunks_exact_mut(2)) {
+                nibbles[0] = byte >> 4;
+                nibbles[1] = byte & 0x0f;
+            }
+        }
+        self.state.disabled = rest[0] != 0;
+        self.state.last_rtc_seconds = u32::from_be_bytes(rest[1..5].try_into().unwrap());
+        self.rtc.deserialize(rtc);
     }
 
     fn get_ram_to_save(&self) -> Option<&[u8]> {
         Some(&self.ram)
     }
 
-    fn get_additional_data_to_save(&self, _: &mut [u8]) -> usize {
-        todo!()
+    fn get_additional_data_to_save(&self, buffer: &mut [u8]) -> usize {
+        let (pages, rest) = buffer.split_at_mut(TAMA5_PAGES_SIZE);
+        for (chunk, page) in pages
+            .chunks_exact_mut(usize::from(GBTAMA6_RTC_MAX) / 2)
+            .zip(self.state.pages())
+        {
+            for (byte, nibbles) in chunk.iter_mut().zip(page.chunks_exact(2)) {
+                *byte = (nibbles[0] << 4) | (nibbles[1] & 0x0f);
+            }
+        }
+        rest[0] = self.state.disabled.into();
+        rest[1..5].copy_from_slice(&self.state.last_rtc_seconds.to_be_bytes());
+        TAMA5_DATA_SIZE + self.rtc.serialize(&mut rest[5..])
+    }
+
+    fn load_saved_registers(&mut self, registers: &[u8]) {
+        let Ok(registers) = registers.try_into() else {
+            log::warn!("Unexpected TAMA5 registers length: {}", registers.len());
+            return;
+        };
+        self.state.deserialize(registers);
+    }
+
+    fn get_registers_to_save(&self, buffer: &mut [u8]) -> usize {
+        self.state
+            .serialize((&mut buffer[..TAMA5_STATE_SIZE]).try_into().unwrap());
+        TAMA5_STATE_SIZE
     }
 
     fn get_rom(&self) -> &[u8] {
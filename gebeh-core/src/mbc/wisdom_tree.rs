@@ -18,12 +18,11 @@ impl<T: Deref<Target = [u8]>> Mbc for WisdomTree<T> {
     fn read(&self, index: u16) -> u8 {
         // Citation: unlike a usual MBC, it switches the whole 32 kiB ROM area instead of just the $4000-$7FFF area
         match index {
-            ROM_BANK..VIDEO_RAM => {
-                self.rom[usize::from(index)
-                    + usize::from(self.rom_bank) * usize::from(ROM_BANK_SIZE) * 2]
-            }
-            EXTERNAL_RAM..WORK_RAM => 0xff,
-            _ => panic!(),
+            ROM_BANK..VIDEO_RAM => read_rom(
+                &self.rom,
+                usize::from(index) + usize::from(self.rom_bank) * usize::from(ROM_BANK_SIZE) * 2,
+            ),
+            _ => 0xff,
         }
     }
 
@@ -31,10 +30,8 @@ impl<T: Deref<Target = [u8]>> Mbc for WisdomTree<T> {
         // Citation: use the A7-A0 address lines for selecting a bank instead of the data lines
         // Thus, the value you write is ignored, and the lower 8 bits of the address is used.
         // For example, to select bank $XX, you would write any value to address $YYXX, where $YY is in the range $00-$7F.
-        match index {
-            ROM_BANK..VIDEO_RAM => self.rom_bank = index as u8,
-            EXTERNAL_RAM..WORK_RAM => {}
-            _ => panic!(),
+        if let ROM_BANK..VIDEO_RAM = index {
+            self.rom_bank = index as u8;
         }
     }
 
@@ -50,6 +47,22 @@ impl<T: Deref<Target = [u8]>> Mbc for WisdomTree<T> {
         0
     }
 
+    fn load_saved_registers(&mut self, registers: &[u8]) {
+        let Ok([rom_bank]) = <[u8; 1]>::try_from(registers) else {
+            log::warn!(
+                "Unexpected Wisdom Tree registers length: {}",
+                registers.len()
+            );
+            return;
+        };
+        self.rom_bank = rom_bank;
+    }
+
+    fn get_registers_to_save(&self, buffer: &mut [u8]) -> usize {
+        buffer[0] = self.rom_bank;
+        1
+    }
+
     fn get_rom(&self) -> &[u8] {
         &self.rom
     }
@@ -0,0 +1,187 @@
+// The real hardware doesn't clear its memory at power-on, some games seed their RNG with it.
+// The content only depends on the configuration, the emulation stays deterministic.
+
+use crate::{Emulator, Model, Ram, ppu::oam_dma::Oam};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MemoryFill {
+    #[default]
+    Zero,
+    Ones,
+    // blocks of 8 bytes alternating 0x00 and 0xff, like the SRAM of some units
+    Pattern,
+    // from the seed of the configuration
+    Random,
+}
+
+impl MemoryFill {
+    // the names given on the command line
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "zero" => Some(Self::Zero),
+            "ones" => Some(Self::Ones),
+            "pattern" => Some(Self::Pattern),
+            "random" => Some(Self::Random),
+            _ => None,
+        }
+    }
+
+    fn fill(self, memory: &mut [u8], seed: u64) {
+        match self {
+            Self::Zero => memory.fill(0x00),
+            Self::Ones => memory.fill(0xff),
+            Self::Pattern => {
+                for (index, byte) in memory.iter_mut().enumerate() {
+                    *byte = if index & 8 == 0 { 0x00 } else { 0xff };
+                }
+            }
+            Self::Random => {
+                let mut random = SplitMix64(seed);
+                for chunk in memory.chunks_mut(8) {
+                    chunk.copy_from_slice(&random.next().to_le_bytes()[..chunk.len()]);
+                }
+            }
+        }
+    }
+}
+
+// https://prng.di.unimi.it/splitmix64.c
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+// each region has its own random sequence
+#[derive(Clone, Copy)]
+enum Region {
+    Wram,
+    Hram,
+    Vram,
+    Oam,
+    CartridgeRam,
+}
+
+/// The fill pattern of each memory region at power-on.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PowerOnMemory {
+    pub seed: u64,
+    pub wram: MemoryFill,
+    pub hram: MemoryFill,
+    pub vram: MemoryFill,
+    pub oam: MemoryFill,
+    pub cartridge_ram: MemoryFill,
+}
+
+impl PowerOnMemory {
+    // the names given on the command line
+    pub const REGION_NAMES: [&str; 5] = ["wram", "hram", "vram", "oam", "cartridge-ram"];
+
+    /// Every region is filled the same way.
+    pub fn new(fill: MemoryFill, seed: u64) -> Self {
+        Self {
+            seed,
+            wram: fill,
+            hram: fill,
+            vram: fill,
+            oam: fill,
+            cartridge_ram: fill,
+        }
+    }
+
+    /// The fill of a single region, named like in [`Self::REGION_NAMES`].
+    pub fn get_fill_mut(&mut self, region: &str) -> Option<&mut MemoryFill> {
+        match region {
+            "wram" => Some(&mut self.wram),
+            "hram" => Some(&mut self.hram),
+            "vram" => Some(&mut self.vram),
+            "oam" => Some(&mut self.oam),
+            "cartridge-ram" => Some(&mut self.cartridge_ram),
+            _ => None,
+        }
+    }
+
+    fn get_seed(&self, region: Region) -> u64 {
+        SplitMix64(self.seed ^ region as u64).next()
+    }
+
+    // the MBCs own the cartridge RAM, the frontends load it before the save
+    pub fn fill_cartridge_ram(&self, ram: &mut [u8]) {
+        self.cartridge_ram
+            .fill(ram, self.get_seed(Region::CartridgeRam));
+    }
+}
+
+impl<M: Model> Emulator<M> {
+    /// Fills the memory of the console, to call before the execution.
+    pub fn fill_memory(&mut self, config: &PowerOnMemory) {
+        config
+            .wram
+            .fill(self.wram.get_banks_mut(), config.get_seed(Region::Wram));
+        config
+            .hram
+            .fill(&mut self.cpu.hram, config.get_seed(Region::Hram));
+        config.vram.fill(
+            self.ppu.get_vram_mut().get_banks_mut(),
+            config.get_seed(Region::Vram),
+        );
+        // the LCD is off, nothing blocks the OAM
+        let mut oam: Oam = [0; _];
+        config.oam.fill(&mut oam, config.get_seed(Region::Oam));
+        for (index, value) in (0..).zip(oam) {
+            self.ppu.write_oam(index, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        Cgb, Dmg, Emulator, Ram,
+        power_on::{MemoryFill, PowerOnMemory},
+    };
+
+    #[test]
+    fn fill() {
+        let config = PowerOnMemory::new(MemoryFill::Random, 42);
+        let mut emulator = Emulator::<Cgb>::default();
+        emulator.fill_memory(&config);
+        let mut other = Emulator::<Cgb>::default();
+        other.fill_memory(&config);
+        // deterministic
+        assert!((0..0x2000).all(|index| emulator.wram.read(index) == other.wram.read(index)));
+        assert!((0..0x2000).any(|index| emulator.wram.read(index) != 0));
+        // the regions differ
+        assert_ne!(emulator.cpu.hram[..0x20], emulator.ppu.get_oam()[..0x20]);
+
+        other.fill_memory(&PowerOnMemory::new(MemoryFill::Random, 43));
+        assert!((0..0x2000).any(|index| emulator.wram.read(index) != other.wram.read(index)));
+
+        let mut emulator = Emulator::<Dmg>::default();
+        emulator.fill_memory(&PowerOnMemory {
+            wram: MemoryFill::Pattern,
+            vram: MemoryFill::Ones,
+            ..Default::default()
+        });
+        assert_eq!(emulator.wram.read(0x07), 0x00);
+        assert_eq!(emulator.wram.read(0x08), 0xff);
+        assert_eq!(emulator.ppu.get_vram().read(0x1fff), 0xff);
+        assert_eq!(emulator.cpu.hram, [0; 0x7f]);
+
+        let mut config = PowerOnMemory::new(MemoryFill::Ones, 0);
+        let mut ram = [0; 0x2000];
+        config.fill_cartridge_ram(&mut ram);
+        assert_eq!(ram, [0xff; 0x2000]);
+        *config.get_fill_mut("cartridge-ram").unwrap() = MemoryFill::Zero;
+        config.fill_cartridge_ram(&mut ram);
+        assert_eq!(ram, [0; 0x2000]);
+        assert_eq!(config.wram, MemoryFill::Ones);
+        assert!(config.get_fill_mut("sram").is_none());
+    }
+}
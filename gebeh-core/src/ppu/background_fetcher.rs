@@ -7,8 +7,9 @@ use crate::{
         vram::VRAM_BANK_SIZE,
     },
 };
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
 pub enum BackgroundFetcherStep {
     // https://gbdev.io/pandocs/Scrolling.html#scrolling
     // Citation: The scroll registers are re-read on each tile fetch, except for the low 3 bits of SCX
@@ -33,7 +34,7 @@ pub enum BackgroundFetcherStep {
 }
 
 // background and window to be precise
-#[derive(Clone, Default)]
+#[derive(Clone, Default, Serialize, Deserialize)]
 pub struct BackgroundFetcher {
     pub step: BackgroundFetcherStep,
     pub x: u8, // will be used like x.max(1) - 1 thus 0 is the dummy fetch
@@ -141,7 +142,7 @@ pub fn get_bg_win_tile(vram: &TileVram, index: u8, is_signed_addressing: bool) -
         .unwrap()
 }
 
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
 pub enum CgbBackgroundFetcherStep {
     // https://gbdev.io/pandocs/Scrolling.html#scrolling
     // Citation: The scroll registers are re-read on each tile fetch, except for the low 3 bits of SCX
@@ -171,7 +172,7 @@ pub enum CgbBackgroundFetcherStep {
 }
 
 // background and window to be precise
-#[derive(Clone, Default)]
+#[derive(Clone, Default, Serialize, Deserialize)]
 pub struct CgbBackgroundFetcher {
     pub step: CgbBackgroundFetcherStep,
     pub x: u8, // will be used like x.max(1) - 1 thus 0 is the dummy fetch
@@ -109,6 +109,21 @@ impl From<u8> for DmgColor {
 
 pub struct CgbColor(pub u16);
 
+// The screen of the GBA is darker, the games detecting it use brighter colors.
+// (level / 31) ^ (4 / 2.2), the LCD gamma of the GBA over the sRGB one.
+const AGB_LEVELS: [u16; 32] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 5, 6, 6, 7, 8, 9, 10, 12, 13, 14, 15, 17, 18, 19, 21, 23, 24,
+    26, 27, 29, 31,
+];
+
+impl CgbColor {
+    /// The color as the AGB screen shows it.
+    pub fn to_agb(self) -> Self {
+        let [r, g, b] = [0, 5, 10].map(|shift| AGB_LEVELS[usize::from((self.0 >> shift) & 0x1f)]);
+        Self(r | g << 5 | b << 10)
+    }
+}
+
 impl From<CgbColor> for u16 {
     fn from(value: CgbColor) -> Self {
         value.0
@@ -1,8 +1,11 @@
 // https://gbdev.io/pandocs/Palettes.html#lcd-color-palettes-cgb-only
 
-#[derive(Clone)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct InnerColorPalettes {
     spec: u8,
+    #[serde(with = "crate::state::byte_array")]
     data: [u8; 64],
 }
 
@@ -57,7 +60,7 @@ impl InnerColorPalettes {
     }
 }
 
-#[derive(Default, Clone)]
+#[derive(Default, Clone, Serialize, Deserialize)]
 pub struct ColorPalettes {
     pub background: InnerColorPalettes,
     pub objects: InnerColorPalettes,
@@ -1,5 +1,7 @@
 // https://gbdev.io/pandocs/CGB_Registers.html#ff6c--opri-cgb-mode-only-object-priority-mode
 
+use serde::{Deserialize, Serialize};
+
 pub trait DmgModeRegs: Default + Clone + Send + Sync {
     fn read_priority_mode(&self) -> u8;
     fn write_priority_mode(&mut self, value: u8);
@@ -24,7 +26,7 @@ impl DmgModeRegs for () {
     fn write_compatibility_mode(&mut self, _: u8) {}
 }
 
-#[derive(Default, Clone)]
+#[derive(Default, Clone, Serialize, Deserialize)]
 pub struct DmgMode {
     is_dmg_style: bool,
     is_dmg_compatibility_mode: bool,
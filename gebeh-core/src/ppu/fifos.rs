@@ -1,5 +1,6 @@
 use arrayvec::ArrayVec;
 use bitfield_struct::bitfield;
+use serde::{Deserialize, Serialize};
 
 use crate::ppu::{
     TileAttributes,
@@ -8,7 +9,7 @@ use crate::ppu::{
 };
 
 // according to https://www.reddit.com/r/EmuDev/comments/s6cpis/comment/ht3lcfq/
-#[derive(Default, Clone)]
+#[derive(Default, Clone, Serialize, Deserialize)]
 pub struct DmgFifos {
     // for low background tile data
     bg0: u8,
@@ -109,6 +110,7 @@ impl DmgFifos {
 }
 
 #[bitfield(u16)]
+#[derive(Serialize, Deserialize)]
 struct PixelInfo {
     // if the background must be drawn over the sprite
     priority: bool,
@@ -122,7 +124,7 @@ struct PixelInfo {
     _padding: u8,
 }
 
-#[derive(Default, Clone)]
+#[derive(Default, Clone, Serialize, Deserialize)]
 pub struct CgbFifos {
     // for low background tile data
     bg0: u8,
@@ -7,21 +7,22 @@ use crate::{
     ppu::{LcdStatus, vram::CgbVram},
     wram::CgbWram,
 };
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Default)]
+#[derive(Clone, Default, Serialize, Deserialize)]
 enum HblankState {
     #[default]
     WaitingForHBlank,
     Copying,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 struct CopyCursor {
     src: u16,
     dst: u16,
 }
 
-#[derive(Clone, Default)]
+#[derive(Clone, Default, Serialize, Deserialize)]
 enum HdmaState {
     #[default]
     Inactive,
@@ -29,7 +30,7 @@ enum HdmaState {
     HBlank(CopyCursor, HblankState),
 }
 
-#[derive(Clone, Default)]
+#[derive(Clone, Default, Serialize, Deserialize)]
 pub struct Hdma {
     source_address: u16,
     destination_address: u16,
@@ -76,8 +77,9 @@ impl Hdma {
         self.length = self.length.wrapping_sub(1);
 
         if ppu_mode == LcdStatus::HBLANK || ppu_mode == LcdStatus::VBLANK {
+            // the destination stays in the VRAM
             vram.write(
-                cursor.dst,
+                cursor.dst & 0x1fff,
                 external_bus_read(cursor.src, mbc, Option::<&CgbVram>::None, wram),
             );
         }
@@ -144,7 +146,8 @@ impl HdmaRegs for Hdma {
                     HblankState::WaitingForHBlank,
                 )
             }
-            (HdmaState::GeneralPurpose(_), _) => panic!("The CPU is supposed to be halted"),
+            // the CPU is halted during a general purpose transfer, it can't write here
+            (HdmaState::GeneralPurpose(_), _) => {}
             // Citation: It is also possible to terminate an active HBlank transfer by writing zero to Bit 7 of FF55
             (HdmaState::HBlank(_, _), 0) => self.state = HdmaState::Inactive,
             _ => {}
@@ -155,11 +158,11 @@ impl HdmaRegs for Hdma {
         // Citation: Reading Bit 7 of FF55 can be used to confirm if the DMA transfer is active (1=Not Active, 0=Active).
         let last_bit = match self.state {
             HdmaState::Inactive => 1,
-            HdmaState::GeneralPurpose(_) => panic!("The CPU is supposed to be halted"),
-            HdmaState::HBlank(_, _) => 0,
+            HdmaState::GeneralPurpose(_) | HdmaState::HBlank(_, _) => 0,
         };
 
-        (u8::try_from((self.length >> 4).wrapping_sub(1)).unwrap() & 0x7f) | (last_bit << 7)
+        // 0xff when no transfer has been started
+        ((self.length >> 4).wrapping_sub(1) as u8 & 0x7f) | (last_bit << 7)
     }
 }
 
@@ -1,4 +1,6 @@
 mod background_fetcher;
+use serde::{Deserialize, Serialize};
+
 pub mod color;
 pub mod color_palettes;
 pub mod dmg_mode;
@@ -12,7 +14,7 @@ mod sprite_fetcher;
 pub mod vram;
 
 use crate::{
-    Cgb, Dmg, Model, Ram, WIDTH,
+    Cgb, DmgModel, Model, Ram, WIDTH,
     interrupts::Interrupts,
     mbc::Mbc,
     ppu::{
@@ -29,7 +31,7 @@ pub use scanline::DmgScanline;
 pub use sprite_fetcher::get_line_from_tile;
 
 bitflags::bitflags! {
-    #[derive(Debug, Clone, Copy,  PartialEq, Eq, Default)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
     pub struct LcdStatus: u8 {
         const LYC_INT = 1 << 6;
         const OAM_INT = 1 << 5;
@@ -47,7 +49,7 @@ bitflags::bitflags! {
 }
 
 bitflags::bitflags! {
-    #[derive(Debug, Clone, Default, Copy, PartialEq, Eq)]
+    #[derive(Debug, Clone, Default, Copy, PartialEq, Eq, Serialize, Deserialize)]
     pub struct TileAttributes: u8 {
         const PRIORITY = 1 << 7;
         const Y_FLIP = 1 << 6;
@@ -63,7 +65,8 @@ impl TileAttributes {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
 pub enum PpuStep<M: Model> {
     // only used on line 0 when the lcd has just turned on
     SkippedOamScan {
@@ -94,7 +97,8 @@ pub enum PpuStep<M: Model> {
     }, // <= 456
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
 pub struct Ppu<M: Model> {
     pub step: PpuStep<M>,
     stat_irq: bool,
@@ -121,7 +125,8 @@ impl<M: Model> Default for Ppu<M> {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
 pub struct PpuState<M: Model> {
     lcd_control: LcdControl,
     bgp: u8,
@@ -165,7 +170,7 @@ impl<M: Model> Default for PpuState<M> {
     }
 }
 
-impl PpuState<Dmg> {
+impl<M: DmgModel> PpuState<M> {
     pub fn get_effective_bgp(&self) -> u8 {
         self.bgp | self.old_bgp
     }
@@ -231,7 +236,7 @@ impl<M: Model> PpuState<M> {
     }
 }
 
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
 pub struct Scrolling {
     // 0 < x < 256
     pub x: u8,
@@ -240,7 +245,7 @@ pub struct Scrolling {
 }
 
 bitflags::bitflags! {
-    #[derive(Debug, Clone, Copy,  PartialEq, Eq, Default)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
     pub struct LcdControl: u8 {
         const LCD_PPU_ENABLE = 1 << 7;
         const WINDOW_TILE_MAP = 1 << 6;
@@ -276,7 +281,7 @@ pub trait StatRegisterHandler: Default + Clone + Send + Sync {
     fn after_interrupt_handling(&mut self, stat_reg: &mut LcdStatus);
 }
 
-#[derive(Default, Clone)]
+#[derive(Default, Clone, Serialize, Deserialize)]
 pub struct StatInterruptWriteQuirk {
     // https://gbdev.io/pandocs/STAT#spurious-stat-interrupts
     queued_interrupt_part_lcd_status: Option<LcdStatus>,
@@ -395,6 +400,13 @@ impl<M: Model> Ppu<M> {
             .set_interrupt_part_lcd_status(value, &mut self.interrupt_part_lcd_status);
     }
 
+    // the boot ROMs jump to the cartridge during the last line of the vertical blank, LY reads 0
+    pub(crate) fn skip_boot_rom(&mut self) {
+        self.step = PpuStep::VerticalBlankScanline {
+            dots_count: VERTICAL_BLANK_DURATION - SCANLINE_DURATION + 7,
+        };
+    }
+
     pub fn get_ly(&self) -> u8 {
         match self.step {
             PpuStep::SkippedOamScan { .. } => 0,
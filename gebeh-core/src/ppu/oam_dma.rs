@@ -1,4 +1,5 @@
 use core::ops::Range;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     Ram,
@@ -12,12 +13,13 @@ use crate::{
 
 pub type Oam = [u8; (NOT_USABLE - OAM) as usize];
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct OamDma {
     range: Range<u16>,
     is_active: bool,
     pub dma_register: u8,
     pub dma_request: bool,
+    #[serde(with = "crate::state::byte_array")]
     oam: Oam,
 }
 
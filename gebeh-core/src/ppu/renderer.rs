@@ -5,9 +5,10 @@
 // https://www.reddit.com/r/EmuDev/comments/s6cpis/gameboy_trying_to_understand_sprite_fifo_behavior/ <- spitting facts
 
 use arrayvec::ArrayVec;
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    Cgb, Dmg, Model,
+    Cgb, DmgModel, Model,
     ppu::{
         LcdControl, PpuState, Scrolling, Sprite,
         background_fetcher::{
@@ -20,7 +21,7 @@ use crate::{
     },
 };
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub enum RendererStep {
     DummyFetch,
     AfterDummy {
@@ -35,7 +36,7 @@ pub trait Renderer<M: Model>: Clone + Send + Sync {
     fn get_scanline_builder(&self) -> &M::ScanlineBuilder;
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct DmgRenderer {
     background_pixel_fetcher: BackgroundFetcher,
     sprite_pixel_fetcher: SpriteFetcher,
@@ -63,10 +64,10 @@ impl DmgRenderer {
         }
     }
 
-    pub(super) fn execute(
+    pub(super) fn execute<M: DmgModel>(
         &mut self,
         window_y: &mut Option<u8>,
-        ppu_state: &PpuState<Dmg>,
+        ppu_state: &PpuState<M>,
         ly: u8,
         _: u64,
     ) {
@@ -200,18 +201,12 @@ impl DmgRenderer {
     }
 }
 
-impl Renderer<Dmg> for DmgRenderer {
+impl<M: DmgModel> Renderer<M> for DmgRenderer {
     fn new(objects: ArrayVec<(u8, Sprite), 10>) -> Self {
         Self::new(objects.into_iter().map(|(_, obj)| obj).collect())
     }
 
-    fn execute(
-        &mut self,
-        window_y: &mut Option<u8>,
-        ppu_state: &PpuState<Dmg>,
-        ly: u8,
-        cycle: u64,
-    ) {
+    fn execute(&mut self, window_y: &mut Option<u8>, ppu_state: &PpuState<M>, ly: u8, cycle: u64) {
         self.execute(window_y, ppu_state, ly, cycle);
     }
 
@@ -223,7 +218,7 @@ impl Renderer<Dmg> for DmgRenderer {
 // yea I'm copy pasting everything. The original rendering logic has specific Game Boy Pocket behavior.
 // So there is a lot of chance that in the future, the cgb rendering logic will be modified.
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct CgbRenderer {
     background_pixel_fetcher: CgbBackgroundFetcher,
     sprite_pixel_fetcher: CgbSpriteFetcher,
@@ -415,7 +410,7 @@ impl Renderer<Cgb> for CgbRenderer {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct RenderingState {
     pub is_shifting: bool,
     pub is_sprite_fetching_enable: bool,
@@ -1,10 +1,11 @@
 use arrayvec::ArrayVec;
 use ref_cast::RefCast;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
 
 use crate::ppu::color::{CgbColor, DmgColor};
 
-#[derive(Clone, Copy)]
-pub struct DmgScanline([u8; 40]);
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct DmgScanline(#[serde(with = "crate::state::byte_array")] [u8; 40]);
 
 impl DmgScanline {
     pub fn raw(&self) -> &[u8; 40] {
@@ -36,7 +37,7 @@ impl Default for DmgScanline {
     }
 }
 
-#[derive(Clone, Default)]
+#[derive(Clone, Default, Serialize, Deserialize)]
 pub struct DmgScanlineBuilder {
     buffer: DmgScanline,
     index: u8, // 0 -> 160, if 160 then the scanline is complete
@@ -57,7 +58,7 @@ impl DmgScanlineBuilder {
     }
 }
 
-pub trait Scanline: Copy + Default + Send + Sync + 'static {
+pub trait Scanline: Copy + Default + Send + Sync + Serialize + DeserializeOwned + 'static {
     type Item: Into<[u8; 4]> + Into<u16>; // u16 = rgb555
     fn iter_colors(&self) -> impl Iterator<Item = Self::Item>;
 }
@@ -81,14 +82,19 @@ impl ScanlineBuilder for DmgScanlineBuilder {
     }
 }
 
-#[derive(RefCast, Clone, Copy)]
+#[derive(RefCast, Clone, Copy, Serialize, Deserialize)]
 #[repr(transparent)]
-pub struct CgbScanline([u16; 160]);
+pub struct CgbScanline(#[serde(with = "crate::state::array")] [u16; 160]);
 
 impl CgbScanline {
     pub fn raw(&self) -> &[u16; 160] {
         &self.0
     }
+
+    /// The scanline as the AGB screen shows it.
+    pub fn to_agb(&self) -> Self {
+        Self(self.0.map(|color| CgbColor(color).to_agb().0))
+    }
 }
 
 impl Default for CgbScanline {
@@ -1,6 +1,7 @@
 use crate::ppu::TileAttributes;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct Sprite {
     pub y: u8,
     pub x: u8,
@@ -1,4 +1,5 @@
 use arrayvec::ArrayVec;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     Cgb,
@@ -11,7 +12,7 @@ use crate::{
     },
 };
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub enum SpriteFetcher {
     // we have access to the object tile_index so it's useless to have it here
     FetchingTileLow { delay: u8 },
@@ -113,7 +114,7 @@ impl SpriteFetcher {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub enum CgbSpriteFetcher {
     // we have access to the object tile_index so it's useless to have it here
     FetchingTileLow { delay: u8 },
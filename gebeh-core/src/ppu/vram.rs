@@ -1,9 +1,10 @@
 use crate::Ram;
+use serde::{Deserialize, Serialize};
 
 pub const VRAM_BANK_SIZE: usize = 0x2000;
 
-#[derive(Clone)]
-pub struct DmgVram([u8; VRAM_BANK_SIZE]);
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DmgVram(#[serde(with = "crate::state::byte_array")] [u8; VRAM_BANK_SIZE]);
 
 impl DmgVram {
     pub fn get_inner(&self) -> &[u8; VRAM_BANK_SIZE] {
@@ -25,11 +26,16 @@ impl Ram for DmgVram {
     fn write(&mut self, index: u16, value: u8) {
         self.0[usize::from(index)] = value;
     }
+
+    fn get_banks_mut(&mut self) -> &mut [u8] {
+        &mut self.0
+    }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct CgbVram {
     bank: u8,
+    #[serde(with = "crate::state::byte_banks")]
     data: [[u8; VRAM_BANK_SIZE]; 2],
 }
 
@@ -56,6 +62,10 @@ impl Ram for CgbVram {
     fn write(&mut self, index: u16, value: u8) {
         self.data[usize::from(self.bank)][usize::from(index)] = value;
     }
+
+    fn get_banks_mut(&mut self) -> &mut [u8] {
+        self.data.as_flattened_mut()
+    }
 }
 
 pub trait VramRegs: Ram {
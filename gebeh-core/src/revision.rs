@@ -0,0 +1,378 @@
+// The hardware revisions of a model, they differ by their boot ROM and a few quirks.
+// https://gbdev.io/pandocs/Power_Up_Sequence.html#cpu-registers
+
+use core::fmt::Debug;
+
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use crate::cpu::{BOOTIX_BOOT_ROM, CGB_BOOT_ROM};
+
+pub trait Revision:
+    Copy + Default + Eq + Debug + Send + Sync + Serialize + DeserializeOwned
+{
+    // like "dmg0" or "agb"
+    fn from_name(name: &str) -> Option<Self>;
+    fn get_name(self) -> &'static str;
+    // None when the revision uses the built-in boot ROM of the model
+    fn get_boot_rom(self) -> Option<&'static [u8]>;
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DmgRevision {
+    // the early Japanese units, the differences of their timer and DIV are not emulated
+    Dmg0,
+    #[default]
+    Dmg,
+    // Game Boy Pocket and Light
+    Mgb,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SgbRevision {
+    #[default]
+    Sgb,
+    Sgb2,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CgbRevision {
+    // the first CGB CPU, only its boot ROM differs
+    Cgb0,
+    #[default]
+    Cgb,
+    // Game Boy Advance, the games detect it with the B register
+    Agb,
+}
+
+impl Revision for DmgRevision {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "dmg0" => Some(Self::Dmg0),
+            "dmg" => Some(Self::Dmg),
+            "mgb" => Some(Self::Mgb),
+            _ => None,
+        }
+    }
+    fn get_name(self) -> &'static str {
+        match self {
+            Self::Dmg0 => "dmg0",
+            Self::Dmg => "dmg",
+            Self::Mgb => "mgb",
+        }
+    }
+    fn get_boot_rom(self) -> Option<&'static [u8]> {
+        match self {
+            Self::Dmg0 => Some(&DMG0_BOOT_ROM),
+            Self::Dmg => None,
+            Self::Mgb => Some(&MGB_BOOT_ROM),
+        }
+    }
+}
+
+impl Revision for SgbRevision {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "sgb" => Some(Self::Sgb),
+            "sgb2" => Some(Self::Sgb2),
+            _ => None,
+        }
+    }
+    fn get_name(self) -> &'static str {
+        match self {
+            Self::Sgb => "sgb",
+            Self::Sgb2 => "sgb2",
+        }
+    }
+    fn get_boot_rom(self) -> Option<&'static [u8]> {
+        match self {
+            Self::Sgb => None,
+            Self::Sgb2 => Some(&SGB2_BOOT_ROM),
+        }
+    }
+}
+
+impl Revision for CgbRevision {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "cgb0" => Some(Self::Cgb0),
+            "cgb" => Some(Self::Cgb),
+            "agb" => Some(Self::Agb),
+            _ => None,
+        }
+    }
+    fn get_name(self) -> &'static str {
+        match self {
+            Self::Cgb0 => "cgb0",
+            Self::Cgb => "cgb",
+            Self::Agb => "agb",
+        }
+    }
+    fn get_boot_rom(self) -> Option<&'static [u8]> {
+        match self {
+            // the built-in boot ROM leaves the same state
+            Self::Cgb0 | Self::Cgb => None,
+            Self::Agb => Some(&AGB_BOOT_ROM),
+        }
+    }
+}
+
+// The registers left by the DMG and SGB boot ROMs, the built-in boot ROMs set them and the
+// skip-boot-ROM mode copies them.
+#[derive(Clone, Copy)]
+pub(crate) struct DmgBootRegisters {
+    pub a: u8,
+    pub b: u8,
+    pub c: u8,
+    pub d: u8,
+    pub e: u8,
+    pub h: u8,
+    pub l: u8,
+    // the flags of the last addition of the header check, cleared otherwise
+    pub header_flags: bool,
+}
+
+const DMG0_REGISTERS: DmgBootRegisters = DmgBootRegisters {
+    a: 0x01,
+    b: 0xff,
+    c: 0x13,
+    d: 0x00,
+    e: 0xc1,
+    h: 0x84,
+    l: 0x03,
+    header_flags: false,
+};
+
+const DMG_REGISTERS: DmgBootRegisters = DmgBootRegisters {
+    a: 0x01,
+    b: 0x00,
+    c: 0x13,
+    d: 0x00,
+    e: 0xd8,
+    h: 0x01,
+    l: 0x4d,
+    header_flags: true,
+};
+
+const MGB_REGISTERS: DmgBootRegisters = DmgBootRegisters {
+    a: 0xff,
+    ..DMG_REGISTERS
+};
+
+const SGB_REGISTERS: DmgBootRegisters = DmgBootRegisters {
+    a: 0x01,
+    b: 0x00,
+    c: 0x14,
+    d: 0x00,
+    e: 0x00,
+    h: 0xc0,
+    l: 0x60,
+    header_flags: false,
+};
+
+const SGB2_REGISTERS: DmgBootRegisters = DmgBootRegisters {
+    a: 0xff,
+    ..SGB_REGISTERS
+};
+
+impl DmgRevision {
+    pub(crate) fn get_boot_registers(self) -> DmgBootRegisters {
+        match self {
+            Self::Dmg0 => DMG0_REGISTERS,
+            Self::Dmg => DMG_REGISTERS,
+            Self::Mgb => MGB_REGISTERS,
+        }
+    }
+}
+
+impl SgbRevision {
+    pub(crate) fn get_boot_registers(self) -> DmgBootRegisters {
+        match self {
+            Self::Sgb => SGB_REGISTERS,
+            Self::Sgb2 => SGB2_REGISTERS,
+        }
+    }
+}
+
+// Bootix jumps to 0x00e8 to set the registers of the DMG, this code is replaced by the registers
+// of the revision. The boot ROM is unmapped at 0x00fe by writing A.
+const fn patch_bootix(registers: DmgBootRegisters) -> [u8; 0x100] {
+    let DmgBootRegisters {
+        a,
+        b,
+        c,
+        d,
+        e,
+        h,
+        l,
+        header_flags,
+    } = registers;
+    assert!(a != 0, "writing 0 doesn't unmap the boot ROM");
+    let flags: &[u8] = if header_flags {
+        // ld hl, 0x014d; ld a, (hl); cpl; inc a; add a, (hl)
+        &[0x21, 0x4d, 0x01, 0x7e, 0x2f, 0x3c, 0x86]
+    } else {
+        // ld a, 0x01; or a
+        &[0x3e, 0x01, 0xb7]
+    };
+    // ld bc, ..; ld de, ..; ld hl, ..; ld a, ..
+    let registers = [0x01, c, b, 0x11, e, d, 0x21, l, h, 0x3e, a];
+
+    let mut boot_rom = BOOTIX_BOOT_ROM;
+    let mut index = 0xe8;
+    while index < 0xfe {
+        let offset = index - 0xe8;
+        boot_rom[index] = if offset < flags.len() {
+            flags[offset]
+        } else if offset - flags.len() < registers.len() {
+            registers[offset - flags.len()]
+        } else {
+            // nop
+            0x00
+        };
+        index += 1;
+    }
+    boot_rom
+}
+
+const DMG0_BOOT_ROM: [u8; 0x100] = patch_bootix(DMG0_REGISTERS);
+pub(crate) const DMG_BOOT_ROM: [u8; 0x100] = patch_bootix(DMG_REGISTERS);
+const MGB_BOOT_ROM: [u8; 0x100] = patch_bootix(MGB_REGISTERS);
+pub(crate) const SGB_BOOT_ROM: [u8; 0x100] = patch_bootix(SGB_REGISTERS);
+const SGB2_BOOT_ROM: [u8; 0x100] = patch_bootix(SGB2_REGISTERS);
+
+// The AGB boot ROM increments B before jumping to 0x00fe, the CGB one is padded with nops there.
+const AGB_BOOT_ROM: [u8; 0x900] = {
+    let mut boot_rom = *CGB_BOOT_ROM;
+    // jr 0x00fd
+    boot_rom[0x8b] = 0x71;
+    // inc b
+    boot_rom[0xfd] = 0x04;
+    boot_rom
+};
+
+#[cfg(test)]
+mod tests {
+    use arrayvec::ArrayVec;
+
+    use crate::{
+        Cgb, Dmg, Emulator, EmulatorExt, Model, Sgb,
+        cpu::Flags,
+        mbc::Mbc1,
+        ppu::scanline::ScanlineBuilder,
+        revision::{CgbRevision, DmgRevision, Revision, SgbRevision},
+    };
+
+    // the first letter of the title gives the header checksum
+    fn get_rom(title: u8) -> [u8; 0x8000] {
+        let mut rom = [0; 0x8000];
+        rom[0x134] = title;
+        let mut checksum = 0u8;
+        for byte in &rom[0x134..0x14d] {
+            checksum = checksum.wrapping_sub(*byte).wrapping_sub(1);
+        }
+        rom[0x14d] = checksum;
+        rom
+    }
+
+    // runs the built-in boot ROM of the revision until it is unmapped
+    fn boot<M: Model>(revision: M::Revision) -> Emulator<M> {
+        boot_with_title(revision, 0)
+    }
+
+    fn boot_with_title<M: Model>(revision: M::Revision, title: u8) -> Emulator<M> {
+        let rom = get_rom(title);
+        let mut mbc = Mbc1::new(rom.as_slice());
+        let mut emulator = Emulator::<M>::with_revision(revision);
+        while !emulator.get_cpu().boot_rom_mapping_control {
+            assert!(emulator.get_cycles() < 20_000_000);
+            emulator.execute(&mut mbc);
+        }
+        emulator
+    }
+
+    #[test]
+    fn registers() {
+        let cpu = boot::<Dmg>(DmgRevision::Dmg0).cpu;
+        assert_eq!(
+            [cpu.a, cpu.b, cpu.c, cpu.d, cpu.e, cpu.h, cpu.l],
+            [0x01, 0xff, 0x13, 0x00, 0xc1, 0x84, 0x03]
+        );
+        assert_eq!(cpu.f, Flags::empty());
+        let cpu = boot::<Dmg>(DmgRevision::Mgb).cpu;
+        assert_eq!([cpu.a, cpu.c, cpu.e, cpu.l], [0xff, 0x13, 0xd8, 0x4d]);
+        assert_eq!(cpu.f, Flags::Z | Flags::H | Flags::C);
+        let cpu = boot::<Sgb>(SgbRevision::Sgb2).cpu;
+        assert_eq!(
+            [cpu.a, cpu.b, cpu.c, cpu.d, cpu.e, cpu.h, cpu.l],
+            [0xff, 0x00, 0x14, 0x00, 0x00, 0xc0, 0x60]
+        );
+        let cgb = boot::<Cgb>(CgbRevision::Cgb).cpu;
+        let cgb0 = boot::<Cgb>(CgbRevision::Cgb0).cpu;
+        assert_eq!(
+            [cgb0.a, cgb0.b, cgb0.c, cgb0.d, cgb0.e, cgb0.h, cgb0.l],
+            [cgb.a, cgb.b, cgb.c, cgb.d, cgb.e, cgb.h, cgb.l]
+        );
+        let agb = boot::<Cgb>(CgbRevision::Agb).cpu;
+        assert_eq!(agb.b, cgb.b + 1);
+        assert_eq!(agb.a, cgb.a);
+    }
+
+    fn assert_skip_matches_boot<M: Model>(revision: M::Revision) {
+        // the checksums 0x00, 0x20 and 0xe7
+        for title in [0xe7, 0xc7, 0x00] {
+            let booted = boot_with_title::<M>(revision, title).cpu;
+            let rom = get_rom(title);
+            let mut skipped = Emulator::<M>::with_revision(revision);
+            M::skip_boot_rom(&mut skipped, &mut Mbc1::new(rom.as_slice()));
+            let skipped = skipped.cpu;
+            assert_eq!(
+                [
+                    booted.a, booted.b, booted.c, booted.d, booted.e, booted.h, booted.l
+                ],
+                [
+                    skipped.a, skipped.b, skipped.c, skipped.d, skipped.e, skipped.h, skipped.l
+                ],
+                "{revision:?}"
+            );
+            assert_eq!(booted.f, skipped.f, "{revision:?} {title:#04x}");
+        }
+    }
+
+    #[test]
+    fn skip_matches_boot() {
+        for revision in [DmgRevision::Dmg0, DmgRevision::Dmg, DmgRevision::Mgb] {
+            assert_skip_matches_boot::<Dmg>(revision);
+        }
+        for revision in [SgbRevision::Sgb, SgbRevision::Sgb2] {
+            assert_skip_matches_boot::<Sgb>(revision);
+        }
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(DmgRevision::from_name("mgb"), Some(DmgRevision::Mgb));
+        assert_eq!(SgbRevision::from_name("sgb2"), Some(SgbRevision::Sgb2));
+        assert_eq!(CgbRevision::from_name("agb"), Some(CgbRevision::Agb));
+        assert_eq!(CgbRevision::from_name("cgb0"), Some(CgbRevision::Cgb0));
+        assert_eq!(CgbRevision::from_name("dmg"), None);
+        assert_eq!(DmgRevision::Dmg0.get_name(), "dmg0");
+    }
+
+    #[test]
+    fn agb_screen() {
+        let mut builder = ArrayVec::<u16, 160>::new();
+        for color in [0x7fff, 0x4210, 0x0000].into_iter().cycle().take(160) {
+            builder.push(color);
+        }
+        let scanline = builder.get_scanline();
+        for revision in [CgbRevision::Cgb0, CgbRevision::Cgb] {
+            assert_eq!(
+                Cgb::get_screen_scanline(revision, scanline).raw(),
+                scanline.raw()
+            );
+        }
+        // white and black are kept, the levels in between are darker
+        let agb = Cgb::get_screen_scanline(CgbRevision::Agb, scanline);
+        assert_eq!(agb.raw()[..3], [0x7fff, 9 | 9 << 5 | 9 << 10, 0x0000]);
+    }
+}
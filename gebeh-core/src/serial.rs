@@ -1,7 +1,8 @@
 use crate::{FallingEdge, interrupts::Interrupts};
+use serde::{Deserialize, Serialize};
 
 bitflags::bitflags! {
-    #[derive(Debug, Clone, Copy,  PartialEq, Eq)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
     pub struct SerialControl: u8 {
         const TRANSFER_ENABLE = 1 << 7;
         const CLOCK_SPEED = 1 << 2;
@@ -9,7 +10,7 @@ bitflags::bitflags! {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 enum SerialControlState {
     NoTransfer { is_master: bool },
     Slave,
@@ -22,7 +23,7 @@ impl Default for SerialControlState {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 struct SerialState {
     pub sb: u8,
     sc: SerialControlState,
@@ -31,7 +32,7 @@ struct SerialState {
     delay_int: bool,
 }
 
-#[derive(Clone, Default)]
+#[derive(Clone, Default, Serialize, Deserialize)]
 pub struct DmgSerial(SerialState);
 
 impl Default for SerialState {
@@ -166,7 +167,7 @@ impl Serial for DmgSerial {
     }
 }
 
-#[derive(Clone, Default)]
+#[derive(Clone, Default, Serialize, Deserialize)]
 pub struct CgbSerial {
     is_fast: bool,
     state: SerialState,
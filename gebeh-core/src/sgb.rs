@@ -0,0 +1,560 @@
+// The Super Game Boy is a DMG inside a SNES cartridge. The game sends commands to the SNES with
+// packets written in the joypad register, the SNES colorizes the screen and draws a border.
+// https://gbdev.io/pandocs/SGB_Functions.html
+
+use core::cmp::Ordering;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    HEIGHT, WIDTH,
+    joypad::{Joypad, SGB_PACKET_SIZE},
+    ppu::DmgScanline,
+};
+
+pub const SGB_WIDTH: usize = 256;
+pub const SGB_HEIGHT: usize = 224;
+// where the Game Boy screen is drawn in the border
+const SCREEN_X: usize = (SGB_WIDTH - WIDTH as usize) / 2;
+const SCREEN_Y: usize = (SGB_HEIGHT - HEIGHT as usize) / 2;
+
+// a command is made of 1 to 7 packets
+const MAX_PACKETS: usize = 7;
+// the palettes are given for each 8x8 area of the screen
+const ATTRIBUTES_WIDTH: usize = WIDTH as usize / 8;
+const ATTRIBUTES_HEIGHT: usize = HEIGHT as usize / 8;
+const ATTRIBUTES_SIZE: usize = ATTRIBUTES_WIDTH * ATTRIBUTES_HEIGHT;
+// 2 bits per attribute
+const ATTRIBUTE_FILE_SIZE: usize = ATTRIBUTES_SIZE / 4;
+const ATTRIBUTE_FILES: usize = 45;
+// the *_TRN commands copy 4 KiB from the screen
+const TRANSFER_SIZE: usize = 0x1000;
+const SYSTEM_PALETTES: usize = 512;
+// 256 tiles, 4 bits per pixel
+const BORDER_TILES_SIZE: usize = 0x2000;
+const BORDER_MAP_WIDTH: usize = 32;
+const BORDER_MAP_SIZE: usize = BORDER_MAP_WIDTH * BORDER_MAP_WIDTH;
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum MaskMode {
+    #[default]
+    Cancel,
+    // the last frame stays on the screen
+    Freeze,
+    Black,
+    // filled with the color 0
+    Color0,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+enum Transfer {
+    SystemPalettes,
+    BorderTiles { is_upper_half: bool },
+    BorderMap,
+    AttributeFiles,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SuperGameBoy {
+    #[serde(with = "crate::state::byte_array")]
+    command: [u8; SGB_PACKET_SIZE * MAX_PACKETS],
+    received_packets: u8,
+    // RGB555, the color 0 is shared
+    palettes: [[u16; 4]; 4],
+    #[serde(with = "crate::state::byte_array")]
+    attributes: [u8; ATTRIBUTES_SIZE],
+    #[serde(with = "crate::state::array")]
+    system_palettes: [u16; SYSTEM_PALETTES * 4],
+    #[serde(with = "crate::state::byte_array")]
+    attribute_files: [u8; ATTRIBUTE_FILE_SIZE * ATTRIBUTE_FILES],
+    // SNES format, 4 bit planes
+    #[serde(with = "crate::state::byte_array")]
+    border_tiles: [u8; BORDER_TILES_SIZE],
+    #[serde(with = "crate::state::array")]
+    border_map: [u16; BORDER_MAP_SIZE],
+    // the palettes 4 to 7
+    border_palettes: [[u16; 16]; 4],
+    mask: MaskMode,
+    // done with the next frame
+    pending_transfer: Option<Transfer>,
+    // the frame being drawn by the PPU
+    #[serde(with = "crate::state::array")]
+    screen: [DmgScanline; HEIGHT as usize],
+    #[serde(with = "crate::state::array")]
+    displayed_screen: [DmgScanline; HEIGHT as usize],
+}
+
+impl Default for SuperGameBoy {
+    fn default() -> Self {
+        Self {
+            command: [0; _],
+            received_packets: 0,
+            palettes: [[0x7fff, 0x56b5, 0x294a, 0x0000]; 4],
+            attributes: [0; _],
+            system_palettes: [0; _],
+            attribute_files: [0; _],
+            border_tiles: [0; _],
+            border_map: [0; _],
+            border_palettes: [[0; _]; _],
+            mask: MaskMode::Cancel,
+            pending_transfer: None,
+            screen: [DmgScanline::default(); _],
+            displayed_screen: [DmgScanline::default(); _],
+        }
+    }
+}
+
+fn read_color(bytes: &[u8]) -> u16 {
+    u16::from_le_bytes([bytes[0], bytes[1]]) & 0x7fff
+}
+
+// 0 is white, unlike the DMG colors
+fn get_shade(scanline: &DmgScanline, x: usize) -> u8 {
+    let color = scanline.raw()[x / 4] >> (6 - (x % 4) * 2);
+    3 - (color & 0b11)
+}
+
+impl SuperGameBoy {
+    pub fn get_mask(&self) -> MaskMode {
+        self.mask
+    }
+
+    pub(crate) fn receive_packet(&mut self, packet: [u8; SGB_PACKET_SIZE], joypad: &mut Joypad) {
+        let index = usize::from(self.received_packets);
+        self.command[index * SGB_PACKET_SIZE..][..SGB_PACKET_SIZE].copy_from_slice(&packet);
+        self.received_packets += 1;
+        let length = (self.command[0] & 0b111).max(1);
+        if self.received_packets < length {
+            return;
+        }
+        self.received_packets = 0;
+        self.execute_command(joypad);
+    }
+
+    fn execute_command(&mut self, joypad: &mut Joypad) {
+        match self.command[0] >> 3 {
+            // PAL01, PAL23, PAL03, PAL12
+            0x00 => self.set_palettes(0, 1),
+            0x01 => self.set_palettes(2, 3),
+            0x02 => self.set_palettes(0, 3),
+            0x03 => self.set_palettes(1, 2),
+            // ATTR_BLK
+            0x04 => self.set_attribute_blocks(),
+            // ATTR_LIN
+            0x05 => self.set_attribute_lines(),
+            // ATTR_DIV
+            0x06 => self.set_attribute_division(),
+            // ATTR_CHR
+            0x07 => self.set_attribute_characters(),
+            // PAL_SET
+            0x0a => self.set_system_palettes(),
+            // PAL_TRN
+            0x0b => self.pending_transfer = Some(Transfer::SystemPalettes),
+            // MLT_REQ
+            0x11 => joypad.set_players(match self.command[1] & 0b11 {
+                0b01 => 2,
+                0b11 => 4,
+                _ => 1,
+            }),
+            // CHR_TRN
+            0x13 => {
+                self.pending_transfer = Some(Transfer::BorderTiles {
+                    is_upper_half: self.command[1] & 1 != 0,
+                })
+            }
+            // PCT_TRN
+            0x14 => self.pending_transfer = Some(Transfer::BorderMap),
+            // ATTR_TRN
+            0x15 => self.pending_transfer = Some(Transfer::AttributeFiles),
+            // ATTR_SET
+            0x16 => {
+                self.load_attribute_file(self.command[1] & 0x3f);
+                if self.command[1] & 0x40 != 0 {
+                    self.mask = MaskMode::Cancel;
+                }
+            }
+            // MASK_EN
+            0x17 => {
+                self.mask = match self.command[1] & 0b11 {
+                    0 => MaskMode::Cancel,
+                    1 => MaskMode::Freeze,
+                    2 => MaskMode::Black,
+                    _ => MaskMode::Color0,
+                }
+            }
+            // the sound and the SNES programs need a SNES
+            command => log::debug!("Unsupported SGB command 0x{command:02x}"),
+        }
+    }
+
+    fn set_palettes(&mut self, first: usize, second: usize) {
+        let color_0 = read_color(&self.command[1..]);
+        for palette in &mut self.palettes {
+            palette[0] = color_0;
+        }
+        for (index, palette) in [first, second].into_iter().enumerate() {
+            for color in 1..4 {
+                self.palettes[palette][color] =
+                    read_color(&self.command[1 + (index * 3 + color) * 2..]);
+            }
+        }
+    }
+
+    fn set_attribute(&mut self, x: usize, y: usize, palette: u8) {
+        if x < ATTRIBUTES_WIDTH && y < ATTRIBUTES_HEIGHT {
+            self.attributes[y * ATTRIBUTES_WIDTH + x] = palette & 0b11;
+        }
+    }
+
+    fn set_attribute_blocks(&mut self) {
+        let count = usize::from(self.command[1]);
+        let command = self.command;
+        for block in command[2..].chunks_exact(6).take(count) {
+            let control = block[0] & 0b111;
+            let inside = block[1] & 0b11;
+            let outside = (block[1] >> 4) & 0b11;
+            // the line takes the palette of the only area changed
+            let line = match control {
+                0b001 => Some(inside),
+                0b100 => Some(outside),
+                _ if control & 0b010 != 0 => Some((block[1] >> 2) & 0b11),
+                _ => None,
+            };
+            let [left, top, right, bottom] =
+                [block[2], block[3], block[4], block[5]].map(|coordinate| coordinate & 0x1f);
+            for y in 0..ATTRIBUTES_HEIGHT as u8 {
+                for x in 0..ATTRIBUTES_WIDTH as u8 {
+                    let is_inside = left < x && x < right && top < y && y < bottom;
+                    let is_line =
+                        !is_inside && (left..=right).contains(&x) && (top..=bottom).contains(&y);
+                    let palette = if is_inside {
+                        (control & 0b001 != 0).then_some(inside)
+                    } else if is_line {
+                        line
+                    } else {
+                        (control & 0b100 != 0).then_some(outside)
+                    };
+                    if let Some(palette) = palette {
+                        self.set_attribute(x.into(), y.into(), palette);
+                    }
+                }
+            }
+        }
+    }
+
+    fn set_attribute_lines(&mut self) {
+        let count = usize::from(self.command[1]);
+        let command = self.command;
+        for &line in command[2..].iter().take(count) {
+            let number = usize::from(line & 0x1f);
+            let palette = (line >> 5) & 0b11;
+            if line & 0x80 != 0 {
+                for x in 0..ATTRIBUTES_WIDTH {
+                    self.set_attribute(x, number, palette);
+                }
+            } else {
+                for y in 0..ATTRIBUTES_HEIGHT {
+                    self.set_attribute(number, y, palette);
+                }
+            }
+        }
+    }
+
+    fn set_attribute_division(&mut self) {
+        let palettes = self.command[1];
+        let is_horizontal = palettes & 0x40 != 0;
+        let division = usize::from(self.command[2] & 0x1f);
+        for y in 0..ATTRIBUTES_HEIGHT {
+            for x in 0..ATTRIBUTES_WIDTH {
+                let position = if is_horizontal { y } else { x };
+                let palette = match position.cmp(&division) {
+                    // above or left
+                    Ordering::Less => palettes >> 2,
+                    Ordering::Equal => palettes >> 4,
+                    // below or right
+                    Ordering::Greater => palettes,
+                };
+                self.set_attribute(x, y, palette);
+            }
+        }
+    }
+
+    fn set_attribute_characters(&mut self) {
+        let mut x = usize::from(self.command[1]).min(ATTRIBUTES_WIDTH - 1);
+        let mut y = usize::from(self.command[2]).min(ATTRIBUTES_HEIGHT - 1);
+        let count = usize::from(u16::from_le_bytes([self.command[3], self.command[4]]))
+            .min(ATTRIBUTES_SIZE);
+        let is_left_to_right = self.command[5] & 1 == 0;
+        let command = self.command;
+        for index in 0..count {
+            let Some(byte) = command.get(6 + index / 4) else {
+                break;
+            };
+            self.set_attribute(x, y, byte >> (6 - (index % 4) * 2));
+            if is_left_to_right {
+                x += 1;
+                if x == ATTRIBUTES_WIDTH {
+                    x = 0;
+                    y = (y + 1) % ATTRIBUTES_HEIGHT;
+                }
+            } else {
+                y += 1;
+                if y == ATTRIBUTES_HEIGHT {
+                    y = 0;
+                    x = (x + 1) % ATTRIBUTES_WIDTH;
+                }
+            }
+        }
+    }
+
+    fn set_system_palettes(&mut self) {
+        for palette in 0..4 {
+            let number = usize::from(u16::from_le_bytes([
+                self.command[1 + palette * 2],
+                self.command[2 + palette * 2],
+            ])) % SYSTEM_PALETTES;
+            self.palettes[palette].copy_from_slice(&self.system_palettes[number * 4..][..4]);
+        }
+        // the color 0 of the first palette is used by all of them
+        let color_0 = self.palettes[0][0];
+        for palette in &mut self.palettes {
+            palette[0] = color_0;
+        }
+        let attributes = self.command[9];
+        if attributes & 0x80 != 0 {
+            self.load_attribute_file(attributes & 0x3f);
+        }
+        if attributes & 0x40 != 0 {
+            self.mask = MaskMode::Cancel;
+        }
+    }
+
+    fn load_attribute_file(&mut self, number: u8) {
+        let number = usize::from(number);
+        if number >= ATTRIBUTE_FILES {
+            return;
+        }
+        let file = &self.attribute_files[number * ATTRIBUTE_FILE_SIZE..][..ATTRIBUTE_FILE_SIZE];
+        for (index, attribute) in self.attributes.iter_mut().enumerate() {
+            *attribute = (file[index / 4] >> (6 - (index % 4) * 2)) & 0b11;
+        }
+    }
+
+    pub(crate) fn push_scanline(&mut self, ly: u8, scanline: &DmgScanline) {
+        let Some(line) = self.screen.get_mut(usize::from(ly)) else {
+            return;
+        };
+        *line = *scanline;
+        if ly != HEIGHT - 1 {
+            return;
+        }
+        if let Some(transfer) = self.pending_transfer.take() {
+            self.transfer(transfer);
+        }
+        if self.mask != MaskMode::Freeze {
+            self.displayed_screen = self.screen;
+        }
+    }
+
+    // The data is displayed as 256 tiles, 20 per line, with the identity palette.
+    // The SNES reads it back from the screen.
+    fn read_transfer_data(&self) -> [u8; TRANSFER_SIZE] {
+        let mut data = [0; TRANSFER_SIZE];
+        for (index, tile) in data.as_chunks_mut::<16>().0.iter_mut().enumerate() {
+            let tile_x = (index % ATTRIBUTES_WIDTH) * 8;
+            let tile_y = (index / ATTRIBUTES_WIDTH) * 8;
+            for (row, bytes) in tile.as_chunks_mut::<2>().0.iter_mut().enumerate() {
+                let scanline = &self.screen[tile_y + row];
+                for column in 0..8 {
+                    let shade = get_shade(scanline, tile_x + column);
+                    bytes[0] |= (shade & 1) << (7 - column);
+                    bytes[1] |= (shade >> 1) << (7 - column);
+                }
+            }
+        }
+        data
+    }
+
+    fn transfer(&mut self, transfer: Transfer) {
+        let data = self.read_transfer_data();
+        match transfer {
+            Transfer::SystemPalettes => {
+                for (color, bytes) in self.system_palettes.iter_mut().zip(data.as_chunks::<2>().0) {
+                    *color = read_color(bytes);
+                }
+            }
+            Transfer::BorderTiles { is_upper_half } => {
+                let offset = if is_upper_half { TRANSFER_SIZE } else { 0 };
+                self.border_tiles[offset..][..TRANSFER_SIZE].copy_from_slice(&data);
+            }
+            Transfer::BorderMap => {
+                let (map, palettes) = data.split_at(BORDER_MAP_SIZE * 2);
+                for (entry, bytes) in self.border_map.iter_mut().zip(map.as_chunks::<2>().0) {
+                    *entry = u16::from_le_bytes(*bytes);
+                }
+                for (color, bytes) in self
+                    .border_palettes
+                    .as_flattened_mut()
+                    .iter_mut()
+                    .zip(palettes.as_chunks::<2>().0)
+                {
+                    *color = read_color(bytes);
+                }
+            }
+            Transfer::AttributeFiles => {
+                let size = self.attribute_files.len();
+                self.attribute_files.copy_from_slice(&data[..size]);
+            }
+        }
+    }
+
+    fn get_screen_color(&self, x: usize, y: usize) -> u16 {
+        match self.mask {
+            MaskMode::Black => 0,
+            MaskMode::Color0 => self.palettes[0][0],
+            MaskMode::Cancel | MaskMode::Freeze => {
+                let attribute = self.attributes[(y / 8) * ATTRIBUTES_WIDTH + x / 8];
+                let shade = get_shade(&self.displayed_screen[y], x);
+                self.palettes[usize::from(attribute)][usize::from(shade)]
+            }
+        }
+    }
+
+    fn get_border_color(&self, x: usize, y: usize) -> u16 {
+        // https://gbdev.io/pandocs/SGB_Command_Border.html
+        let entry = self.border_map[(y / 8) * BORDER_MAP_WIDTH + x / 8];
+        let tile = usize::from(entry & 0xff);
+        let palette = usize::from((entry >> 10) & 0b11);
+        let column = if entry & 0x4000 != 0 {
+            7 - x % 8
+        } else {
+            x % 8
+        };
+        let row = if entry & 0x8000 != 0 {
+            7 - y % 8
+        } else {
+            y % 8
+        };
+        let planes = &self.border_tiles[tile * 32..][..32];
+        let color = [
+            planes[row * 2],
+            planes[row * 2 + 1],
+            planes[16 + row * 2],
+            planes[17 + row * 2],
+        ]
+        .into_iter()
+        .enumerate()
+        .fold(0, |color, (plane, byte)| {
+            color | (((byte >> (7 - column)) & 1) << plane)
+        });
+        // transparent
+        if color == 0 {
+            return self.palettes[0][0];
+        }
+        self.border_palettes[palette][usize::from(color)]
+    }
+
+    /// Draws the 256x224 frame in RGB555, the Game Boy screen is in the middle of the border.
+    pub fn render(&self, frame: &mut [u16; SGB_WIDTH * SGB_HEIGHT]) {
+        for (y, line) in frame.as_chunks_mut::<SGB_WIDTH>().0.iter_mut().enumerate() {
+            for (x, pixel) in line.iter_mut().enumerate() {
+                let screen_x = x.wrapping_sub(SCREEN_X);
+                let screen_y = y.wrapping_sub(SCREEN_Y);
+                *pixel = if screen_x < usize::from(WIDTH) && screen_y < usize::from(HEIGHT) {
+                    self.get_screen_color(screen_x, screen_y)
+                } else {
+                    self.get_border_color(x, y)
+                };
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::joypad::{Joypad, SGB_PACKET_SIZE};
+
+    use super::{MaskMode, SGB_HEIGHT, SGB_WIDTH, SuperGameBoy};
+
+    fn send_packet(joypad: &mut Joypad, packet: [u8; SGB_PACKET_SIZE]) {
+        // reset pulse
+        joypad.set_register(0x00);
+        joypad.set_register(0x30);
+        for index in 0..SGB_PACKET_SIZE * 8 {
+            let bit = (packet[index / 8] >> (index % 8)) & 1;
+            joypad.set_register(if bit == 0 { 0x20 } else { 0x10 });
+            joypad.set_register(0x30);
+        }
+        // stop bit
+        joypad.set_register(0x20);
+        joypad.set_register(0x30);
+    }
+
+    #[test]
+    fn packets() {
+        let mut joypad = Joypad::default();
+        let mut sgb = SuperGameBoy::default();
+        // PAL01
+        let mut packet = [0; SGB_PACKET_SIZE];
+        packet[0] = 0x01;
+        packet[1..15].copy_from_slice(&[
+            0x1f, 0x00, 0xe0, 0x03, 0x00, 0x7c, 0x00, 0x00, 0xff, 0x7f, 0x10, 0x42, 0x08, 0x21,
+        ]);
+        send_packet(&mut joypad, packet);
+        sgb.receive_packet(joypad.take_packet().unwrap(), &mut joypad);
+        assert!(joypad.take_packet().is_none());
+        assert_eq!(sgb.palettes[0], [0x001f, 0x03e0, 0x7c00, 0x0000]);
+        assert_eq!(sgb.palettes[1], [0x001f, 0x7fff, 0x4210, 0x2108]);
+        // the color 0 is shared
+        assert_eq!(sgb.palettes[3][0], 0x001f);
+
+        // MLT_REQ with 2 players
+        let mut packet = [0; SGB_PACKET_SIZE];
+        packet[0] = 0x89;
+        packet[1] = 0x01;
+        send_packet(&mut joypad, packet);
+        sgb.receive_packet(joypad.take_packet().unwrap(), &mut joypad);
+        joypad.set_register(0x30);
+        assert_eq!(joypad.get_register() & 0x0f, 0x0f);
+        joypad.set_register(0x10);
+        joypad.set_register(0x30);
+        assert_eq!(joypad.get_register() & 0x0f, 0x0e);
+    }
+
+    #[test]
+    fn attributes() {
+        let mut joypad = Joypad::default();
+        let mut sgb = SuperGameBoy::default();
+        // ATTR_DIV, horizontal split at line 5: 1 above, 2 on the line, 3 below
+        let mut command = [0; SGB_PACKET_SIZE];
+        command[0] = 0x31;
+        command[1] = 0x40 | (2 << 4) | (1 << 2) | 3;
+        command[2] = 5;
+        sgb.receive_packet(command, &mut joypad);
+        assert_eq!(sgb.attributes[0], 1);
+        assert_eq!(sgb.attributes[5 * 20 + 7], 2);
+        assert_eq!(sgb.attributes[17 * 20 + 19], 3);
+
+        // ATTR_BLK, inside and border of a 3x3 block at (1, 1) with the palette 2
+        let mut command = [0; SGB_PACKET_SIZE];
+        command[0] = 0x21;
+        command[1] = 1;
+        command[2..8].copy_from_slice(&[0b011, 0b1010, 1, 1, 3, 3]);
+        sgb.receive_packet(command, &mut joypad);
+        assert_eq!(sgb.attributes[20 + 1], 2);
+        assert_eq!(sgb.attributes[2 * 20 + 2], 2);
+        assert_eq!(sgb.attributes[4 * 20 + 4], 1);
+
+        // MASK_EN black
+        let mut command = [0; SGB_PACKET_SIZE];
+        command[0] = 0xb9;
+        command[1] = 2;
+        sgb.receive_packet(command, &mut joypad);
+        assert_eq!(sgb.get_mask(), MaskMode::Black);
+        let mut frame = [0xffff; SGB_WIDTH * SGB_HEIGHT];
+        sgb.render(&mut frame);
+        assert_eq!(frame[100 * SGB_WIDTH + 100], 0);
+        // empty border, shows the color 0
+        assert_eq!(frame[0], 0x7fff);
+    }
+}
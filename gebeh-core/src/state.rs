@@ -0,0 +1,157 @@
+// Helpers used by the save states. Serde only implements its traits for arrays up to 32 elements,
+// so the memories are (de)serialized as byte strings, which are compact with binary formats.
+
+use core::{fmt, marker::PhantomData};
+
+use serde::{
+    Deserialize, Deserializer, Serialize, Serializer,
+    de::{self, SeqAccess, Visitor},
+    ser::SerializeTuple,
+};
+
+pub(crate) mod byte_array {
+    use super::*;
+
+    pub fn serialize<S: Serializer, const N: usize>(
+        array: &[u8; N],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(array)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, const N: usize>(
+        deserializer: D,
+    ) -> Result<[u8; N], D::Error> {
+        deserializer.deserialize_bytes(ByteArrayVisitor::<N>)
+    }
+}
+
+// for the arrays that are not made of bytes
+pub(crate) mod array {
+    use super::*;
+
+    pub fn serialize<S: Serializer, T: Serialize, const N: usize>(
+        array: &[T; N],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut tuple = serializer.serialize_tuple(N)?;
+        for element in array {
+            tuple.serialize_element(element)?;
+        }
+        tuple.end()
+    }
+
+    pub fn deserialize<'de, D, T, const N: usize>(deserializer: D) -> Result<[T; N], D::Error>
+    where
+        D: Deserializer<'de>,
+        T: Deserialize<'de> + Default + Copy,
+    {
+        deserializer.deserialize_tuple(N, ArrayVisitor::<T, N>(PhantomData))
+    }
+}
+
+// for banked memories like [[u8; 0x1000]; 8]
+pub(crate) mod byte_banks {
+    use super::*;
+
+    pub fn serialize<S: Serializer, const N: usize, const M: usize>(
+        banks: &[[u8; N]; M],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut tuple = serializer.serialize_tuple(M)?;
+        for bank in banks {
+            tuple.serialize_element(&ByteArrayRef(bank))?;
+        }
+        tuple.end()
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, const N: usize, const M: usize>(
+        deserializer: D,
+    ) -> Result<[[u8; N]; M], D::Error> {
+        deserializer.deserialize_tuple(M, ByteBanksVisitor::<N, M>(PhantomData))
+    }
+}
+
+struct ByteArrayRef<'a, const N: usize>(&'a [u8; N]);
+
+impl<const N: usize> Serialize for ByteArrayRef<'_, N> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        byte_array::serialize(self.0, serializer)
+    }
+}
+
+struct ByteArray<const N: usize>([u8; N]);
+
+impl<'de, const N: usize> Deserialize<'de> for ByteArray<N> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        byte_array::deserialize(deserializer).map(Self)
+    }
+}
+
+struct ByteArrayVisitor<const N: usize>;
+
+impl<'de, const N: usize> Visitor<'de> for ByteArrayVisitor<N> {
+    type Value = [u8; N];
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "{N} bytes")
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+        v.try_into().map_err(|_| E::invalid_length(v.len(), &self))
+    }
+
+    // for the formats that don't know about byte strings
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut array = [0; N];
+        for (i, byte) in array.iter_mut().enumerate() {
+            *byte = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(i, &self))?;
+        }
+        Ok(array)
+    }
+}
+
+struct ArrayVisitor<T, const N: usize>(PhantomData<[T; N]>);
+
+impl<'de, T: Deserialize<'de> + Default + Copy, const N: usize> Visitor<'de>
+    for ArrayVisitor<T, N>
+{
+    type Value = [T; N];
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "an array of length {N}")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut array = [T::default(); N];
+        for (i, element) in array.iter_mut().enumerate() {
+            *element = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(i, &self))?;
+        }
+        Ok(array)
+    }
+}
+
+struct ByteBanksVisitor<const N: usize, const M: usize>(PhantomData<[[u8; N]; M]>);
+
+impl<'de, const N: usize, const M: usize> Visitor<'de> for ByteBanksVisitor<N, M> {
+    type Value = [[u8; N]; M];
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "{M} banks of {N} bytes")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut banks = [[0; N]; M];
+        for (i, bank) in banks.iter_mut().enumerate() {
+            *bank = seq
+                .next_element::<ByteArray<N>>()?
+                .ok_or_else(|| de::Error::invalid_length(i, &self))?
+                .0;
+        }
+        Ok(banks)
+    }
+}
@@ -1,4 +1,5 @@
 use crate::interrupts::Interrupts;
+use serde::{Deserialize, Serialize};
 
 // There is a system counter which is 14 bits wide
 // The div register is the height most significant bits of this system counter
@@ -8,7 +9,7 @@ use crate::interrupts::Interrupts;
 // so div frequency = 4.194304 MHz / 4 / 2^6 = 16384 Hz as pandocs says
 // https://gbdev.io/pandocs/Timer_and_Divider_Registers.html#ff04--div-divider-register
 
-#[derive(Clone, Default)]
+#[derive(Clone, Default, Serialize, Deserialize)]
 pub struct Timer {
     falling_edge_detector: bool,
     tma: u8,
@@ -30,6 +31,9 @@ impl Timer {
     pub fn reset_system_counter(&mut self) {
         self.system_counter = 0;
     }
+    pub(crate) fn set_system_counter(&mut self, value: u16) {
+        self.system_counter = value;
+    }
     pub fn set_tma(&mut self, value: u8) {
         if self.has_tima_just_overflowed {
             // should not conflict with a timer increment hopefully
@@ -1,11 +1,12 @@
 use core::num::NonZeroU8;
+use serde::{Deserialize, Serialize};
 
 use crate::Ram;
 
 const WRAM_BANK_SIZE: u16 = 0x1000;
 
-#[derive(Clone)]
-pub struct DmgWram([u8; WRAM_BANK_SIZE as usize * 2]);
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DmgWram(#[serde(with = "crate::state::byte_array")] [u8; WRAM_BANK_SIZE as usize * 2]);
 
 impl Default for DmgWram {
     fn default() -> Self {
@@ -21,11 +22,16 @@ impl Ram for DmgWram {
     fn write(&mut self, index: u16, value: u8) {
         self.0[usize::from(index)] = value;
     }
+
+    fn get_banks_mut(&mut self) -> &mut [u8] {
+        &mut self.0
+    }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct CgbWram {
     bank: NonZeroU8,
+    #[serde(with = "crate::state::byte_banks")]
     data: [[u8; WRAM_BANK_SIZE as usize]; 8],
 }
 
@@ -54,6 +60,10 @@ impl Ram for CgbWram {
             self.data[usize::from(self.bank.get())][usize::from(index - WRAM_BANK_SIZE)] = value;
         }
     }
+
+    fn get_banks_mut(&mut self) -> &mut [u8] {
+        self.data.as_flattened_mut()
+    }
 }
 
 pub trait Wram: Ram {
@@ -0,0 +1,252 @@
+// ROMs are often distributed in archives, they are opened before anything reads the header.
+
+use std::{
+    borrow::Cow,
+    fmt::Display,
+    io::{Cursor, Read},
+};
+
+use crate::MAX_ROM_SIZE;
+
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const SEVEN_ZIP_MAGIC: &[u8] = &[b'7', b'z', 0xbc, 0xaf, 0x27, 0x1c];
+
+#[derive(Debug)]
+pub enum ArchiveError {
+    Zip(zip::result::ZipError),
+    Gzip(std::io::Error),
+    SevenZip(sevenz_rust::Error),
+    NoRom,
+    EntryNotFound(String),
+    // an archive bomb, the decompression stops at the biggest ROM size
+    TooBig,
+}
+
+impl Display for ArchiveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ArchiveError::Zip(error) => write!(f, "Can't read the zip archive: {error}"),
+            ArchiveError::Gzip(error) => write!(f, "Can't read the gzip file: {error}"),
+            ArchiveError::SevenZip(error) => write!(f, "Can't read the 7z archive: {error}"),
+            ArchiveError::NoRom => write!(f, "No .gb or .gbc file in the archive"),
+            ArchiveError::EntryNotFound(name) => write!(f, "No {name} in the archive"),
+            ArchiveError::TooBig => write!(f, "The file in the archive is bigger than 8 MiB"),
+        }
+    }
+}
+
+impl std::error::Error for ArchiveError {}
+
+fn is_rom_name(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    name.ends_with(".gb") || name.ends_with(".gbc")
+}
+
+fn is_wanted(name: &str, entry_name: Option<&str>) -> bool {
+    match entry_name {
+        Some(entry_name) => name == entry_name,
+        None => is_rom_name(name),
+    }
+}
+
+fn not_found(entry_name: Option<&str>) -> ArchiveError {
+    match entry_name {
+        Some(entry_name) => ArchiveError::EntryNotFound(entry_name.to_owned()),
+        None => ArchiveError::NoRom,
+    }
+}
+
+// one byte more than a ROM to know that the limit was passed
+fn read_limited(reader: impl Read, rom: &mut Vec<u8>) -> std::io::Result<u64> {
+    reader.take(MAX_ROM_SIZE as u64 + 1).read_to_end(rom)?;
+    Ok(rom.len() as u64)
+}
+
+fn check_size(rom: Vec<u8>) -> Result<Vec<u8>, ArchiveError> {
+    if rom.len() > MAX_ROM_SIZE {
+        return Err(ArchiveError::TooBig);
+    }
+    Ok(rom)
+}
+
+fn extract_zip(bytes: &[u8], entry_name: Option<&str>) -> Result<Vec<u8>, ArchiveError> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).map_err(ArchiveError::Zip)?;
+    // the central directory order is the order shown by the archive managers
+    let index = (0..archive.len())
+        .find(|&index| {
+            archive
+                .name_for_index(index)
+                .is_some_and(|name| is_wanted(name, entry_name))
+        })
+        .ok_or_else(|| not_found(entry_name))?;
+    let file = archive.by_index(index).map_err(ArchiveError::Zip)?;
+    let mut rom = Vec::new();
+    read_limited(file, &mut rom).map_err(|error| ArchiveError::Zip(error.into()))?;
+    check_size(rom)
+}
+
+// a gzip file holds a single file, its name is not checked
+fn extract_gzip(bytes: &[u8]) -> Result<Vec<u8>, ArchiveError> {
+    let mut rom = Vec::new();
+    read_limited(flate2::read::GzDecoder::new(bytes), &mut rom).map_err(ArchiveError::Gzip)?;
+    check_size(rom)
+}
+
+fn extract_seven_zip(bytes: &[u8], entry_name: Option<&str>) -> Result<Vec<u8>, ArchiveError> {
+    let mut reader = sevenz_rust::SevenZReader::new(
+        Cursor::new(bytes),
+        bytes.len() as u64,
+        sevenz_rust::Password::empty(),
+    )
+    .map_err(ArchiveError::SevenZip)?;
+    let mut rom = None;
+    reader
+        .for_each_entries(|entry, entry_reader| {
+            // the next blocks are still given when an entry returns false
+            if rom.is_some() {
+                return Ok(false);
+            }
+            if !entry.is_directory() && is_wanted(entry.name(), entry_name) {
+                let mut bytes = Vec::new();
+                read_limited(entry_reader, &mut bytes)?;
+                rom = Some(bytes);
+                return Ok(false);
+            }
+            // the entries of a solid block are decompressed one after the other, the ones before
+            // the ROM are thrown away without counting in the limit
+            std::io::copy(entry_reader, &mut std::io::sink())?;
+            Ok(true)
+        })
+        .map_err(ArchiveError::SevenZip)?;
+    check_size(rom.ok_or_else(|| not_found(entry_name))?)
+}
+
+/// Returns the ROM in a zip, gzip or 7z archive, or the bytes themselves if they are not an archive.
+/// Without an entry name, the first .gb or .gbc file is taken.
+pub fn extract_rom<'a>(
+    bytes: &'a [u8],
+    entry_name: Option<&str>,
+) -> Result<Cow<'a, [u8]>, ArchiveError> {
+    if bytes.starts_with(ZIP_MAGIC) {
+        extract_zip(bytes, entry_name).map(Cow::Owned)
+    } else if bytes.starts_with(GZIP_MAGIC) {
+        extract_gzip(bytes).map(Cow::Owned)
+    } else if bytes.starts_with(SEVEN_ZIP_MAGIC) {
+        extract_seven_zip(bytes, entry_name).map(Cow::Owned)
+    } else {
+        Ok(Cow::Borrowed(bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use sevenz_rust::{SevenZArchiveEntry, SevenZWriter};
+
+    use super::{ArchiveError, extract_rom};
+    use crate::MAX_ROM_SIZE;
+
+    #[test]
+    fn archives() {
+        let rom: Vec<u8> = (0..0x8000u32).map(|i| (i % 251) as u8).collect();
+
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Deflated);
+        zip.start_file("readme.txt", options).unwrap();
+        zip.write_all(b"hello").unwrap();
+        zip.start_file("game.GBC", options).unwrap();
+        zip.write_all(&rom).unwrap();
+        zip.start_file("other.gb", options).unwrap();
+        zip.write_all(&[0; 0x8000]).unwrap();
+        let zip = zip.finish().unwrap().into_inner();
+        assert_eq!(extract_rom(&zip, None).unwrap(), rom.as_slice());
+        assert_eq!(
+            extract_rom(&zip, Some("other.gb")).unwrap(),
+            [0; 0x8000].as_slice()
+        );
+        assert!(matches!(
+            extract_rom(&zip, Some("missing.gb")),
+            Err(ArchiveError::EntryNotFound(_))
+        ));
+
+        let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gzip.write_all(&rom).unwrap();
+        let gzip = gzip.finish().unwrap();
+        assert_eq!(extract_rom(&gzip, None).unwrap(), rom.as_slice());
+
+        let mut seven_zip = SevenZWriter::new(Cursor::new(Vec::new())).unwrap();
+        for (name, content) in [("readme.txt", b"hello".as_slice()), ("game.gb", &rom)] {
+            let mut entry = SevenZArchiveEntry::new();
+            entry.name = name.to_owned();
+            seven_zip.push_archive_entry(entry, Some(content)).unwrap();
+        }
+        let seven_zip = seven_zip.finish().unwrap().into_inner();
+        assert_eq!(extract_rom(&seven_zip, None).unwrap(), rom.as_slice());
+        assert_eq!(
+            extract_rom(&seven_zip, Some("readme.txt")).unwrap(),
+            b"hello".as_slice()
+        );
+        assert!(matches!(
+            extract_rom(&seven_zip, Some("missing.gb")),
+            Err(ArchiveError::EntryNotFound(_))
+        ));
+
+        assert_eq!(extract_rom(&rom, None).unwrap(), rom.as_slice());
+    }
+
+    #[test]
+    fn archive_bombs() {
+        // a few KiB once compressed
+        let zeros = vec![0; MAX_ROM_SIZE + 1];
+
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Deflated);
+        zip.start_file("game.gb", options).unwrap();
+        zip.write_all(&zeros).unwrap();
+        let zip = zip.finish().unwrap().into_inner();
+        assert!(matches!(extract_rom(&zip, None), Err(ArchiveError::TooBig)));
+
+        let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gzip.write_all(&zeros).unwrap();
+        let gzip = gzip.finish().unwrap();
+        assert!(matches!(
+            extract_rom(&gzip, None),
+            Err(ArchiveError::TooBig)
+        ));
+
+        let mut seven_zip = SevenZWriter::new(Cursor::new(Vec::new())).unwrap();
+        let mut entry = SevenZArchiveEntry::new();
+        entry.name = "game.gb".to_owned();
+        seven_zip
+            .push_archive_entry(entry, Some(zeros.as_slice()))
+            .unwrap();
+        let seven_zip = seven_zip.finish().unwrap().into_inner();
+        assert!(matches!(
+            extract_rom(&seven_zip, None),
+            Err(ArchiveError::TooBig)
+        ));
+    }
+
+    #[test]
+    fn big_entry_before_the_rom() {
+        // like a scanned manual, only the ROM counts in the limit
+        let manual = vec![0; MAX_ROM_SIZE + 1];
+        let rom = [0x42; 0x8000];
+        let mut seven_zip = SevenZWriter::new(Cursor::new(Vec::new())).unwrap();
+        for (name, content) in [("manual.pdf", manual.as_slice()), ("game.gb", &rom)] {
+            let mut entry = SevenZArchiveEntry::new();
+            entry.name = name.to_owned();
+            seven_zip.push_archive_entry(entry, Some(content)).unwrap();
+        }
+        let seven_zip = seven_zip.finish().unwrap().into_inner();
+        assert_eq!(extract_rom(&seven_zip, None).unwrap(), rom.as_slice());
+        assert!(matches!(
+            extract_rom(&seven_zip, Some("manual.pdf")),
+            Err(ArchiveError::TooBig)
+        ));
+    }
+}
@@ -0,0 +1,232 @@
+// Dumps of the genuine boot ROMs, to compare against the built-in ones.
+// https://gbdev.io/pandocs/Power_Up_Sequence.html
+
+use std::fmt;
+
+use gebeh_core::{Model, cpu::BootRom, revision::Revision};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BootRomKind {
+    Dmg0,
+    Dmg,
+    Mgb,
+    Sgb,
+    Sgb2,
+    Cgb0,
+    Cgb,
+    Agb,
+}
+
+impl BootRomKind {
+    pub fn get_size(self) -> usize {
+        match self {
+            BootRomKind::Dmg0
+            | BootRomKind::Dmg
+            | BootRomKind::Mgb
+            | BootRomKind::Sgb
+            | BootRomKind::Sgb2 => 0x100,
+            BootRomKind::Cgb0 | BootRomKind::Cgb | BootRomKind::Agb => 0x900,
+        }
+    }
+
+    // the name of the revision shipping this boot ROM
+    pub fn get_revision_name(self) -> &'static str {
+        match self {
+            BootRomKind::Dmg0 => "dmg0",
+            BootRomKind::Dmg => "dmg",
+            BootRomKind::Mgb => "mgb",
+            BootRomKind::Sgb => "sgb",
+            BootRomKind::Sgb2 => "sgb2",
+            BootRomKind::Cgb0 => "cgb0",
+            BootRomKind::Cgb => "cgb",
+            BootRomKind::Agb => "agb",
+        }
+    }
+    // None when the dump belongs to another model
+    pub fn get_revision<M: Model>(self) -> Option<M::Revision> {
+        M::Revision::from_name(self.get_revision_name())
+    }
+}
+
+// SHA-1 of the known dumps
+const KNOWN_BOOT_ROMS: [(BootRomKind, &str); 8] = [
+    (
+        BootRomKind::Dmg0,
+        "8bd501e31921e9601788316dbd3ce9833a97bcbc",
+    ),
+    (BootRomKind::Dmg, "4ed31ec6b0b175bb109c0eb5fd3d193da823339f"),
+    (BootRomKind::Mgb, "4e68f9da03c310e84c523654b9026e51f26ce7f0"),
+    (BootRomKind::Sgb, "aa2f50a77dfb4823da96ba99309085a3c6278515"),
+    (
+        BootRomKind::Sgb2,
+        "93407ea10d2f30ab96a314d8eca44fe160aea734",
+    ),
+    (
+        BootRomKind::Cgb0,
+        "df5a0d2d49de38fbd31cc2aab8e62c8550e655c0",
+    ),
+    (BootRomKind::Cgb, "1293d68bf9643bc4f36954c1e80e38f39864528d"),
+    (BootRomKind::Agb, "fa5287e24b0fa533b3b5ef2b28a81245346c1a0f"),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BootRomError {
+    // 256 bytes for the DMG, MGB and SGB, 2304 for the CGB and AGB
+    InvalidSize {
+        expected: usize,
+        actual: usize,
+    },
+    // a known dump of another revision, its registers would not match the emulated quirks
+    WrongRevision {
+        expected: &'static str,
+        actual: BootRomKind,
+    },
+}
+
+impl fmt::Display for BootRomError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BootRomError::InvalidSize { expected, actual } => write!(
+                f,
+                "The boot ROM is {actual} bytes long, this model expects {expected} bytes"
+            ),
+            BootRomError::WrongRevision { expected, actual } => write!(
+                f,
+                "The boot ROM is the one of the {}, the {} is selected",
+                actual.get_revision_name().to_ascii_uppercase(),
+                expected.to_ascii_uppercase()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BootRomError {}
+
+pub fn find_boot_rom(dump: &[u8]) -> Option<BootRomKind> {
+    let sha1 = sha1_smol::Sha1::from(dump).digest().to_string();
+    KNOWN_BOOT_ROMS
+        .iter()
+        .find(|(_, known_sha1)| *known_sha1 == sha1)
+        .map(|(kind, _)| *kind)
+}
+
+// without a selected revision, any revision of the model is accepted
+fn check_revision<M: Model>(
+    kind: Option<BootRomKind>,
+    revision: Option<M::Revision>,
+) -> Result<(), BootRomError> {
+    let Some(kind) = kind else {
+        return Ok(());
+    };
+    match (kind.get_revision::<M>(), revision) {
+        (Some(found), Some(revision)) if found != revision => Err(BootRomError::WrongRevision {
+            expected: revision.get_name(),
+            actual: kind,
+        }),
+        (None, revision) => Err(BootRomError::WrongRevision {
+            expected: revision.unwrap_or_default().get_name(),
+            actual: kind,
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Checks the size of the dump against the model, and a known dump against the revision. The kind
+/// is `None` for an unknown dump, it can be modified or homebrew.
+pub fn load_boot_rom<M: Model>(
+    dump: &[u8],
+    revision: Option<M::Revision>,
+) -> Result<(BootRom, Option<BootRomKind>), BootRomError> {
+    let expected = M::BOOT_ROM.len();
+    let invalid_size = BootRomError::InvalidSize {
+        expected,
+        actual: dump.len(),
+    };
+    if dump.len() != expected {
+        return Err(invalid_size);
+    }
+    let boot_rom = BootRom::new(dump).ok_or(invalid_size)?;
+    let kind = find_boot_rom(dump);
+    check_revision::<M>(kind, revision)?;
+    Ok((boot_rom, kind))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use gebeh_core::{
+        Cgb, Dmg, Sgb,
+        cpu::BOOTIX_BOOT_ROM,
+        revision::{CgbRevision, DmgRevision, Revision, SgbRevision},
+    };
+
+    use super::{BootRomError, BootRomKind, KNOWN_BOOT_ROMS, check_revision, load_boot_rom};
+
+    #[test]
+    fn known_boot_roms() {
+        let mut hashes = HashSet::new();
+        for (kind, sha1) in KNOWN_BOOT_ROMS {
+            assert!(hashes.insert(sha1), "{kind:?}");
+            assert_eq!(sha1.len(), 40);
+            assert!(sha1.bytes().all(|digit| digit.is_ascii_hexdigit()));
+        }
+    }
+
+    #[test]
+    fn revision_names() {
+        for (kind, _) in KNOWN_BOOT_ROMS {
+            let name = kind.get_revision_name();
+            let found = [
+                DmgRevision::from_name(name).map(Revision::get_name),
+                SgbRevision::from_name(name).map(Revision::get_name),
+                CgbRevision::from_name(name).map(Revision::get_name),
+            ];
+            assert_eq!(found.iter().flatten().count(), 1, "{kind:?}");
+        }
+    }
+
+    #[test]
+    fn load() {
+        let (boot_rom, kind) =
+            load_boot_rom::<Dmg>(&BOOTIX_BOOT_ROM, Some(DmgRevision::Mgb)).unwrap();
+        assert_eq!(&*boot_rom, BOOTIX_BOOT_ROM.as_slice());
+        assert_eq!(kind, None);
+        assert_eq!(
+            load_boot_rom::<Cgb>(&BOOTIX_BOOT_ROM, Some(CgbRevision::Cgb)).err(),
+            Some(BootRomError::InvalidSize {
+                expected: 0x900,
+                actual: 0x100
+            })
+        );
+    }
+
+    #[test]
+    fn wrong_revision() {
+        assert_eq!(
+            check_revision::<Dmg>(Some(BootRomKind::Dmg0), Some(DmgRevision::Mgb)),
+            Err(BootRomError::WrongRevision {
+                expected: "mgb",
+                actual: BootRomKind::Dmg0
+            })
+        );
+        assert!(check_revision::<Dmg>(Some(BootRomKind::Sgb), Some(DmgRevision::Dmg)).is_err());
+        assert!(check_revision::<Sgb>(Some(BootRomKind::Sgb2), Some(SgbRevision::Sgb2)).is_ok());
+        assert!(check_revision::<Cgb>(Some(BootRomKind::Agb), Some(CgbRevision::Agb)).is_ok());
+        assert!(check_revision::<Cgb>(None, Some(CgbRevision::Agb)).is_ok());
+        assert!(check_revision::<Cgb>(Some(BootRomKind::Cgb0), Some(CgbRevision::Cgb)).is_err());
+        // the revision of the dump is used
+        assert!(check_revision::<Dmg>(Some(BootRomKind::Mgb), None).is_ok());
+        assert_eq!(
+            BootRomKind::Cgb0.get_revision::<Cgb>(),
+            Some(CgbRevision::Cgb0)
+        );
+        assert_eq!(
+            check_revision::<Dmg>(Some(BootRomKind::Sgb2), None),
+            Err(BootRomError::WrongRevision {
+                expected: "dmg",
+                actual: BootRomKind::Sgb2
+            })
+        );
+    }
+}
@@ -0,0 +1,76 @@
+use std::sync::Arc;
+
+use gebeh_core::mbc::{ImageSensor, SENSOR_HEIGHT, SENSOR_WIDTH, SensorImage};
+
+/// A covered lens, the picture is black.
+#[derive(Clone, Copy, Default)]
+pub struct NoSensor;
+
+impl ImageSensor for NoSensor {
+    fn capture(&mut self, image: &mut SensorImage) {
+        for row in image {
+            row.fill(0x00);
+        }
+    }
+}
+
+/// Vertical bars of every shade crossed by a checkerboard, for the frontends without a camera.
+#[derive(Clone, Copy, Default)]
+pub struct TestPattern;
+
+impl ImageSensor for TestPattern {
+    fn capture(&mut self, image: &mut SensorImage) {
+        for (y, row) in image.iter_mut().enumerate() {
+            for (x, pixel) in row.iter_mut().enumerate() {
+                *pixel = if (y / 16) % 2 == 0 {
+                    (x * 255 / (SENSOR_WIDTH - 1)) as u8
+                } else if (x / 16 + y / 16) % 2 == 0 {
+                    0xff
+                } else {
+                    0x00
+                };
+            }
+        }
+    }
+}
+
+/// A still picture, stretched to the size of the sensor.
+#[derive(Clone)]
+pub struct StaticImage(Arc<SensorImage>);
+
+impl StaticImage {
+    /// `luminance` has one byte per pixel, row by row.
+    pub fn new(width: usize, height: usize, luminance: &[u8]) -> Self {
+        let mut image = [[0; SENSOR_WIDTH]; SENSOR_HEIGHT];
+        if width != 0 && height != 0 {
+            for (y, row) in image.iter_mut().enumerate() {
+                for (x, pixel) in row.iter_mut().enumerate() {
+                    let source = (y * height / SENSOR_HEIGHT) * width + x * width / SENSOR_WIDTH;
+                    *pixel = luminance.get(source).copied().unwrap_or(0);
+                }
+            }
+        }
+        Self(Arc::new(image))
+    }
+
+    /// Converts red, green, blue and alpha bytes with the Rec. 601 luma weights.
+    pub fn from_rgba(width: usize, height: usize, rgba: &[u8]) -> Self {
+        let luminance: Vec<u8> = rgba
+            .as_chunks::<4>()
+            .0
+            .iter()
+            .map(|[r, g, b, _]| get_luma(*r, *g, *b))
+            .collect();
+        Self::new(width, height, &luminance)
+    }
+}
+
+impl ImageSensor for StaticImage {
+    fn capture(&mut self, image: &mut SensorImage) {
+        *image = *self.0;
+    }
+}
+
+pub fn get_luma(r: u8, g: u8, b: u8) -> u8 {
+    ((299 * u32::from(r) + 587 * u32::from(g) + 114 * u32::from(b)) / 1000) as u8
+}
@@ -0,0 +1,367 @@
+// Game Genie codes patch the ROM reads, GameShark codes write into the RAM once per frame.
+// https://gbdev.io/pandocs/Shark_Cheats.html
+
+use std::fmt;
+
+use gebeh_core::{
+    Emulator, Model,
+    addresses::{ECHO_RAM, VIDEO_RAM, WRAM_BANK},
+    joypad::Tilt,
+    mbc::Mbc,
+    wram::Wram,
+};
+
+// D000-DFFF, the switchable bank of the work RAM
+const WRAM_SWITCHABLE_BANK: u16 = 0xd000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CheatError {
+    // neither ABC-DEF, ABC-DEF-GHI nor ABCDEFGH
+    InvalidFormat,
+    InvalidDigit(char),
+    // a Game Genie code can only patch the ROM
+    NotInRom(u16),
+}
+
+impl fmt::Display for CheatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheatError::InvalidFormat => write!(
+                f,
+                "Unknown code format, Game Genie (ABC-DEF or ABC-DEF-GHI) or GameShark (ABCDEFGH) expected"
+            ),
+            CheatError::InvalidDigit(digit) => write!(f, "{digit:?} is not an hexadecimal digit"),
+            CheatError::NotInRom(address) => {
+                write!(
+                    f,
+                    "The Game Genie code targets 0x{address:04x}, outside of the ROM"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for CheatError {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GameGenieCode {
+    pub address: u16,
+    pub value: u8,
+    // the substitution happens only if the ROM has this byte, to target a single bank
+    pub compare: Option<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GameSharkCode {
+    // 0x9X selects the work RAM bank X on CGB, the others write to the current bank
+    pub bank: u8,
+    pub value: u8,
+    pub address: u16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CheatCode {
+    GameGenie(GameGenieCode),
+    GameShark(GameSharkCode),
+}
+
+fn parse_digits(code: &str) -> Result<Vec<u8>, CheatError> {
+    code.chars()
+        .map(|digit| {
+            digit
+                .to_digit(16)
+                .map(|digit| digit as u8)
+                .ok_or(CheatError::InvalidDigit(digit))
+        })
+        .collect()
+}
+
+impl CheatCode {
+    pub fn parse(code: &str) -> Result<Self, CheatError> {
+        let code = code.trim();
+        let groups: Vec<&str> = code.split('-').collect();
+        match groups.as_slice() {
+            [game_shark] if game_shark.len() == 8 => {
+                let digits = parse_digits(game_shark)?;
+                let byte = |index: usize| digits[index] << 4 | digits[index + 1];
+                Ok(CheatCode::GameShark(GameSharkCode {
+                    bank: byte(0),
+                    value: byte(2),
+                    // little endian
+                    address: u16::from_le_bytes([byte(4), byte(6)]),
+                }))
+            }
+            [first, second, rest @ ..]
+                if first.len() == 3
+                    && second.len() == 3
+                    && rest.iter().all(|third| third.len() == 3)
+                    && rest.len() <= 1 =>
+            {
+                let digits = parse_digits(&groups.concat())?;
+                // https://web.archive.org/web/2019/http://www.devrs.com/gb/files/gg.txt
+                let address = u16::from(digits[5] ^ 0xf) << 12
+                    | u16::from(digits[2]) << 8
+                    | u16::from(digits[3]) << 4
+                    | u16::from(digits[4]);
+                if address >= VIDEO_RAM {
+                    return Err(CheatError::NotInRom(address));
+                }
+                Ok(CheatCode::GameGenie(GameGenieCode {
+                    address,
+                    value: digits[0] << 4 | digits[1],
+                    // the 8th digit is not used
+                    compare: (digits.len() == 9)
+                        .then(|| (digits[6] << 4 | digits[8]).rotate_right(2) ^ 0xba),
+                }))
+            }
+            _ => Err(CheatError::InvalidFormat),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cheat {
+    pub code: CheatCode,
+    pub is_enabled: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Cheats {
+    cheats: Vec<Cheat>,
+}
+
+impl Cheats {
+    /// Returns the index of the new cheat, it is enabled.
+    pub fn add(&mut self, code: &str) -> Result<usize, CheatError> {
+        self.cheats.push(Cheat {
+            code: CheatCode::parse(code)?,
+            is_enabled: true,
+        });
+        Ok(self.cheats.len() - 1)
+    }
+
+    // does nothing if the index is out of bounds
+    pub fn set_enabled(&mut self, index: usize, is_enabled: bool) {
+        if let Some(cheat) = self.cheats.get_mut(index) {
+            cheat.is_enabled = is_enabled;
+        }
+    }
+
+    // the indices of the next cheats are shifted
+    pub fn remove(&mut self, index: usize) {
+        if index < self.cheats.len() {
+            self.cheats.remove(index);
+        }
+    }
+
+    pub fn get_cheats(&self) -> &[Cheat] {
+        &self.cheats
+    }
+
+    fn enabled_codes(&self) -> impl Iterator<Item = &CheatCode> {
+        self.cheats
+            .iter()
+            .filter(|cheat| cheat.is_enabled)
+            .map(|cheat| &cheat.code)
+    }
+
+    /// The returned MBC sees its ROM reads substituted by the Game Genie codes.
+    pub fn wrap<'a, M: Mbc + ?Sized>(&'a self, mbc: &'a mut M) -> CheatMbc<'a, M> {
+        CheatMbc { cheats: self, mbc }
+    }
+
+    /// Applies the GameShark codes, the frontends should call it once per frame.
+    pub fn apply_game_shark<Mo: Model>(
+        &self,
+        emulator: &mut Emulator<Mo>,
+        mbc: &mut (impl Mbc + ?Sized),
+    ) {
+        for code in self.enabled_codes() {
+            let CheatCode::GameShark(code) = code else {
+                continue;
+            };
+            let is_banked = code.bank & 0xf0 == 0x90
+                && (WRAM_SWITCHABLE_BANK..ECHO_RAM).contains(&code.address);
+            if !is_banked {
+                emulator.write_memory(code.address, code.value, mbc);
+                continue;
+            }
+            // ignored by the DMG
+            let previous_bank = emulator.get_wram().read_bank();
+            emulator.write_memory(WRAM_BANK, code.bank & 0x0f, mbc);
+            emulator.write_memory(code.address, code.value, mbc);
+            emulator.write_memory(WRAM_BANK, previous_bank, mbc);
+        }
+    }
+}
+
+pub struct CheatMbc<'a, M: Mbc + ?Sized> {
+    cheats: &'a Cheats,
+    mbc: &'a mut M,
+}
+
+impl<M: Mbc + ?Sized> CheatMbc<'_, M> {
+    fn apply_game_genie(&self, address: u16, value: u8) -> u8 {
+        if address >= VIDEO_RAM {
+            return value;
+        }
+        self.cheats
+            .enabled_codes()
+            .find_map(|code| match code {
+                CheatCode::GameGenie(code)
+                    if code.address == address
+                        && code.compare.is_none_or(|compare| compare == value) =>
+                {
+                    Some(code.value)
+                }
+                _ => None,
+            })
+            .unwrap_or(value)
+    }
+}
+
+impl<M: Mbc + ?Sized> Mbc for CheatMbc<'_, M> {
+    fn get_rom(&self) -> &[u8] {
+        self.mbc.get_rom()
+    }
+    fn load_saved_ram(&mut self, save: &[u8]) {
+        self.mbc.load_saved_ram(save)
+    }
+    fn load_additional_data(&mut self, additional_data: &[u8]) {
+        self.mbc.load_additional_data(additional_data)
+    }
+    fn read(&self, address: u16) -> u8 {
+        self.apply_game_genie(address, self.mbc.read(address))
+    }
+    fn peek(&self, address: u16) -> u8 {
+        self.apply_game_genie(address, self.mbc.peek(address))
+    }
+    fn write(&mut self, address: u16, value: u8) {
+        self.mbc.write(address, value)
+    }
+    fn get_ram_to_save(&self) -> Option<&[u8]> {
+        self.mbc.get_ram_to_save()
+    }
+    fn get_volatile_ram_size(&self) -> usize {
+        self.mbc.get_volatile_ram_size()
+    }
+    fn get_additional_data_to_save(&self, buffer: &mut [u8]) -> usize {
+        self.mbc.get_additional_data_to_save(buffer)
+    }
+    fn load_saved_registers(&mut self, registers: &[u8]) {
+        self.mbc.load_saved_registers(registers)
+    }
+    fn get_registers_to_save(&self, buffer: &mut [u8]) -> usize {
+        self.mbc.get_registers_to_save(buffer)
+    }
+    fn take_rumble_intensity(&mut self) -> Option<f32> {
+        self.mbc.take_rumble_intensity()
+    }
+    fn set_tilt(&mut self, tilt: Tilt) {
+        self.mbc.set_tilt(tilt)
+    }
+    fn get_tone_frequency(&self) -> Option<f32> {
+        self.mbc.get_tone_frequency()
+    }
+    fn is_infrared_led_on(&self) -> Option<bool> {
+        self.mbc.is_infrared_led_on()
+    }
+    fn set_infrared_light_received(&mut self, is_received: bool) {
+        self.mbc.set_infrared_light_received(is_received)
+    }
+    fn execute(&mut self) {
+        self.mbc.execute()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gebeh_core::{
+        Cgb, Emulator, Ram,
+        mbc::{Mbc, Mbc7},
+    };
+
+    use super::{CheatCode, CheatError, Cheats, GameGenieCode, GameSharkCode};
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            CheatCode::parse("00A-17B-C49"),
+            Ok(CheatCode::GameGenie(GameGenieCode {
+                address: 0x4a17,
+                value: 0x00,
+                compare: Some(0xc8),
+            }))
+        );
+        assert_eq!(
+            CheatCode::parse("3ED-58F"),
+            Ok(CheatCode::GameGenie(GameGenieCode {
+                address: 0x0d58,
+                value: 0x3e,
+                compare: None,
+            }))
+        );
+        assert_eq!(
+            CheatCode::parse("010238cd"),
+            Ok(CheatCode::GameShark(GameSharkCode {
+                bank: 0x01,
+                value: 0x02,
+                address: 0xcd38,
+            }))
+        );
+        assert_eq!(
+            CheatCode::parse("3ED-587"),
+            Err(CheatError::NotInRom(0x8d58))
+        );
+        assert_eq!(
+            CheatCode::parse("3ED-58G"),
+            Err(CheatError::InvalidDigit('G'))
+        );
+        assert_eq!(CheatCode::parse("3ED58F"), Err(CheatError::InvalidFormat));
+        assert_eq!(CheatCode::parse("3ED-58F-"), Err(CheatError::InvalidFormat));
+    }
+
+    #[test]
+    fn game_genie() {
+        let mut rom = vec![0; 0x8000].into_boxed_slice();
+        rom[0x0d58] = 0x12;
+        rom[0x4a17] = 0x34;
+        let mut cheats = Cheats::default();
+        cheats.add("3ED-58F").unwrap();
+        // the compare byte doesn't match
+        cheats.add("00A-17B-C49").unwrap();
+        let mbc = cheats.wrap(&mut rom);
+        assert_eq!(mbc.read(0x0d58), 0x3e);
+        assert_eq!(mbc.read(0x4a17), 0x34);
+
+        cheats.set_enabled(0, false);
+        assert_eq!(cheats.wrap(&mut rom).read(0x0d58), 0x12);
+
+        rom[0x4a17] = 0xc8;
+        assert_eq!(cheats.wrap(&mut rom).read(0x4a17), 0x00);
+    }
+
+    #[test]
+    fn game_shark() {
+        let mut rom = vec![0; 0x8000].into_boxed_slice();
+        let mut emulator = Emulator::<Cgb>::default();
+        let mut cheats = Cheats::default();
+        cheats.add("0142c0c0").unwrap();
+        // bank 3 of the work RAM
+        cheats.add("9356d0d0").unwrap();
+        cheats.apply_game_shark(&mut emulator, &mut rom);
+        assert_eq!(emulator.get_wram().read(0x00c0), 0x42);
+        // the bank 1 is still selected
+        assert_eq!(emulator.get_wram().read(0x10d0), 0x00);
+        emulator.write_memory(0xff70, 3, &mut rom);
+        assert_eq!(emulator.get_wram().read(0x10d0), 0x56);
+    }
+
+    #[test]
+    fn forwarded_volatile_ram_size() {
+        let rom = vec![0; 0x8000];
+        let mut mbc = Mbc7::new(rom.as_slice());
+        let cheats = Cheats::default();
+        assert_eq!(cheats.wrap(&mut mbc).get_volatile_ram_size(), 0);
+    }
+}
@@ -0,0 +1,357 @@
+// Everything the frontends choose before the first instruction, from the model to the content of
+// the memory, so they share the same setup.
+
+use std::{fmt, ops::Deref};
+
+use gebeh_core::{
+    Emulator, Model,
+    mbc::{CartridgeType, ImageSensor, Mbc, Rtc},
+    power_on::PowerOnMemory,
+    revision::Revision,
+};
+
+use crate::{
+    BootRomError, CloneMbc, Compatibility, PreferredModel, RomEntry, RomHeaderError,
+    fill_cartridge_ram, find_rom, get_compatibility, get_header_rom, get_mbc_send_with_sensor,
+    get_mbc_with_sensor, is_sgb_supported, load_boot_rom,
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ModelPolicy {
+    #[default]
+    CgbWhenExplicit,
+    DmgWhenPossible,
+    AlwaysCgb,
+    // the Super Game Boy is used by the DMG games supporting it
+    SgbWhenPossible,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectedModel {
+    Dmg,
+    Sgb,
+    Cgb,
+}
+
+impl ModelPolicy {
+    /// The entry of the database, if any, replaces the guess made from the header with the default
+    /// policy. The other policies are chosen by the user and win.
+    pub fn select(self, rom: &[u8], entry: Option<&RomEntry>) -> SelectedModel {
+        let header = get_header_rom(rom);
+        let compatibility = get_compatibility(&header);
+        if self == ModelPolicy::CgbWhenExplicit {
+            match (entry.and_then(|entry| entry.model), compatibility) {
+                (Some(PreferredModel::Dmg), Compatibility::Dmg | Compatibility::Both) => {
+                    return SelectedModel::Dmg;
+                }
+                (Some(PreferredModel::Sgb), Compatibility::Dmg | Compatibility::Both) => {
+                    return SelectedModel::Sgb;
+                }
+                (Some(PreferredModel::Cgb), Compatibility::Cgb | Compatibility::Both) => {
+                    return SelectedModel::Cgb;
+                }
+                _ => {}
+            }
+        }
+        match (compatibility, self) {
+            (Compatibility::Dmg | Compatibility::Both, ModelPolicy::SgbWhenPossible)
+            | (Compatibility::Dmg, ModelPolicy::CgbWhenExplicit)
+                if is_sgb_supported(&header) =>
+            {
+                SelectedModel::Sgb
+            }
+            (
+                Compatibility::Dmg,
+                ModelPolicy::CgbWhenExplicit
+                | ModelPolicy::DmgWhenPossible
+                | ModelPolicy::SgbWhenPossible,
+            )
+            | (Compatibility::Both, ModelPolicy::DmgWhenPossible | ModelPolicy::SgbWhenPossible) => {
+                SelectedModel::Dmg
+            }
+            (Compatibility::Cgb, _)
+            | (_, ModelPolicy::AlwaysCgb)
+            | (Compatibility::Both, ModelPolicy::CgbWhenExplicit) => SelectedModel::Cgb,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigError {
+    RomHeader(RomHeaderError),
+    BootRom(BootRomError),
+    UnknownRevision { name: String, model: &'static str },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::RomHeader(err) => err.fmt(f),
+            ConfigError::BootRom(err) => err.fmt(f),
+            ConfigError::UnknownRevision { name, model } => {
+                write!(f, "{name} is not a revision of the {model}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<RomHeaderError> for ConfigError {
+    fn from(err: RomHeaderError) -> Self {
+        ConfigError::RomHeader(err)
+    }
+}
+
+impl From<BootRomError> for ConfigError {
+    fn from(err: BootRomError) -> Self {
+        ConfigError::BootRom(err)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct EmulatorConfig<R = ()> {
+    pub policy: ModelPolicy,
+    // like "mgb", the default revision of the selected model otherwise
+    pub revision: Option<String>,
+    // a dump given by the user, its size must match the selected model
+    pub boot_rom: Option<Box<[u8]>>,
+    // starts the cartridge directly without the logo, the boot ROM is ignored
+    pub skip_boot_rom: bool,
+    pub memory: PowerOnMemory,
+    // the clock of the cartridges with an RTC, each MBC gets its own copy
+    pub rtc: R,
+}
+
+impl<R> EmulatorConfig<R> {
+    /// The same choices with the clock of the frontend, which can't be built here.
+    pub fn with_rtc<U>(self, rtc: U) -> EmulatorConfig<U> {
+        let EmulatorConfig {
+            policy,
+            revision,
+            boot_rom,
+            skip_boot_rom,
+            memory,
+            rtc: _,
+        } = self;
+        EmulatorConfig {
+            policy,
+            revision,
+            boot_rom,
+            skip_boot_rom,
+            memory,
+            rtc,
+        }
+    }
+
+    /// The model given by the policy, looking the ROM up in the database.
+    pub fn select_model(&self, rom: &[u8]) -> SelectedModel {
+        self.policy.select(rom, find_rom(rom))
+    }
+
+    fn get_revision<M: Model>(&self) -> Result<Option<M::Revision>, ConfigError> {
+        let Some(name) = &self.revision else {
+            return Ok(None);
+        };
+        M::Revision::from_name(name)
+            .map(Some)
+            .ok_or_else(|| ConfigError::UnknownRevision {
+                name: name.clone(),
+                model: M::NAME,
+            })
+    }
+
+    /// The emulator at power-on, for an MBC built by the frontend.
+    pub fn get_emulator<M: Model>(
+        &self,
+        mbc: &mut (impl Mbc + ?Sized),
+    ) -> Result<Emulator<M>, ConfigError> {
+        let revision = self.get_revision::<M>()?;
+        let boot_rom = match &self.boot_rom {
+            Some(boot_rom) if !self.skip_boot_rom => Some(load_boot_rom::<M>(boot_rom, revision)?),
+            _ => None,
+        };
+        // without a selected revision, the one of a known dump or the default one
+        let revision = revision
+            .or_else(|| boot_rom.as_ref()?.1?.get_revision::<M>())
+            .unwrap_or_default();
+        let mut emulator = Emulator::<M>::with_revision(revision);
+        emulator.fill_memory(&self.memory);
+        fill_cartridge_ram(mbc, &self.memory);
+        if self.skip_boot_rom {
+            M::skip_boot_rom(&mut emulator, mbc);
+        } else if let Some((boot_rom, _)) = boot_rom {
+            emulator.set_boot_rom(boot_rom);
+        }
+        Ok(emulator)
+    }
+}
+
+impl<R: Rtc + Clone> EmulatorConfig<R> {
+    /// A ready to run emulator and MBC, the save has to be loaded afterwards.
+    #[allow(clippy::type_complexity)]
+    pub fn build<'a, M: Model, T: Deref<Target = [u8]> + Clone + 'a, S: ImageSensor + Clone + 'a>(
+        &self,
+        rom: T,
+        sensor: S,
+    ) -> Result<(CartridgeType, Emulator<M>, Box<dyn CloneMbc<'a> + 'a>), ConfigError>
+    where
+        R: 'a,
+    {
+        let (cartridge_type, mut mbc) = get_mbc_with_sensor(rom, self.rtc.clone(), sensor)?;
+        let emulator = self.get_emulator(mbc.as_mut())?;
+        Ok((cartridge_type, emulator, mbc))
+    }
+
+    #[allow(clippy::type_complexity)]
+    pub fn build_send<
+        'a,
+        M: Model,
+        T: Deref<Target = [u8]> + Clone + Send + 'a,
+        S: ImageSensor + Send + Clone + 'a,
+    >(
+        &self,
+        rom: T,
+        sensor: S,
+    ) -> Result<
+        (
+            CartridgeType,
+            Emulator<M>,
+            Box<dyn CloneMbc<'a> + Send + 'a>,
+        ),
+        ConfigError,
+    >
+    where
+        R: Send + 'a,
+    {
+        let (cartridge_type, mut mbc) = get_mbc_send_with_sensor(rom, self.rtc.clone(), sensor)?;
+        let emulator = self.get_emulator(mbc.as_mut())?;
+        Ok((cartridge_type, emulator, mbc))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gebeh_core::{
+        Cgb, Dmg, Ram, Sgb,
+        cpu::BOOTIX_BOOT_ROM,
+        mbc::{MBC6_RAM_SIZE, MBC6_SAVE_SIZE, Mbc, Mbc1, Mbc6, Mbc7},
+        power_on::{MemoryFill, PowerOnMemory},
+        revision::SgbRevision,
+    };
+
+    use crate::{
+        BootRomError, ConfigError, EmulatorConfig, ModelPolicy, PreferredModel, RomEntry,
+        SelectedModel,
+    };
+
+    fn get_rom(cgb_flag: u8, sgb_flag: u8) -> Vec<u8> {
+        let mut rom = vec![0; 0x8000];
+        rom[0x143] = cgb_flag;
+        rom[0x146] = sgb_flag;
+        // MBC1+RAM+BATTERY with 8 KiB of RAM
+        rom[0x147] = 0x03;
+        rom[0x149] = 0x02;
+        rom
+    }
+
+    #[test]
+    fn select() {
+        let dmg = get_rom(0x00, 0x00);
+        let sgb = get_rom(0x00, 0x03);
+        let both = get_rom(0x80, 0x03);
+        let cgb = get_rom(0xc0, 0x00);
+        for (policy, rom, model) in [
+            (ModelPolicy::CgbWhenExplicit, &dmg, SelectedModel::Dmg),
+            (ModelPolicy::CgbWhenExplicit, &sgb, SelectedModel::Sgb),
+            (ModelPolicy::CgbWhenExplicit, &both, SelectedModel::Cgb),
+            (ModelPolicy::DmgWhenPossible, &sgb, SelectedModel::Dmg),
+            (ModelPolicy::DmgWhenPossible, &cgb, SelectedModel::Cgb),
+            (ModelPolicy::SgbWhenPossible, &both, SelectedModel::Sgb),
+            (ModelPolicy::SgbWhenPossible, &dmg, SelectedModel::Dmg),
+            (ModelPolicy::AlwaysCgb, &sgb, SelectedModel::Cgb),
+        ] {
+            assert_eq!(policy.select(rom, None), model, "{policy:?}");
+        }
+
+        let entry = RomEntry {
+            model: Some(PreferredModel::Sgb),
+            ..RomEntry::new("", 0, "")
+        };
+        for (policy, rom, model) in [
+            (ModelPolicy::CgbWhenExplicit, &both, SelectedModel::Sgb),
+            (ModelPolicy::CgbWhenExplicit, &dmg, SelectedModel::Sgb),
+            // the preferred model can't run the game
+            (ModelPolicy::CgbWhenExplicit, &cgb, SelectedModel::Cgb),
+            (ModelPolicy::AlwaysCgb, &both, SelectedModel::Cgb),
+            (ModelPolicy::DmgWhenPossible, &both, SelectedModel::Dmg),
+        ] {
+            assert_eq!(policy.select(rom, Some(&entry)), model, "{policy:?}");
+        }
+    }
+
+    #[test]
+    fn power_on() {
+        let rom = get_rom(0x00, 0x03);
+        let mut mbc = Mbc1::new(rom.as_slice());
+        let config: EmulatorConfig = EmulatorConfig {
+            revision: Some("sgb2".to_owned()),
+            skip_boot_rom: true,
+            memory: PowerOnMemory::new(MemoryFill::Ones, 0),
+            ..Default::default()
+        };
+        let emulator = config.get_emulator::<Sgb>(&mut mbc).unwrap();
+        assert_eq!(emulator.get_revision(), SgbRevision::Sgb2);
+        assert_eq!(emulator.get_cpu().pc, 0x100);
+        assert_eq!(emulator.get_wram().read(0), 0xff);
+        assert_eq!(mbc.get_ram_to_save().unwrap()[0], 0xff);
+
+        let config: EmulatorConfig = EmulatorConfig {
+            revision: Some("sgb2".to_owned()),
+            ..Default::default()
+        };
+        assert_eq!(
+            config.get_emulator::<Dmg>(&mut mbc).err(),
+            Some(ConfigError::UnknownRevision {
+                name: "sgb2".to_owned(),
+                model: "DMG"
+            })
+        );
+
+        let config: EmulatorConfig = EmulatorConfig {
+            boot_rom: Some(Box::new(BOOTIX_BOOT_ROM)),
+            ..Default::default()
+        };
+        assert!(config.get_emulator::<Dmg>(&mut mbc).is_ok());
+        assert_eq!(
+            config.get_emulator::<Cgb>(&mut mbc).err(),
+            Some(ConfigError::BootRom(BootRomError::InvalidSize {
+                expected: 0x900,
+                actual: 0x100
+            }))
+        );
+    }
+
+    #[test]
+    fn erased_non_volatile_memory() {
+        let rom = get_rom(0x00, 0x00);
+        let config: EmulatorConfig = EmulatorConfig {
+            skip_boot_rom: true,
+            ..Default::default()
+        };
+        let mut mbc = Mbc7::new(rom.as_slice());
+        config.get_emulator::<Dmg>(&mut mbc).unwrap();
+        assert!(
+            mbc.get_ram_to_save()
+                .unwrap()
+                .iter()
+                .all(|byte| *byte == 0xff)
+        );
+
+        let mut mbc = Mbc6::new(rom.as_slice(), vec![0xff; MBC6_SAVE_SIZE]);
+        config.get_emulator::<Dmg>(&mut mbc).unwrap();
+        let (ram, flash) = mbc.get_ram_to_save().unwrap().split_at(MBC6_RAM_SIZE);
+        assert!(ram.iter().all(|byte| *byte == 0));
+        assert!(flash.iter().all(|byte| *byte == 0xff));
+    }
+}
@@ -0,0 +1,309 @@
+// The known dumps are recognized by the CRC32 of the whole ROM, and by their SHA-1 when it is known
+// because the CRC32 can collide. Some cartridges can't be emulated from their header alone, and
+// the header can't tell which model a game looks best on.
+
+use std::ops::Deref;
+
+use gebeh_core::mbc::{CartridgeType, Mbc3};
+
+use crate::get_title_from_rom;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PreferredModel {
+    Dmg,
+    Cgb,
+    Sgb,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Quirk {
+    // the games are in 256 KiB slices, even if the logo heuristic misses them
+    Mbc1Multicart,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RomKey {
+    // the SHA-1 is in hexadecimal
+    Hash {
+        crc32: u32,
+        sha1: Option<&'static str>,
+    },
+    // the title of the header, for the cartridges without a known dump
+    Title(&'static str),
+}
+
+#[derive(Debug, Clone)]
+pub struct RomEntry {
+    // the No-Intro name, or the name on the cartridge without a known dump
+    pub name: &'static str,
+    pub key: RomKey,
+    // None when the header is right
+    pub cartridge_type: Option<CartridgeType>,
+    // in bytes
+    pub ram_size: Option<usize>,
+    // overrides the model policy of the frontend, None when the header is enough
+    pub model: Option<PreferredModel>,
+    pub has_rtc: Option<bool>,
+    pub quirks: &'static [Quirk],
+}
+
+impl RomEntry {
+    pub(crate) const fn new(name: &'static str, crc32: u32, sha1: &'static str) -> Self {
+        Self::with_key(
+            name,
+            RomKey::Hash {
+                crc32,
+                sha1: Some(sha1),
+            },
+        )
+    }
+
+    const fn with_key(name: &'static str, key: RomKey) -> Self {
+        Self {
+            name,
+            key,
+            cartridge_type: None,
+            ram_size: None,
+            model: None,
+            has_rtc: None,
+            quirks: &[],
+        }
+    }
+
+    const fn model(self, model: PreferredModel) -> Self {
+        Self {
+            model: Some(model),
+            ..self
+        }
+    }
+
+    const fn cartridge_type(self, cartridge_type: CartridgeType) -> Self {
+        Self {
+            cartridge_type: Some(cartridge_type),
+            ..self
+        }
+    }
+
+    pub fn has_quirk(&self, quirk: Quirk) -> bool {
+        self.quirks.contains(&quirk)
+    }
+
+    pub(crate) fn apply_to_mbc3<T: Deref<Target = [u8]>, U>(&self, mbc: &mut Mbc3<T, U>) {
+        if let Some(has_rtc) = self.has_rtc {
+            mbc.set_has_rtc(has_rtc);
+        }
+        if let Some(ram_size) = self.ram_size {
+            mbc.set_ram_size(ram_size);
+        }
+    }
+}
+
+// only the entries with an override change the emulation, the others name the dump
+const ROMS: &[RomEntry] = &[
+    RomEntry::new(
+        "Pokemon - Red Version (USA, Europe) (SGB Enhanced)",
+        0x9f7fdd53,
+        "ea9bcae617fdf159b045185467ae58b2e4a48b9a",
+    ),
+    RomEntry::new(
+        "Pokemon - Blue Version (USA, Europe) (SGB Enhanced)",
+        0xd6da8a1a,
+        "d7037c83e1ae5b39bde3c30787637ba1d4c48ce2",
+    ),
+    // the borders and palettes of the SGB look better than the colors of the CGB
+    RomEntry::new(
+        "Pokemon - Yellow Version - Special Pikachu Edition (USA, Europe) (CGB+SGB Enhanced)",
+        0x7d527d62,
+        "cc7d03262ebfaf2f06772c1a480c7d9d5f4a38e1",
+    )
+    .model(PreferredModel::Sgb),
+    RomEntry::new(
+        "Pokemon - Gold Version (USA, Europe) (SGB Enhanced) (GB Compatible)",
+        0x6bde3c3e,
+        "d8b8a3600a465308c9953dfa04f0081c05bdcb94",
+    ),
+    RomEntry::new(
+        "Pokemon - Silver Version (USA, Europe) (SGB Enhanced) (GB Compatible)",
+        0x8ad48636,
+        "49b163f7e57702bc939d642a18f591de55d92dae",
+    ),
+    RomEntry::new(
+        "Pokemon - Crystal Version (USA, Europe)",
+        0xee6f5188,
+        "f4cd194bdee0d04ca4eac29e09b8e4e9d818c133",
+    ),
+    // https://gbdev.io/pandocs/M161.html
+    // Citation: This mapper is only known to be used in a single cartridge: Mani’s 4 in 1 cartridge
+    // the header says MBC3+TIMER+RAM+BATTERY
+    RomEntry::with_key("Mani 4 in 1", RomKey::Title("TETRIS SET"))
+        .cartridge_type(CartridgeType::M161),
+];
+
+pub fn find_rom(rom: &[u8]) -> Option<&'static RomEntry> {
+    find_rom_in(ROMS, rom)
+}
+
+fn find_rom_in<'a>(roms: &'a [RomEntry], rom: &[u8]) -> Option<&'a RomEntry> {
+    let title = get_title_from_rom(rom);
+    if let Some(entry) = roms
+        .iter()
+        .find(|entry| matches!(entry.key, RomKey::Title(entry_title) if entry_title == title))
+    {
+        return Some(entry);
+    }
+    let crc32 = crc32fast::hash(rom);
+    let mut candidates = roms
+        .iter()
+        .filter(|entry| matches!(entry.key, RomKey::Hash { crc32: entry_crc32, .. } if entry_crc32 == crc32))
+        .peekable();
+    candidates.peek()?;
+    let sha1 = sha1_smol::Sha1::from(rom).digest().to_string();
+    candidates.find(|entry| {
+        matches!(entry.key, RomKey::Hash { sha1: entry_sha1, .. } if entry_sha1.is_none_or(|entry_sha1| entry_sha1 == sha1))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use gebeh_core::mbc::{CARTRIDGE_TYPE_HEADER, CartridgeType, Rtc, RtcRegisters};
+
+    use super::{Quirk, ROMS, RomEntry, RomKey, find_rom, find_rom_in};
+    use crate::{NoSensor, get_cartridge_type, get_mbc_with_entry};
+
+    #[derive(Clone)]
+    struct FrozenRtc;
+
+    impl Rtc for FrozenRtc {
+        fn get_clock_data(&mut self) -> RtcRegisters {
+            RtcRegisters::default()
+        }
+        fn set_clock_data(&mut self, _: RtcRegisters) {}
+        fn deserialize(&mut self, _: &[u8]) {}
+        fn serialize(&self, _: &mut [u8]) -> usize {
+            0
+        }
+    }
+
+    // the first byte of each bank is its number
+    fn get_rom(size: usize, cartridge_type: u8, ram_size: u8) -> Vec<u8> {
+        let mut rom = vec![0; size];
+        for (bank, chunk) in (0..).zip(rom.chunks_exact_mut(0x4000)) {
+            chunk[0] = bank;
+        }
+        rom[CARTRIDGE_TYPE_HEADER] = cartridge_type;
+        rom[0x148] = (size / 0x8000).trailing_zeros() as u8;
+        rom[0x149] = ram_size;
+        rom
+    }
+
+    // keyed by the hashes of the ROMs above
+    const TEST_ROMS: &[RomEntry] = &[
+        RomEntry {
+            cartridge_type: Some(CartridgeType::Mbc5),
+            ..RomEntry::new(
+                "ROM only",
+                0xcf4d2066,
+                "6c7783cb1cb888cc2a96f96afde0d7782c410f85",
+            )
+        },
+        // same CRC32, another SHA-1
+        RomEntry {
+            has_rtc: Some(false),
+            ..RomEntry::new(
+                "MBC3 collision",
+                0x2a9e4074,
+                "0000000000000000000000000000000000000000",
+            )
+        },
+        RomEntry {
+            ram_size: Some(0x2000),
+            has_rtc: Some(true),
+            ..RomEntry::new(
+                "MBC3",
+                0x2a9e4074,
+                "51ccd5e61d3dc0d8e19ea75b67823a5c22b0e5d0",
+            )
+        },
+        RomEntry {
+            quirks: &[Quirk::Mbc1Multicart],
+            ..RomEntry::new(
+                "MBC1",
+                0xd19c5cce,
+                "2c465698752a3ab4a52814b65b29d70248257b27",
+            )
+        },
+    ];
+
+    #[test]
+    fn database() {
+        let mut keys = HashSet::new();
+        for entry in ROMS {
+            assert!(keys.insert(format!("{:?}", entry.key)), "{}", entry.name);
+            if let RomKey::Hash {
+                sha1: Some(sha1), ..
+            } = entry.key
+            {
+                assert_eq!(sha1.len(), 40);
+                assert!(
+                    sha1.bytes()
+                        .all(|byte| matches!(byte, b'0'..=b'9' | b'a'..=b'f'))
+                );
+            }
+        }
+        assert!(find_rom(&[0; 0x8000]).is_none());
+
+        let mut rom = get_rom(0x8000, 0x10, 0x03);
+        rom[0x134..0x13e].copy_from_slice(b"TETRIS SET");
+        assert_eq!(get_cartridge_type(&rom), Ok(CartridgeType::M161));
+    }
+
+    #[test]
+    fn cartridge_type_override() {
+        let rom = get_rom(0x10000, 0x00, 0x00);
+        let entry = find_rom_in(TEST_ROMS, &rom);
+        assert_eq!(entry.map(|entry| entry.name), Some("ROM only"));
+        let (cartridge_type, mut mbc) =
+            get_mbc_with_entry(rom.as_slice(), entry, FrozenRtc, NoSensor).unwrap();
+        assert_eq!(cartridge_type, CartridgeType::Mbc5);
+        mbc.write(0x2000, 3);
+        assert_eq!(mbc.read(0x4000), 3);
+    }
+
+    #[test]
+    fn mbc3_overrides() {
+        // MBC3 without RAM
+        let rom = get_rom(0x8000, 0x11, 0x00);
+        let entry = find_rom_in(TEST_ROMS, &rom);
+        assert_eq!(entry.map(|entry| entry.name), Some("MBC3"));
+        for (entry, ram, rtc) in [(None, 0xff, 0xff), (entry, 0x42, 0)] {
+            let (_, mut mbc) =
+                get_mbc_with_entry(rom.as_slice(), entry, FrozenRtc, NoSensor).unwrap();
+            mbc.write(0x0000, 0x0a);
+            mbc.write(0xa000, 0x42);
+            assert_eq!(mbc.read(0xa000), ram);
+            // latch the seconds
+            mbc.write(0x4000, 0x08);
+            mbc.write(0x6000, 0x00);
+            mbc.write(0x6000, 0x01);
+            assert_eq!(mbc.read(0xa000), rtc);
+        }
+    }
+
+    #[test]
+    fn multicart_quirk() {
+        // 1 MiB of MBC1 without the logos of a multicart
+        let rom = get_rom(0x100000, 0x01, 0x00);
+        let entry = find_rom_in(TEST_ROMS, &rom);
+        assert_eq!(entry.map(|entry| entry.name), Some("MBC1"));
+        // the upper bits of the bank are shifted by 4 instead of 5
+        for (entry, bank) in [(None, 0x21), (entry, 0x11)] {
+            let (_, mut mbc) =
+                get_mbc_with_entry(rom.as_slice(), entry, FrozenRtc, NoSensor).unwrap();
+            mbc.write(0x4000, 0x01);
+            mbc.write(0x2000, 0x01);
+            assert_eq!(mbc.read(0x4000), bank);
+        }
+    }
+}
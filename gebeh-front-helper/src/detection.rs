@@ -0,0 +1,184 @@
+// The unlicensed cartridges can't be told apart by their cartridge type, they are recognized by
+// the tricks they use to boot or by the logo of their publisher.
+
+use std::borrow::Cow;
+
+use gebeh_core::mbc::{
+    CARTRIDGE_TYPE_HEADER, CartridgeType, MMM01_MENU_SIZE, ROM_BANK_SIZE, ROM_SIZE_HEADER,
+    unscramble_sachen_address,
+};
+
+use crate::{CGB_FLAG, HEADER_END, RomEntry, RomHeader, RomHeaderError, find_rom};
+
+const LOGO_START: usize = 0x0104;
+// some unlicensed cartridges keep another logo right after the header
+const SECONDARY_LOGO_START: usize = 0x0184;
+
+pub(crate) const LOGO: [u8; 0x30] = [
+    0xCE, 0xED, 0x66, 0x66, 0xCC, 0x0D, 0x00, 0x0B, 0x03, 0x73, 0x00, 0x83, 0x00, 0x0C, 0x00, 0x0D,
+    0x00, 0x08, 0x11, 0x1F, 0x88, 0x89, 0x00, 0x0E, 0xDC, 0xCC, 0x6E, 0xE6, 0xDD, 0xDD, 0xD9, 0x99,
+    0xBB, 0xBB, 0x67, 0x63, 0x6E, 0x0E, 0xEC, 0xCC, 0xDD, 0xDC, 0x99, 0x9F, 0xBB, 0xB9, 0x33, 0x3E,
+];
+
+pub(crate) fn get_logo(rom: &[u8]) -> Option<&[u8; 0x30]> {
+    rom.get(LOGO_START..LOGO_START + LOGO.len())?
+        .try_into()
+        .ok()
+}
+
+const MINIMUM_GAMES_COUNT_IN_MULTICART: usize = 3;
+
+pub(crate) fn is_multicart(rom: &[u8]) -> bool {
+    // copied from gameroy and adapted
+    rom.get(ROM_SIZE_HEADER) == Some(&5)
+        && (0..4)
+            .filter_map(|i| get_logo(rom.get(i * 0x40000..)?))
+            .filter(|logo| *logo == &LOGO)
+            .take(MINIMUM_GAMES_COUNT_IN_MULTICART)
+            .count()
+            >= MINIMUM_GAMES_COUNT_IN_MULTICART
+}
+
+// https://gbdev.io/pandocs/MMM01.html
+// The menu is mapped at boot, so the header describing the whole cartridge is in the last 32 KiB
+// while the first bank holds the header of the first game.
+pub fn get_mmm01_menu(rom: &[u8]) -> Option<&[u8]> {
+    let menu = rom.get(rom.len().checked_sub(MMM01_MENU_SIZE)?..)?;
+    (get_logo(menu) == Some(&LOGO) && matches!(menu.get(CARTRIDGE_TYPE_HEADER), Some(0x0b..=0x0d)))
+        .then_some(menu)
+}
+
+pub fn is_wisdom_tree(cartridge_type: CartridgeType, rom: &[u8]) -> bool {
+    // https://gbdev.gg8.se/wiki/articles/Memory_Bank_Controllers#Wisdom_Tree
+    const NEEDLES: [&[u8]; 2] = [b"WISDOM TREE", b"WISDOM\0TREE"];
+    cartridge_type == CartridgeType::RomOnly
+        && rom.len() > usize::from(ROM_BANK_SIZE) * 2
+        && NEEDLES
+            .iter()
+            .any(|needle| rom.windows(needle.len()).any(|w| w == *needle))
+}
+
+// the header as seen by the boot ROM once the mapper is unlocked
+fn read_sachen_header(rom: &[u8], index: u16) -> u8 {
+    rom.get(usize::from(unscramble_sachen_address(index)))
+        .copied()
+        .unwrap_or(0xff)
+}
+
+fn is_sachen(rom: &[u8]) -> bool {
+    get_logo(rom) != Some(&LOGO)
+        && (0..)
+            .zip(LOGO)
+            .all(|(offset, byte)| read_sachen_header(rom, LOGO_START as u16 + offset) == byte)
+}
+
+// https://github.com/mgba-emu/mgba/blob/master/src/gb/mbc.c
+// CRC32 of the secondary logos
+const HITEK_LOGOS: [u32; 1] = [0x4fdab691];
+// the second one is Garou's
+const BBD_LOGOS: [u32; 2] = [0xc7d8c1df, 0x6d1ea662];
+const LI_CHENG_LOGOS: [u32; 2] = [0x20d092e2, 0xd2b57657];
+
+pub fn get_unlicensed_cartridge_type(rom: &[u8]) -> Option<CartridgeType> {
+    if is_sachen(rom) {
+        // the MMC2 is only found in the games that support the CGB
+        return Some(if read_sachen_header(rom, CGB_FLAG as u16) & 0x80 != 0 {
+            CartridgeType::SachenMmc2
+        } else {
+            CartridgeType::SachenMmc1
+        });
+    }
+
+    let secondary_logo = rom.get(SECONDARY_LOGO_START..SECONDARY_LOGO_START + LOGO.len())?;
+    if get_logo(rom) != Some(&LOGO) && secondary_logo == LOGO {
+        return Some(CartridgeType::Rocket);
+    }
+
+    let secondary_logo = crc32fast::hash(secondary_logo);
+    if HITEK_LOGOS.contains(&secondary_logo) {
+        Some(CartridgeType::Hitek)
+    } else if BBD_LOGOS.contains(&secondary_logo) {
+        // the patched dumps run on a regular MBC5
+        (rom.get(0x7fff) != Some(&0x01)).then_some(CartridgeType::Bbd)
+    } else if LI_CHENG_LOGOS.contains(&secondary_logo) {
+        // the patched dumps have a correct header
+        let is_patched = rom.get(CARTRIDGE_TYPE_HEADER) != Some(&0x01)
+            && rom
+                .get(ROM_SIZE_HEADER)
+                .and_then(|size| 0x8000usize.checked_shl(u32::from(*size)))
+                == Some(rom.len());
+        (!is_patched).then_some(CartridgeType::LiCheng)
+    } else {
+        None
+    }
+}
+
+/// The bytes where the boot ROM finds the header: the MMM01 menu, or the unscrambled header of the
+/// Sachen cartridges.
+pub fn get_header_rom(rom: &[u8]) -> Cow<'_, [u8]> {
+    if let Some(menu) = get_mmm01_menu(rom) {
+        Cow::Borrowed(menu)
+    } else if is_sachen(rom) {
+        let mut header: Vec<u8> = rom.get(..HEADER_END).unwrap_or(rom).to_vec();
+        for (index, byte) in (0..).zip(header.iter_mut()).skip(0x100) {
+            *byte = read_sachen_header(rom, index);
+        }
+        Cow::Owned(header)
+    } else {
+        Cow::Borrowed(rom)
+    }
+}
+
+pub fn get_cartridge_type(rom: &[u8]) -> Result<CartridgeType, RomHeaderError> {
+    get_cartridge_type_with_entry(rom, find_rom(rom))
+}
+
+/// See [`get_cartridge_type`], for a ROM already looked up in the database.
+pub fn get_cartridge_type_with_entry(
+    rom: &[u8],
+    entry: Option<&RomEntry>,
+) -> Result<CartridgeType, RomHeaderError> {
+    if let Some(cartridge_type) = entry.and_then(|entry| entry.cartridge_type) {
+        return Ok(cartridge_type);
+    }
+    if let Some(cartridge_type) = get_unlicensed_cartridge_type(rom) {
+        return Ok(cartridge_type);
+    }
+    let cartridge_type = RomHeader::parse(&get_header_rom(rom))?.cartridge_type;
+    Ok(if is_wisdom_tree(cartridge_type, rom) {
+        CartridgeType::WisdomTree
+    } else {
+        cartridge_type
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use gebeh_core::mbc::{CartridgeType, unscramble_sachen_address};
+
+    use crate::{LOGO, get_cartridge_type, get_header_rom};
+
+    #[test]
+    fn unlicensed() {
+        let mut rom = vec![0; 0x8000];
+        rom[0x104..0x134].copy_from_slice(&LOGO);
+        assert_eq!(get_cartridge_type(&rom), Ok(CartridgeType::RomOnly));
+
+        // the Rocket logo in the header, the Nintendo one after it
+        rom[0x184..0x1b4].copy_from_slice(&LOGO);
+        rom[0x104..0x134].fill(0x5a);
+        assert_eq!(get_cartridge_type(&rom), Ok(CartridgeType::Rocket));
+
+        // a scrambled header with a CGB flag
+        let mut rom = vec![0; 0x8000];
+        for (index, byte) in (0x104..).zip(LOGO) {
+            rom[usize::from(unscramble_sachen_address(index))] = byte;
+        }
+        rom[usize::from(unscramble_sachen_address(0x143))] = 0x80;
+        rom[usize::from(unscramble_sachen_address(0x147))] = 0x01;
+        assert_eq!(get_cartridge_type(&rom), Ok(CartridgeType::SachenMmc2));
+        let header = get_header_rom(&rom);
+        assert_eq!(header[0x104..0x134], LOGO);
+        assert_eq!(header[0x147], 0x01);
+    }
+}
@@ -1,11 +1,38 @@
 use std::{collections::HashSet, ops::Deref};
 
-use gebeh_core::mbc::{
-    CartridgeType, Huc1, M161, Mbc, Mbc1, Mbc1M, Mbc2, Mbc3, Mbc5, ROM_BANK_SIZE, ROM_SIZE_HEADER,
-    Rtc, Tama5, WisdomTree,
+use gebeh_core::{
+    mbc::{
+        Bbd, BbdWiring, CartridgeType, Huc1, Huc3, ImageSensor, LiCheng, M161, MBC6_RAM_SIZE,
+        MBC6_SAVE_SIZE, Mbc, Mbc1, Mbc1M, Mbc2, Mbc3, Mbc5, Mbc6, Mbc7, Mmm01, PocketCamera,
+        Rocket, Rtc, Sachen, SachenModel, Tama5, WisdomTree,
+    },
+    power_on::PowerOnMemory,
 };
 
+mod archive;
+mod boot_rom;
+mod camera;
+mod cheats;
+mod config;
+mod database;
+mod detection;
+mod patch;
+mod rom_header;
+mod save_state;
+
+pub use archive::*;
+pub use boot_rom::*;
+pub use camera::*;
+pub use cheats::*;
+pub use config::*;
+pub use database::*;
+pub use detection::*;
+pub use patch::*;
+pub use rom_header::*;
+pub use save_state::*;
+
 pub type EasyMbc = Box<dyn CloneMbc<'static>>;
+pub type SendMbc = Box<dyn CloneMbc<'static> + Send>;
 
 pub trait CloneMbc<'a>: Mbc {
     fn clone_boxed(&self) -> Box<dyn CloneMbc<'a> + 'a>;
@@ -17,115 +44,181 @@ impl<'a, T: Mbc + Clone + 'a> CloneMbc<'a> for T {
     }
 }
 
-const LOGO: [u8; 0x30] = [
-    0xCE, 0xED, 0x66, 0x66, 0xCC, 0x0D, 0x00, 0x0B, 0x03, 0x73, 0x00, 0x83, 0x00, 0x0C, 0x00, 0x0D,
-    0x00, 0x08, 0x11, 0x1F, 0x88, 0x89, 0x00, 0x0E, 0xDC, 0xCC, 0x6E, 0xE6, 0xDD, 0xDD, 0xD9, 0x99,
-    0xBB, 0xBB, 0x67, 0x63, 0x6E, 0x0E, 0xEC, 0xCC, 0xDD, 0xDC, 0x99, 0x9F, 0xBB, 0xB9, 0x33, 0x3E,
-];
-
-fn get_logo(rom: &[u8]) -> Option<&[u8; 0x30]> {
-    rom[0x0104..0x0134].try_into().ok()
+/// The cartridge RAM at power-on, to call before loading the save.
+pub fn fill_cartridge_ram(mbc: &mut (impl Mbc + ?Sized), config: &PowerOnMemory) {
+    let size = mbc.get_volatile_ram_size();
+    let Some(save) = mbc.get_ram_to_save() else {
+        return;
+    };
+    if size == 0 {
+        return;
+    }
+    let mut save = save.to_vec();
+    config.fill_cartridge_ram(&mut save[..size]);
+    mbc.load_saved_ram(&save);
 }
 
-const MINIMUM_GAMES_COUNT_IN_MULTICART: usize = 3;
-
-fn is_multicart(rom: &[u8]) -> bool {
-    // copied from gameroy and adapted
-    rom[ROM_SIZE_HEADER] == 5
-        && (0..4)
-            .filter_map(|i| get_logo(&rom[i * 0x40000..]))
-            .filter(|logo| *logo == &LOGO)
-            .take(MINIMUM_GAMES_COUNT_IN_MULTICART)
-            .count()
-            >= MINIMUM_GAMES_COUNT_IN_MULTICART
+// the RAM starts cleared like the other MBCs and the flash starts erased
+fn get_mbc6_save() -> Box<[u8]> {
+    let mut save = vec![0xff; MBC6_SAVE_SIZE];
+    save[..MBC6_RAM_SIZE].fill(0);
+    save.into_boxed_slice()
 }
 
-pub fn is_wisdom_tree(cartridge_type: CartridgeType, rom: &[u8]) -> bool {
-    // https://gbdev.gg8.se/wiki/articles/Memory_Bank_Controllers#Wisdom_Tree
-    const NEEDLES: [&[u8]; 2] = [b"WISDOM TREE", b"WISDOM\0TREE"];
-    cartridge_type == CartridgeType::RomOnly
-        && rom.len() > usize::from(ROM_BANK_SIZE) * 2
-        && NEEDLES
-            .iter()
-            .any(|needle| rom.windows(needle.len()).any(|w| w == *needle))
+/// The Pocket Camera sees nothing, see [`get_mbc_with_sensor`].
+pub fn get_mbc<'a, T: Deref<Target = [u8]> + Clone + 'a, U: Rtc + Clone + 'a>(
+    rom: T,
+    rtc: U,
+) -> Result<(CartridgeType, Box<dyn CloneMbc<'a> + 'a>), RomHeaderError> {
+    get_mbc_with_sensor(rom, rtc, NoSensor)
 }
 
-pub fn is_m161(cartridge_type: CartridgeType, rom: &[u8]) -> bool {
-    // https://gbdev.io/pandocs/M161.html
-    // Citation: This mapper is only known to be used in a single cartridge: Mani’s 4 in 1 cartridge
-    cartridge_type == CartridgeType::Mbc3TimerRamBattery && get_title_from_rom(rom) == "TETRIS SET"
+pub fn get_mbc_with_sensor<
+    'a,
+    T: Deref<Target = [u8]> + Clone + 'a,
+    U: Rtc + Clone + 'a,
+    S: ImageSensor + Clone + 'a,
+>(
+    rom: T,
+    rtc: U,
+    sensor: S,
+) -> Result<(CartridgeType, Box<dyn CloneMbc<'a> + 'a>), RomHeaderError> {
+    let entry = find_rom(&rom);
+    get_mbc_with_entry(rom, entry, rtc, sensor)
 }
 
-pub fn get_mbc<'a, T: Deref<Target = [u8]> + Clone + 'a, U: Rtc + Clone + 'a>(
+// the database overrides the header
+pub(crate) fn get_mbc_with_entry<
+    'a,
+    T: Deref<Target = [u8]> + Clone + 'a,
+    U: Rtc + Clone + 'a,
+    S: ImageSensor + Clone + 'a,
+>(
     rom: T,
+    entry: Option<&RomEntry>,
     rtc: U,
-) -> Option<(CartridgeType, Box<dyn CloneMbc<'a> + 'a>)> {
-    let mut cartridge_type = CartridgeType::try_from(rom.get(0x147).copied().unwrap_or(0)).ok()?;
-
-    if is_wisdom_tree(cartridge_type, &rom) {
-        cartridge_type = CartridgeType::WisdomTree;
-    } else if is_m161(cartridge_type, &rom) {
-        cartridge_type = CartridgeType::M161;
-    }
+    sensor: S,
+) -> Result<(CartridgeType, Box<dyn CloneMbc<'a> + 'a>), RomHeaderError> {
+    let cartridge_type = get_cartridge_type_with_entry(&rom, entry)?;
 
     let mbc: Box<dyn CloneMbc<'a>> = match cartridge_type {
         CartridgeType::RomOnly => Box::new(rom),
         CartridgeType::Mbc1 | CartridgeType::Mbc1Ram | CartridgeType::Mbc1RamBattery => {
-            if is_multicart(&rom) {
+            if is_multicart(&rom)
+                || entry.is_some_and(|entry| entry.has_quirk(Quirk::Mbc1Multicart))
+            {
                 Box::new(Mbc1M::new(rom))
             } else {
                 Box::new(Mbc1::new(rom))
             }
         }
         CartridgeType::Mbc2 | CartridgeType::Mbc2Battery => Box::new(Mbc2::new(rom)),
+        CartridgeType::Mmm01 | CartridgeType::Mmm01Ram | CartridgeType::Mmm01RamBattery => {
+            Box::new(Mmm01::new(rom))
+        }
         CartridgeType::Mbc3
         | CartridgeType::Mbc3Ram
         | CartridgeType::Mbc3RamBattery
         | CartridgeType::Mbc3TimerBattery
-        | CartridgeType::Mbc3TimerRamBattery => Box::new(Mbc3::new(rom, rtc)),
-        CartridgeType::Mbc5 | CartridgeType::Mbc5RamBattery => Box::new(Mbc5::new(rom)),
-        CartridgeType::Tama5 => Box::new(Tama5::new(rom)),
+        | CartridgeType::Mbc3TimerRamBattery => {
+            let mut mbc = Mbc3::new(rom, rtc);
+            if let Some(entry) = entry {
+                entry.apply_to_mbc3(&mut mbc);
+            }
+            Box::new(mbc)
+        }
+        CartridgeType::Mbc5
+        | CartridgeType::Mbc5Ram
+        | CartridgeType::Mbc5RamBattery
+        | CartridgeType::Mbc5Rumble
+        | CartridgeType::Mbc5RumbleRam
+        | CartridgeType::Mbc5RumbleRamBattery => Box::new(Mbc5::new(rom)),
+        CartridgeType::Mbc6 => Box::new(Mbc6::new(rom, get_mbc6_save())),
+        CartridgeType::Mbc7 => Box::new(Mbc7::new(rom)),
+        CartridgeType::PocketCamera => Box::new(PocketCamera::new(rom, sensor)),
+        CartridgeType::Tama5 => Box::new(Tama5::new(rom, rtc)),
         CartridgeType::Huc1 => Box::new(Huc1::new(rom)),
+        CartridgeType::Huc3 => Box::new(Huc3::new(rom, rtc)),
         CartridgeType::WisdomTree => Box::new(WisdomTree::new(rom)),
         CartridgeType::M161 => Box::new(M161::new(rom)),
+        CartridgeType::SachenMmc1 => Box::new(Sachen::new(rom, SachenModel::Mmc1)),
+        CartridgeType::SachenMmc2 => Box::new(Sachen::new(rom, SachenModel::Mmc2)),
+        CartridgeType::Rocket => Box::new(Rocket::new(rom)),
+        CartridgeType::Bbd => Box::new(Bbd::new(rom, BbdWiring::Bbd)),
+        CartridgeType::Hitek => Box::new(Bbd::new(rom, BbdWiring::Hitek)),
+        CartridgeType::LiCheng => Box::new(LiCheng::new(rom)),
     };
-    Some((cartridge_type, mbc))
+    Ok((cartridge_type, mbc))
 }
 
 pub fn get_mbc_send<'a, T: Deref<Target = [u8]> + Clone + Send + 'a, U: Rtc + Send + Clone + 'a>(
     rom: T,
     rtc: U,
-) -> Option<(CartridgeType, Box<dyn CloneMbc<'a> + Send + 'a>)> {
-    let mut cartridge_type = CartridgeType::try_from(rom.get(0x147).copied().unwrap_or(0)).ok()?;
+) -> Result<(CartridgeType, Box<dyn CloneMbc<'a> + Send + 'a>), RomHeaderError> {
+    get_mbc_send_with_sensor(rom, rtc, NoSensor)
+}
 
-    if is_wisdom_tree(cartridge_type, &rom) {
-        cartridge_type = CartridgeType::WisdomTree;
-    } else if is_m161(cartridge_type, &rom) {
-        cartridge_type = CartridgeType::M161;
-    }
+pub fn get_mbc_send_with_sensor<
+    'a,
+    T: Deref<Target = [u8]> + Clone + Send + 'a,
+    U: Rtc + Send + Clone + 'a,
+    S: ImageSensor + Send + Clone + 'a,
+>(
+    rom: T,
+    rtc: U,
+    sensor: S,
+) -> Result<(CartridgeType, Box<dyn CloneMbc<'a> + Send + 'a>), RomHeaderError> {
+    let entry = find_rom(&rom);
+    let cartridge_type = get_cartridge_type_with_entry(&rom, entry)?;
 
     let mbc: Box<dyn CloneMbc<'a> + Send> = match cartridge_type {
         CartridgeType::RomOnly => Box::new(rom),
         CartridgeType::Mbc1 | CartridgeType::Mbc1Ram | CartridgeType::Mbc1RamBattery => {
-            if is_multicart(&rom) {
+            if is_multicart(&rom)
+                || entry.is_some_and(|entry| entry.has_quirk(Quirk::Mbc1Multicart))
+            {
                 Box::new(Mbc1M::new(rom))
             } else {
                 Box::new(Mbc1::new(rom))
             }
         }
         CartridgeType::Mbc2 | CartridgeType::Mbc2Battery => Box::new(Mbc2::new(rom)),
+        CartridgeType::Mmm01 | CartridgeType::Mmm01Ram | CartridgeType::Mmm01RamBattery => {
+            Box::new(Mmm01::new(rom))
+        }
         CartridgeType::Mbc3
         | CartridgeType::Mbc3Ram
         | CartridgeType::Mbc3RamBattery
         | CartridgeType::Mbc3TimerBattery
-        | CartridgeType::Mbc3TimerRamBattery => Box::new(Mbc3::new(rom, rtc)),
-        CartridgeType::Mbc5 | CartridgeType::Mbc5RamBattery => Box::new(Mbc5::new(rom)),
-        CartridgeType::Tama5 => Box::new(Tama5::new(rom)),
+        | CartridgeType::Mbc3TimerRamBattery => {
+            let mut mbc = Mbc3::new(rom, rtc);
+            if let Some(entry) = entry {
+                entry.apply_to_mbc3(&mut mbc);
+            }
+            Box::new(mbc)
+        }
+        CartridgeType::Mbc5
+        | CartridgeType::Mbc5Ram
+        | CartridgeType::Mbc5RamBattery
+        | CartridgeType::Mbc5Rumble
+        | CartridgeType::Mbc5RumbleRam
+        | CartridgeType::Mbc5RumbleRamBattery => Box::new(Mbc5::new(rom)),
+        CartridgeType::Mbc6 => Box::new(Mbc6::new(rom, get_mbc6_save())),
+        CartridgeType::Mbc7 => Box::new(Mbc7::new(rom)),
+        CartridgeType::PocketCamera => Box::new(PocketCamera::new(rom, sensor)),
+        CartridgeType::Tama5 => Box::new(Tama5::new(rom, rtc)),
         CartridgeType::Huc1 => Box::new(Huc1::new(rom)),
+        CartridgeType::Huc3 => Box::new(Huc3::new(rom, rtc)),
         CartridgeType::WisdomTree => Box::new(WisdomTree::new(rom)),
         CartridgeType::M161 => Box::new(M161::new(rom)),
+        CartridgeType::SachenMmc1 => Box::new(Sachen::new(rom, SachenModel::Mmc1)),
+        CartridgeType::SachenMmc2 => Box::new(Sachen::new(rom, SachenModel::Mmc2)),
+        CartridgeType::Rocket => Box::new(Rocket::new(rom)),
+        CartridgeType::Bbd => Box::new(Bbd::new(rom, BbdWiring::Bbd)),
+        CartridgeType::Hitek => Box::new(Bbd::new(rom, BbdWiring::Hitek)),
+        CartridgeType::LiCheng => Box::new(LiCheng::new(rom)),
     };
-    Some((cartridge_type, mbc))
+    Ok((cartridge_type, mbc))
 }
 
 // for sampling reasons we have to generate the noise values at program start
@@ -155,30 +248,3 @@ pub fn get_noise(is_short: bool) -> Vec<u8> {
     }
     noise
 }
-
-// https://gbdev.io/pandocs/The_Cartridge_Header.html#0134-0143--title
-pub fn get_title_from_rom(rom: &[u8]) -> &str {
-    let title = &rom[0x134..0x143];
-    let end_zero_pos = title
-        .iter()
-        .position(|byte| *byte == 0)
-        .unwrap_or(title.len());
-    str::from_utf8(&title[..end_zero_pos]).unwrap()
-}
-
-const CGB_FLAG: usize = 0x0143;
-
-#[derive(Debug, PartialEq)]
-pub enum Compatibility {
-    Dmg,
-    Both,
-    Cgb,
-}
-
-pub fn get_compatibility(rom: &[u8]) -> Compatibility {
-    match rom[CGB_FLAG] {
-        0x80 => Compatibility::Both,
-        0xc0 => Compatibility::Cgb,
-        _ => Compatibility::Dmg,
-    }
-}
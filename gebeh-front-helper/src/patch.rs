@@ -0,0 +1,384 @@
+// The translations and romhacks are distributed as patches, they are applied to the ROM before
+// giving it to get_mbc.
+// IPS: https://zerosoft.zophar.net/ips.php
+// UPS: https://www.romhacking.net/documents/392/
+// BPS: https://www.romhacking.net/documents/746/
+
+use std::fmt;
+
+use crate::MAX_ROM_SIZE;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatchError {
+    UnknownFormat,
+    // the patch ends in the middle of a record
+    Truncated,
+    // a record reads outside of the ROM or writes outside of the result, or the result is bigger
+    // than any ROM
+    OutOfBounds,
+    // the patch was made for another ROM, or another version of it
+    WrongSource { expected: u32, actual: u32 },
+    WrongTarget { expected: u32, actual: u32 },
+    WrongPatch { expected: u32, actual: u32 },
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatchError::UnknownFormat => {
+                write!(f, "Unknown patch format, IPS, UPS or BPS expected")
+            }
+            PatchError::Truncated => write!(f, "The patch is truncated"),
+            PatchError::OutOfBounds => write!(f, "The patch goes beyond the ROM"),
+            PatchError::WrongSource { expected, actual } => write!(
+                f,
+                "The patch is for another ROM (CRC32 0x{expected:08x} expected, 0x{actual:08x} found)"
+            ),
+            PatchError::WrongTarget { expected, actual } => write!(
+                f,
+                "The patched ROM is wrong (CRC32 0x{expected:08x} expected, 0x{actual:08x} found)"
+            ),
+            PatchError::WrongPatch { expected, actual } => write!(
+                f,
+                "The patch is corrupted (CRC32 0x{expected:08x} expected, 0x{actual:08x} found)"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PatchError {}
+
+const IPS_MAGIC: &[u8] = b"PATCH";
+const IPS_END: &[u8] = b"EOF";
+const UPS_MAGIC: &[u8] = b"UPS1";
+const BPS_MAGIC: &[u8] = b"BPS1";
+// the CRC32 of the source, of the target and of the patch itself
+const FOOTER_SIZE: usize = 12;
+
+/// Returns the patched ROM, the format is detected from the patch.
+pub fn apply_patch(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+    if let Some(records) = patch.strip_prefix(IPS_MAGIC) {
+        apply_ips(rom, records)
+    } else if patch.starts_with(UPS_MAGIC) {
+        apply_ups(rom, patch)
+    } else if patch.starts_with(BPS_MAGIC) {
+        apply_bps(rom, patch)
+    } else {
+        Err(PatchError::UnknownFormat)
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8], offset: usize) -> Self {
+        Self { bytes, offset }
+    }
+
+    fn read_slice(&mut self, length: usize) -> Result<&'a [u8], PatchError> {
+        let end = self.offset.saturating_add(length);
+        let slice = self
+            .bytes
+            .get(self.offset..end)
+            .ok_or(PatchError::Truncated)?;
+        self.offset = end;
+        Ok(slice)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, PatchError> {
+        Ok(self.read_slice(1)?[0])
+    }
+
+    fn read_be(&mut self, length: usize) -> Result<usize, PatchError> {
+        Ok(self
+            .read_slice(length)?
+            .iter()
+            .fold(0, |value, byte| (value << 8) | usize::from(*byte)))
+    }
+
+    // UPS and BPS: 7 bits per byte, the last one has its bit 7 set
+    fn read_number(&mut self) -> Result<usize, PatchError> {
+        let mut value = 0usize;
+        let mut shift = 1usize;
+        loop {
+            let byte = self.read_u8()?;
+            value = shift
+                .checked_mul(usize::from(byte & 0x7f))
+                .and_then(|data| value.checked_add(data))
+                .ok_or(PatchError::OutOfBounds)?;
+            if byte & 0x80 != 0 {
+                return Ok(value);
+            }
+            shift = shift.checked_shl(7).ok_or(PatchError::OutOfBounds)?;
+            value = value.checked_add(shift).ok_or(PatchError::OutOfBounds)?;
+        }
+    }
+}
+
+fn apply_ips(rom: &[u8], records: &[u8]) -> Result<Vec<u8>, PatchError> {
+    let mut target = rom.to_vec();
+    let mut reader = Reader::new(records, 0);
+    loop {
+        let offset = reader.read_slice(3)?;
+        if offset == IPS_END {
+            break;
+        }
+        let offset = offset
+            .iter()
+            .fold(0, |value, byte| (value << 8) | usize::from(*byte));
+        let (length, value) = match reader.read_be(2)? {
+            // run-length encoded
+            0 => (reader.read_be(2)?, None),
+            length => (length, Some(reader.read_slice(length)?)),
+        };
+        if target.len() < offset + length {
+            target.resize(offset + length, 0);
+        }
+        let destination = &mut target[offset..offset + length];
+        match value {
+            Some(value) => destination.copy_from_slice(value),
+            None => destination.fill(reader.read_u8()?),
+        }
+    }
+    // an extension of Lunar IPS
+    if let Ok(size) = reader.read_be(3) {
+        target.truncate(size);
+    }
+    Ok(target)
+}
+
+struct Footer {
+    source: u32,
+    target: u32,
+}
+
+fn check_footer(patch: &[u8]) -> Result<Footer, PatchError> {
+    let (content, footer) = patch
+        .split_last_chunk::<FOOTER_SIZE>()
+        .ok_or(PatchError::Truncated)?;
+    let [source, target, expected] =
+        [0, 4, 8].map(|offset| u32::from_le_bytes(footer[offset..offset + 4].try_into().unwrap()));
+    let actual = crc32fast::hash(&patch[..content.len() + 8]);
+    if actual != expected {
+        return Err(PatchError::WrongPatch { expected, actual });
+    }
+    Ok(Footer { source, target })
+}
+
+fn check_target(target: &[u8], expected: u32) -> Result<(), PatchError> {
+    let actual = crc32fast::hash(target);
+    if actual != expected {
+        return Err(PatchError::WrongTarget { expected, actual });
+    }
+    Ok(())
+}
+
+fn apply_ups(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+    let Footer {
+        source: source_crc32,
+        target: target_crc32,
+    } = check_footer(patch)?;
+    let mut reader = Reader::new(patch, UPS_MAGIC.len());
+    let source_size = reader.read_number()?;
+    let target_size = reader.read_number()?;
+
+    // the patches go both ways
+    let actual = crc32fast::hash(rom);
+    let (target_size, target_crc32) = if actual == source_crc32 {
+        (target_size, target_crc32)
+    } else if actual == target_crc32 {
+        (source_size, source_crc32)
+    } else {
+        return Err(PatchError::WrongSource {
+            expected: source_crc32,
+            actual,
+        });
+    };
+
+    if target_size > MAX_ROM_SIZE {
+        return Err(PatchError::OutOfBounds);
+    }
+    let mut target = rom.to_vec();
+    target.resize(target_size, 0);
+    let mut offset = 0usize;
+    while reader.offset < patch.len() - FOOTER_SIZE {
+        offset = offset
+            .checked_add(reader.read_number()?)
+            .ok_or(PatchError::OutOfBounds)?;
+        // XORed with the ROM until a zero
+        loop {
+            let value = reader.read_u8()?;
+            if let Some(byte) = target.get_mut(offset) {
+                *byte = rom.get(offset).copied().unwrap_or(0) ^ value;
+            }
+            offset += 1;
+            if value == 0 {
+                break;
+            }
+        }
+    }
+
+    check_target(&target, target_crc32)?;
+    Ok(target)
+}
+
+fn apply_bps(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+    let Footer {
+        source: source_crc32,
+        target: target_crc32,
+    } = check_footer(patch)?;
+    let actual = crc32fast::hash(rom);
+    if actual != source_crc32 {
+        return Err(PatchError::WrongSource {
+            expected: source_crc32,
+            actual,
+        });
+    }
+
+    let mut reader = Reader::new(patch, BPS_MAGIC.len());
+    let _source_size = reader.read_number()?;
+    let target_size = reader.read_number()?;
+    let metadata_size = reader.read_number()?;
+    reader.read_slice(metadata_size)?;
+
+    if target_size > MAX_ROM_SIZE {
+        return Err(PatchError::OutOfBounds);
+    }
+    let mut target = Vec::with_capacity(target_size);
+    let mut source_offset = 0usize;
+    let mut target_offset = 0usize;
+    let apply_relative_offset = |offset: usize, data: usize| {
+        let distance = data >> 1;
+        if data & 1 == 0 {
+            offset.checked_add(distance)
+        } else {
+            offset.checked_sub(distance)
+        }
+        .ok_or(PatchError::OutOfBounds)
+    };
+    while reader.offset < patch.len() - FOOTER_SIZE {
+        let data = reader.read_number()?;
+        let length = (data >> 2) + 1;
+        if target.len() + length > target_size {
+            return Err(PatchError::OutOfBounds);
+        }
+        match data & 3 {
+            // source read, at the same offset
+            0 => {
+                let offset = target.len();
+                let bytes = rom
+                    .get(offset..offset.saturating_add(length))
+                    .ok_or(PatchError::OutOfBounds)?;
+                target.extend_from_slice(bytes);
+            }
+            // target read, from the patch
+            1 => target.extend_from_slice(reader.read_slice(length)?),
+            // source copy
+            2 => {
+                source_offset = apply_relative_offset(source_offset, reader.read_number()?)?;
+                let bytes = rom
+                    .get(source_offset..source_offset.saturating_add(length))
+                    .ok_or(PatchError::OutOfBounds)?;
+                target.extend_from_slice(bytes);
+                source_offset += length;
+            }
+            // target copy, byte by byte because it can overlap what it writes
+            _ => {
+                target_offset = apply_relative_offset(target_offset, reader.read_number()?)?;
+                for _ in 0..length {
+                    let byte = *target.get(target_offset).ok_or(PatchError::OutOfBounds)?;
+                    target.push(byte);
+                    target_offset += 1;
+                }
+            }
+        }
+    }
+
+    check_target(&target, target_crc32)?;
+    Ok(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{PatchError, apply_patch};
+
+    fn with_footer(mut patch: Vec<u8>, source: &[u8], target: &[u8]) -> Vec<u8> {
+        patch.extend_from_slice(&crc32fast::hash(source).to_le_bytes());
+        patch.extend_from_slice(&crc32fast::hash(target).to_le_bytes());
+        patch.extend_from_slice(&crc32fast::hash(&patch).to_le_bytes());
+        patch
+    }
+
+    #[test]
+    fn ips() {
+        let rom = [0u8; 8];
+        let mut patch = b"PATCH".to_vec();
+        // 2 bytes at 1
+        patch.extend_from_slice(&[0, 0, 1, 0, 2, 0xaa, 0xbb]);
+        // 3 times 0xcc at 8, beyond the ROM
+        patch.extend_from_slice(&[0, 0, 8, 0, 0, 0, 3, 0xcc]);
+        patch.extend_from_slice(b"EOF");
+        assert_eq!(
+            apply_patch(&rom, &patch).unwrap(),
+            [0, 0xaa, 0xbb, 0, 0, 0, 0, 0, 0xcc, 0xcc, 0xcc]
+        );
+        assert_eq!(
+            apply_patch(&rom, &patch[..patch.len() - 1]),
+            Err(PatchError::Truncated)
+        );
+    }
+
+    #[test]
+    fn ups() {
+        let source = [1u8, 2, 3, 4];
+        let target = [1u8, 0x22, 3, 4, 5];
+        // sizes, then twice: skip 1 byte and XOR the next one
+        let mut patch = b"UPS1".to_vec();
+        patch.extend_from_slice(&[0x84, 0x85, 0x81, 0x20, 0x00, 0x81, 0x05, 0x00]);
+        let patch = with_footer(patch, &source, &target);
+        assert_eq!(apply_patch(&source, &patch).unwrap(), target);
+        // and back
+        assert_eq!(apply_patch(&target, &patch).unwrap(), source);
+        assert!(matches!(
+            apply_patch(&[0; 4], &patch),
+            Err(PatchError::WrongSource { .. })
+        ));
+
+        // a target size of 512 MiB
+        let mut patch = b"UPS1".to_vec();
+        patch.extend_from_slice(&[0x84, 0x7f, 0x7f, 0x7f, 0x7f, 0x80]);
+        let patch = with_footer(patch, &source, &target);
+        assert_eq!(apply_patch(&source, &patch), Err(PatchError::OutOfBounds));
+    }
+
+    #[test]
+    fn bps() {
+        let source = b"abcdef";
+        let target = b"abcXYXYdef";
+        let mut patch = b"BPS1".to_vec();
+        // sizes, no metadata
+        patch.extend_from_slice(&[0x86, 0x8a, 0x80]);
+        // source read of 3 bytes, target read of "XY", target copy of 2 bytes from 3
+        patch.extend_from_slice(&[0x88, 0x85, b'X', b'Y', 0x87, 0x86]);
+        // source copy of 3 bytes from 3
+        patch.extend_from_slice(&[0x8a, 0x86]);
+        let mut patch = with_footer(patch, source, target);
+        assert_eq!(apply_patch(source, &patch).unwrap(), target);
+
+        let last = patch.len() - 1;
+        patch[last] ^= 1;
+        assert!(matches!(
+            apply_patch(source, &patch),
+            Err(PatchError::WrongPatch { .. })
+        ));
+
+        // a target size of 512 MiB
+        let mut patch = b"BPS1".to_vec();
+        patch.extend_from_slice(&[0x86, 0x7f, 0x7f, 0x7f, 0x7f, 0x80, 0x80]);
+        let patch = with_footer(patch, source, target);
+        assert_eq!(apply_patch(source, &patch), Err(PatchError::OutOfBounds));
+    }
+}
@@ -0,0 +1,238 @@
+// https://gbdev.io/pandocs/The_Cartridge_Header.html
+
+use std::fmt;
+
+use gebeh_core::mbc::{CARTRIDGE_TYPE_HEADER, CartridgeType, RAM_SIZE_HEADER, ROM_SIZE_HEADER};
+
+const TITLE: usize = 0x134;
+const MANUFACTURER_CODE: usize = 0x13f;
+pub(crate) const CGB_FLAG: usize = 0x143;
+const NEW_LICENSEE_CODE: usize = 0x144;
+const SGB_FLAG: usize = 0x146;
+const OLD_LICENSEE_CODE: usize = 0x14b;
+const VERSION: usize = 0x14c;
+const HEADER_CHECKSUM: usize = 0x14d;
+const GLOBAL_CHECKSUM: usize = 0x14e;
+pub const HEADER_END: usize = 0x150;
+// the biggest size the header can announce, the files above are not ROMs
+pub const MAX_ROM_SIZE: usize = 0x8000 << 8;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Compatibility {
+    Dmg,
+    Both,
+    Cgb,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Licensee {
+    Old(u8),
+    // Citation: If the Old licensee code is $33, the New licensee code must be considered instead.
+    New(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct RomHeader {
+    pub title: String,
+    // only in the newer cartridges
+    pub manufacturer_code: Option<String>,
+    pub compatibility: Compatibility,
+    pub supports_sgb: bool,
+    pub licensee: Licensee,
+    pub cartridge_type: CartridgeType,
+    pub rom_size: usize,
+    pub ram_size: usize,
+    pub version: u8,
+    pub header_checksum: u8,
+    pub global_checksum: u16,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RomHeaderError {
+    // the length of the ROM
+    Truncated(usize),
+    UnknownCartridgeType(u8),
+    UnknownRomSize(u8),
+    UnknownRamSize(u8),
+}
+
+impl fmt::Display for RomHeaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RomHeaderError::Truncated(length) => write!(
+                f,
+                "The ROM is too small to contain a header ({length} bytes, at least {HEADER_END} expected)"
+            ),
+            RomHeaderError::UnknownCartridgeType(value) => {
+                write!(f, "Unknown or unsupported cartridge type 0x{value:02x}")
+            }
+            RomHeaderError::UnknownRomSize(value) => write!(f, "Unknown ROM size 0x{value:02x}"),
+            RomHeaderError::UnknownRamSize(value) => write!(f, "Unknown RAM size 0x{value:02x}"),
+        }
+    }
+}
+
+impl std::error::Error for RomHeaderError {}
+
+// https://gbdev.io/pandocs/The_Cartridge_Header.html#0134-0143--title
+// Citation: If the title is less than 16 characters long, the remaining bytes should be padded with $00s.
+fn parse_title(title: &[u8]) -> String {
+    let end_zero_pos = title
+        .iter()
+        .position(|byte| *byte == 0)
+        .unwrap_or(title.len());
+    String::from_utf8_lossy(&title[..end_zero_pos]).into_owned()
+}
+
+pub fn get_title_from_rom(rom: &[u8]) -> String {
+    parse_title(rom.get(TITLE..CGB_FLAG).unwrap_or_default())
+}
+
+pub fn get_compatibility(rom: &[u8]) -> Compatibility {
+    // https://gbdev.io/pandocs/The_Cartridge_Header.html#0143--cgb-flag
+    match rom.get(CGB_FLAG) {
+        Some(0x80) => Compatibility::Both,
+        Some(0xc0) => Compatibility::Cgb,
+        _ => Compatibility::Dmg,
+    }
+}
+
+// https://gbdev.io/pandocs/The_Cartridge_Header.html#0146--sgb-flag
+pub fn is_sgb_supported(rom: &[u8]) -> bool {
+    rom.get(SGB_FLAG) == Some(&0x03)
+}
+
+impl RomHeader {
+    pub fn parse(rom: &[u8]) -> Result<Self, RomHeaderError> {
+        let Some(header) = rom.get(..HEADER_END) else {
+            return Err(RomHeaderError::Truncated(rom.len()));
+        };
+
+        let cartridge_type = CartridgeType::try_from(header[CARTRIDGE_TYPE_HEADER])
+            .map_err(RomHeaderError::UnknownCartridgeType)?;
+
+        // https://gbdev.io/pandocs/The_Cartridge_Header.html#0148--rom-size
+        let rom_size = match header[ROM_SIZE_HEADER] {
+            value @ 0..=8 => 0x8000 << value,
+            value => return Err(RomHeaderError::UnknownRomSize(value)),
+        };
+
+        // https://gbdev.io/pandocs/The_Cartridge_Header.html#0149--ram-size
+        let ram_size = match header[RAM_SIZE_HEADER] {
+            0 => 0,
+            // unofficial, listed in old documents
+            1 => 0x800,
+            2 => 0x2000,
+            3 => 0x8000,
+            4 => 0x20000,
+            5 => 0x10000,
+            value => return Err(RomHeaderError::UnknownRamSize(value)),
+        };
+
+        // https://gbdev.io/pandocs/The_Cartridge_Header.html#013f-0142--manufacturer-code
+        let manufacturer_code = &header[MANUFACTURER_CODE..CGB_FLAG];
+        let manufacturer_code = manufacturer_code
+            .iter()
+            .all(u8::is_ascii_uppercase)
+            .then(|| String::from_utf8_lossy(manufacturer_code).into_owned());
+
+        // https://gbdev.io/pandocs/The_Cartridge_Header.html#014b--old-licensee-code
+        let licensee = match header[OLD_LICENSEE_CODE] {
+            0x33 => Licensee::New(
+                String::from_utf8_lossy(&header[NEW_LICENSEE_CODE..SGB_FLAG]).into_owned(),
+            ),
+            code => Licensee::Old(code),
+        };
+
+        Ok(Self {
+            title: get_title_from_rom(header),
+            manufacturer_code,
+            compatibility: get_compatibility(header),
+            supports_sgb: is_sgb_supported(header),
+            licensee,
+            cartridge_type,
+            rom_size,
+            ram_size,
+            version: header[VERSION],
+            header_checksum: header[HEADER_CHECKSUM],
+            global_checksum: u16::from_be_bytes([
+                header[GLOBAL_CHECKSUM],
+                header[GLOBAL_CHECKSUM + 1],
+            ]),
+        })
+    }
+
+    // https://gbdev.io/pandocs/The_Cartridge_Header.html#014d--header-checksum
+    // Citation: The boot ROM verifies this checksum. If the byte at $014D does not match the lower 8 bits of checksum,
+    // the boot ROM will lock up and the program in the cartridge won't run.
+    pub fn is_header_checksum_valid(rom: &[u8]) -> bool {
+        let Some(bytes) = rom.get(TITLE..HEADER_CHECKSUM) else {
+            return false;
+        };
+        let checksum = bytes.iter().fold(0u8, |checksum, byte| {
+            checksum.wrapping_sub(*byte).wrapping_sub(1)
+        });
+        rom[HEADER_CHECKSUM] == checksum
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gebeh_core::mbc::CartridgeType;
+
+    use crate::{Compatibility, Licensee, RomHeader, RomHeaderError};
+
+    fn get_rom() -> Vec<u8> {
+        let mut rom = vec![0; 0x8000];
+        rom[0x134..0x13f].copy_from_slice(b"POKEMON RED");
+        rom[0x146] = 0x03;
+        rom[0x147] = 0x13;
+        rom[0x148] = 0x05;
+        rom[0x149] = 0x03;
+        rom[0x14b] = 0x01;
+        rom[0x14d] = 0xb4;
+        rom[0x14e..0x150].copy_from_slice(&[0x91, 0xe6]);
+        rom
+    }
+
+    #[test]
+    fn parse() {
+        let rom = get_rom();
+        let header = RomHeader::parse(&rom).unwrap();
+        assert_eq!(header.title, "POKEMON RED");
+        assert_eq!(header.manufacturer_code, None);
+        assert_eq!(header.compatibility, Compatibility::Dmg);
+        assert!(header.supports_sgb);
+        assert_eq!(header.licensee, Licensee::Old(0x01));
+        assert_eq!(header.cartridge_type, CartridgeType::Mbc3RamBattery);
+        assert_eq!(header.rom_size, 1024 * 1024);
+        assert_eq!(header.ram_size, 32 * 1024);
+        assert_eq!(header.global_checksum, 0x91e6);
+        assert!(RomHeader::is_header_checksum_valid(&rom));
+    }
+
+    #[test]
+    fn errors() {
+        let mut rom = get_rom();
+        assert_eq!(
+            RomHeader::parse(&rom[..0x14f]).unwrap_err(),
+            RomHeaderError::Truncated(0x14f)
+        );
+        rom[0x149] = 0x06;
+        assert_eq!(
+            RomHeader::parse(&rom).unwrap_err(),
+            RomHeaderError::UnknownRamSize(0x06)
+        );
+        rom[0x148] = 0x09;
+        assert_eq!(
+            RomHeader::parse(&rom).unwrap_err(),
+            RomHeaderError::UnknownRomSize(0x09)
+        );
+        rom[0x147] = 0x21;
+        assert_eq!(
+            RomHeader::parse(&rom).unwrap_err(),
+            RomHeaderError::UnknownCartridgeType(0x21)
+        );
+        assert!(!RomHeader::is_header_checksum_valid(&rom));
+    }
+}
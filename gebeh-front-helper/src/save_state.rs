@@ -0,0 +1,236 @@
+// Save state layout, integers are big endian:
+// - magic "GBHS"
+// - u16 format version
+// - u8 length + model name
+// - ROM identity: u32 CRC32 of the whole ROM, u8 header checksum, u16 global checksum
+// - u32 length + MBC registers
+// - u32 length + MBC RAM
+// - u32 length + MBC additional data (RTC)
+// - the emulator encoded with postcard until the end
+
+use std::fmt;
+
+use gebeh_core::{Emulator, Model, mbc::Mbc};
+
+const MAGIC: &[u8; 4] = b"GBHS";
+// must be incremented each time the layout or the emulator structures change
+pub const SAVE_STATE_VERSION: u16 = 7;
+// big enough for all the MBCs
+const MBC_BUFFER_SIZE: usize = 256;
+
+// https://gbdev.io/pandocs/The_Cartridge_Header.html#014d--header-checksum
+const HEADER_CHECKSUM: usize = 0x14d;
+const GLOBAL_CHECKSUM: usize = 0x14e;
+
+#[derive(Debug)]
+pub enum LoadStateError {
+    NotASaveState,
+    UnsupportedVersion(u16),
+    WrongModel(String),
+    WrongRom,
+    Truncated,
+    InvalidEmulator(postcard::Error),
+}
+
+impl fmt::Display for LoadStateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadStateError::NotASaveState => write!(f, "Not a save state"),
+            LoadStateError::UnsupportedVersion(version) => write!(
+                f,
+                "Save state version {version} is not supported (expected {SAVE_STATE_VERSION})"
+            ),
+            LoadStateError::WrongModel(model) => {
+                write!(f, "Save state made for another model ({model})")
+            }
+            LoadStateError::WrongRom => write!(f, "Save state made for another ROM"),
+            LoadStateError::Truncated => write!(f, "Save state is truncated"),
+            LoadStateError::InvalidEmulator(err) => write!(f, "Invalid emulator state: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for LoadStateError {}
+
+#[derive(PartialEq)]
+struct RomIdentity {
+    crc32: u32,
+    header_checksum: u8,
+    global_checksum: u16,
+}
+
+impl RomIdentity {
+    const SIZE: usize = 7;
+
+    fn new(rom: &[u8]) -> Self {
+        Self {
+            crc32: crc32fast::hash(rom),
+            header_checksum: rom.get(HEADER_CHECKSUM).copied().unwrap_or(0),
+            global_checksum: rom
+                .get(GLOBAL_CHECKSUM..GLOBAL_CHECKSUM + 2)
+                .map(|bytes| u16::from_be_bytes(bytes.try_into().unwrap()))
+                .unwrap_or(0),
+        }
+    }
+
+    fn serialize(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(&self.crc32.to_be_bytes());
+        buffer.push(self.header_checksum);
+        buffer.extend_from_slice(&self.global_checksum.to_be_bytes());
+    }
+
+    fn deserialize(bytes: &[u8; Self::SIZE]) -> Self {
+        Self {
+            crc32: u32::from_be_bytes(bytes[..4].try_into().unwrap()),
+            header_checksum: bytes[4],
+            global_checksum: u16::from_be_bytes(bytes[5..].try_into().unwrap()),
+        }
+    }
+}
+
+fn push_with_length(buffer: &mut Vec<u8>, data: &[u8]) {
+    buffer.extend_from_slice(&u32::try_from(data.len()).unwrap().to_be_bytes());
+    buffer.extend_from_slice(data);
+}
+
+pub fn save_state<M: Model>(emulator: &Emulator<M>, mbc: &dyn Mbc) -> Vec<u8> {
+    let mut state = Vec::new();
+    state.extend_from_slice(MAGIC);
+    state.extend_from_slice(&SAVE_STATE_VERSION.to_be_bytes());
+    state.push(u8::try_from(M::NAME.len()).unwrap());
+    state.extend_from_slice(M::NAME.as_bytes());
+    RomIdentity::new(mbc.get_rom()).serialize(&mut state);
+
+    let mut buffer = [0; MBC_BUFFER_SIZE];
+    let count = mbc.get_registers_to_save(&mut buffer);
+    push_with_length(&mut state, &buffer[..count]);
+    push_with_length(&mut state, mbc.get_ram_to_save().unwrap_or_default());
+    let count = mbc.get_additional_data_to_save(&mut buffer);
+    push_with_length(&mut state, &buffer[..count]);
+
+    postcard::to_extend(emulator, state).unwrap()
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], LoadStateError> {
+        if self.0.len() < count {
+            return Err(LoadStateError::Truncated);
+        }
+        let (taken, rest) = self.0.split_at(count);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn take_array<const N: usize>(&mut self) -> Result<&'a [u8; N], LoadStateError> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn take_with_length(&mut self) -> Result<&'a [u8], LoadStateError> {
+        let length = u32::from_be_bytes(*self.take_array()?);
+        self.take(usize::try_from(length).unwrap())
+    }
+}
+
+/// The MBC must have been created from the same ROM as the save state.
+/// It is left untouched if an error is returned.
+pub fn load_state<M: Model>(
+    state: &[u8],
+    mbc: &mut dyn Mbc,
+) -> Result<Emulator<M>, LoadStateError> {
+    let mut reader = Reader(state);
+    if reader.take_array::<4>().ok() != Some(MAGIC) {
+        return Err(LoadStateError::NotASaveState);
+    }
+    let version = u16::from_be_bytes(*reader.take_array()?);
+    if version != SAVE_STATE_VERSION {
+        return Err(LoadStateError::UnsupportedVersion(version));
+    }
+    let [name_length] = *reader.take_array()?;
+    let name = reader.take(usize::from(name_length))?;
+    if name != M::NAME.as_bytes() {
+        return Err(LoadStateError::WrongModel(
+            String::from_utf8_lossy(name).into_owned(),
+        ));
+    }
+    if RomIdentity::deserialize(reader.take_array()?) != RomIdentity::new(mbc.get_rom()) {
+        return Err(LoadStateError::WrongRom);
+    }
+
+    let registers = reader.take_with_length()?;
+    let ram = reader.take_with_length()?;
+    let additional_data = reader.take_with_length()?;
+    let emulator = postcard::from_bytes(reader.0).map_err(LoadStateError::InvalidEmulator)?;
+
+    mbc.load_saved_registers(registers);
+    mbc.load_saved_ram(ram);
+    if !additional_data.is_empty() {
+        mbc.load_additional_data(additional_data);
+    }
+
+    Ok(emulator)
+}
+
+#[cfg(test)]
+mod tests {
+    use gebeh_core::{
+        Cgb, Dmg, Emulator, EmulatorExt, Model, Sgb,
+        cpu::{BOOTIX_BOOT_ROM, BootRom},
+        mbc::Mbc1,
+    };
+
+    use crate::{LoadStateError, load_state, save_state};
+
+    fn round_trip<M: Model>() {
+        let mut rom = vec![0; 0x8000];
+        // MBC1+RAM+BATTERY with 8 KiB of RAM
+        rom[0x147] = 0x03;
+        rom[0x149] = 0x02;
+        let mut mbc = Mbc1::new(rom.as_slice());
+        let mut emulator = Emulator::<M>::default();
+        for _ in 0..100_000 {
+            emulator.execute(&mut mbc);
+        }
+
+        let state = save_state(&emulator, &mbc);
+        let mut loaded_mbc = Mbc1::new(rom.as_slice());
+        let mut loaded_emulator = load_state::<M>(&state, &mut loaded_mbc).unwrap();
+        for _ in 0..100_000 {
+            emulator.execute(&mut mbc);
+            loaded_emulator.execute(&mut loaded_mbc);
+        }
+        assert!(save_state(&emulator, &mbc) == save_state(&loaded_emulator, &loaded_mbc));
+
+        rom[0x14d] = 0x42;
+        assert!(matches!(
+            load_state::<M>(&state, &mut Mbc1::new(rom.as_slice())),
+            Err(LoadStateError::WrongRom)
+        ));
+    }
+
+    #[test]
+    fn dmg_round_trip() {
+        round_trip::<Dmg>();
+    }
+
+    #[test]
+    fn cgb_round_trip() {
+        round_trip::<Cgb>();
+    }
+
+    #[test]
+    fn sgb_round_trip() {
+        round_trip::<Sgb>();
+    }
+
+    #[test]
+    fn custom_boot_rom_round_trip() {
+        let rom = vec![0; 0x8000];
+        let mut mbc = Mbc1::new(rom.as_slice());
+        let emulator = Emulator::<Dmg>::with_boot_rom(BootRom::new(&BOOTIX_BOOT_ROM).unwrap());
+        let state = save_state(&emulator, &mbc);
+        let loaded_emulator = load_state::<Dmg>(&state, &mut mbc).unwrap();
+        assert!(loaded_emulator.get_cpu().boot_rom.is_some());
+    }
+}
@@ -3,7 +3,7 @@ use std::time::{Duration, Instant};
 use gebeh::InstantRtc;
 use gebeh_core::{
     Dmg, Emulator, EmulatorExt, HEIGHT, WIDTH,
-    mbc::{CartridgeType, Mbc, get_factor_8_kib_ram, get_factor_32_kib_rom},
+    mbc::Mbc,
     ppu::{
         LcdControl, PpuStep,
         color::ColorIndex,
@@ -12,7 +12,10 @@ use gebeh_core::{
         vram::DmgVram,
     },
 };
-use gebeh_front_helper::get_mbc;
+use gebeh_front_helper::{
+    RomHeader, extract_rom, find_rom, get_cartridge_type_with_entry, get_header_rom, get_mbc,
+    load_state, save_state,
+};
 use pixels::{Pixels, PixelsBuilder, SurfaceTexture};
 use winit::{
     dpi::LogicalSize,
@@ -50,35 +53,34 @@ enum DebugMode {
     Pixel(u8), // previous scanline len
 }
 
-fn main() {
-    color_eyre::install().unwrap();
+fn main() -> color_eyre::Result<()> {
+    color_eyre::install()?;
     env_logger::init();
 
-    let rom = std::fs::read(
+    let file = std::fs::read(
         std::env::args()
             .nth(1)
             .expect("Please provide a path as first argument"),
     )
     .unwrap();
+    let rom = extract_rom(&file, None)?.into_owned();
 
-    // https://gbdev.io/pandocs/The_Cartridge_Header.html#0134-0143--title
-    let title = &rom[0x134..0x143];
-    let end_zero_pos = title
-        .iter()
-        .position(|byte| *byte == 0)
-        .unwrap_or(title.len());
-    let title = str::from_utf8(&title[..end_zero_pos]).unwrap();
+    let header = RomHeader::parse(&get_header_rom(&rom))?;
+    let title = header.title.as_str();
     println!("Title: {title}");
-
-    // https://gbdev.io/pandocs/The_Cartridge_Header.html#0147--cartridge-type
-    let cartridge_type = CartridgeType::try_from(rom[0x147]).unwrap();
-    println!("Cartridge type: {cartridge_type:?}");
-    // https://gbdev.io/pandocs/The_Cartridge_Header.html#0148--rom-size
-    println!("ROM size: {} KiB", get_factor_32_kib_rom(&rom) * 32);
-    println!("RAM size: {} KiB", get_factor_8_kib_ram(&rom) * 8);
+    let entry = find_rom(&rom);
+    if let Some(entry) = entry {
+        println!("Known dump: {}", entry.name);
+    }
+    println!(
+        "Cartridge type: {:?}",
+        get_cartridge_type_with_entry(&rom, entry)?
+    );
+    println!("ROM size: {} KiB", header.rom_size / 1024);
+    println!("RAM size: {} KiB", header.ram_size / 1024);
 
     // don't forget to slice the vec or you will clone it for each save state
-    let (_, mut mbc) = get_mbc(rom.as_slice(), InstantRtc::default()).unwrap();
+    let (_, mut mbc) = get_mbc(rom.as_slice(), InstantRtc::default())?;
     let mut emulator = Emulator::default();
 
     if let Ok(file) = std::fs::read(format!("{title}.save")) {
@@ -154,146 +156,159 @@ fn main() {
 
     let mut debug_mode = DebugMode::None;
 
-    event_loop
-        .run(|event, elwt| match event {
-            Event::WindowEvent {
-                event: WindowEvent::RedrawRequested,
-                window_id,
-                ..
-            } if window_id == window.id() => {
-                if !is_paused {
-                    if last_save.elapsed() >= Duration::from_secs(2) {
-                        last_save = Instant::now();
-                        save_states.push((emulator.clone(), mbc.clone_boxed()));
-                    }
-                    drive_emulator(
-                        mbc.as_mut(),
-                        &mut emulator,
-                        pixels.frame_mut().as_chunks_mut::<4>().0,
-                        &mut debug_mode,
-                    );
-                    pixels.render().unwrap();
+    event_loop.run(|event, elwt| match event {
+        Event::WindowEvent {
+            event: WindowEvent::RedrawRequested,
+            window_id,
+            ..
+        } if window_id == window.id() => {
+            if !is_paused {
+                if last_save.elapsed() >= Duration::from_secs(2) {
+                    last_save = Instant::now();
+                    save_states.push((emulator.clone(), mbc.clone_boxed()));
                 }
-
-                window.request_redraw();
+                drive_emulator(
+                    mbc.as_mut(),
+                    &mut emulator,
+                    pixels.frame_mut().as_chunks_mut::<4>().0,
+                    &mut debug_mode,
+                );
+                pixels.render().unwrap();
             }
-            Event::WindowEvent {
-                event: WindowEvent::RedrawRequested,
-                window_id,
-                ..
-            } if window_id == debug_window.id() => {
-                if !is_paused {
-                    draw_tiles_debug(
-                        emulator.get_ppu().get_vram(),
-                        debug_pixels.frame_mut().as_chunks_mut::<4>().0,
-                    );
-                    debug_pixels.render().unwrap();
-                }
 
-                debug_window.request_redraw();
+            window.request_redraw();
+        }
+        Event::WindowEvent {
+            event: WindowEvent::RedrawRequested,
+            window_id,
+            ..
+        } if window_id == debug_window.id() => {
+            if !is_paused {
+                draw_tiles_debug(
+                    emulator.get_ppu().get_vram(),
+                    debug_pixels.frame_mut().as_chunks_mut::<4>().0,
+                );
+                debug_pixels.render().unwrap();
             }
-            Event::WindowEvent {
-                event: WindowEvent::RedrawRequested,
-                window_id,
-                ..
-            } if window_id == debug_tile_map_window.id() => {
-                if !is_paused {
-                    draw_tile_map_debug(
-                        &emulator,
-                        debug_tile_map_pixels.frame_mut().as_chunks_mut::<4>().0,
-                    );
-                    debug_tile_map_pixels.render().unwrap();
-                }
 
-                debug_tile_map_window.request_redraw();
-            }
-            Event::WindowEvent {
-                event: WindowEvent::Resized(size),
-                window_id,
-                ..
-            } if window_id == window.id() => {
-                pixels.resize_surface(size.width, size.height).unwrap();
-            }
-            Event::WindowEvent {
-                event:
-                    WindowEvent::KeyboardInput {
-                        event:
-                            KeyEvent {
-                                state: ElementState::Released,
-                                physical_key: PhysicalKey::Code(keycode),
-                                ..
-                            },
-                        ..
-                    },
-                ..
-            } => {
-                let mut joypad = *emulator.get_joypad();
-                match keycode {
-                    KeyCode::KeyA => joypad.a = false,
-                    KeyCode::KeyB => joypad.b = false,
-                    KeyCode::ArrowLeft => joypad.left = false,
-                    KeyCode::ArrowRight => joypad.right = false,
-                    KeyCode::ArrowUp => joypad.up = false,
-                    KeyCode::ArrowDown => joypad.down = false,
-                    KeyCode::Enter => joypad.start = false,
-                    KeyCode::Tab => joypad.select = false,
-                    _ => {}
-                };
-                emulator.set_joypad(joypad);
+            debug_window.request_redraw();
+        }
+        Event::WindowEvent {
+            event: WindowEvent::RedrawRequested,
+            window_id,
+            ..
+        } if window_id == debug_tile_map_window.id() => {
+            if !is_paused {
+                draw_tile_map_debug(
+                    &emulator,
+                    debug_tile_map_pixels.frame_mut().as_chunks_mut::<4>().0,
+                );
+                debug_tile_map_pixels.render().unwrap();
             }
-            Event::WindowEvent {
-                event:
-                    WindowEvent::KeyboardInput {
-                        event:
-                            KeyEvent {
-                                state: ElementState::Pressed,
-                                physical_key: PhysicalKey::Code(keycode),
-                                ..
-                            },
-                        ..
-                    },
-                ..
-            } => {
-                let mut joypad = *emulator.get_joypad();
-                match keycode {
-                    KeyCode::Space => is_paused = !is_paused,
-                    KeyCode::KeyS => {
-                        debug_mode = match debug_mode {
-                            DebugMode::Scanline => DebugMode::None,
-                            _ => DebugMode::Scanline,
-                        }
+
+            debug_tile_map_window.request_redraw();
+        }
+        Event::WindowEvent {
+            event: WindowEvent::Resized(size),
+            window_id,
+            ..
+        } if window_id == window.id() => {
+            pixels.resize_surface(size.width, size.height).unwrap();
+        }
+        Event::WindowEvent {
+            event:
+                WindowEvent::KeyboardInput {
+                    event:
+                        KeyEvent {
+                            state: ElementState::Released,
+                            physical_key: PhysicalKey::Code(keycode),
+                            ..
+                        },
+                    ..
+                },
+            ..
+        } => {
+            let mut joypad = *emulator.get_joypad();
+            match keycode {
+                KeyCode::KeyA => joypad.a = false,
+                KeyCode::KeyB => joypad.b = false,
+                KeyCode::ArrowLeft => joypad.left = false,
+                KeyCode::ArrowRight => joypad.right = false,
+                KeyCode::ArrowUp => joypad.up = false,
+                KeyCode::ArrowDown => joypad.down = false,
+                KeyCode::Enter => joypad.start = false,
+                KeyCode::Tab => joypad.select = false,
+                _ => {}
+            };
+            emulator.set_joypad(joypad);
+        }
+        Event::WindowEvent {
+            event:
+                WindowEvent::KeyboardInput {
+                    event:
+                        KeyEvent {
+                            state: ElementState::Pressed,
+                            physical_key: PhysicalKey::Code(keycode),
+                            ..
+                        },
+                    ..
+                },
+            ..
+        } => {
+            let mut joypad = *emulator.get_joypad();
+            match keycode {
+                KeyCode::Space => is_paused = !is_paused,
+                KeyCode::KeyS => {
+                    debug_mode = match debug_mode {
+                        DebugMode::Scanline => DebugMode::None,
+                        _ => DebugMode::Scanline,
                     }
-                    KeyCode::KeyP => {
-                        debug_mode = match debug_mode {
-                            DebugMode::Pixel(_) => DebugMode::None,
-                            _ => DebugMode::Pixel(0),
-                        }
+                }
+                KeyCode::KeyP => {
+                    debug_mode = match debug_mode {
+                        DebugMode::Pixel(_) => DebugMode::None,
+                        _ => DebugMode::Pixel(0),
                     }
-                    KeyCode::Backspace => {
-                        if let Some(old) = save_states.pop() {
-                            (emulator, mbc) = old
-                        }
+                }
+                KeyCode::Backspace => {
+                    if let Some(old) = save_states.pop() {
+                        (emulator, mbc) = old
                     }
-                    KeyCode::Escape => exit(elwt, title, mbc.as_ref()),
-                    KeyCode::KeyA => joypad.a = true,
-                    KeyCode::KeyB => joypad.b = true,
-                    KeyCode::ArrowLeft => joypad.left = true,
-                    KeyCode::ArrowRight => joypad.right = true,
-                    KeyCode::ArrowUp => joypad.up = true,
-                    KeyCode::ArrowDown => joypad.down = true,
-                    KeyCode::Enter => joypad.start = true,
-                    KeyCode::Tab => joypad.select = true,
-                    _ => {}
                 }
-                emulator.set_joypad(joypad);
+                // persistent save states, to share them in bug reports
+                KeyCode::F5 => {
+                    let path = format!("{title}.state");
+                    std::fs::write(&path, save_state(&emulator, mbc.as_ref())).unwrap();
+                    log::info!("State saved to {path}");
+                }
+                KeyCode::F9 => match std::fs::read(format!("{title}.state")) {
+                    Ok(file) => match load_state(&file, mbc.as_mut()) {
+                        Ok(state) => emulator = state,
+                        Err(err) => log::error!("Can't load the state: {err}"),
+                    },
+                    Err(err) => log::error!("Can't read the state: {err}"),
+                },
+                KeyCode::Escape => exit(elwt, title, mbc.as_ref()),
+                KeyCode::KeyA => joypad.a = true,
+                KeyCode::KeyB => joypad.b = true,
+                KeyCode::ArrowLeft => joypad.left = true,
+                KeyCode::ArrowRight => joypad.right = true,
+                KeyCode::ArrowUp => joypad.up = true,
+                KeyCode::ArrowDown => joypad.down = true,
+                KeyCode::Enter => joypad.start = true,
+                KeyCode::Tab => joypad.select = true,
+                _ => {}
             }
-            Event::WindowEvent {
-                event: WindowEvent::CloseRequested,
-                ..
-            } => exit(elwt, title, mbc.as_ref()),
-            _ => {}
-        })
-        .unwrap();
+            emulator.set_joypad(joypad);
+        }
+        Event::WindowEvent {
+            event: WindowEvent::CloseRequested,
+            ..
+        } => exit(elwt, title, mbc.as_ref()),
+        _ => {}
+    })?;
+
+    Ok(())
 }
 
 // TODO save at fixed interval
@@ -302,7 +317,7 @@ fn exit(elwt: &EventLoopWindowTarget<()>, title: &str, mbc: &dyn Mbc) {
         std::fs::write(format!("{title}.save"), save).unwrap();
     }
     // at the moment there is only mbc3 that is using that
-    let mut buffer = [0u8; 12];
+    let mut buffer = [0u8; 64];
     let count = mbc.get_additional_data_to_save(&mut buffer);
     if count > 0 {
         std::fs::write(format!("{title}.extra.save"), &buffer[..count]).unwrap();
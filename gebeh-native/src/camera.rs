@@ -0,0 +1,79 @@
+use std::{
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::SystemTime,
+};
+
+use gebeh_core::mbc::{ImageSensor, SensorImage};
+use gebeh_front_helper::{StaticImage, TestPattern, get_luma};
+
+/// The Pocket Camera sees a PNG file, read again each time it changes so another program can
+/// update it. Without a file, the camera sees a test pattern.
+#[derive(Clone, Default)]
+pub struct CameraFile {
+    path: Option<Arc<Path>>,
+    // the modification time of the file when it was decoded
+    image: Option<(SystemTime, StaticImage)>,
+}
+
+impl CameraFile {
+    pub fn new(path: Option<PathBuf>) -> Self {
+        Self {
+            path: path.map(Arc::from),
+            image: None,
+        }
+    }
+
+    fn refresh(&mut self, path: &Path) -> color_eyre::Result<()> {
+        let modified = std::fs::metadata(path)?.modified()?;
+        if matches!(&self.image, Some((time, _)) if *time == modified) {
+            return Ok(());
+        }
+        let mut decoder = png::Decoder::new(BufReader::new(File::open(path)?));
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info()?;
+        let mut buffer = vec![0; reader.output_buffer_size().unwrap_or_default()];
+        let info = reader.next_frame(&mut buffer)?;
+        let buffer = &buffer[..info.buffer_size()];
+        let (width, height) = (info.width as usize, info.height as usize);
+        let image = match info.color_type {
+            png::ColorType::Grayscale => StaticImage::new(width, height, buffer),
+            png::ColorType::GrayscaleAlpha => {
+                let luminance: Vec<u8> = buffer.iter().step_by(2).copied().collect();
+                StaticImage::new(width, height, &luminance)
+            }
+            png::ColorType::Rgb => {
+                let luminance: Vec<u8> = buffer
+                    .as_chunks::<3>()
+                    .0
+                    .iter()
+                    .map(|[r, g, b]| get_luma(*r, *g, *b))
+                    .collect();
+                StaticImage::new(width, height, &luminance)
+            }
+            // the palettes are expanded by the transformations
+            png::ColorType::Rgba | png::ColorType::Indexed => {
+                StaticImage::from_rgba(width, height, buffer)
+            }
+        };
+        self.image = Some((modified, image));
+        Ok(())
+    }
+}
+
+impl ImageSensor for CameraFile {
+    fn capture(&mut self, image: &mut SensorImage) {
+        if let Some(path) = self.path.clone()
+            && let Err(err) = self.refresh(&path)
+        {
+            log::warn!("Can't read the camera image {}: {err}", path.display());
+            self.image = None;
+        }
+        match &mut self.image {
+            Some((_, static_image)) => static_image.capture(image),
+            None => TestPattern.capture(image),
+        }
+    }
+}
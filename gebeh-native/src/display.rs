@@ -0,0 +1,89 @@
+use gebeh::Frame;
+use gebeh_core::{
+    Cgb, Dmg, Emulator, HEIGHT, Model, Sgb, WIDTH,
+    ppu::{
+        color::CgbColor,
+        scanline::{Scanline, ScanlineBuilder},
+    },
+    sgb::{SGB_HEIGHT, SGB_WIDTH},
+};
+
+// What the window shows, the Super Game Boy draws a border around the screen
+pub trait Display: Model {
+    type Frame: Clone + Send + 'static;
+    const SIZE: (u32, u32);
+    fn new_frame() -> Self::Frame;
+    // called when a scanline is ready, the frame is complete after the last one
+    fn update_frame(emulator: &Emulator<Self>, frame: &mut Self::Frame);
+    fn draw(frame: &Self::Frame, pixels: &mut [u8]);
+}
+
+type ScanlineFrame<M> = Frame<<<M as Model>::ScanlineBuilder as ScanlineBuilder>::Scanline>;
+
+fn new_scanline_frame<M: Model>() -> ScanlineFrame<M> {
+    [Default::default(); HEIGHT as usize]
+}
+
+fn update_scanline_frame<M: Model>(emulator: &Emulator<M>, frame: &mut ScanlineFrame<M>) {
+    if let Some(scanline) = emulator.get_screen_scanline_if_ready() {
+        frame[usize::from(emulator.get_ppu().get_ly())] = scanline;
+    }
+}
+
+fn draw_scanline_frame<M: Model>(frame: &ScanlineFrame<M>, pixels: &mut [u8]) {
+    for (pixel, color) in pixels
+        .as_chunks_mut::<4>()
+        .0
+        .iter_mut()
+        .zip(frame.iter().flat_map(|scanline| scanline.iter_colors()))
+    {
+        *pixel = color.into();
+    }
+}
+
+impl Display for Dmg {
+    type Frame = ScanlineFrame<Self>;
+    const SIZE: (u32, u32) = (WIDTH as u32, HEIGHT as u32);
+    fn new_frame() -> Self::Frame {
+        new_scanline_frame::<Self>()
+    }
+    fn update_frame(emulator: &Emulator<Self>, frame: &mut Self::Frame) {
+        update_scanline_frame(emulator, frame);
+    }
+    fn draw(frame: &Self::Frame, pixels: &mut [u8]) {
+        draw_scanline_frame::<Self>(frame, pixels);
+    }
+}
+
+impl Display for Cgb {
+    type Frame = ScanlineFrame<Self>;
+    const SIZE: (u32, u32) = (WIDTH as u32, HEIGHT as u32);
+    fn new_frame() -> Self::Frame {
+        new_scanline_frame::<Self>()
+    }
+    fn update_frame(emulator: &Emulator<Self>, frame: &mut Self::Frame) {
+        update_scanline_frame(emulator, frame);
+    }
+    fn draw(frame: &Self::Frame, pixels: &mut [u8]) {
+        draw_scanline_frame::<Self>(frame, pixels);
+    }
+}
+
+impl Display for Sgb {
+    // RGB555
+    type Frame = Box<[u16; SGB_WIDTH * SGB_HEIGHT]>;
+    const SIZE: (u32, u32) = (SGB_WIDTH as u32, SGB_HEIGHT as u32);
+    fn new_frame() -> Self::Frame {
+        Box::new([0; _])
+    }
+    fn update_frame(emulator: &Emulator<Self>, frame: &mut Self::Frame) {
+        if emulator.get_scanline_if_ready().is_some() && emulator.get_ppu().get_ly() == HEIGHT - 1 {
+            emulator.get_super_game_boy().render(frame);
+        }
+    }
+    fn draw(frame: &Self::Frame, pixels: &mut [u8]) {
+        for (pixel, color) in pixels.as_chunks_mut::<4>().0.iter_mut().zip(frame.iter()) {
+            *pixel = CgbColor(*color).into();
+        }
+    }
+}
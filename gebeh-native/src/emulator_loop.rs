@@ -4,57 +4,124 @@ use cpal::{
     BufferSize, FromSample, I24, SizedSample, StreamConfig,
     traits::{DeviceTrait, StreamTrait},
 };
-use gebeh::{Frame, InstantRtc};
 use gebeh_core::{
-    Emulator, EmulatorExt, HEIGHT, Model, SYSTEM_CLOCK_FREQUENCY, apu::Mixer, joypad::JoypadInput,
-    ppu::scanline::ScanlineBuilder,
+    Emulator, EmulatorExt, HEIGHT, SYSTEM_CLOCK_FREQUENCY,
+    apu::Mixer,
+    joypad::{JoypadInput, Tilt},
 };
-use gebeh_front_helper::{get_mbc_send, get_noise};
+use gebeh_front_helper::{Cheats, SendMbc, get_noise};
 
-pub fn spawn_emulator<M: Model>(
+use crate::display::Display;
+
+#[derive(Clone, Default)]
+pub struct Input {
+    pub joypad: JoypadInput,
+    pub tilt: Tilt,
+    pub cheats: Cheats,
+    // bumped on every change of the cheats, they are only copied to the audio thread then
+    pub cheats_generation: u32,
+}
+
+pub fn spawn_emulator<M: Display>(
     device: &cpal::Device,
-    shared_frame: SyncSender<Frame<<M::ScanlineBuilder as ScanlineBuilder>::Scanline>>,
-    shared_joypad: Arc<RwLock<JoypadInput>>,
-    rom: Vec<u8>,
+    shared_frame: SyncSender<M::Frame>,
+    shared_input: Arc<RwLock<Input>>,
+    emulator: Emulator<M>,
+    mbc: SendMbc,
 ) -> cpal::Stream {
     let config = device.default_output_config().unwrap();
     let stream = match config.sample_format() {
-        cpal::SampleFormat::I8 => {
-            create_stream::<i8, M>(device, config.into(), shared_frame, shared_joypad, rom)
-        }
-        cpal::SampleFormat::I16 => {
-            create_stream::<i16, M>(device, config.into(), shared_frame, shared_joypad, rom)
-        }
-        cpal::SampleFormat::I24 => {
-            create_stream::<I24, M>(device, config.into(), shared_frame, shared_joypad, rom)
-        }
-        cpal::SampleFormat::I32 => {
-            create_stream::<i32, M>(device, config.into(), shared_frame, shared_joypad, rom)
-        }
+        cpal::SampleFormat::I8 => create_stream::<i8, M>(
+            device,
+            config.into(),
+            shared_frame,
+            shared_input,
+            emulator,
+            mbc,
+        ),
+        cpal::SampleFormat::I16 => create_stream::<i16, M>(
+            device,
+            config.into(),
+            shared_frame,
+            shared_input,
+            emulator,
+            mbc,
+        ),
+        cpal::SampleFormat::I24 => create_stream::<I24, M>(
+            device,
+            config.into(),
+            shared_frame,
+            shared_input,
+            emulator,
+            mbc,
+        ),
+        cpal::SampleFormat::I32 => create_stream::<i32, M>(
+            device,
+            config.into(),
+            shared_frame,
+            shared_input,
+            emulator,
+            mbc,
+        ),
         // cpal::SampleFormat::I48 => run::<I48>(&device, &config.into(),shared_frame),
-        cpal::SampleFormat::I64 => {
-            create_stream::<i64, M>(device, config.into(), shared_frame, shared_joypad, rom)
-        }
-        cpal::SampleFormat::U8 => {
-            create_stream::<u8, M>(device, config.into(), shared_frame, shared_joypad, rom)
-        }
-        cpal::SampleFormat::U16 => {
-            create_stream::<u16, M>(device, config.into(), shared_frame, shared_joypad, rom)
-        }
+        cpal::SampleFormat::I64 => create_stream::<i64, M>(
+            device,
+            config.into(),
+            shared_frame,
+            shared_input,
+            emulator,
+            mbc,
+        ),
+        cpal::SampleFormat::U8 => create_stream::<u8, M>(
+            device,
+            config.into(),
+            shared_frame,
+            shared_input,
+            emulator,
+            mbc,
+        ),
+        cpal::SampleFormat::U16 => create_stream::<u16, M>(
+            device,
+            config.into(),
+            shared_frame,
+            shared_input,
+            emulator,
+            mbc,
+        ),
         // cpal::SampleFormat::U24 => run::<U24>(&device, &config.into(),shared_frame),
-        cpal::SampleFormat::U32 => {
-            create_stream::<u32, M>(device, config.into(), shared_frame, shared_joypad, rom)
-        }
+        cpal::SampleFormat::U32 => create_stream::<u32, M>(
+            device,
+            config.into(),
+            shared_frame,
+            shared_input,
+            emulator,
+            mbc,
+        ),
         // cpal::SampleFormat::U48 => run::<U48>(&device, &config.into(),shared_frame),
-        cpal::SampleFormat::U64 => {
-            create_stream::<u64, M>(device, config.into(), shared_frame, shared_joypad, rom)
-        }
-        cpal::SampleFormat::F32 => {
-            create_stream::<f32, M>(device, config.into(), shared_frame, shared_joypad, rom)
-        }
-        cpal::SampleFormat::F64 => {
-            create_stream::<f64, M>(device, config.into(), shared_frame, shared_joypad, rom)
-        }
+        cpal::SampleFormat::U64 => create_stream::<u64, M>(
+            device,
+            config.into(),
+            shared_frame,
+            shared_input,
+            emulator,
+            mbc,
+        ),
+        cpal::SampleFormat::F32 => create_stream::<f32, M>(
+            device,
+            config.into(),
+            shared_frame,
+            shared_input,
+            emulator,
+            mbc,
+        ),
+        cpal::SampleFormat::F64 => create_stream::<f64, M>(
+            device,
+            config.into(),
+            shared_frame,
+            shared_input,
+            emulator,
+            mbc,
+        ),
         sample_format => panic!("Unsupported sample format '{sample_format}'"),
     };
     stream.play().unwrap();
@@ -62,21 +129,17 @@ pub fn spawn_emulator<M: Model>(
     stream
 }
 
-fn create_stream<T, M: Model>(
+fn create_stream<T, M: Display>(
     device: &cpal::Device,
     config: cpal::StreamConfig,
-    shared_frame: SyncSender<Frame<<M::ScanlineBuilder as ScanlineBuilder>::Scanline>>,
-    shared_joypad: Arc<RwLock<JoypadInput>>,
-    rom: Vec<u8>,
+    shared_frame: SyncSender<M::Frame>,
+    shared_input: Arc<RwLock<Input>>,
+    mut emulator: Emulator<M>,
+    mut mbc: SendMbc,
 ) -> cpal::Stream
 where
     T: SizedSample + FromSample<f32>,
 {
-    // don't forget to use arc or you will clone the rom for each save state
-    let (_, mut mbc) =
-        get_mbc_send(Arc::from(rom.into_boxed_slice()), InstantRtc::default()).unwrap();
-    let mut emulator = Emulator::<M>::default();
-
     let config = StreamConfig {
         channels: 2,
         // same as web
@@ -93,16 +156,28 @@ where
     let base = SYSTEM_CLOCK_FREQUENCY / sample_rate;
     let remainder = SYSTEM_CLOCK_FREQUENCY % sample_rate;
     let mut error = 0;
-    let mut current_frame =
-        [<M::ScanlineBuilder as ScanlineBuilder>::Scanline::default(); HEIGHT as usize];
+    let mut current_frame = M::new_frame();
     let mut mixer = Mixer::new(sample_rate as f32, noise, short_noise);
+    let mut was_cpu_locked_up = false;
+    let mut is_rumbling = false;
+    let mut cheats = Cheats::default();
+    let mut cheats_generation = None;
 
     device
         .build_output_stream(
             &config,
             move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
-                if let Ok(input) = shared_joypad.try_read() {
-                    emulator.set_joypad(*input);
+                if let Ok(input) = shared_input.try_read() {
+                    emulator.set_joypad(input.joypad);
+                    mbc.set_tilt(input.tilt);
+                    if cheats_generation != Some(input.cheats_generation) {
+                        cheats.clone_from(&input.cheats);
+                        cheats_generation = Some(input.cheats_generation);
+                    }
+                }
+                if !was_cpu_locked_up && emulator.get_cpu().is_locked_up {
+                    was_cpu_locked_up = true;
+                    log::warn!("The CPU has executed an illegal opcode and is locked up");
                 }
                 for frame in data.as_chunks_mut::<2>().0 {
                     let mut cycles = base;
@@ -114,20 +189,30 @@ where
                     }
 
                     for _ in 0..cycles {
-                        emulator.execute(mbc.as_mut());
-                        if let Some(scanline) = emulator.get_ppu().get_scanline_if_ready() {
-                            current_frame[usize::from(emulator.get_ppu().get_ly())] = *scanline;
-                            if emulator.get_ppu().get_ly() == HEIGHT - 1
-                                && let Err(std::sync::mpsc::TrySendError::Disconnected(_)) =
-                                    shared_frame.try_send(current_frame)
-                            {
-                                panic!()
+                        emulator.execute(&mut cheats.wrap(mbc.as_mut()));
+                        if emulator.get_scanline_if_ready().is_some() {
+                            M::update_frame(&emulator, &mut current_frame);
+                            if emulator.get_ppu().get_ly() == HEIGHT - 1 {
+                                if let Err(std::sync::mpsc::TrySendError::Disconnected(_)) =
+                                    shared_frame.try_send(current_frame.clone())
+                                {
+                                    panic!()
+                                }
+                                cheats.apply_game_shark(&mut emulator, mbc.as_mut());
+                                if let Some(intensity) = mbc.take_rumble_intensity()
+                                    && (intensity > 0.0) != is_rumbling
+                                {
+                                    is_rumbling = intensity > 0.0;
+                                    log::info!("Rumble intensity: {:.0}%", intensity * 100.0);
+                                }
                             }
                         }
                     }
 
                     let sample = sample_index as f32 / sample_rate as f32;
-                    let mut sampler = mixer.mix(emulator.get_apu().get_sampler(), sample);
+                    let mut sampler = mixer
+                        .mix(emulator.get_apu().get_sampler(), sample)
+                        .with_cartridge_tone(mbc.get_tone_frequency());
 
                     frame[0] = T::from_sample(sampler.sample_left());
                     frame[1] = T::from_sample(sampler.sample_right());
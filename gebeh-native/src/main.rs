@@ -1,16 +1,23 @@
+mod camera;
+mod display;
 mod emulator_loop;
 
-use std::sync::{Arc, RwLock};
+use std::{
+    path::PathBuf,
+    sync::{Arc, RwLock, mpsc::RecvTimeoutError},
+    time::Duration,
+};
 
 use cpal::traits::HostTrait;
-use gebeh::Frame;
+use gebeh::InstantRtc;
 use gebeh_core::{
-    Cgb, Dmg, HEIGHT, Model, WIDTH,
-    joypad::JoypadInput,
-    mbc::{CartridgeType, get_factor_8_kib_ram, get_factor_32_kib_rom},
-    ppu::scanline::{Scanline, ScanlineBuilder},
+    Cgb, Dmg, Sgb,
+    power_on::{MemoryFill, PowerOnMemory},
+};
+use gebeh_front_helper::{
+    Cheats, ConfigError, EmulatorConfig, ModelPolicy, RomHeader, SelectedModel, apply_patch,
+    extract_rom, find_boot_rom, find_rom, get_cartridge_type_with_entry, get_header_rom,
 };
-use gebeh_front_helper::{Compatibility, get_compatibility, get_title_from_rom};
 use pixels::{Pixels, PixelsBuilder, SurfaceTexture};
 use winit::{
     dpi::LogicalSize,
@@ -20,7 +27,11 @@ use winit::{
     window::{Window, WindowBuilder},
 };
 
-use crate::emulator_loop::spawn_emulator;
+use crate::{
+    camera::CameraFile,
+    display::Display,
+    emulator_loop::{Input, spawn_emulator},
+};
 
 fn get_pixels_from_window(window: &Window, width: u32, height: u32) -> Pixels<'_> {
     let window_size = window.inner_size();
@@ -31,60 +42,189 @@ fn get_pixels_from_window(window: &Window, width: u32, height: u32) -> Pixels<'_
         .unwrap()
 }
 
-pub enum Mode {
-    CgbWhenExplicit,
-    DmgWhenPossible,
-    AlwaysCgb,
+fn take_option(args: &mut Vec<String>, name: &str) -> Option<String> {
+    let index = args.iter().position(|arg| arg == name)?;
+    args.remove(index);
+    if index >= args.len() {
+        panic!("Please provide a value after {name}");
+    }
+    Some(args.remove(index))
+}
+
+fn take_flag(args: &mut Vec<String>, name: &str) -> bool {
+    let Some(index) = args.iter().position(|arg| arg == name) else {
+        return false;
+    };
+    args.remove(index);
+    true
 }
 
-fn main() {
-    color_eyre::install().unwrap();
+fn main() -> color_eyre::Result<()> {
+    color_eyre::install()?;
     env_logger::init();
 
-    let mut args = std::env::args();
+    let mut args: Vec<String> = std::env::args().collect();
+
+    // the options can be given anywhere, the other arguments are positional
+    let patch = take_option(&mut args, "--patch")
+        .map(std::fs::read)
+        .transpose()?;
+    // the file to load from an archive
+    let entry = take_option(&mut args, "--entry");
+    // Game Genie or GameShark codes, more can be typed in the terminal while playing
+    let mut cheats = Cheats::default();
+    while let Some(code) = take_option(&mut args, "--cheat") {
+        cheats.add(&code)?;
+    }
+    // starts the cartridge directly without the logo
+    let skip_boot_rom = take_flag(&mut args, "--skip-boot-rom");
+    // a dump of a genuine boot ROM, replaces the built-in one
+    let boot_rom = take_option(&mut args, "--boot-rom")
+        .map(std::fs::read)
+        .transpose()?;
+    // dmg0, dmg or mgb for the DMG, sgb or sgb2 for the SGB, cgb0, cgb or agb for the CGB
+    let revision = take_option(&mut args, "--revision");
+    // zero, ones, pattern or random, the content of the memory at power-on
+    let parse_fill = |name: String| {
+        MemoryFill::from_name(&name).unwrap_or_else(|| panic!("{name} is not a memory fill"))
+    };
+    let fill = take_option(&mut args, "--fill").map(parse_fill);
+    // for the random fill, the same seed gives the same memory
+    let seed = take_option(&mut args, "--seed")
+        .map(|seed| seed.parse::<u64>())
+        .transpose()?
+        .unwrap_or_default();
+    let mut memory = PowerOnMemory::new(fill.unwrap_or_default(), seed);
+    // like --fill-wram ones, replaces --fill for a single region
+    for region in PowerOnMemory::REGION_NAMES {
+        if let Some(fill) = take_option(&mut args, &format!("--fill-{region}")) {
+            *memory.get_fill_mut(region).unwrap() = parse_fill(fill);
+        }
+    }
+    if let Some(boot_rom) = &boot_rom
+        && !skip_boot_rom
+    {
+        match find_boot_rom(boot_rom) {
+            Some(kind) => println!("Boot ROM: {kind:?}"),
+            None => log::warn!("Unknown boot ROM dump"),
+        }
+    }
+
+    let mut args = args.into_iter();
 
-    let rom = std::fs::read(
+    let file = std::fs::read(
         args.nth(1)
             .expect("Please provide a path as first argument"),
     )
     .unwrap();
+    let mut rom = extract_rom(&file, entry.as_deref())?.into_owned();
 
-    let mode = match args.next().map(|mode| mode.to_lowercase()).as_deref() {
-        Some("cgb") => Mode::AlwaysCgb,
-        Some("dmg") => Mode::DmgWhenPossible,
-        _ => Mode::CgbWhenExplicit,
+    if let Some(patch) = patch {
+        rom = apply_patch(&rom, &patch)?;
+    }
+
+    let policy = match args.next().map(|mode| mode.to_lowercase()).as_deref() {
+        Some("cgb") => ModelPolicy::AlwaysCgb,
+        Some("dmg") => ModelPolicy::DmgWhenPossible,
+        Some("sgb") => ModelPolicy::SgbWhenPossible,
+        _ => ModelPolicy::CgbWhenExplicit,
     };
+    let config = EmulatorConfig {
+        policy,
+        revision,
+        boot_rom: boot_rom.map(Vec::into_boxed_slice),
+        skip_boot_rom,
+        memory,
+        rtc: InstantRtc::default(),
+    };
+
+    // a PNG file seen by the Pocket Camera
+    let camera = CameraFile::new(args.next().map(PathBuf::from));
 
-    match (get_compatibility(&rom), mode) {
-        (Compatibility::Dmg, Mode::CgbWhenExplicit | Mode::DmgWhenPossible)
-        | (Compatibility::Both, Mode::DmgWhenPossible) => {
+    let header = RomHeader::parse(&get_header_rom(&rom))?;
+    println!("Title: {}", header.title);
+    let entry = find_rom(&rom);
+    if let Some(entry) = entry {
+        println!("Known dump: {}", entry.name);
+    }
+    println!(
+        "Cartridge type: {:?}",
+        get_cartridge_type_with_entry(&rom, entry)?
+    );
+    println!("ROM size: {} KiB", header.rom_size / 1024);
+    println!("RAM size: {} KiB", header.ram_size / 1024);
+    if !RomHeader::is_header_checksum_valid(&rom) {
+        log::warn!("Invalid header checksum, the real hardware would refuse to boot this ROM");
+    }
+
+    // same as config.select_model, without hashing the ROM again
+    match config.policy.select(&rom, entry) {
+        SelectedModel::Sgb => {
+            println!("Running in SGB mode");
+            execute::<Sgb>(rom, camera, cheats, &config)?;
+        }
+        SelectedModel::Dmg => {
             println!("Running in DMG mode");
-            execute::<Dmg>(rom);
+            execute::<Dmg>(rom, camera, cheats, &config)?;
         }
-        (Compatibility::Cgb, _)
-        | (_, Mode::AlwaysCgb)
-        | (Compatibility::Both, Mode::CgbWhenExplicit) => {
+        SelectedModel::Cgb => {
             println!("Running in CGB mode");
-            execute::<Cgb>(rom);
+            execute::<Cgb>(rom, camera, cheats, &config)?;
         }
     }
+
+    Ok(())
 }
 
-fn execute<M: Model>(rom: Vec<u8>) {
-    println!("Title: {}", get_title_from_rom(&rom));
+// one code per line, "-N" disables the Nth cheat and "+N" enables it again
+fn read_cheats_from_stdin(input: Arc<RwLock<Input>>) {
+    for line in std::io::stdin().lines() {
+        let Ok(line) = line else {
+            return;
+        };
+        let line = line.trim();
+        let Input {
+            cheats,
+            cheats_generation,
+            ..
+        } = &mut *input.write().unwrap();
+        let toggle = line
+            .strip_prefix('-')
+            .map(|index| (index, false))
+            .or_else(|| line.strip_prefix('+').map(|index| (index, true)));
+        if let Some((index, is_enabled)) = toggle
+            && let Ok(index) = index.parse()
+        {
+            cheats.set_enabled(index, is_enabled);
+            *cheats_generation = cheats_generation.wrapping_add(1);
+            continue;
+        }
+        match cheats.add(line) {
+            Ok(index) => {
+                *cheats_generation = cheats_generation.wrapping_add(1);
+                println!("Cheat {index} added");
+            }
+            Err(err) => eprintln!("{err}"),
+        }
+    }
+}
 
-    // https://gbdev.io/pandocs/The_Cartridge_Header.html#0147--cartridge-type
-    let cartridge_type = CartridgeType::try_from(rom[0x147]).unwrap();
-    println!("Cartridge type: {cartridge_type:?}");
-    // https://gbdev.io/pandocs/The_Cartridge_Header.html#0148--rom-size
-    println!("ROM size: {} KiB", get_factor_32_kib_rom(&rom) * 32);
-    println!("RAM size: {} KiB", get_factor_8_kib_ram(&rom) * 8);
+fn execute<M: Display>(
+    rom: Vec<u8>,
+    camera: CameraFile,
+    cheats: Cheats,
+    config: &EmulatorConfig<InstantRtc>,
+) -> Result<(), ConfigError> {
+    // don't forget to use arc or you will clone the rom for each save state
+    let (_, emulator, mbc) =
+        config.build_send::<M, _, _>(Arc::from(rom.into_boxed_slice()), camera)?;
 
     let event_loop = EventLoop::new().unwrap();
 
+    let (width, height) = M::SIZE;
     let window = {
-        let size = LogicalSize::new(WIDTH as f64, HEIGHT as f64);
-        let scaled_size = LogicalSize::new(WIDTH as f64 * 4.0, HEIGHT as f64 * 4.0);
+        let size = LogicalSize::new(width as f64, height as f64);
+        let scaled_size = LogicalSize::new(width as f64 * 4.0, height as f64 * 4.0);
         WindowBuilder::new()
             .with_title("gebeh")
             .with_inner_size(scaled_size)
@@ -93,14 +233,17 @@ fn execute<M: Model>(rom: Vec<u8>) {
             .unwrap()
     };
 
-    let mut pixels = get_pixels_from_window(&window, WIDTH.into(), HEIGHT.into());
+    let mut pixels = get_pixels_from_window(&window, width, height);
 
-    let joypad: Arc<RwLock<JoypadInput>> = Default::default();
-    let (tx_frame, rx_frame) = std::sync::mpsc::sync_channel::<
-        Frame<<M::ScanlineBuilder as ScanlineBuilder>::Scanline>,
-    >(2);
+    let input = Arc::new(RwLock::new(Input {
+        cheats,
+        ..Default::default()
+    }));
+    let stdin_input = input.clone();
+    std::thread::spawn(move || read_cheats_from_stdin(stdin_input));
+    let (tx_frame, rx_frame) = std::sync::mpsc::sync_channel::<M::Frame>(2);
 
-    let shared_joypad = joypad.clone();
+    let shared_input = input.clone();
 
     let host = cpal::default_host();
 
@@ -108,7 +251,7 @@ fn execute<M: Model>(rom: Vec<u8>) {
         .default_output_device()
         .expect("failed to find output device");
 
-    let _handle = spawn_emulator::<M>(&device, tx_frame, shared_joypad, rom);
+    let _handle = spawn_emulator::<M>(&device, tx_frame, shared_input, emulator, mbc);
 
     event_loop
         .run(|event, elwt| match event {
@@ -117,14 +260,12 @@ fn execute<M: Model>(rom: Vec<u8>) {
                 window_id,
                 ..
             } if window_id == window.id() => {
-                for (pixel, color) in pixels.frame_mut().as_chunks_mut::<4>().0.iter_mut().zip(
-                    rx_frame
-                        .recv()
-                        .unwrap()
-                        .iter()
-                        .flat_map(|scanline| scanline.iter_colors()),
-                ) {
-                    *pixel = color.into();
+                // in STOP mode no frame comes, the window keeps the last image and handles the
+                // keys waking the console
+                match rx_frame.recv_timeout(Duration::from_millis(100)) {
+                    Ok(frame) => M::draw(&frame, pixels.frame_mut()),
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => panic!(),
                 }
 
                 pixels.render().unwrap();
@@ -150,7 +291,7 @@ fn execute<M: Model>(rom: Vec<u8>) {
                     },
                 ..
             } => {
-                let mut joypad = joypad.write().unwrap();
+                let Input { joypad, tilt, .. } = &mut *input.write().unwrap();
                 match keycode {
                     KeyCode::KeyA => joypad.a = false,
                     KeyCode::KeyB => joypad.b = false,
@@ -160,6 +301,8 @@ fn execute<M: Model>(rom: Vec<u8>) {
                     KeyCode::ArrowDown => joypad.down = false,
                     KeyCode::Enter => joypad.start = false,
                     KeyCode::Tab => joypad.select = false,
+                    KeyCode::KeyJ | KeyCode::KeyL => tilt.x = 0.0,
+                    KeyCode::KeyI | KeyCode::KeyK => tilt.y = 0.0,
                     _ => {}
                 }
             }
@@ -176,7 +319,7 @@ fn execute<M: Model>(rom: Vec<u8>) {
                     },
                 ..
             } => {
-                let mut joypad = joypad.write().unwrap();
+                let Input { joypad, tilt, .. } = &mut *input.write().unwrap();
                 match keycode {
                     KeyCode::Escape => elwt.exit(),
                     KeyCode::KeyA => joypad.a = true,
@@ -187,6 +330,11 @@ fn execute<M: Model>(rom: Vec<u8>) {
                     KeyCode::ArrowDown => joypad.down = true,
                     KeyCode::Enter => joypad.start = true,
                     KeyCode::Tab => joypad.select = true,
+                    // IJKL to tilt the cartridges with an accelerometer
+                    KeyCode::KeyJ => tilt.x = -1.0,
+                    KeyCode::KeyL => tilt.x = 1.0,
+                    KeyCode::KeyI => tilt.y = -1.0,
+                    KeyCode::KeyK => tilt.y = 1.0,
                     _ => {}
                 }
             }
@@ -197,4 +345,6 @@ fn execute<M: Model>(rom: Vec<u8>) {
             _ => {}
         })
         .unwrap();
+
+    Ok(())
 }
@@ -8,6 +8,9 @@ import GamepadButton from "./gamepad-button.tsx";
 import Dpad from "./dpad";
 import initNode from "./init-node.ts";
 import RomInput from "./rom-input.tsx";
+import CheatInput from "./cheat-input.tsx";
+import BootRomInput from "./boot-rom-input.tsx";
+import MemoryFillInput from "./memory-fill-input.tsx";
 import "./bulma.scss";
 import Button from "./bulma/button.tsx";
 import { faArrowLeft } from "@fortawesome/free-solid-svg-icons/faArrowLeft";
@@ -117,6 +120,12 @@ function Settings({
     port.postMessage({ type: "compatibilityMode", value: mode } satisfies FromMainMessage, []);
   }, [mode, port]);
 
+  const [skipBootRom, setSkipBootRom] = useState(false);
+
+  useEffect(() => {
+    port.postMessage({ type: "skipBootRom", value: skipBootRom } satisfies FromMainMessage, []);
+  }, [skipBootRom, port]);
+
   return (
     <section className="section" style={{ display: isHidden ? "none" : undefined }}>
       <div className="container">
@@ -169,6 +178,23 @@ function Settings({
             Always CGB
           </label>
         </div>
+        <div className="field">
+          <label className="checkbox">
+            <input
+              type="checkbox"
+              checked={skipBootRom}
+              onChange={(event) => {
+                setSkipBootRom(event.target.checked);
+              }}
+            />{" "}
+            Skip the boot ROM
+          </label>
+        </div>
+        <BootRomInput port={port} />
+        <h5 className="title is-5">Power-on memory</h5>
+        <MemoryFillInput port={port} />
+        <h5 className="title is-5">Cheats</h5>
+        <CheatInput port={port} />
         <h1 className="title">Save</h1>
         {/* to trash the component when hidden and refresh the internal state when mounted */}
         {!isHidden && <SaveSettings />}
@@ -0,0 +1,43 @@
+import type { FromMainMessage } from "./common";
+import { useState } from "react";
+import FileInput from "./bulma/file-input";
+
+// the dumps are applied to the next loaded ROM
+function BootRomInput({ port }: { port: MessagePort }) {
+  const [dmgFileName, setDmgFileName] = useState<string>();
+  const [cgbFileName, setCgbFileName] = useState<string>();
+  const onChange =
+    (model: "dmg" | "cgb", setFileName: (fileName: string) => void) =>
+    async (event: React.ChangeEvent<HTMLInputElement>) => {
+      const file = event.target.files?.item(0);
+      if (file) {
+        setFileName(file.name);
+        const bytes = new Uint8Array(await file.arrayBuffer());
+        port.postMessage({ type: "bootRom", model, bytes } satisfies FromMainMessage, [
+          bytes.buffer,
+        ]);
+      } else {
+        console.error("Can't load file");
+      }
+    };
+  return (
+    <div className="field is-grouped">
+      <div className="control">
+        <FileInput
+          label="DMG boot ROM"
+          fileName={dmgFileName}
+          onChange={onChange("dmg", setDmgFileName)}
+        />
+      </div>
+      <div className="control">
+        <FileInput
+          label="CGB boot ROM"
+          fileName={cgbFileName}
+          onChange={onChange("cgb", setCgbFileName)}
+        />
+      </div>
+    </div>
+  );
+}
+
+export default BootRomInput;
@@ -0,0 +1,37 @@
+import type { FromMainMessage } from "./common";
+import { useState } from "react";
+import Button from "./bulma/button";
+import { faPlus } from "@fortawesome/free-solid-svg-icons/faPlus";
+
+// the codes are added to the current game, they are lost when another ROM is loaded
+function CheatInput({ port }: { port: MessagePort }) {
+  const [code, setCode] = useState("");
+  return (
+    <div className="field has-addons">
+      <div className="control">
+        <input
+          className="input"
+          type="text"
+          placeholder="ABC-DEF-GHI or ABCDEFGH"
+          value={code}
+          onChange={(event) => {
+            setCode(event.target.value);
+          }}
+        />
+      </div>
+      <div className="control">
+        <Button
+          label="Add cheat"
+          icon={faPlus}
+          disabled={code.trim() === ""}
+          onClick={() => {
+            port.postMessage({ type: "cheat", code } satisfies FromMainMessage);
+            setCode("");
+          }}
+        />
+      </div>
+    </div>
+  );
+}
+
+export default CheatInput;
@@ -12,12 +12,16 @@ export type FromNodeMessage =
       extra: Uint8Array | undefined;
       title: string;
     }
-  | { type: "serial"; buffer: Uint8Array };
+  | { type: "serial"; buffer: Uint8Array }
+  // between 0 and 1, sent when it changes
+  | { type: "rumble"; intensity: number };
 export type GebehButton = "a" | "b" | "start" | "select" | "left" | "right" | "up" | "down";
 export type FromMainMessage =
   | {
       type: "rom";
       bytes: Uint8Array;
+      // IPS, UPS or BPS
+      patch: Uint8Array | undefined;
       save: Uint8Array | undefined;
       extra: Uint8Array | undefined;
       seconds_since_epoch: number;
@@ -36,7 +40,21 @@ export type FromMainMessage =
       type: "serial";
       buffer: Uint8Array;
     }
-  | { type: "compatibilityMode"; value: CompatibilityMode };
+  | { type: "compatibilityMode"; value: CompatibilityMode }
+  // starts the cartridge directly without the logo
+  | { type: "skipBootRom"; value: boolean }
+  // a dump replacing the built-in boot ROM of the model
+  | { type: "bootRom"; model: "dmg" | "cgb"; bytes: Uint8Array }
+  // the content of a memory region at power-on, applied to the next loaded ROM
+  | { type: "memoryFill"; region: MemoryRegion; fill: MemoryFill }
+  // for the random fill
+  | { type: "memorySeed"; seed: number }
+  // in g, for the cartridges with an accelerometer
+  | { type: "tilt"; x: number; y: number }
+  // Game Genie or GameShark
+  | { type: "cheat"; code: string };
 export const GB_WIDTH = 160;
 export const GB_HEIGHT = 144;
+export type MemoryRegion = "wram" | "hram" | "vram" | "oam" | "cartridge-ram";
+export type MemoryFill = "zero" | "ones" | "pattern" | "random";
 export type CompatibilityMode = "cgb-when-explicit" | "dmg-when-possible" | "always-cgb";
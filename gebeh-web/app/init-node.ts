@@ -3,6 +3,36 @@ import { writeExtra, writeSave } from "./saves";
 import workletURL from "./worklet.ts?worker&url";
 import wasm from "../pkg/gebeh_web_bg.wasm?url";
 
+// the vibration API can't change the strength of the motor so it is switched on and off quickly
+const RUMBLE_PERIOD_MS = 20;
+// the pattern is sent again each time the intensity changes
+const RUMBLE_DURATION_MS = 1000;
+
+function rumble(intensity: number) {
+  // not supported by all browsers
+  if (!("vibrate" in navigator)) {
+    return;
+  }
+  if (intensity === 0) {
+    navigator.vibrate(0);
+    return;
+  }
+  const on = Math.max(1, Math.round(RUMBLE_PERIOD_MS * intensity));
+  const off = RUMBLE_PERIOD_MS - on;
+  if (off === 0) {
+    navigator.vibrate(RUMBLE_DURATION_MS);
+    return;
+  }
+  const pattern: number[] = [];
+  for (let i = 0; i < RUMBLE_DURATION_MS / RUMBLE_PERIOD_MS; i++) {
+    pattern.push(on, off);
+  }
+  navigator.vibrate(pattern);
+}
+
+// m/s²
+const STANDARD_GRAVITY = 9.80665;
+
 async function initNode(): Promise<AudioWorkletNode> {
   const audioContext = new AudioContext();
   await audioContext.audioWorklet.addModule(workletURL);
@@ -34,9 +64,27 @@ async function initNode(): Promise<AudioWorkletNode> {
           if (data.extra) {
             await writeExtra(data.title, data.extra);
           }
+          break;
+        }
+        case "rumble": {
+          rumble(data.intensity);
         }
       }
     });
+    // https://developer.mozilla.org/en-US/docs/Web/API/DeviceMotionEvent/accelerationIncludingGravity
+    window.addEventListener("devicemotion", ({ accelerationIncludingGravity }) => {
+      const x = accelerationIncludingGravity?.x;
+      const y = accelerationIncludingGravity?.y;
+      if (x == null || y == null) {
+        return;
+      }
+      // the device measures the opposite of the gravity
+      port.postMessage({
+        type: "tilt",
+        x: -x / STANDARD_GRAVITY,
+        y: y / STANDARD_GRAVITY,
+      } satisfies FromMainMessage);
+    });
     document.addEventListener("visibilitychange", () => {
       if (document.visibilityState == "visible") {
         port.postMessage({
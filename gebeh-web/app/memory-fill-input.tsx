@@ -0,0 +1,69 @@
+import type { FromMainMessage, MemoryFill, MemoryRegion } from "./common";
+import { useState } from "react";
+
+const REGIONS: { region: MemoryRegion; label: string }[] = [
+  { region: "wram", label: "Work RAM" },
+  { region: "hram", label: "High RAM" },
+  { region: "vram", label: "Video RAM" },
+  { region: "oam", label: "OAM" },
+  { region: "cartridge-ram", label: "Cartridge RAM" },
+];
+
+const FILLS: { fill: MemoryFill; label: string }[] = [
+  { fill: "zero", label: "Zeros" },
+  { fill: "ones", label: "Ones" },
+  { fill: "pattern", label: "Pattern" },
+  { fill: "random", label: "Random" },
+];
+
+// applied to the next loaded ROM, the online games always start with zeros
+function MemoryFillInput({ port }: { port: MessagePort }) {
+  const [seed, setSeed] = useState(0);
+  return (
+    <>
+      <div className="field is-grouped is-grouped-multiline">
+        {REGIONS.map(({ region, label }) => (
+          <div className="control" key={region}>
+            <label className="label">{label}</label>
+            <div className="select">
+              <select
+                defaultValue="zero"
+                onChange={(event) => {
+                  port.postMessage({
+                    type: "memoryFill",
+                    region,
+                    fill: event.target.value as MemoryFill,
+                  } satisfies FromMainMessage);
+                }}
+              >
+                {FILLS.map(({ fill, label }) => (
+                  <option key={fill} value={fill}>
+                    {label}
+                  </option>
+                ))}
+              </select>
+            </div>
+          </div>
+        ))}
+      </div>
+      <div className="field">
+        <label className="label">Seed of the random fill</label>
+        <div className="control">
+          <input
+            className="input"
+            type="number"
+            min={0}
+            value={seed}
+            onChange={(event) => {
+              const value = Math.max(0, Math.trunc(Number(event.target.value))) >>> 0;
+              setSeed(value);
+              port.postMessage({ type: "memorySeed", seed: value } satisfies FromMainMessage);
+            }}
+          />
+        </div>
+      </div>
+    </>
+  );
+}
+
+export default MemoryFillInput;
@@ -2,6 +2,11 @@ import type { FromMainMessage } from "./common";
 import { getExtra, getSave } from "./saves";
 import { useState } from "react";
 import FileInput from "./bulma/file-input";
+import init, { extract_rom } from "../pkg/gebeh_web";
+import wasm from "../pkg/gebeh_web_bg.wasm?url";
+
+// the worklet has its own instance, this one only opens the archives
+let wasmReady: Promise<unknown> | undefined;
 
 function getTitleFromRom(rom: Uint8Array): string {
   const title = rom.slice(0x134, 0x143);
@@ -17,11 +22,31 @@ function getTitleFromRom(rom: Uint8Array): string {
 
 function RomInput({ port, onLoad }: { port: MessagePort; onLoad?: () => void }) {
   const [fileName, setFileName] = useState<string>();
+  const [patchFileName, setPatchFileName] = useState<string>();
+  // applied to the next loaded ROM only
+  const [patch, setPatch] = useState<Uint8Array>();
+  const onPatchChange = async (event: React.ChangeEvent<HTMLInputElement>) => {
+    const file = event.target.files?.item(0);
+    if (file) {
+      setPatchFileName(file.name);
+      setPatch(new Uint8Array(await file.arrayBuffer()));
+    } else {
+      console.error("Can't load file");
+    }
+  };
   const onFileChange = async (event: React.ChangeEvent<HTMLInputElement>) => {
     const file = event.target.files?.item(0);
     if (file) {
       setFileName(file.name);
-      const bytes = new Uint8Array(await file.arrayBuffer());
+      wasmReady ??= init({ module_or_path: wasm });
+      await wasmReady;
+      let bytes: Uint8Array;
+      try {
+        bytes = extract_rom(new Uint8Array(await file.arrayBuffer()));
+      } catch (error) {
+        console.error(error);
+        return;
+      }
 
       const title = getTitleFromRom(bytes);
       const save = await getSave(title);
@@ -33,25 +58,42 @@ function RomInput({ port, onLoad }: { port: MessagePort; onLoad?: () => void })
       if (extra) {
         transfer.push(extra.buffer);
       }
+      if (patch) {
+        transfer.push(patch.buffer);
+      }
 
       port.postMessage(
         {
           type: "rom",
           bytes,
+          patch,
           save,
           extra,
           seconds_since_epoch: Date.now() / 1000,
         } satisfies FromMainMessage,
         transfer,
       );
+      // a patch is made for one ROM, it must not be applied to the next one
+      setPatch(undefined);
+      setPatchFileName(undefined);
       onLoad?.();
     } else {
       console.error("Can't load file");
     }
   };
   return (
-    <div className="field">
-      <FileInput label="Load ROM" fileName={fileName} onChange={onFileChange} color="is-success" />
+    <div className="field is-grouped">
+      <div className="control">
+        <FileInput
+          label="Load ROM"
+          fileName={fileName}
+          onChange={onFileChange}
+          color="is-success"
+        />
+      </div>
+      <div className="control">
+        <FileInput label="Load patch" fileName={patchFileName} onChange={onPatchChange} />
+      </div>
     </div>
   );
 }
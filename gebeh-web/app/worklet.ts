@@ -33,6 +33,7 @@ class WasmProcessor extends AudioWorkletProcessor implements AudioWorkletProcess
   emulator?: WebEmulator;
   poor_mans_time = 0;
   isMessagesEnabled = true;
+  rumbleIntensity = 0;
 
   constructor() {
     super();
@@ -40,14 +41,19 @@ class WasmProcessor extends AudioWorkletProcessor implements AudioWorkletProcess
       switch (data.type) {
         case "rom": {
           console.log({ sampleRate });
-          this.emulator?.init_emulator(
-            new Uint8Array(data.bytes),
-            data.save,
-            data.extra,
-            sampleRate,
-            data.seconds_since_epoch,
-            currentTime,
-          );
+          try {
+            this.emulator?.init_emulator(
+              new Uint8Array(data.bytes),
+              data.patch,
+              data.save,
+              data.extra,
+              sampleRate,
+              data.seconds_since_epoch,
+              currentTime,
+            );
+          } catch (error) {
+            console.error("Can't load the ROM:", error);
+          }
           break;
         }
         case "wasm": {
@@ -135,6 +141,46 @@ class WasmProcessor extends AudioWorkletProcessor implements AudioWorkletProcess
 
           break;
         }
+        case "tilt": {
+          this.emulator?.set_tilt(data.x, data.y);
+          break;
+        }
+        case "cheat": {
+          try {
+            this.emulator?.add_cheat(data.code);
+          } catch (error) {
+            console.error("Can't add the cheat:", error);
+          }
+          break;
+        }
+        case "skipBootRom": {
+          this.emulator?.set_skip_boot_rom(data.value);
+          break;
+        }
+        case "memoryFill": {
+          try {
+            this.emulator?.set_memory_fill(data.region, data.fill);
+          } catch (error) {
+            console.error("Can't set the memory fill:", error);
+          }
+          break;
+        }
+        case "memorySeed": {
+          this.emulator?.set_memory_seed(data.seed);
+          break;
+        }
+        case "bootRom": {
+          try {
+            if (data.model === "dmg") {
+              this.emulator?.set_dmg_boot_rom(data.bytes);
+            } else {
+              this.emulator?.set_cgb_boot_rom(data.bytes);
+            }
+          } catch (error) {
+            console.error("Can't load the boot ROM:", error);
+          }
+          break;
+        }
         case "compatibilityMode": {
           if (!this.emulator) {
             throw new Error("Emulator not ready for serial");
@@ -202,6 +248,16 @@ class WasmProcessor extends AudioWorkletProcessor implements AudioWorkletProcess
       );
     }
 
+    // navigator.vibrate is not available in the worklet
+    const rumbleIntensity = emulator.get_rumble_intensity();
+    if (rumbleIntensity !== this.rumbleIntensity) {
+      this.rumbleIntensity = rumbleIntensity;
+      this.port.postMessage({
+        type: "rumble",
+        intensity: rumbleIntensity,
+      } satisfies FromNodeMessage);
+    }
+
     // https://developer.mozilla.org/en-US/docs/Web/API/AudioWorkletProcessor/process
     // Citation: audio data blocks are always 128 frames long
     // loop every ~5 seconds
@@ -2,11 +2,16 @@ use std::{cell::Cell, rc::Rc};
 
 use arrayvec::ArrayVec;
 use gebeh_core::{
-    Cgb, Dmg, Emulator, EmulatorExt, HEIGHT, Model, SYSTEM_CLOCK_FREQUENCY, WIDTH, apu::Mixer,
-    joypad::JoypadInput, ppu::scanline::Scanline, serial::Serial,
+    Cgb, Dmg, Emulator, EmulatorExt, HEIGHT, Model, SYSTEM_CLOCK_FREQUENCY, WIDTH,
+    apu::Mixer,
+    joypad::{JoypadInput, Tilt},
+    power_on::MemoryFill,
+    ppu::scanline::Scanline,
+    serial::Serial,
 };
 use gebeh_front_helper::{
-    Compatibility, EasyMbc, get_compatibility, get_mbc, get_noise, get_title_from_rom,
+    Cheats, EasyMbc, EmulatorConfig, ModelPolicy, SelectedModel, TestPattern, apply_patch,
+    get_header_rom, get_noise, get_title_from_rom, load_boot_rom, load_state, save_state,
 };
 use wasm_bindgen::prelude::*;
 use web_sys::{
@@ -21,9 +26,8 @@ use crate::rtc::AudioRtc;
 mod rtc;
 
 #[wasm_bindgen]
-#[derive(Default, Clone, Copy)]
+#[derive(Clone, Copy)]
 pub enum Mode {
-    #[default]
     CgbWhenExplicit,
     DmgWhenPossible,
     AlwaysCgb,
@@ -51,13 +55,20 @@ struct WebEmulatorInner<M: Model> {
     start_time: u64,
     seconds_since_epoch: Rc<Cell<u64>>,
     network: Option<RollbackSerial<M>>,
+    // updated at each frame
+    rumble_intensity: f32,
+    // not applied while connected, the peer would desync
+    cheats: Cheats,
 }
 
 #[wasm_bindgen]
 #[derive(Default)]
 pub struct WebEmulator {
     inner: Inner,
-    mode: Mode,
+    config: EmulatorConfig,
+    // dumps given by the user, the built-in boot ROMs are used otherwise
+    dmg_boot_rom: Option<Box<[u8]>>,
+    cgb_boot_rom: Option<Box<[u8]>>,
 }
 
 impl<M: Model> WebEmulatorInner<M> {
@@ -69,7 +80,9 @@ impl<M: Model> WebEmulatorInner<M> {
         self.emulator.set_joypad(joypad);
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
+        config: EmulatorConfig,
         rom: Box<[u8]>,
         save: Option<Box<[u8]>>,
         extra: Option<Box<[u8]>>,
@@ -77,17 +90,16 @@ impl<M: Model> WebEmulatorInner<M> {
         seconds_since_epoch: u32,
         audio_time: u32,
         enable_network: bool,
-    ) -> Option<Self> {
+    ) -> Result<Self, String> {
         console::log_1(&JsValue::from_str("Loading rom"));
         let start_time = seconds_since_epoch - audio_time;
         let seconds_since_epoch = Rc::new(Cell::new(u64::from(seconds_since_epoch)));
-        // rc to easily clone the mbc for the rollback netcode
-        let Some((cartridge_type, mut mbc)) =
-            get_mbc(Rc::from(rom), AudioRtc::new(seconds_since_epoch.clone()))
-        else {
-            console::error_1(&JsValue::from_str("MBC type not recognized"));
-            return None;
-        };
+        // rc to easily clone the mbc for the rollback netcode,
+        // there is no webcam yet so the Pocket Camera sees a test pattern
+        let (cartridge_type, emulator, mut mbc) = config
+            .with_rtc(AudioRtc::new(seconds_since_epoch.clone()))
+            .build::<M, _, _>(Rc::from(rom), TestPattern)
+            .map_err(|err| err.to_string())?;
         if let Some(save) = save {
             console::log_1(&JsValue::from_str("Loading save"));
             mbc.load_saved_ram(&save);
@@ -101,10 +113,10 @@ impl<M: Model> WebEmulatorInner<M> {
         if cartridge_type.has_battery() {
             console::log_1(&JsValue::from_str("Saves enabled"));
         }
-        Some(Self {
+        Ok(Self {
             mbc,
             is_save_enabled: cartridge_type.has_battery(),
-            emulator: Default::default(),
+            emulator,
             sample_index: 0,
             error: 0,
             mixer: Mixer::new(sample_rate, get_noise(false), get_noise(true)),
@@ -116,6 +128,8 @@ impl<M: Model> WebEmulatorInner<M> {
             } else {
                 None
             },
+            rumble_intensity: 0.0,
+            cheats: Cheats::default(),
         })
     }
 
@@ -155,7 +169,8 @@ impl<M: Model> WebEmulatorInner<M> {
                         synchro.execute_and_take_snapshot(&mut self.emulator, self.mbc.as_mut()),
                     );
                 } else {
-                    self.emulator.execute(self.mbc.as_mut());
+                    self.emulator
+                        .execute(&mut self.cheats.wrap(self.mbc.as_mut()));
                 }
                 self.handle_graphics(on_new_frame);
             }
@@ -170,14 +185,15 @@ impl<M: Model> WebEmulatorInner<M> {
         let sample = self.sample_index as f32 / sample_rate as f32;
         let mut sampler = self
             .mixer
-            .mix(self.emulator.get_apu().get_sampler(), sample);
+            .mix(self.emulator.get_apu().get_sampler(), sample)
+            .with_cartridge_tone(self.mbc.get_tone_frequency());
         // 2 minutes without popping (sample_index must not be huge to prevent precision errors)
         self.sample_index = self.sample_index.wrapping_add(1) % (sample_rate * 2 * 60);
         (sampler.sample_left(), sampler.sample_right())
     }
 
     fn handle_graphics(&mut self, on_new_frame: &js_sys::Function) {
-        let Some(scanline) = self.emulator.get_ppu().get_scanline_if_ready() else {
+        let Some(scanline) = self.emulator.get_screen_scanline_if_ready() else {
             return;
         };
 
@@ -189,14 +205,25 @@ impl<M: Model> WebEmulatorInner<M> {
             *input = color.into();
         }
 
-        if self.emulator.get_ppu().get_ly() == HEIGHT - 1
-            && let Err(err) = on_new_frame.call1(
-                &JsValue::null(),
-                &js_sys::Uint16Array::new_from_slice(&self.current_frame),
-            )
-        {
+        if self.emulator.get_ppu().get_ly() != HEIGHT - 1 {
+            return;
+        }
+
+        if let Err(err) = on_new_frame.call1(
+            &JsValue::null(),
+            &js_sys::Uint16Array::new_from_slice(&self.current_frame),
+        ) {
             console::error_1(&err);
         }
+
+        if let Some(intensity) = self.mbc.take_rumble_intensity() {
+            self.rumble_intensity = intensity;
+        }
+
+        if self.network.is_none() {
+            self.cheats
+                .apply_game_shark(&mut self.emulator, self.mbc.as_mut());
+        }
     }
 
     pub fn get_save(&self) -> Option<Save> {
@@ -214,15 +241,76 @@ impl<M: Model> WebEmulatorInner<M> {
             } else {
                 None
             },
-            game_title: get_title_from_rom(self.mbc.get_rom()).to_owned(),
+            game_title: get_title_from_rom(&get_header_rom(self.mbc.get_rom())),
         })
     }
+
+    pub fn save_state(&self) -> Box<[u8]> {
+        save_state(&self.emulator, self.mbc.as_ref()).into()
+    }
+
+    pub fn load_state(&mut self, state: &[u8]) -> Result<(), String> {
+        // the peer would desync
+        if self.network.is_some() {
+            return Err("Can't load a state while connected".to_owned());
+        }
+        self.emulator = load_state(state, self.mbc.as_mut()).map_err(|err| err.to_string())?;
+        Ok(())
+    }
+
+    pub fn add_cheat(&mut self, code: &str) -> Result<usize, String> {
+        if self.network.is_some() {
+            return Err("Can't add a cheat while connected".to_owned());
+        }
+        self.cheats.add(code).map_err(|err| err.to_string())
+    }
 }
 
 #[wasm_bindgen]
 impl WebEmulator {
     pub fn set_mode(&mut self, mode: Mode) {
-        self.mode = mode;
+        self.config.policy = match mode {
+            Mode::CgbWhenExplicit => ModelPolicy::CgbWhenExplicit,
+            Mode::DmgWhenPossible => ModelPolicy::DmgWhenPossible,
+            Mode::AlwaysCgb => ModelPolicy::AlwaysCgb,
+        };
+    }
+
+    pub fn set_skip_boot_rom(&mut self, skip_boot_rom: bool) {
+        self.config.skip_boot_rom = skip_boot_rom;
+    }
+
+    // applied to the next loaded ROM, the names are the ones of the native frontend
+    pub fn set_memory_fill(&mut self, region: &str, fill: &str) -> Result<(), String> {
+        let fill =
+            MemoryFill::from_name(fill).ok_or_else(|| format!("{fill} is not a memory fill"))?;
+        let region = self
+            .config
+            .memory
+            .get_fill_mut(region)
+            .ok_or_else(|| format!("{region} is not a memory region"))?;
+        *region = fill;
+        Ok(())
+    }
+
+    // a JS number, for the random fill
+    pub fn set_memory_seed(&mut self, seed: u32) {
+        self.config.memory.seed = u64::from(seed);
+    }
+
+    // applied to the next loaded ROM, a known dump selects its revision
+    pub fn set_dmg_boot_rom(&mut self, dump: Box<[u8]>) -> Result<(), String> {
+        let (_, kind) = load_boot_rom::<Dmg>(&dump, None).map_err(|err| err.to_string())?;
+        log::info!("DMG boot ROM: {kind:?}");
+        self.dmg_boot_rom = Some(dump);
+        Ok(())
+    }
+
+    pub fn set_cgb_boot_rom(&mut self, dump: Box<[u8]>) -> Result<(), String> {
+        let (_, kind) = load_boot_rom::<Cgb>(&dump, None).map_err(|err| err.to_string())?;
+        log::info!("CGB boot ROM: {kind:?}");
+        self.cgb_boot_rom = Some(dump);
+        Ok(())
     }
 
     #[wasm_bindgen(constructor)]
@@ -232,15 +320,24 @@ impl WebEmulator {
         Default::default()
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn init_emulator(
         &mut self,
         rom: Box<[u8]>,
+        patch: Option<Box<[u8]>>,
         save: Option<Box<[u8]>>,
         extra: Option<Box<[u8]>>,
         sample_rate: f32,
         seconds_since_epoch: u32,
         audio_time: u32,
-    ) {
+    ) -> Result<(), String> {
+        let rom = match patch {
+            Some(patch) => apply_patch(&rom, &patch)
+                .map_err(|error| error.to_string())?
+                .into_boxed_slice(),
+            None => rom,
+        };
+
         let network_enabled = match &self.inner {
             Inner::Dmg(web_emulator_inner) => web_emulator_inner.network.is_some(),
             Inner::Cgb(web_emulator_inner) => web_emulator_inner.network.is_some(),
@@ -248,9 +345,30 @@ impl WebEmulator {
             Inner::None => false,
         };
 
-        let inner = match (get_compatibility(&rom), self.mode) {
-            (Compatibility::Dmg, Mode::CgbWhenExplicit | Mode::DmgWhenPossible)
-            | (Compatibility::Both, Mode::DmgWhenPossible) => WebEmulatorInner::new(
+        let model = self.config.select_model(&rom);
+        if model == SelectedModel::Sgb {
+            log::warn!("The Super Game Boy is not supported on the web, running on a DMG instead");
+        }
+        // both peers must start from the same state
+        let config = if network_enabled {
+            EmulatorConfig {
+                policy: self.config.policy,
+                ..Default::default()
+            }
+        } else {
+            let boot_rom = match model {
+                SelectedModel::Dmg | SelectedModel::Sgb => &self.dmg_boot_rom,
+                SelectedModel::Cgb => &self.cgb_boot_rom,
+            };
+            EmulatorConfig {
+                boot_rom: boot_rom.clone(),
+                ..self.config.clone()
+            }
+        };
+
+        let inner = match model {
+            SelectedModel::Dmg | SelectedModel::Sgb => WebEmulatorInner::new(
+                config,
                 rom,
                 save,
                 extra,
@@ -260,9 +378,8 @@ impl WebEmulator {
                 network_enabled,
             )
             .map(Inner::Dmg),
-            (Compatibility::Cgb, _)
-            | (_, Mode::AlwaysCgb)
-            | (Compatibility::Both, Mode::CgbWhenExplicit) => WebEmulatorInner::new(
+            SelectedModel::Cgb => WebEmulatorInner::new(
+                config,
                 rom,
                 save,
                 extra,
@@ -274,11 +391,20 @@ impl WebEmulator {
             .map(Inner::Cgb),
         };
 
-        self.inner = inner.unwrap_or(if network_enabled {
-            Inner::NetworkPreEnabled
-        } else {
-            Inner::None
-        });
+        match inner {
+            Ok(inner) => {
+                self.inner = inner;
+                Ok(())
+            }
+            Err(err) => {
+                self.inner = if network_enabled {
+                    Inner::NetworkPreEnabled
+                } else {
+                    Inner::None
+                };
+                Err(err)
+            }
+        }
     }
 
     // this function is executed every 128 (RENDER_QUANTUM_SIZE) frames
@@ -319,6 +445,23 @@ impl WebEmulator {
         }
     }
 
+    pub fn save_state(&self) -> Option<Box<[u8]>> {
+        match &self.inner {
+            Inner::Dmg(web_emulator_inner) => Some(web_emulator_inner.save_state()),
+            Inner::Cgb(web_emulator_inner) => Some(web_emulator_inner.save_state()),
+            Inner::NetworkPreEnabled => None,
+            Inner::None => None,
+        }
+    }
+
+    pub fn load_state(&mut self, state: &[u8]) -> Result<(), String> {
+        match &mut self.inner {
+            Inner::Dmg(web_emulator_inner) => web_emulator_inner.load_state(state),
+            Inner::Cgb(web_emulator_inner) => web_emulator_inner.load_state(state),
+            Inner::NetworkPreEnabled | Inner::None => Err("No game loaded".to_owned()),
+        }
+    }
+
     pub fn set_a(&mut self, value: bool) {
         match &mut self.inner {
             Inner::Dmg(web_emulator_inner) => web_emulator_inner.set_joypad(JoypadInput {
@@ -494,6 +637,85 @@ impl WebEmulator {
             Inner::None => 0,
         }
     }
+
+    // in g, only useful for the cartridges with an accelerometer
+    pub fn set_tilt(&mut self, x: f32, y: f32) {
+        let tilt = Tilt { x, y };
+        match &mut self.inner {
+            Inner::Dmg(web_emulator_inner) => web_emulator_inner.mbc.set_tilt(tilt),
+            Inner::Cgb(web_emulator_inner) => web_emulator_inner.mbc.set_tilt(tilt),
+            Inner::NetworkPreEnabled | Inner::None => {}
+        }
+    }
+
+    // undefined if the cartridge has no infrared port
+    pub fn is_infrared_led_on(&self) -> Option<bool> {
+        match &self.inner {
+            Inner::Dmg(web_emulator_inner) => web_emulator_inner.mbc.is_infrared_led_on(),
+            Inner::Cgb(web_emulator_inner) => web_emulator_inner.mbc.is_infrared_led_on(),
+            Inner::NetworkPreEnabled | Inner::None => None,
+        }
+    }
+
+    pub fn set_infrared_light_received(&mut self, is_received: bool) {
+        match &mut self.inner {
+            Inner::Dmg(web_emulator_inner) => web_emulator_inner
+                .mbc
+                .set_infrared_light_received(is_received),
+            Inner::Cgb(web_emulator_inner) => web_emulator_inner
+                .mbc
+                .set_infrared_light_received(is_received),
+            Inner::NetworkPreEnabled | Inner::None => {}
+        }
+    }
+
+    // between 0 and 1, always 0 if the cartridge has no rumble motor
+    pub fn get_rumble_intensity(&self) -> f32 {
+        match &self.inner {
+            Inner::Dmg(web_emulator_inner) => web_emulator_inner.rumble_intensity,
+            Inner::Cgb(web_emulator_inner) => web_emulator_inner.rumble_intensity,
+            Inner::NetworkPreEnabled | Inner::None => 0.0,
+        }
+    }
+
+    // Game Genie (ABC-DEF-GHI) or GameShark (ABCDEFGH), returns the index of the cheat
+    pub fn add_cheat(&mut self, code: &str) -> Result<usize, String> {
+        match &mut self.inner {
+            Inner::Dmg(web_emulator_inner) => web_emulator_inner.add_cheat(code),
+            Inner::Cgb(web_emulator_inner) => web_emulator_inner.add_cheat(code),
+            Inner::NetworkPreEnabled | Inner::None => Err("No game loaded".to_owned()),
+        }
+    }
+
+    pub fn set_cheat_enabled(&mut self, index: usize, is_enabled: bool) {
+        match &mut self.inner {
+            Inner::Dmg(web_emulator_inner) => {
+                web_emulator_inner.cheats.set_enabled(index, is_enabled)
+            }
+            Inner::Cgb(web_emulator_inner) => {
+                web_emulator_inner.cheats.set_enabled(index, is_enabled)
+            }
+            Inner::NetworkPreEnabled | Inner::None => {}
+        }
+    }
+
+    // the indices of the next cheats are shifted
+    pub fn remove_cheat(&mut self, index: usize) {
+        match &mut self.inner {
+            Inner::Dmg(web_emulator_inner) => web_emulator_inner.cheats.remove(index),
+            Inner::Cgb(web_emulator_inner) => web_emulator_inner.cheats.remove(index),
+            Inner::NetworkPreEnabled | Inner::None => {}
+        }
+    }
+
+    // an illegal opcode has been executed, the game must be reset
+    pub fn is_cpu_locked_up(&self) -> bool {
+        match &self.inner {
+            Inner::Dmg(web_emulator_inner) => web_emulator_inner.emulator.get_cpu().is_locked_up,
+            Inner::Cgb(web_emulator_inner) => web_emulator_inner.emulator.get_cpu().is_locked_up,
+            Inner::NetworkPreEnabled | Inner::None => false,
+        }
+    }
 }
 
 #[wasm_bindgen]
@@ -517,3 +739,11 @@ impl Save {
         self.game_title.clone()
     }
 }
+
+// called by the main thread before reading the title of the ROM to find its save
+#[wasm_bindgen]
+pub fn extract_rom(bytes: &[u8], entry_name: Option<String>) -> Result<Box<[u8]>, String> {
+    gebeh_front_helper::extract_rom(bytes, entry_name.as_deref())
+        .map(Into::into)
+        .map_err(|error| error.to_string())
+}
@@ -1,4 +1,4 @@
-use std::time::{Instant, UNIX_EPOCH};
+use std::time::{Duration, Instant, UNIX_EPOCH};
 
 use gebeh_core::{HEIGHT, mbc::*};
 
@@ -19,11 +19,12 @@ impl Default for InstantRtc {
     }
 }
 
-impl Rtc for InstantRtc {
-    fn get_clock_data(&mut self) -> RtcRegisters {
-        let now = self.last_halt.unwrap_or_else(Instant::now);
+impl InstantRtc {
+    fn get_clock_data_at(&mut self, now: Instant) -> RtcRegisters {
+        let now = self.last_halt.unwrap_or(now);
         let elapsed = now - self.last_seen;
-        self.last_seen = now;
+        // the fraction of second is kept for the next read
+        self.last_seen += Duration::from_secs(elapsed.as_secs());
         let new_seconds = u64::from(self.registers.get_total_seconds()) + elapsed.as_secs();
         let new_registers = RtcRegisters::from_seconds(
             u32::try_from(new_seconds % u64::from(MAX_RTC_SECONDS)).unwrap(),
@@ -33,6 +34,12 @@ impl Rtc for InstantRtc {
         self.registers = new_registers;
         new_registers
     }
+}
+
+impl Rtc for InstantRtc {
+    fn get_clock_data(&mut self) -> RtcRegisters {
+        self.get_clock_data_at(Instant::now())
+    }
 
     fn set_clock_data(&mut self, registers: RtcRegisters) {
         let now = Instant::now();
@@ -64,3 +71,56 @@ impl Rtc for InstantRtc {
 }
 
 pub type Frame<S> = [S; HEIGHT as usize];
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use super::*;
+
+    struct SteppedRtc {
+        rtc: InstantRtc,
+        now: Rc<Cell<Instant>>,
+    }
+
+    impl Rtc for SteppedRtc {
+        fn get_clock_data(&mut self) -> RtcRegisters {
+            self.rtc.get_clock_data_at(self.now.get())
+        }
+        fn set_clock_data(&mut self, registers: RtcRegisters) {
+            self.rtc.set_clock_data(registers);
+        }
+        fn deserialize(&mut self, save: &[u8]) {
+            self.rtc.deserialize(save);
+        }
+        fn serialize(&self, buffer: &mut [u8]) -> usize {
+            self.rtc.serialize(buffer)
+        }
+    }
+
+    #[test]
+    fn mbc3_latched_every_frame() {
+        let mut rom = [0; 0x8000];
+        // MBC3+TIMER+BATTERY
+        rom[CARTRIDGE_TYPE_HEADER] = 0x0f;
+        let rtc = InstantRtc::default();
+        let now = Rc::new(Cell::new(rtc.last_seen));
+        let mut mbc = Mbc3::new(
+            rom.as_slice(),
+            SteppedRtc {
+                rtc,
+                now: now.clone(),
+            },
+        );
+        // enable the RTC, select the seconds
+        mbc.write(0x0000, 0x0a);
+        mbc.write(0x4000, 0x08);
+        // the fraction of second between the latches isn't lost
+        for _ in 0..180 {
+            now.set(now.get() + Duration::from_micros(16_743));
+            mbc.write(0x6000, 0x00);
+            mbc.write(0x6000, 0x01);
+        }
+        assert_eq!(mbc.read(0xa000), 3);
+    }
+}
@@ -1,15 +1,31 @@
 use gebeh::InstantRtc;
-use gebeh_core::{Cgb, Dmg, Emulator, EmulatorExt, Model};
+use gebeh_core::{
+    Cgb, Dmg, Emulator, EmulatorExt, Model, Sgb,
+    revision::{CgbRevision, DmgRevision, SgbRevision},
+};
 use gebeh_front_helper::get_mbc;
 
 fn test_mooneye<M: Model>(path: &str) {
+    test_mooneye_inner::<M>(path, None);
+}
+
+// the boot_regs tests need the registers of the revision, the built-in boot ROMs are not exact
+fn test_mooneye_without_boot_rom<M: Model>(path: &str, revision: M::Revision) {
+    test_mooneye_inner::<M>(path, Some(revision));
+}
+
+fn test_mooneye_inner<M: Model>(path: &str, skip_boot_rom_of: Option<M::Revision>) {
     let rom = std::fs::read(format!(
         "./downloads/mts-20240926-1737-443f6e1/acceptance/{path}"
     ))
     .unwrap();
     let rom = rom.as_slice();
-    let mut emulator = Emulator::<M>::default();
     let (_, mut mbc) = get_mbc(rom, InstantRtc::default()).unwrap();
+    let mut emulator = Emulator::<M>::default();
+    if let Some(revision) = skip_boot_rom_of {
+        emulator = Emulator::<M>::with_revision(revision);
+        M::skip_boot_rom(&mut emulator, mbc.as_mut());
+    }
 
     // https://github.com/Gekkio/mooneye-test-suite/tree/main?tab=readme-ov-file#passfail-reporting
     while emulator.get_cpu().current_opcode != 0x40 {
@@ -674,3 +690,38 @@ fn tima_write_reloading_cgb() {
 fn tma_write_reloading_cgb() {
     test_mooneye::<Cgb>("timer/tma_write_reloading.gb");
 }
+
+#[test]
+fn boot_regs_dmg0() {
+    test_mooneye_without_boot_rom::<Dmg>("boot_regs-dmg0.gb", DmgRevision::Dmg0);
+}
+
+#[test]
+fn boot_regs_dmg_abc() {
+    test_mooneye_without_boot_rom::<Dmg>("boot_regs-dmgABC.gb", DmgRevision::Dmg);
+}
+
+#[test]
+fn boot_regs_mgb() {
+    test_mooneye_without_boot_rom::<Dmg>("boot_regs-mgb.gb", DmgRevision::Mgb);
+}
+
+#[test]
+fn boot_regs_sgb() {
+    test_mooneye_without_boot_rom::<Sgb>("boot_regs-sgb.gb", SgbRevision::Sgb);
+}
+
+#[test]
+fn boot_regs_sgb2() {
+    test_mooneye_without_boot_rom::<Sgb>("boot_regs-sgb2.gb", SgbRevision::Sgb2);
+}
+
+#[test]
+fn boot_regs_cgb() {
+    test_mooneye_without_boot_rom::<Cgb>("../misc/boot_regs-cgb.gb", CgbRevision::Cgb);
+}
+
+#[test]
+fn boot_regs_agb() {
+    test_mooneye_without_boot_rom::<Cgb>("../misc/boot_regs-A.gb", CgbRevision::Agb);
+}